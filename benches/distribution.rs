@@ -0,0 +1,88 @@
+//! 比较各分布策略的负载均衡度、增删节点时的数据迁移量以及查询耗时
+//!
+//! 运行: cargo bench --bench distribution
+
+use coretex::distribution::{
+    ConsistentHashRing, DistributionNode, DistributionStrategy, JumpHashing, RendezvousHashing,
+};
+use std::collections::HashMap;
+use std::time::Instant;
+
+const NODES: usize = 10;
+const KEYS: usize = 100_000;
+
+fn keys() -> Vec<Vec<u8>> {
+    (0..KEYS).map(|i| format!("key-{}", i).into_bytes()).collect()
+}
+
+fn owners(strategy: &dyn DistributionStrategy, keys: &[Vec<u8>]) -> Vec<String> {
+    keys.iter()
+        .map(|key| strategy.get_primary(key).map(|n| n.id).unwrap_or_default())
+        .collect()
+}
+
+fn moved(before: &[String], after: &[String]) -> f64 {
+    let count = before.iter().zip(after).filter(|(a, b)| a != b).count();
+    count as f64 / before.len() as f64
+}
+
+/// 各节点 key 数的相对标准差（标准差 / 平均值）
+fn relative_stddev(owners: &[String]) -> f64 {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for owner in owners {
+        *counts.entry(owner.as_str()).or_default() += 1;
+    }
+    let mean = owners.len() as f64 / counts.len() as f64;
+    let variance = counts
+        .values()
+        .map(|&c| (c as f64 - mean).powi(2))
+        .sum::<f64>()
+        / counts.len() as f64;
+    variance.sqrt() / mean
+}
+
+fn run(name: &str, mut strategy: Box<dyn DistributionStrategy>, keys: &[Vec<u8>]) {
+    for i in 0..NODES {
+        strategy.add_node(DistributionNode::new(format!("node-{}", i), 100));
+    }
+
+    let start = Instant::now();
+    let base = owners(strategy.as_ref(), keys);
+    let lookup_ns = start.elapsed().as_nanos() as f64 / keys.len() as f64;
+
+    strategy.add_node(DistributionNode::new(format!("node-{}", NODES), 100));
+    let added = owners(strategy.as_ref(), keys);
+    let add_moved = moved(&base, &added);
+
+    strategy.remove_node(&format!("node-{}", NODES));
+    strategy.remove_node("node-3");
+    let removed = owners(strategy.as_ref(), keys);
+    let remove_moved = moved(&base, &removed);
+
+    println!(
+        "{:<16} {:>12.4} {:>14.2}% {:>16.2}% {:>12.1}",
+        name,
+        relative_stddev(&base),
+        add_moved * 100.0,
+        remove_moved * 100.0,
+        lookup_ns,
+    );
+}
+
+fn main() {
+    let keys = keys();
+    println!(
+        "{} nodes, {} keys (ideal movement on add: {:.2}%, on remove: {:.2}%)",
+        NODES,
+        KEYS,
+        100.0 / (NODES + 1) as f64,
+        100.0 / NODES as f64,
+    );
+    println!(
+        "{:<16} {:>12} {:>15} {:>17} {:>12}",
+        "strategy", "rel-stddev", "moved(add)", "moved(remove)", "ns/lookup"
+    );
+    run("consistent-hash", Box::new(ConsistentHashRing::new()), &keys);
+    run("rendezvous", Box::new(RendezvousHashing::new()), &keys);
+    run("jump", Box::new(JumpHashing::new()), &keys);
+}
@@ -82,22 +82,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut hash_ring = ConsistentHashRing::new();
     
     // Add nodes to the hash ring
-    let dist_node1 = DistributionNode {
-        id: node1_id.clone(),
-        weight: 100,
-    };
-    let dist_node2 = DistributionNode {
-        id: node2_id.clone(),
-        weight: 100,
-    };
-    
-    hash_ring.add_node(dist_node1).await;
-    hash_ring.add_node(dist_node2).await;
+    let dist_node1 = DistributionNode::new(node1_id.clone(), 100).with_zone("zone-a");
+    let dist_node2 = DistributionNode::new(node2_id.clone(), 100).with_zone("zone-b");
+    
+    hash_ring.add_node(dist_node1);
+    hash_ring.add_node(dist_node2);
     
     // Test key distribution
     let test_keys = [b"key1", b"key2", b"key3", b"key4"];
     for key in test_keys.iter() {
-        if let Some(primary) = hash_ring.get_primary(*key).await {
+        if let Some(primary) = hash_ring.get_primary(*key) {
             println!("  🔑 {} -> Primary: {}", 
                 String::from_utf8_lossy(*key), 
                 primary.id
@@ -106,7 +100,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     
     // Get replicas for a key
-    let replicas = hash_ring.get_replicas(b"important_data", 2).await;
+    let replicas = hash_ring.get_replicas(b"important_data", 2);
     println!("  📋 Replicas for 'important_data': {} nodes", replicas.len());
     
     println!("\n✨ Demo completed successfully!");
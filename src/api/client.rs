@@ -1,21 +1,59 @@
+use crate::distribution::PartitionMap;
 use crate::Result;
 use bytes::Bytes;
+use std::future::Future;
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::net::TcpStream;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
+/// 客户端每个请求（包括建立连接）的默认超时
+pub const DEFAULT_CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// 一个简单的 TCP 客户端示例，实际生产环境建议用 gRPC/HTTP/自定义协议
+///
+/// 每个请求在超时后返回 [`Error::Timeout`](crate::error::Error::Timeout)，此时写入可能已经生效。
 pub struct Client {
     addr: SocketAddr,
+    timeout: Duration,
 }
 
 impl Client {
     pub async fn new(addr: impl Into<SocketAddr>) -> Result<Self> {
         // 这里只是简单保存地址，不做连接池
-        Ok(Self { addr: addr.into() })
+        Ok(Self {
+            addr: addr.into(),
+            timeout: DEFAULT_CLIENT_TIMEOUT,
+        })
+    }
+
+    /// 设置每个请求的超时
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 在超时内完成 `request`
+    async fn within<T>(&self, request: impl Future<Output = Result<T>>) -> Result<T> {
+        tokio::time::timeout(self.timeout, request)
+            .await
+            .map_err(|_| crate::error::Error::Timeout(format!("{} 在 {:?} 内未响应", self.addr, self.timeout)))?
     }
 
     pub async fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.within(self.put_once(key, value)).await
+    }
+
+    pub async fn get(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        self.within(self.get_once(key)).await
+    }
+
+    /// 下载节点当前的分区映射，用于在客户端直接路由到副本节点
+    pub async fn partition_map(&self) -> Result<PartitionMap> {
+        self.within(self.partition_map_once()).await
+    }
+
+    async fn put_once(&self, key: &[u8], value: &[u8]) -> Result<()> {
         let mut stream = TcpStream::connect(self.addr).await
             .map_err(|e| crate::error::Error::Storage(format!("连接失败: {}", e)))?;
         // 简单协议: "PUT key_len value_len key value"
@@ -37,7 +75,7 @@ impl Client {
         }
     }
 
-    pub async fn get(&self, key: &[u8]) -> Result<Option<Bytes>> {
+    async fn get_once(&self, key: &[u8]) -> Result<Option<Bytes>> {
         let mut stream = TcpStream::connect(self.addr).await
             .map_err(|e| crate::error::Error::Storage(format!("连接失败: {}", e)))?;
         // 简单协议: "GET key_len key"
@@ -59,4 +97,19 @@ impl Client {
             .map_err(|e| crate::error::Error::Storage(format!("读取值失败: {}", e)))?;
         Ok(Some(Bytes::from(value)))
     }
+
+    async fn partition_map_once(&self) -> Result<PartitionMap> {
+        let mut stream = TcpStream::connect(self.addr).await
+            .map_err(|e| crate::error::Error::Storage(format!("连接失败: {}", e)))?;
+        // 简单协议: "MAP "，响应为 "len map_json"
+        stream.write_all(b"MAP ").await
+            .map_err(|e| crate::error::Error::Storage(format!("写入失败: {}", e)))?;
+        let mut len_buf = [0u8; 4];
+        stream.read_exact(&mut len_buf).await
+            .map_err(|e| crate::error::Error::Storage(format!("读取长度失败: {}", e)))?;
+        let mut data = vec![0u8; u32::from_be_bytes(len_buf) as usize];
+        stream.read_exact(&mut data).await
+            .map_err(|e| crate::error::Error::Storage(format!("读取分区映射失败: {}", e)))?;
+        PartitionMap::from_bytes(&data)
+    }
 }
@@ -1,6 +1,11 @@
+pub mod client;
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use crate::consistency::{
+    Acknowledged, CausalContext, ConsistencyLevel, ConsistencyManager, Crdt, CrdtOp, SessionToken, Siblings,
+};
+use crate::error::Error;
 use crate::Result;
 
 /// 客户端 API trait
@@ -10,6 +15,83 @@ pub trait ClientApi: Send + Sync + 'static {
     async fn put(&self, key: &[u8], value: &[u8]) -> Result<()>;
     async fn delete(&self, key: &[u8]) -> Result<()>;
     async fn batch_put(&self, items: Vec<(Bytes, Bytes)>) -> Result<()>;
+
+    /// 按指定的一致性级别读取，返回值和确认的副本数
+    ///
+    /// 默认忽略级别，确认数记为 1。
+    async fn get_with_level(&self, key: &[u8], _level: ConsistencyLevel) -> Result<Acknowledged<Option<Bytes>>> {
+        Ok(Acknowledged::new(self.get(key).await?, 1))
+    }
+
+    async fn put_with_level(&self, key: &[u8], value: &[u8], _level: ConsistencyLevel) -> Result<Acknowledged<()>> {
+        self.put(key, value).await?;
+        Ok(Acknowledged::new((), 1))
+    }
+
+    async fn delete_with_level(&self, key: &[u8], _level: ConsistencyLevel) -> Result<Acknowledged<()>> {
+        self.delete(key).await?;
+        Ok(Acknowledged::new((), 1))
+    }
+
+    /// 读取全部并发版本及因果上下文，默认最多返回一个值
+    async fn get_versioned(&self, key: &[u8]) -> Result<Siblings> {
+        Ok(Siblings {
+            values: self.get(key).await?.into_iter().collect(),
+            context: CausalContext::default(),
+        })
+    }
+
+    /// 携带 [`ClientApi::get_versioned`] 返回的上下文写入，合并其中的 siblings
+    async fn put_with_context(&self, key: &[u8], value: &[u8], _context: &CausalContext) -> Result<()> {
+        self.put(key, value).await
+    }
+
+    /// 因果一致读取，返回值和下一次请求应携带的会话令牌
+    async fn get_causal(&self, key: &[u8], token: &SessionToken) -> Result<(Option<Bytes>, SessionToken)> {
+        Ok((self.get(key).await?, token.clone()))
+    }
+
+    async fn put_causal(&self, key: &[u8], value: &[u8], token: &SessionToken) -> Result<SessionToken> {
+        self.put(key, value).await?;
+        Ok(token.clone())
+    }
+
+    async fn delete_causal(&self, key: &[u8], token: &SessionToken) -> Result<SessionToken> {
+        self.delete(key).await?;
+        Ok(token.clone())
+    }
+
+    /// 读取 CRDT 类型的值，默认不支持
+    async fn get_crdt(&self, _key: &[u8]) -> Result<Option<Crdt>> {
+        Err(Error::Consistency("该客户端不支持 CRDT".to_string()))
+    }
+
+    /// 对 CRDT 类型的值执行一次更新，返回更新后的状态
+    async fn update_crdt(&self, _key: &[u8], _op: CrdtOp) -> Result<Crdt> {
+        Err(Error::Consistency("该客户端不支持 CRDT".to_string()))
+    }
+
+    /// PN-Counter 加上 `delta`（可以为负），返回更新后的计数
+    async fn increment(&self, key: &[u8], delta: i64) -> Result<i64> {
+        match self.update_crdt(key, CrdtOp::PnCounterIncrement(delta)).await? {
+            Crdt::PnCounter(counter) => Ok(counter.value()),
+            other => Err(Error::Consistency(format!("{} 不是计数器", other.kind()))),
+        }
+    }
+
+    /// 向 OR-Set 添加元素
+    async fn add_element(&self, key: &[u8], element: &[u8]) -> Result<()> {
+        self.update_crdt(key, CrdtOp::SetAdd(Bytes::copy_from_slice(element)))
+            .await?;
+        Ok(())
+    }
+
+    /// 从 OR-Set 删除元素，只删除已观察到的添加
+    async fn remove_element(&self, key: &[u8], element: &[u8]) -> Result<()> {
+        self.update_crdt(key, CrdtOp::SetRemove(Bytes::copy_from_slice(element)))
+            .await?;
+        Ok(())
+    }
 }
 
 /// 一个简单的本地客户端实现（直接调用存储引擎）
@@ -49,3 +131,76 @@ impl ClientApi for LocalClient {
         self.storage.batch_write(ops).await
     }
 }
+
+/// 通过一致性层读写的客户端，请求由本节点作为协调者分发到各副本
+pub struct CoordinatorClient {
+    consistency: Arc<dyn ConsistencyManager>,
+}
+
+impl CoordinatorClient {
+    pub fn new(consistency: Arc<dyn ConsistencyManager>) -> Self {
+        Self { consistency }
+    }
+}
+
+#[async_trait]
+impl ClientApi for CoordinatorClient {
+    async fn get(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        self.consistency.get(key).await
+    }
+
+    async fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.consistency.put(key, value).await
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<()> {
+        self.consistency.delete(key).await
+    }
+
+    async fn batch_put(&self, items: Vec<(Bytes, Bytes)>) -> Result<()> {
+        for (key, value) in items {
+            self.consistency.put(&key, &value).await?;
+        }
+        Ok(())
+    }
+
+    async fn get_with_level(&self, key: &[u8], level: ConsistencyLevel) -> Result<Acknowledged<Option<Bytes>>> {
+        self.consistency.get_with_level(key, level).await
+    }
+
+    async fn put_with_level(&self, key: &[u8], value: &[u8], level: ConsistencyLevel) -> Result<Acknowledged<()>> {
+        self.consistency.put_with_level(key, value, level).await
+    }
+
+    async fn delete_with_level(&self, key: &[u8], level: ConsistencyLevel) -> Result<Acknowledged<()>> {
+        self.consistency.delete_with_level(key, level).await
+    }
+
+    async fn get_versioned(&self, key: &[u8]) -> Result<Siblings> {
+        self.consistency.get_versioned(key).await
+    }
+
+    async fn put_with_context(&self, key: &[u8], value: &[u8], context: &CausalContext) -> Result<()> {
+        self.consistency.put_with_context(key, value, context).await
+    }
+
+    async fn get_causal(&self, key: &[u8], token: &SessionToken) -> Result<(Option<Bytes>, SessionToken)> {
+        self.consistency.get_causal(key, token).await
+    }
+
+    async fn get_crdt(&self, key: &[u8]) -> Result<Option<Crdt>> {
+        self.consistency.get_crdt(key).await
+    }
+
+    async fn update_crdt(&self, key: &[u8], op: CrdtOp) -> Result<Crdt> {
+        self.consistency.update_crdt(key, op).await
+    }
+
+    async fn put_causal(&self, key: &[u8], value: &[u8], token: &SessionToken) -> Result<SessionToken> {
+        self.consistency.put_causal(key, value, token).await
+    }
+
+    async fn delete_causal(&self, key: &[u8], token: &SessionToken) -> Result<SessionToken> {
+        self.consistency.delete_causal(key, token).await
+    }
+}
@@ -9,6 +9,8 @@ use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::pin::Pin;
 
+use crate::distribution::{RACK_METADATA_KEY, ZONE_METADATA_KEY};
+use crate::error::Error;
 use crate::Result;
 
 pub use file::FileConfigProvider;
@@ -20,13 +22,164 @@ pub struct Config {
     pub storage: StorageConfig,
     pub replication: ReplicationConfig,
     pub consistency: ConsistencyConfig,
+    #[serde(default)]
+    pub distribution: DistributionConfig,
+    #[serde(default)]
+    pub clock: ClockConfig,
+    #[serde(default)]
+    pub membership: MembershipConfig,
+}
+
+/// SWIM 成员协议的故障检测和传播参数
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MembershipConfig {
+    /// 每个周期探测一个成员
+    #[serde(default = "default_probe_interval_ms")]
+    pub probe_interval_ms: u64,
+    /// 直接探测等待确认的时间，超时后请其他成员间接探测
+    #[serde(default = "default_probe_timeout_ms")]
+    pub probe_timeout_ms: u64,
+    /// 间接探测时委托的成员数
+    #[serde(default = "default_indirect_probes")]
+    pub indirect_probes: usize,
+    /// 被怀疑的成员在这段时间内未反驳即判定为宕机
+    #[serde(default = "default_suspicion_timeout_ms")]
+    pub suspicion_timeout_ms: u64,
+    /// 每条成员变更最多随 `retransmit_mult * log2(n + 1)` 条消息传播
+    #[serde(default = "default_retransmit_mult")]
+    pub retransmit_mult: u32,
+    /// 每条消息最多携带的成员变更数
+    #[serde(default = "default_max_piggyback")]
+    pub max_piggyback: usize,
+    /// 与随机成员（包括已判定宕机的成员和种子节点）交换完整成员表的间隔
+    #[serde(default = "default_sync_interval_ms")]
+    pub sync_interval_ms: u64,
+    /// 判定为宕机的成员超过这段时间仍未恢复即从成员表中移除，视为离开集群
+    #[serde(default = "default_dead_member_timeout_ms")]
+    pub dead_member_timeout_ms: u64,
+}
+
+impl Default for MembershipConfig {
+    fn default() -> Self {
+        Self {
+            probe_interval_ms: default_probe_interval_ms(),
+            probe_timeout_ms: default_probe_timeout_ms(),
+            indirect_probes: default_indirect_probes(),
+            suspicion_timeout_ms: default_suspicion_timeout_ms(),
+            retransmit_mult: default_retransmit_mult(),
+            max_piggyback: default_max_piggyback(),
+            sync_interval_ms: default_sync_interval_ms(),
+            dead_member_timeout_ms: default_dead_member_timeout_ms(),
+        }
+    }
+}
+
+fn default_probe_interval_ms() -> u64 {
+    1000
+}
+
+fn default_probe_timeout_ms() -> u64 {
+    300
+}
+
+fn default_indirect_probes() -> usize {
+    3
+}
+
+fn default_suspicion_timeout_ms() -> u64 {
+    5000
+}
+
+fn default_retransmit_mult() -> u32 {
+    4
+}
+
+fn default_max_piggyback() -> usize {
+    8
+}
+
+fn default_sync_interval_ms() -> u64 {
+    30000
+}
+
+fn default_dead_member_timeout_ms() -> u64 {
+    3_600_000
+}
+
+/// 混合逻辑时钟参数
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClockConfig {
+    /// 允许的最大时钟偏差（毫秒），超前更多的消息被拒绝
+    #[serde(default = "default_max_offset_ms")]
+    pub max_offset_ms: u64,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            max_offset_ms: default_max_offset_ms(),
+        }
+    }
+}
+
+fn default_max_offset_ms() -> u64 {
+    crate::utils::DEFAULT_MAX_OFFSET_MS
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NodeConfig {
+    /// 节点在集群中的标识，重启后保持不变；未配置时使用 `data_dir` 下保存的标识
+    #[serde(default)]
+    pub node_id: Option<String>,
     pub bind_address: SocketAddr,
+    /// 向客户端提供分区映射下载的地址，未配置时不提供
+    #[serde(default)]
+    pub client_address: Option<SocketAddr>,
     pub data_dir: PathBuf,
     pub seed_nodes: Vec<SocketAddr>,
+    /// 节点所在的可用区，副本放置时分散到不同可用区
+    #[serde(default)]
+    pub zone: Option<String>,
+    /// 节点所在的机架，副本放置时分散到不同机架
+    #[serde(default)]
+    pub rack: Option<String>,
+}
+
+/// 未配置节点标识时保存自动生成的标识的文件，位于 `data_dir` 下
+pub const NODE_ID_FILE: &str = "node_id";
+
+impl NodeConfig {
+    /// 注册本节点时携带的元数据：配置的可用区和机架
+    pub fn metadata(&self) -> HashMap<String, String> {
+        let mut metadata = HashMap::new();
+        if let Some(zone) = &self.zone {
+            metadata.insert(ZONE_METADATA_KEY.to_string(), zone.clone());
+        }
+        if let Some(rack) = &self.rack {
+            metadata.insert(RACK_METADATA_KEY.to_string(), rack.clone());
+        }
+        metadata
+    }
+
+    /// 本节点的标识：优先使用配置，其次读取 `data_dir` 下保存的标识，都没有时生成并保存
+    ///
+    /// 重启后沿用同一个标识，其他节点不会把重启前后的本节点当作两个成员。
+    pub fn resolve_node_id(&self) -> Result<String> {
+        if let Some(id) = self.node_id.as_ref().filter(|id| !id.is_empty()) {
+            return Ok(id.clone());
+        }
+        let path = self.data_dir.join(NODE_ID_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(id) if !id.trim().is_empty() => return Ok(id.trim().to_string()),
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(Error::Configuration(format!("读取节点标识 {} 失败: {}", path.display(), e))),
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        std::fs::create_dir_all(&self.data_dir)?;
+        std::fs::write(&path, &id)?;
+        Ok(id)
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -41,12 +194,296 @@ pub struct ReplicationConfig {
     pub factor: usize,
     pub read_quorum: usize,
     pub write_quorum: usize,
+    #[serde(default)]
+    pub hinted_handoff: HintedHandoffConfig,
+    #[serde(default)]
+    pub requests: RequestConfig,
+}
+
+/// 协调者发往副本的请求的超时、重试和对冲参数
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RequestConfig {
+    /// 单次副本请求的超时
+    #[serde(default = "default_replica_timeout_ms")]
+    pub replica_timeout_ms: u64,
+    /// 一次读写（包括重试和对冲）的总截止时间，超过时返回超时错误
+    #[serde(default = "default_replica_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    /// 读取在这段时间内未凑齐法定数时向一个额外的副本发送请求，0 表示不对冲
+    #[serde(default)]
+    pub hedge_after_ms: u64,
+}
+
+impl Default for RequestConfig {
+    fn default() -> Self {
+        Self {
+            replica_timeout_ms: default_replica_timeout_ms(),
+            request_timeout_ms: default_replica_request_timeout_ms(),
+            retry: RetryConfig::default(),
+            hedge_after_ms: 0,
+        }
+    }
+}
+
+fn default_replica_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_replica_request_timeout_ms() -> u64 {
+    5000
+}
+
+/// 副本请求超时或无法送达时的重试参数，退避时间按 `multiplier` 指数增长
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RetryConfig {
+    /// 每个副本最多尝试的次数，1 表示不重试
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    #[serde(default = "default_backoff_multiplier")]
+    pub multiplier: f64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            multiplier: default_backoff_multiplier(),
+        }
+    }
+}
+
+fn default_max_attempts() -> u32 {
+    1
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    20
+}
+
+fn default_max_backoff_ms() -> u64 {
+    500
+}
+
+fn default_backoff_multiplier() -> f64 {
+    2.0
+}
+
+/// 提示移交（hinted handoff）参数
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HintedHandoffConfig {
+    /// 副本宕机时是否把写入交给偏好列表中后续的健康节点暂存
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 每个节点最多暂存的提示数
+    #[serde(default = "default_max_hints")]
+    pub max_hints: usize,
+    /// 提示的有效期（秒），过期的提示不再回放
+    #[serde(default = "default_hint_ttl_secs")]
+    pub hint_ttl_secs: u64,
+    /// 定期重试回放未送达提示的间隔（毫秒）
+    #[serde(default = "default_hint_retry_interval_ms")]
+    pub retry_interval_ms: u64,
+}
+
+impl Default for HintedHandoffConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_hints: default_max_hints(),
+            hint_ttl_secs: default_hint_ttl_secs(),
+            retry_interval_ms: default_hint_retry_interval_ms(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_hints() -> usize {
+    10_000
+}
+
+fn default_hint_ttl_secs() -> u64 {
+    3 * 60 * 60
+}
+
+fn default_hint_retry_interval_ms() -> u64 {
+    10_000
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ConsistencyConfig {
     pub mode: ConsistencyMode,
     pub vector_clock_enabled: bool,
+    /// 默认的冲突解决方式
+    #[serde(default)]
+    pub conflict_resolution: ConflictResolution,
+    /// 按 key 前缀（命名空间）覆盖冲突解决方式，最长前缀优先
+    #[serde(default)]
+    pub namespaces: HashMap<String, ConflictResolution>,
+    #[serde(default)]
+    pub read_repair: ReadRepairMode,
+    #[serde(default)]
+    pub anti_entropy: AntiEntropyConfig,
+    /// `mode = "Strong"` 时每个副本组的 Raft 参数
+    #[serde(default)]
+    pub raft: RaftConfig,
+    /// 每个一致性事件订阅者最多缓冲的事件数，落后更多时丢弃最早的事件
+    #[serde(default = "default_event_buffer")]
+    pub event_buffer: usize,
+    /// 因果写入随新版本保存的依赖数上限，超出的依赖先写回足够多的副本
+    #[serde(default = "default_max_causal_dependencies")]
+    pub max_causal_dependencies: usize,
+}
+
+fn default_event_buffer() -> usize {
+    1024
+}
+
+fn default_max_causal_dependencies() -> usize {
+    32
+}
+
+/// Raft 参数
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RaftConfig {
+    /// 选举超时在 `[election_timeout_min_ms, election_timeout_max_ms)` 内随机选取
+    #[serde(default = "default_election_timeout_min_ms")]
+    pub election_timeout_min_ms: u64,
+    #[serde(default = "default_election_timeout_max_ms")]
+    pub election_timeout_max_ms: u64,
+    /// leader 发送心跳的间隔
+    #[serde(default = "default_heartbeat_interval_ms")]
+    pub heartbeat_interval_ms: u64,
+    /// 已应用的日志超过该数量时生成快照并截断日志
+    #[serde(default = "default_snapshot_threshold")]
+    pub snapshot_threshold: u64,
+    /// 单条 AppendEntries 最多携带的日志数
+    #[serde(default = "default_max_append_entries")]
+    pub max_append_entries: usize,
+    /// 客户端请求的总超时，包括寻找 leader 的重试
+    #[serde(default = "default_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    /// 启动时等待分布环中至少有 `factor` 个节点的最长时间，超时后拒绝启动
+    #[serde(default = "default_startup_timeout_ms")]
+    pub startup_timeout_ms: u64,
+    /// 选举超时抖动的随机种子，用于确定性模拟；未设置时每次启动随机选取
+    #[serde(default)]
+    pub election_seed: Option<u64>,
+}
+
+impl Default for RaftConfig {
+    fn default() -> Self {
+        Self {
+            election_timeout_min_ms: default_election_timeout_min_ms(),
+            election_timeout_max_ms: default_election_timeout_max_ms(),
+            heartbeat_interval_ms: default_heartbeat_interval_ms(),
+            snapshot_threshold: default_snapshot_threshold(),
+            max_append_entries: default_max_append_entries(),
+            request_timeout_ms: default_request_timeout_ms(),
+            startup_timeout_ms: default_startup_timeout_ms(),
+            election_seed: None,
+        }
+    }
+}
+
+fn default_election_timeout_min_ms() -> u64 {
+    150
+}
+
+fn default_election_timeout_max_ms() -> u64 {
+    300
+}
+
+fn default_heartbeat_interval_ms() -> u64 {
+    50
+}
+
+fn default_snapshot_threshold() -> u64 {
+    1000
+}
+
+fn default_max_append_entries() -> usize {
+    64
+}
+
+fn default_request_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_startup_timeout_ms() -> u64 {
+    30000
+}
+
+/// 副本之间基于 Merkle 树的反熵同步参数，强一致模式下不运行
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AntiEntropyConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 两轮同步之间的间隔（秒）
+    #[serde(default = "default_anti_entropy_interval_secs")]
+    pub interval_secs: u64,
+    /// 每秒最多同步的 key 数，0 表示不限制
+    #[serde(default = "default_max_keys_per_sec")]
+    pub max_keys_per_sec: u64,
+    /// Merkle 树深度，每个分区的叶子数为 `2^tree_depth`
+    #[serde(default = "default_tree_depth")]
+    pub tree_depth: u32,
+}
+
+impl Default for AntiEntropyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: default_anti_entropy_interval_secs(),
+            max_keys_per_sec: default_max_keys_per_sec(),
+            tree_depth: default_tree_depth(),
+        }
+    }
+}
+
+fn default_anti_entropy_interval_secs() -> u64 {
+    600
+}
+
+fn default_max_keys_per_sec() -> u64 {
+    1000
+}
+
+fn default_tree_depth() -> u32 {
+    10
+}
+
+/// 读修复的执行方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReadRepairMode {
+    Disabled,
+    /// 读取返回后在后台修复
+    #[default]
+    Background,
+    /// 修复完已响应的副本后再返回读取结果
+    Sync,
+}
+
+/// 并发版本的冲突解决方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictResolution {
+    /// 不自动解决，把全部 siblings 返回给客户端
+    #[default]
+    Siblings,
+    /// 时间戳最大的版本胜出
+    LastWriterWins,
+    /// 向量时钟计数之和最大的版本胜出
+    HighestVectorClock,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -56,13 +493,273 @@ pub enum ConsistencyMode {
     Causal,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DistributionConfig {
+    #[serde(default)]
+    pub strategy: DistributionKind,
+    /// 一致性哈希中默认权重节点的虚拟节点数
+    #[serde(default = "default_virtual_nodes")]
+    pub virtual_nodes: usize,
+    /// rendezvous / jump 策略的分区数量
+    #[serde(default = "default_partitions")]
+    pub partitions: usize,
+    /// range 策略下范围的分裂与合并
+    #[serde(default)]
+    pub range: RangeConfig,
+    /// 按实际负载自动调整节点的负载系数，默认关闭；强一致模式下不生效
+    #[serde(default)]
+    pub load_aware: Option<LoadAwareConfig>,
+    /// 热点 key 检测与缓解，默认关闭
+    #[serde(default)]
+    pub hot_keys: Option<HotKeyConfig>,
+    /// 分布环变化后的数据迁移
+    #[serde(default)]
+    pub rebalance: RebalanceConfig,
+}
+
+impl Default for DistributionConfig {
+    fn default() -> Self {
+        Self {
+            strategy: DistributionKind::default(),
+            virtual_nodes: default_virtual_nodes(),
+            partitions: default_partitions(),
+            range: RangeConfig::default(),
+            load_aware: None,
+            hot_keys: None,
+            rebalance: RebalanceConfig::default(),
+        }
+    }
+}
+
+/// 分布环变化后的数据迁移参数，只在最终一致和因果一致模式下运行
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RebalanceConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// 同时进行迁移的源节点数量
+    #[serde(default = "default_rebalance_concurrency")]
+    pub max_concurrent: usize,
+    /// 迁移带宽上限（字节/秒），0 表示不限速
+    #[serde(default = "default_rebalance_bytes_per_sec")]
+    pub bytes_per_sec: u64,
+    /// 每批发给目标节点的 key 数量
+    #[serde(default = "default_rebalance_batch_size")]
+    pub batch_size: usize,
+}
+
+impl Default for RebalanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_concurrent: default_rebalance_concurrency(),
+            bytes_per_sec: default_rebalance_bytes_per_sec(),
+            batch_size: default_rebalance_batch_size(),
+        }
+    }
+}
+
+fn default_rebalance_concurrency() -> usize {
+    2
+}
+
+fn default_rebalance_bytes_per_sec() -> u64 {
+    16 * 1024 * 1024
+}
+
+fn default_rebalance_batch_size() -> usize {
+    256
+}
+
+fn default_virtual_nodes() -> usize {
+    crate::distribution::DEFAULT_VIRTUAL_NODES
+}
+
+fn default_partitions() -> usize {
+    crate::distribution::DEFAULT_PARTITIONS
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DistributionKind {
+    #[default]
+    ConsistentHash,
+    Rendezvous,
+    Jump,
+    /// 按 key 范围分区，范围按读写负载分裂/合并，各节点的范围划分由
+    /// [`RangeSplitService`](crate::distribution::RangeSplitService) 保持一致
+    Range,
+}
+
+/// 衡量负载的指标
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BalanceMetric {
+    Keys,
+    #[default]
+    Bytes,
+    Requests,
+}
+
+/// range 策略下范围分裂与合并的参数（见 [`RangeSplitService`](crate::distribution::RangeSplitService)）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RangeConfig {
+    /// 范围的写入数据量超过该值（字节）时分裂
+    #[serde(default = "default_range_max_bytes")]
+    pub max_bytes: u64,
+    /// 范围的读写次数超过该值时分裂
+    #[serde(default = "default_range_max_load")]
+    pub max_load: u64,
+    /// 相邻两个范围合计的数据量与读写次数都低于上述阈值的该比例时合并
+    #[serde(default = "default_merge_ratio")]
+    pub merge_ratio: f64,
+    /// 汇总负载并决定分裂/合并的间隔
+    #[serde(default = "default_split_interval_ms")]
+    pub interval_ms: u64,
+}
+
+impl Default for RangeConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: default_range_max_bytes(),
+            max_load: default_range_max_load(),
+            merge_ratio: default_merge_ratio(),
+            interval_ms: default_split_interval_ms(),
+        }
+    }
+}
+
+fn default_range_max_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+fn default_range_max_load() -> u64 {
+    100_000
+}
+
+fn default_merge_ratio() -> f64 {
+    0.25
+}
+
+fn default_split_interval_ms() -> u64 {
+    10000
+}
+
+/// 按负载调整节点负载系数的参数
+///
+/// 各节点每隔 `interval_ms` 广播本节点的负载统计，由分布环中标识最小的节点汇总成报告、
+/// 计算调整并广播给所有节点（见 [`LoadBalancer`](crate::distribution::LoadBalancer)）。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LoadAwareConfig {
+    #[serde(default)]
+    pub metric: BalanceMetric,
+    /// 相对标准差低于该值时不调整
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f64,
+    /// 单次调整时负载系数变化的最大比例
+    #[serde(default = "default_max_step")]
+    pub max_step: f64,
+    /// 广播负载统计和调整的间隔
+    #[serde(default = "default_load_interval_ms")]
+    pub interval_ms: u64,
+}
+
+impl Default for LoadAwareConfig {
+    fn default() -> Self {
+        Self {
+            metric: BalanceMetric::default(),
+            tolerance: default_tolerance(),
+            max_step: default_max_step(),
+            interval_ms: default_load_interval_ms(),
+        }
+    }
+}
+
+fn default_tolerance() -> f64 {
+    0.1
+}
+
+fn default_max_step() -> f64 {
+    0.25
+}
+
+fn default_load_interval_ms() -> u64 {
+    60000
+}
+
+/// 热点 key 的缓解方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HotKeyMitigation {
+    /// 只检测，不做处理
+    None,
+    /// 热点 key 的读请求轮流发往所有副本
+    #[default]
+    SpreadReads,
+    /// 协调节点在短时间内缓存热点 key 的值
+    Cache,
+}
+
+/// 热点 key 检测参数
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HotKeyConfig {
+    /// 每隔多少个请求采样一次
+    #[serde(default = "default_sample_every")]
+    pub sample_every: u64,
+    /// 统计窗口长度（毫秒）
+    #[serde(default = "default_window_ms")]
+    pub window_ms: u64,
+    /// 估算的请求速率（次/秒）达到该值即视为热点
+    #[serde(default = "default_hot_threshold")]
+    pub threshold: f64,
+    /// 同时跟踪的候选 key 数量上限
+    #[serde(default = "default_hot_capacity")]
+    pub capacity: usize,
+    #[serde(default)]
+    pub mitigation: HotKeyMitigation,
+    /// 热点 key 缓存的有效期（毫秒）
+    #[serde(default = "default_cache_ttl_ms")]
+    pub cache_ttl_ms: u64,
+}
+
+impl Default for HotKeyConfig {
+    fn default() -> Self {
+        Self {
+            sample_every: default_sample_every(),
+            window_ms: default_window_ms(),
+            threshold: default_hot_threshold(),
+            capacity: default_hot_capacity(),
+            mitigation: HotKeyMitigation::default(),
+            cache_ttl_ms: default_cache_ttl_ms(),
+        }
+    }
+}
+
+fn default_sample_every() -> u64 {
+    16
+}
+
+fn default_window_ms() -> u64 {
+    1000
+}
+
+fn default_hot_threshold() -> f64 {
+    1000.0
+}
+
+fn default_hot_capacity() -> usize {
+    64
+}
+
+fn default_cache_ttl_ms() -> u64 {
+    100
+}
+
 #[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum ConfigChange {
     Full(Config),
     Node(NodeConfig),
     Storage(StorageConfig),
     Replication(ReplicationConfig),
     Consistency(ConsistencyConfig),
+    Distribution(DistributionConfig),
 }
 
 #[async_trait]
@@ -0,0 +1,260 @@
+use super::merkle::{bucket_digests, leaf_of, range_digests, tree_of, MerkleTree, MAX_TREE_DEPTH};
+use super::replica::{ReplicaClient, ReplicaOp, ReplicaReply};
+use crate::config::AntiEntropyConfig;
+use crate::distribution::{PartitionRange, SharedRing};
+use crate::error::Error;
+use crate::storage::StorageEngine;
+use crate::Result;
+use bytes::Bytes;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// 一轮反熵同步的结果
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AntiEntropyStats {
+    /// 比较过的（分区, 副本）对数
+    pub ranges_compared: usize,
+    /// Merkle 根不一致的数量
+    pub ranges_differing: usize,
+    /// 实际交换了版本的 key 数
+    pub keys_repaired: usize,
+}
+
+/// 副本之间基于 Merkle 树的反熵同步
+///
+/// 对本节点负责的每个分区，与同一分区的其他副本交换 Merkle 树，只沿哈希不同的子树向下比较，
+/// 再交换不同叶子中 key 的摘要，最后只传输摘要不同的 key：双方把自己的值经副本请求合并给对方，
+/// 版本列表按向量时钟合并，CRDT 合并双方的状态，带时间戳的值（[`Stamped`](super::Stamped)）保留较晚的写入。
+///
+/// 未加时间戳的旧数据无法判断新旧，两侧都有值时不会被覆盖。
+pub struct AntiEntropyService {
+    syncer: Arc<Syncer>,
+    task: Option<JoinHandle<()>>,
+}
+
+struct Syncer {
+    storage: Arc<dyn StorageEngine>,
+    ring: Arc<SharedRing>,
+    client: Arc<ReplicaClient>,
+    factor: usize,
+    depth: u32,
+    timeout: Duration,
+    limiter: RateLimiter,
+}
+
+impl AntiEntropyService {
+    /// `client` 的节点 id 即本节点，`factor` 为副本数；配置启用时按 `interval_secs` 定期同步
+    pub fn start(
+        storage: Arc<dyn StorageEngine>,
+        ring: Arc<SharedRing>,
+        client: Arc<ReplicaClient>,
+        factor: usize,
+        config: &AntiEntropyConfig,
+        timeout: Duration,
+    ) -> Self {
+        let syncer = Arc::new(Syncer {
+            storage,
+            ring,
+            client,
+            factor,
+            depth: config.tree_depth,
+            timeout,
+            limiter: RateLimiter::new(config.max_keys_per_sec),
+        });
+        let task = config.enabled.then(|| {
+            let syncer = syncer.clone();
+            let interval = Duration::from_secs(config.interval_secs.max(1));
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(interval);
+                // 第一次 tick 立即返回，启动时不做同步
+                ticker.tick().await;
+                loop {
+                    ticker.tick().await;
+                    let stats = syncer.run_round().await;
+                    if stats.keys_repaired > 0 {
+                        tracing::info!(
+                            "反熵同步: {} 个分区不一致，修复 {} 个 key",
+                            stats.ranges_differing,
+                            stats.keys_repaired
+                        );
+                    }
+                }
+            })
+        });
+        Self { syncer, task }
+    }
+
+    /// 立即执行一轮同步，单个副本失败只记录日志
+    pub async fn run_round(&self) -> AntiEntropyStats {
+        self.syncer.run_round().await
+    }
+
+    /// 与 `peer` 同步一个分区，返回交换了版本的 key 数
+    pub async fn sync_range(&self, range: &PartitionRange, peer: &str) -> Result<usize> {
+        let mut stats = AntiEntropyStats::default();
+        self.syncer.sync_range(range, peer, &mut stats).await?;
+        Ok(stats.keys_repaired)
+    }
+}
+
+impl Drop for AntiEntropyService {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+impl Syncer {
+    /// 只扫描一遍本地存储，得出本节点负责的每个分区的摘要和 Merkle 树，再与各副本比较
+    async fn run_round(&self) -> AntiEntropyStats {
+        let node_id = self.client.node_id();
+        let ownership = self.ring.snapshot().ownership(self.factor);
+        let owned: Vec<_> = ownership
+            .iter()
+            .filter(|o| o.replicas.iter().any(|r| r == node_id))
+            .collect();
+        let mut stats = AntiEntropyStats::default();
+        let ranges: Vec<&PartitionRange> = owned.iter().map(|o| &o.range).collect();
+        let buckets = match bucket_digests(self.storage.as_ref(), &ranges).await {
+            Ok(buckets) => buckets,
+            Err(e) => {
+                tracing::warn!("反熵同步扫描本地数据失败: {}", e);
+                return stats;
+            }
+        };
+        for (owned, digests) in owned.iter().zip(buckets) {
+            let tree = tree_of(&digests, self.depth);
+            for peer in owned.replicas.iter().filter(|r| *r != node_id) {
+                if let Err(e) = self.compare(&owned.range, &tree, &digests, peer, &mut stats).await {
+                    tracing::debug!("与 {} 反熵同步失败: {}", peer, e);
+                }
+            }
+        }
+        stats
+    }
+
+    async fn sync_range(&self, range: &PartitionRange, peer: &str, stats: &mut AntiEntropyStats) -> Result<()> {
+        let digests = range_digests(self.storage.as_ref(), range, self.depth, None).await?;
+        let tree = tree_of(&digests, self.depth);
+        self.compare(range, &tree, &digests, peer, stats).await
+    }
+
+    /// 用本地的树和摘要与 `peer` 比较一个分区，同步摘要不同的 key
+    async fn compare(
+        &self,
+        range: &PartitionRange,
+        ours: &MerkleTree,
+        digests: &[(Bytes, u64)],
+        peer: &str,
+        stats: &mut AntiEntropyStats,
+    ) -> Result<()> {
+        stats.ranges_compared += 1;
+        let op = ReplicaOp::GetTree {
+            range: range.clone(),
+            depth: self.depth,
+        };
+        let ReplicaReply::Tree(theirs) = self.client.call(peer, op, self.timeout).await? else {
+            return Err(unexpected(peer));
+        };
+        let leaves = ours.diff(&theirs);
+        if leaves.is_empty() {
+            return Ok(());
+        }
+        stats.ranges_differing += 1;
+
+        let depth = self.depth.min(MAX_TREE_DEPTH);
+        let differing: HashSet<usize> = leaves.iter().copied().collect();
+        let local = digests
+            .iter()
+            .filter(|(key, _)| differing.contains(&leaf_of(key, depth)))
+            .cloned();
+        let op = ReplicaOp::GetDigests {
+            range: range.clone(),
+            depth: self.depth,
+            leaves,
+        };
+        let ReplicaReply::Digests(remote) = self.client.call(peer, op, self.timeout).await? else {
+            return Err(unexpected(peer));
+        };
+        let mut digests: BTreeMap<Bytes, (Option<u64>, Option<u64>)> = BTreeMap::new();
+        for (key, digest) in local {
+            digests.entry(key).or_default().0 = Some(digest);
+        }
+        for (key, digest) in remote {
+            digests.entry(key).or_default().1 = Some(digest);
+        }
+        for (key, (ours, theirs)) in digests {
+            if ours == theirs {
+                continue;
+            }
+            self.limiter.acquire().await;
+            match self.sync_key(&key, peer).await {
+                Ok(true) => stats.keys_repaired += 1,
+                Ok(false) => {}
+                Err(e) => tracing::debug!("反熵同步 key {:?} 失败: {}", key, e),
+            }
+        }
+        Ok(())
+    }
+
+    /// 双方把各自保存的值合并给对方，返回是否有值被传输
+    ///
+    /// 合并规则与分区迁移相同：版本列表按向量时钟合并，CRDT 合并状态，带时间戳的值保留较晚的写入。
+    async fn sync_key(&self, key: &Bytes, peer: &str) -> Result<bool> {
+        let ours = self.storage.get(key).await?;
+        let op = ReplicaOp::GetStored { key: key.clone() };
+        let ReplicaReply::Stored(theirs) = self.client.call(peer, op, self.timeout).await? else {
+            return Err(unexpected(peer));
+        };
+        if ours == theirs {
+            return Ok(false);
+        }
+        let node_id = self.client.node_id().to_string();
+        let mut transferred = false;
+        for (target, value) in [(peer, ours), (node_id.as_str(), theirs)] {
+            let Some(value) = value else { continue };
+            let op = ReplicaOp::Merge {
+                entries: vec![(key.clone(), value)],
+            };
+            self.client.call(target, op, self.timeout).await?;
+            transferred = true;
+        }
+        Ok(transferred)
+    }
+}
+
+fn unexpected(peer: &str) -> Error {
+    Error::Communication(format!("副本 {} 返回了意外的响应", peer))
+}
+
+/// 按固定间隔放行的限速器，`per_second` 为 0 时不限速
+struct RateLimiter {
+    interval: Option<Duration>,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(per_second: u64) -> Self {
+        Self {
+            interval: (per_second > 0).then(|| Duration::from_secs_f64(1.0 / per_second as f64)),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    async fn acquire(&self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let mut next = self.next.lock().await;
+        let now = Instant::now();
+        if *next > now {
+            tokio::time::sleep_until(*next).await;
+        }
+        *next = (*next).max(now) + interval;
+    }
+}
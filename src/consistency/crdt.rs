@@ -0,0 +1,317 @@
+use crate::error::Error;
+use crate::Result;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// 存储中 CRDT 状态的前缀，用于与普通值和版本列表区分
+pub const CRDT_TAG: &[u8] = b"\x00crdt:";
+
+/// 只增计数器，每个执行者（副本）各自计数，合并时逐项取最大值
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GCounter {
+    counts: BTreeMap<String, u64>,
+}
+
+impl GCounter {
+    pub fn increment(&mut self, actor: &str, delta: u64) {
+        *self.counts.entry(actor.to_string()).or_default() += delta;
+    }
+
+    pub fn value(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    pub fn merge(&mut self, other: &GCounter) {
+        for (actor, count) in &other.counts {
+            let entry = self.counts.entry(actor.clone()).or_default();
+            *entry = (*entry).max(*count);
+        }
+    }
+}
+
+/// 可增可减计数器，由增、减两个 [`GCounter`] 组成
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PnCounter {
+    increments: GCounter,
+    decrements: GCounter,
+}
+
+impl PnCounter {
+    pub fn increment(&mut self, actor: &str, delta: i64) {
+        if delta >= 0 {
+            self.increments.increment(actor, delta as u64);
+        } else {
+            self.decrements.increment(actor, delta.unsigned_abs());
+        }
+    }
+
+    pub fn value(&self) -> i64 {
+        self.increments.value() as i64 - self.decrements.value() as i64
+    }
+
+    pub fn merge(&mut self, other: &PnCounter) {
+        self.increments.merge(&other.increments);
+        self.decrements.merge(&other.decrements);
+    }
+}
+
+/// 添加优先的 OR-Set（observed-remove set）
+///
+/// 每次添加生成一个唯一的点（执行者, 序号），删除只移除本副本已观察到的点；
+/// 并发的添加与删除合并后元素仍然存在。已删除的点由 `clock` 记录，无需保留墓碑。
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrSet {
+    #[serde(with = "pairs")]
+    entries: BTreeMap<Bytes, BTreeSet<(String, u64)>>,
+    /// 每个执行者已生成的点的最大序号
+    clock: BTreeMap<String, u64>,
+}
+
+impl OrSet {
+    pub fn add(&mut self, actor: &str, element: Bytes) {
+        let counter = self.clock.entry(actor.to_string()).or_default();
+        *counter += 1;
+        let dot = (actor.to_string(), *counter);
+        self.entries.insert(element, BTreeSet::from([dot]));
+    }
+
+    pub fn remove(&mut self, element: &[u8]) {
+        self.entries.remove(element);
+    }
+
+    pub fn contains(&self, element: &[u8]) -> bool {
+        self.entries.contains_key(element)
+    }
+
+    /// 按字节序排列的全部元素
+    pub fn elements(&self) -> Vec<Bytes> {
+        self.entries.keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn merge(&mut self, other: &OrSet) {
+        let seen = |clock: &BTreeMap<String, u64>, (actor, counter): &(String, u64)| {
+            clock.get(actor).is_some_and(|c| c >= counter)
+        };
+        let elements: BTreeSet<Bytes> = self.entries.keys().chain(other.entries.keys()).cloned().collect();
+        let empty = BTreeSet::new();
+        let mut entries = BTreeMap::new();
+        for element in elements {
+            let ours = self.entries.get(&element).unwrap_or(&empty);
+            let theirs = other.entries.get(&element).unwrap_or(&empty);
+            // 双方都有的点保留；只在一方的点，若另一方未观察到则是新的添加，否则已被删除
+            let dots: BTreeSet<(String, u64)> = ours
+                .iter()
+                .filter(|dot| theirs.contains(*dot) || !seen(&other.clock, dot))
+                .chain(theirs.iter().filter(|dot| !ours.contains(*dot) && !seen(&self.clock, dot)))
+                .cloned()
+                .collect();
+            if !dots.is_empty() {
+                entries.insert(element, dots);
+            }
+        }
+        self.entries = entries;
+        for (actor, counter) in &other.clock {
+            let entry = self.clock.entry(actor.clone()).or_default();
+            *entry = (*entry).max(*counter);
+        }
+    }
+}
+
+/// 最后写入者胜出寄存器，时间戳相同时按执行者 id 决定
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LwwRegister {
+    value: Option<Bytes>,
+    timestamp: u64,
+    actor: String,
+}
+
+impl LwwRegister {
+    /// `value` 为 `None` 表示删除
+    pub fn set(&mut self, value: Option<Bytes>, timestamp: u64, actor: &str) {
+        if (timestamp, actor) > (self.timestamp, self.actor.as_str()) {
+            self.value = value;
+            self.timestamp = timestamp;
+            self.actor = actor.to_string();
+        }
+    }
+
+    pub fn value(&self) -> Option<&Bytes> {
+        self.value.as_ref()
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    pub fn merge(&mut self, other: &LwwRegister) {
+        self.set(other.value.clone(), other.timestamp, &other.actor);
+    }
+}
+
+/// 每个字段都是 [`LwwRegister`] 的映射，删除的字段保留为墓碑
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LwwMap {
+    #[serde(with = "pairs")]
+    entries: BTreeMap<Bytes, LwwRegister>,
+}
+
+impl LwwMap {
+    pub fn put(&mut self, field: Bytes, value: Bytes, timestamp: u64, actor: &str) {
+        self.entries.entry(field).or_default().set(Some(value), timestamp, actor);
+    }
+
+    pub fn remove(&mut self, field: Bytes, timestamp: u64, actor: &str) {
+        self.entries.entry(field).or_default().set(None, timestamp, actor);
+    }
+
+    pub fn get(&self, field: &[u8]) -> Option<&Bytes> {
+        self.entries.get(field).and_then(|r| r.value())
+    }
+
+    /// 未删除的字段，按字节序排列
+    pub fn entries(&self) -> Vec<(Bytes, Bytes)> {
+        self.entries
+            .iter()
+            .filter_map(|(field, register)| Some((field.clone(), register.value()?.clone())))
+            .collect()
+    }
+
+    pub fn merge(&mut self, other: &LwwMap) {
+        for (field, register) in &other.entries {
+            self.entries.entry(field.clone()).or_default().merge(register);
+        }
+    }
+}
+
+/// 无冲突复制数据类型的状态
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Crdt {
+    GCounter(GCounter),
+    PnCounter(PnCounter),
+    OrSet(OrSet),
+    LwwRegister(LwwRegister),
+    LwwMap(LwwMap),
+}
+
+/// 对 CRDT 的一次更新，key 不存在时按操作创建对应类型
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrdtOp {
+    /// G-Counter 加上 `delta`
+    GCounterIncrement(u64),
+    /// PN-Counter 加上 `delta`，可以为负
+    PnCounterIncrement(i64),
+    SetAdd(Bytes),
+    SetRemove(Bytes),
+    RegisterSet(Bytes),
+    MapPut { field: Bytes, value: Bytes },
+    MapRemove(Bytes),
+}
+
+impl CrdtOp {
+    /// 操作对应类型的空状态
+    fn empty(&self) -> Crdt {
+        match self {
+            CrdtOp::GCounterIncrement(_) => Crdt::GCounter(GCounter::default()),
+            CrdtOp::PnCounterIncrement(_) => Crdt::PnCounter(PnCounter::default()),
+            CrdtOp::SetAdd(_) | CrdtOp::SetRemove(_) => Crdt::OrSet(OrSet::default()),
+            CrdtOp::RegisterSet(_) => Crdt::LwwRegister(LwwRegister::default()),
+            CrdtOp::MapPut { .. } | CrdtOp::MapRemove(_) => Crdt::LwwMap(LwwMap::default()),
+        }
+    }
+}
+
+impl Crdt {
+    /// 在 `state`（不存在时为空状态）上以 `actor` 的身份执行 `op`
+    ///
+    /// `timestamp` 用于最后写入者胜出的类型，通常取自协调者的混合逻辑时钟。
+    pub fn apply(state: Option<Crdt>, actor: &str, op: CrdtOp, timestamp: u64) -> Result<Crdt> {
+        let mut state = state.unwrap_or_else(|| op.empty());
+        match (&mut state, op) {
+            (Crdt::GCounter(c), CrdtOp::GCounterIncrement(delta)) => c.increment(actor, delta),
+            (Crdt::PnCounter(c), CrdtOp::PnCounterIncrement(delta)) => c.increment(actor, delta),
+            (Crdt::OrSet(s), CrdtOp::SetAdd(element)) => s.add(actor, element),
+            (Crdt::OrSet(s), CrdtOp::SetRemove(element)) => s.remove(&element),
+            (Crdt::LwwRegister(r), CrdtOp::RegisterSet(value)) => r.set(Some(value), timestamp, actor),
+            (Crdt::LwwMap(m), CrdtOp::MapPut { field, value }) => m.put(field, value, timestamp, actor),
+            (Crdt::LwwMap(m), CrdtOp::MapRemove(field)) => m.remove(field, timestamp, actor),
+            (state, op) => return Err(mismatch(state, &op)),
+        }
+        Ok(state)
+    }
+
+    /// 合并另一个副本的状态，两者类型必须相同
+    pub fn merge(&mut self, other: &Crdt) -> Result<()> {
+        match (self, other) {
+            (Crdt::GCounter(a), Crdt::GCounter(b)) => a.merge(b),
+            (Crdt::PnCounter(a), Crdt::PnCounter(b)) => a.merge(b),
+            (Crdt::OrSet(a), Crdt::OrSet(b)) => a.merge(b),
+            (Crdt::LwwRegister(a), Crdt::LwwRegister(b)) => a.merge(b),
+            (Crdt::LwwMap(a), Crdt::LwwMap(b)) => a.merge(b),
+            (a, b) => {
+                return Err(Error::Consistency(format!(
+                    "CRDT 类型不一致，无法合并: {} 与 {}",
+                    a.kind(),
+                    b.kind()
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Crdt::GCounter(_) => "GCounter",
+            Crdt::PnCounter(_) => "PnCounter",
+            Crdt::OrSet(_) => "OrSet",
+            Crdt::LwwRegister(_) => "LwwRegister",
+            Crdt::LwwMap(_) => "LwwMap",
+        }
+    }
+
+    /// 带 [`CRDT_TAG`] 前缀的存储格式
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        Ok([CRDT_TAG, &serde_json::to_vec(self)?].concat())
+    }
+
+    /// 解析存储中的值，不是 CRDT 时返回 `None`
+    pub fn decode(data: &[u8]) -> Option<Crdt> {
+        serde_json::from_slice(data.strip_prefix(CRDT_TAG)?).ok()
+    }
+}
+
+fn mismatch(state: &Crdt, op: &CrdtOp) -> Error {
+    Error::Consistency(format!("CRDT 类型 {} 不支持操作 {:?}", state.kind(), op))
+}
+
+/// 以 `(key, value)` 列表编码键为字节串的映射，JSON 对象的键只能是字符串
+mod pairs {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<K, V, S>(map: &BTreeMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Ord,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Ok(Vec::<(K, V)>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
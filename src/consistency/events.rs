@@ -0,0 +1,154 @@
+use super::ConsistencyEvent;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::Notify;
+
+/// 默认每个订阅者最多缓冲的事件数
+pub const DEFAULT_EVENT_BUFFER: usize = 1024;
+
+/// 订阅一致性事件时的过滤条件，按 key 前缀匹配
+///
+/// 没有前缀时匹配全部事件；[`ConsistencyEvent::Lagged`] 总是发给订阅者。
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+    prefixes: Vec<Bytes>,
+}
+
+impl EventFilter {
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// 只接收 key 以 `prefix` 开头的事件
+    pub fn prefix(prefix: impl AsRef<[u8]>) -> Self {
+        Self::all().with_prefix(prefix)
+    }
+
+    /// 追加一个前缀，匹配任意一个前缀即可
+    pub fn with_prefix(mut self, prefix: impl AsRef<[u8]>) -> Self {
+        self.prefixes.push(Bytes::copy_from_slice(prefix.as_ref()));
+        self
+    }
+
+    pub fn matches(&self, event: &ConsistencyEvent) -> bool {
+        match event.key() {
+            Some(key) => self.prefixes.is_empty() || self.prefixes.iter().any(|p| key.starts_with(p)),
+            None => true,
+        }
+    }
+}
+
+/// 一致性层的事件分发
+///
+/// 发布时按各订阅者的过滤条件分发到订阅者自己的有界队列中，不匹配的事件不占用队列。
+/// 订阅者落后超过容量时丢弃其队列中最早的事件，订阅者随后收到一个 [`ConsistencyEvent::Lagged`]，
+/// 说明丢失了多少符合其过滤条件的事件。发布事件从不阻塞写入路径。
+#[derive(Clone)]
+pub struct EventBus {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    capacity: usize,
+    subscribers: Mutex<Vec<Weak<Subscriber>>>,
+}
+
+struct Subscriber {
+    filter: EventFilter,
+    queue: Mutex<Queue>,
+    notify: Notify,
+}
+
+#[derive(Default)]
+struct Queue {
+    events: VecDeque<ConsistencyEvent>,
+    missed: u64,
+    closed: bool,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                capacity: capacity.max(1),
+                subscribers: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// 发布事件，没有订阅者时直接丢弃
+    pub fn publish(&self, event: ConsistencyEvent) {
+        let mut subscribers = self.shared.subscribers.lock().unwrap();
+        // 顺便清理已经丢弃的订阅
+        subscribers.retain(|subscriber| {
+            let Some(subscriber) = subscriber.upgrade() else {
+                return false;
+            };
+            if subscriber.filter.matches(&event) {
+                let mut queue = subscriber.queue.lock().unwrap();
+                if queue.events.len() >= self.shared.capacity {
+                    queue.events.pop_front();
+                    queue.missed += 1;
+                }
+                queue.events.push_back(event.clone());
+                drop(queue);
+                subscriber.notify.notify_one();
+            }
+            true
+        });
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        let subscribers = self.shared.subscribers.lock().unwrap();
+        subscribers.iter().filter(|s| s.strong_count() > 0).count()
+    }
+
+    /// 订阅之后发布的、符合 `filter` 的事件
+    pub fn subscribe(&self, filter: EventFilter) -> BoxStream<'static, ConsistencyEvent> {
+        let subscriber = Arc::new(Subscriber {
+            filter,
+            queue: Mutex::new(Queue::default()),
+            notify: Notify::new(),
+        });
+        self.shared.subscribers.lock().unwrap().push(Arc::downgrade(&subscriber));
+        Box::pin(futures::stream::unfold(subscriber, |subscriber| async move {
+            loop {
+                {
+                    let mut queue = subscriber.queue.lock().unwrap();
+                    if queue.missed > 0 {
+                        let missed = std::mem::take(&mut queue.missed);
+                        drop(queue);
+                        tracing::warn!("一致性事件订阅者落后，丢失了 {} 个事件", missed);
+                        return Some((ConsistencyEvent::Lagged { missed }, subscriber));
+                    }
+                    if let Some(event) = queue.events.pop_front() {
+                        drop(queue);
+                        return Some((event, subscriber));
+                    }
+                    if queue.closed {
+                        return None;
+                    }
+                }
+                subscriber.notify.notified().await;
+            }
+        }))
+    }
+}
+
+impl Drop for Shared {
+    /// 所有发布端都已丢弃，订阅者取完剩余事件后结束
+    fn drop(&mut self) {
+        for subscriber in self.subscribers.get_mut().unwrap().iter().filter_map(Weak::upgrade) {
+            subscriber.queue.lock().unwrap().closed = true;
+            subscriber.notify.notify_one();
+        }
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_BUFFER)
+    }
+}
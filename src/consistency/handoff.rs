@@ -0,0 +1,317 @@
+use super::replica::{ReplicaClient, ReplicaOp};
+use crate::config::HintedHandoffConfig;
+use crate::error::Error;
+use crate::membership::{MembershipEvent, MembershipManager, NodeState};
+use crate::storage::StorageEngine;
+use crate::utils::HybridClock;
+use crate::Result;
+use bytes::Bytes;
+use dashmap::DashSet;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// 存储引擎中保存提示的命名空间（key 前缀）
+pub const HINT_PREFIX: &[u8] = b"__hints/";
+
+/// 暂存在替代节点上、等待回放给原副本的写入
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Hint {
+    /// 原本应接收写入的副本
+    pub target: String,
+    pub op: ReplicaOp,
+    /// 创建时间（毫秒），取自混合逻辑时钟的物理部分
+    pub created_at: u64,
+}
+
+impl Hint {
+    fn is_expired(&self, now_ms: u64, ttl: Duration) -> bool {
+        now_ms.saturating_sub(self.created_at) >= ttl.as_millis() as u64
+    }
+}
+
+/// 某个节点上的提示存储，数量有上限，过期的提示会被清理
+pub struct HintStore {
+    storage: Arc<dyn StorageEngine>,
+    max_hints: usize,
+    ttl: Duration,
+    count: AtomicUsize,
+    seq: AtomicU64,
+    clock: Arc<HybridClock>,
+}
+
+impl HintStore {
+    pub async fn open(storage: Arc<dyn StorageEngine>, config: &HintedHandoffConfig) -> Result<Self> {
+        let store = Self {
+            storage,
+            max_hints: config.max_hints,
+            ttl: Duration::from_secs(config.hint_ttl_secs),
+            count: AtomicUsize::new(0),
+            seq: AtomicU64::new(0),
+            clock: Arc::new(HybridClock::default()),
+        };
+        let existing = store.scan(HINT_PREFIX).await?.len();
+        store.count.store(existing, Ordering::Relaxed);
+        Ok(store)
+    }
+
+    /// 提示的创建时间和过期判断使用的时钟，默认为独立的时钟
+    pub fn with_clock(mut self, clock: Arc<HybridClock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 为 `target` 暂存一个写入，已满时先清理过期提示，仍然满则拒绝
+    pub async fn add(&self, target: &str, op: ReplicaOp) -> Result<()> {
+        if self.len() >= self.max_hints {
+            self.purge_expired().await?;
+            if self.len() >= self.max_hints {
+                return Err(Error::Storage(format!("提示数量已达上限 {}", self.max_hints)));
+            }
+        }
+        let hint = Hint {
+            target: target.to_string(),
+            op,
+            created_at: self.clock.now().physical_ms(),
+        };
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let key = [
+            &prefix_for(target)[..],
+            format!("{:016x}-{:016x}", hint.created_at, seq).as_bytes(),
+        ]
+        .concat();
+        self.storage.put(&key, &serde_json::to_vec(&hint)?).await?;
+        self.count.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// 有待回放提示的全部目标节点
+    pub async fn targets(&self) -> Result<BTreeSet<String>> {
+        Ok(self.scan(HINT_PREFIX).await?.into_iter().map(|(_, hint)| hint.target).collect())
+    }
+
+    /// `target` 的全部提示，按创建顺序排列
+    pub async fn hints_for(&self, target: &str) -> Result<Vec<(Bytes, Hint)>> {
+        self.scan(&prefix_for(target)).await
+    }
+
+    pub async fn remove(&self, key: &[u8]) -> Result<()> {
+        if self.storage.get(key).await?.is_some() {
+            self.storage.delete(key).await?;
+            let _ = self
+                .count
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |c| Some(c.saturating_sub(1)));
+        }
+        Ok(())
+    }
+
+    /// 删除过期的提示，返回删除的数量
+    pub async fn purge_expired(&self) -> Result<usize> {
+        let mut purged = 0;
+        let now = self.clock.now().physical_ms();
+        for (key, hint) in self.scan(HINT_PREFIX).await? {
+            if hint.is_expired(now, self.ttl) {
+                self.remove(&key).await?;
+                purged += 1;
+            }
+        }
+        Ok(purged)
+    }
+
+    fn is_expired(&self, hint: &Hint) -> bool {
+        hint.is_expired(self.clock.now().physical_ms(), self.ttl)
+    }
+
+    async fn scan(&self, prefix: &[u8]) -> Result<Vec<(Bytes, Hint)>> {
+        let mut end = prefix.to_vec();
+        end.push(0xff);
+        let mut items = self.storage.scan(prefix, Some(&end), None).await?;
+        let mut hints = Vec::new();
+        while let Some(kv) = items.next().await {
+            let kv = kv?;
+            hints.push((kv.key, serde_json::from_slice(&kv.value)?));
+        }
+        Ok(hints)
+    }
+}
+
+fn prefix_for(target: &str) -> Vec<u8> {
+    [HINT_PREFIX, target.as_bytes(), b"/"].concat()
+}
+
+/// 根据成员事件维护的宕机节点集合
+pub struct NodeHealth {
+    down: Arc<DashSet<String>>,
+    task: JoinHandle<()>,
+}
+
+impl NodeHealth {
+    pub async fn start(membership: Arc<dyn MembershipManager>) -> Result<Self> {
+        // 先订阅再读取当前成员，避免两者之间的事件丢失
+        let mut events = membership.watch_nodes().await?;
+        let down = Arc::new(DashSet::new());
+        for node in membership.get_nodes().await? {
+            if node.state == NodeState::Down {
+                down.insert(node.id);
+            }
+        }
+        let task = {
+            let down = down.clone();
+            tokio::spawn(async move {
+                while let Some(event) = events.next().await {
+                    match event {
+                        Ok(MembershipEvent::NodeStateChanged { id, state }) => {
+                            if state == NodeState::Down {
+                                down.insert(id);
+                            } else {
+                                down.remove(&id);
+                            }
+                        }
+                        Ok(MembershipEvent::NodeLeft(id)) => {
+                            down.remove(&id);
+                        }
+                        Ok(MembershipEvent::NodeJoined(_)) => {}
+                        Err(e) => tracing::warn!("成员事件错误: {}", e),
+                    }
+                }
+            })
+        };
+        Ok(Self { down, task })
+    }
+
+    pub fn is_down(&self, node_id: &str) -> bool {
+        self.down.contains(node_id)
+    }
+}
+
+impl Drop for NodeHealth {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// 原副本恢复为 Active 后把本节点暂存的提示回放给它
+///
+/// 回放失败（例如原副本的副本服务尚未就绪）的提示不会等到下一次状态变化，
+/// 每隔 `retry_interval` 向所有未宕机的目标重新回放一次。
+pub struct HandoffService {
+    hints: Arc<HintStore>,
+    client: Arc<ReplicaClient>,
+    timeout: Duration,
+    task: JoinHandle<()>,
+}
+
+impl HandoffService {
+    pub async fn start(
+        hints: Arc<HintStore>,
+        client: Arc<ReplicaClient>,
+        membership: Arc<dyn MembershipManager>,
+        timeout: Duration,
+        retry_interval: Duration,
+    ) -> Result<Self> {
+        let mut events = membership.watch_nodes().await?;
+        let task = {
+            let hints = hints.clone();
+            let client = client.clone();
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(retry_interval);
+                ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                ticker.tick().await;
+                loop {
+                    tokio::select! {
+                        event = events.next() => match event {
+                            Some(Ok(MembershipEvent::NodeStateChanged {
+                                id,
+                                state: NodeState::Active,
+                            })) => replay_logged(&hints, &client, &id, timeout).await,
+                            Some(_) => {}
+                            None => break,
+                        },
+                        _ = ticker.tick() => {
+                            if let Err(e) = retry_pending(&hints, &client, membership.as_ref(), timeout).await {
+                                tracing::warn!("重试回放提示失败: {}", e);
+                            }
+                        }
+                    }
+                }
+            })
+        };
+        Ok(Self {
+            hints,
+            client,
+            timeout,
+            task,
+        })
+    }
+
+    /// 立即把 `target` 的提示回放给它，返回成功回放的数量
+    pub async fn replay(&self, target: &str) -> Result<usize> {
+        replay(&self.hints, &self.client, target, self.timeout).await
+    }
+}
+
+impl Drop for HandoffService {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn replay_logged(hints: &HintStore, client: &ReplicaClient, target: &str, timeout: Duration) {
+    match replay(hints, client, target, timeout).await {
+        Ok(n) if n > 0 => tracing::info!("已向 {} 回放 {} 条提示", target, n),
+        Ok(_) => {}
+        Err(e) => tracing::warn!("向 {} 回放提示失败: {}", target, e),
+    }
+}
+
+/// 向每个仍有提示且未宕机的目标回放
+async fn retry_pending(
+    hints: &HintStore,
+    client: &ReplicaClient,
+    membership: &dyn MembershipManager,
+    timeout: Duration,
+) -> Result<()> {
+    if hints.is_empty() {
+        return Ok(());
+    }
+    let down: BTreeSet<String> = membership
+        .get_nodes()
+        .await?
+        .into_iter()
+        .filter(|node| node.state == NodeState::Down)
+        .map(|node| node.id)
+        .collect();
+    for target in hints.targets().await? {
+        if !down.contains(&target) {
+            replay_logged(hints, client, &target, timeout).await;
+        }
+    }
+    Ok(())
+}
+
+/// 按创建顺序回放，遇到失败即停止以保持顺序；过期的提示直接丢弃
+async fn replay(hints: &HintStore, client: &ReplicaClient, target: &str, timeout: Duration) -> Result<usize> {
+    let mut replayed = 0;
+    for (key, hint) in hints.hints_for(target).await? {
+        if hints.is_expired(&hint) {
+            hints.remove(&key).await?;
+            continue;
+        }
+        client.call(target, hint.op, timeout).await?;
+        hints.remove(&key).await?;
+        replayed += 1;
+    }
+    Ok(replayed)
+}
@@ -0,0 +1,480 @@
+//! 记录并发客户端的操作历史，检查其是否满足一致性保证
+//!
+//! 风格与 Jepsen/Knossos 相同：每个操作记录发起和完成两个时间点，结束后离线检查。
+//! [`History::check_linearizable`] 用于 `Strong` 模式，[`History::check_causal`] 检查因果一致及会话保证。
+//! 检查假定同一个 key 上写入的值互不相同，以便确定每次读取读到的是哪次写入。
+
+use super::{ConsistencyManager, SessionToken};
+use crate::Result;
+use bytes::Bytes;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// 客户端对 key 发起的操作
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Call {
+    Get,
+    Put(Bytes),
+    Delete,
+}
+
+impl Call {
+    /// 写入后的值，读取时为 `None`
+    fn written(&self) -> Option<Option<&Bytes>> {
+        match self {
+            Call::Get => None,
+            Call::Put(value) => Some(Some(value)),
+            Call::Delete => Some(None),
+        }
+    }
+}
+
+/// 操作的结果
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// 成功，读取时为读到的值，写入时为 `None`
+    Ok(Option<Bytes>),
+    /// 失败、超时或尚未完成：写入可能已生效也可能没有，读取的结果不参与检查
+    Unknown,
+}
+
+/// 历史中的一个操作
+#[derive(Clone, Debug)]
+pub struct Operation {
+    pub id: usize,
+    pub client: String,
+    pub key: Bytes,
+    pub call: Call,
+    /// 发起时间，自历史创建起的纳秒数
+    pub invoked_at: u64,
+    /// 完成时间，未完成时为 `None`
+    pub completed_at: Option<u64>,
+    pub outcome: Outcome,
+}
+
+impl Operation {
+    fn is_write(&self) -> bool {
+        self.call.written().is_some()
+    }
+
+    /// 成功读取到的值
+    fn read(&self) -> Option<&Option<Bytes>> {
+        match (&self.call, &self.outcome) {
+            (Call::Get, Outcome::Ok(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn succeeded(&self) -> bool {
+        matches!(self.outcome, Outcome::Ok(_))
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |v: Option<&Bytes>| match v {
+            Some(v) => String::from_utf8_lossy(v).into_owned(),
+            None => "nil".to_string(),
+        };
+        let call = match &self.call {
+            Call::Get => "get".to_string(),
+            Call::Put(value) => format!("put {}", show(Some(value))),
+            Call::Delete => "delete".to_string(),
+        };
+        let outcome = match (&self.call, &self.outcome) {
+            (_, Outcome::Unknown) => "?".to_string(),
+            (Call::Get, Outcome::Ok(value)) => show(value.as_ref()),
+            (_, Outcome::Ok(_)) => "ok".to_string(),
+        };
+        let completed = self.completed_at.map_or("-".to_string(), |t| t.to_string());
+        write!(f, "#{} {} {} -> {} [{}, {}]", self.id, self.client, call, outcome, self.invoked_at, completed)
+    }
+}
+
+/// 检查发现的违反一致性保证的情况
+#[derive(Clone, Debug)]
+pub struct Anomaly {
+    pub key: Bytes,
+    pub description: String,
+    /// 相关的操作
+    pub operations: Vec<Operation>,
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}):", self.description, String::from_utf8_lossy(&self.key))?;
+        for op in &self.operations {
+            write!(f, "\n  {}", op)?;
+        }
+        Ok(())
+    }
+}
+
+struct Log {
+    operations: Vec<Operation>,
+    /// 上一个时间点，保证时间点严格递增
+    last: u64,
+}
+
+/// 并发客户端共享的操作历史
+#[derive(Clone)]
+pub struct History {
+    start: Instant,
+    log: Arc<Mutex<Log>>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            log: Arc::new(Mutex::new(Log {
+                operations: Vec::new(),
+                last: 0,
+            })),
+        }
+    }
+
+    fn tick(&self, log: &mut Log) -> u64 {
+        log.last = (self.start.elapsed().as_nanos() as u64).max(log.last + 1);
+        log.last
+    }
+
+    /// 记录操作的发起，返回操作 id
+    pub fn invoke(&self, client: &str, key: &[u8], call: Call) -> usize {
+        let mut log = self.log.lock().unwrap();
+        let invoked_at = self.tick(&mut log);
+        let id = log.operations.len();
+        log.operations.push(Operation {
+            id,
+            client: client.to_string(),
+            key: Bytes::copy_from_slice(key),
+            call,
+            invoked_at,
+            completed_at: None,
+            outcome: Outcome::Unknown,
+        });
+        id
+    }
+
+    /// 记录操作的完成
+    pub fn complete(&self, id: usize, outcome: Outcome) {
+        let mut log = self.log.lock().unwrap();
+        let completed_at = self.tick(&mut log);
+        let op = &mut log.operations[id];
+        op.completed_at = Some(completed_at);
+        op.outcome = outcome;
+    }
+
+    pub fn operations(&self) -> Vec<Operation> {
+        self.log.lock().unwrap().operations.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.log.lock().unwrap().operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 通过 `manager` 发起请求并记录到本历史的客户端
+    pub fn client(&self, name: &str, manager: Arc<dyn ConsistencyManager>) -> HistoryClient {
+        HistoryClient {
+            history: self.clone(),
+            name: name.to_string(),
+            manager,
+            session: None,
+        }
+    }
+
+    /// 携带会话令牌发起因果一致请求的客户端
+    pub fn causal_client(&self, name: &str, manager: Arc<dyn ConsistencyManager>) -> HistoryClient {
+        HistoryClient {
+            session: Some(Mutex::new(SessionToken::new())),
+            ..self.client(name, manager)
+        }
+    }
+
+    /// 按 key 分别检查历史能否线性化，返回无法线性化的 key
+    ///
+    /// 结果未知的写入可以在发起之后的任意时间生效，也可以从未生效。
+    pub fn check_linearizable(&self) -> Vec<Anomaly> {
+        let mut by_key: HashMap<Bytes, Vec<Operation>> = HashMap::new();
+        for op in self.operations() {
+            if op.succeeded() || op.is_write() {
+                by_key.entry(op.key.clone()).or_default().push(op);
+            }
+        }
+        let mut anomalies: Vec<Anomaly> = by_key
+            .into_iter()
+            .filter(|(_, ops)| !Linearization::new(ops).search())
+            .map(|(key, operations)| Anomaly {
+                key,
+                description: "操作历史无法线性化".to_string(),
+                operations,
+            })
+            .collect();
+        anomalies.sort_by(|a, b| a.key.cmp(&b.key));
+        anomalies
+    }
+
+    /// 检查每次读取是否读到了因果上最新的写入
+    ///
+    /// 因果顺序由同一客户端的操作顺序以及读取与其读到的写入之间的关系组成，
+    /// 因此同时覆盖读己之写、单调读、单调写和读后写等会话保证。
+    pub fn check_causal(&self) -> Vec<Anomaly> {
+        CausalGraph::new(self.operations()).check()
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 把请求记录到 [`History`] 的客户端，同一客户端的请求应依次发起
+pub struct HistoryClient {
+    history: History,
+    name: String,
+    manager: Arc<dyn ConsistencyManager>,
+    session: Option<Mutex<SessionToken>>,
+}
+
+impl HistoryClient {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 改为通过 `manager` 发起之后的请求，会话令牌保持不变（例如客户端切换到另一个协调节点）
+    pub fn with_manager(mut self, manager: Arc<dyn ConsistencyManager>) -> Self {
+        self.manager = manager;
+        self
+    }
+
+    pub async fn get(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        let id = self.history.invoke(&self.name, key, Call::Get);
+        let result = match self.token() {
+            Some(token) => self.manager.get_causal(key, &token).await.map(|(value, token)| {
+                self.observe(token);
+                value
+            }),
+            None => self.manager.get(key).await,
+        };
+        self.finish(id, result.as_ref().ok().cloned());
+        result
+    }
+
+    pub async fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let id = self.history.invoke(&self.name, key, Call::Put(Bytes::copy_from_slice(value)));
+        let result = match self.token() {
+            Some(token) => self.manager.put_causal(key, value, &token).await.map(|token| self.observe(token)),
+            None => self.manager.put(key, value).await,
+        };
+        self.finish(id, result.as_ref().ok().map(|_| None));
+        result
+    }
+
+    pub async fn delete(&self, key: &[u8]) -> Result<()> {
+        let id = self.history.invoke(&self.name, key, Call::Delete);
+        let result = match self.token() {
+            Some(token) => self.manager.delete_causal(key, &token).await.map(|token| self.observe(token)),
+            None => self.manager.delete(key).await,
+        };
+        self.finish(id, result.as_ref().ok().map(|_| None));
+        result
+    }
+
+    fn token(&self) -> Option<SessionToken> {
+        self.session.as_ref().map(|s| s.lock().unwrap().clone())
+    }
+
+    fn observe(&self, token: SessionToken) {
+        if let Some(session) = &self.session {
+            session.lock().unwrap().merge(&token);
+        }
+    }
+
+    fn finish(&self, id: usize, value: Option<Option<Bytes>>) {
+        let outcome = value.map_or(Outcome::Unknown, Outcome::Ok);
+        self.history.complete(id, outcome);
+    }
+}
+
+/// 单个 key 上的线性化搜索（Wing & Gong 算法，按已线性化的操作集合和寄存器值记忆化）
+struct Linearization<'a> {
+    ops: &'a [Operation],
+    /// 结果未知的写入视为在无穷远处完成
+    completed: Vec<u64>,
+    seen: HashSet<(Vec<u64>, Option<Bytes>)>,
+}
+
+impl<'a> Linearization<'a> {
+    fn new(ops: &'a [Operation]) -> Self {
+        let completed = ops
+            .iter()
+            .map(|op| match op.outcome {
+                Outcome::Ok(_) => op.completed_at.unwrap_or(u64::MAX),
+                Outcome::Unknown => u64::MAX,
+            })
+            .collect();
+        Self {
+            ops,
+            completed,
+            seen: HashSet::new(),
+        }
+    }
+
+    fn search(&mut self) -> bool {
+        let required = self.ops.iter().filter(|op| op.succeeded()).count();
+        let mut done = vec![0u64; self.ops.len().div_ceil(64)];
+        self.step(&mut done, None, required)
+    }
+
+    fn step(&mut self, done: &mut Vec<u64>, state: Option<Bytes>, remaining: usize) -> bool {
+        if remaining == 0 {
+            return true;
+        }
+        if !self.seen.insert((done.clone(), state.clone())) {
+            return false;
+        }
+        let is_done = |done: &[u64], i: usize| done[i / 64] & (1 << (i % 64)) != 0;
+        // 只有在所有未线性化操作中最早完成者之前发起的操作才能排在下一个
+        let horizon = (0..self.ops.len())
+            .filter(|&i| !is_done(done, i))
+            .map(|i| self.completed[i])
+            .min()
+            .unwrap_or(u64::MAX);
+        for i in 0..self.ops.len() {
+            let op = &self.ops[i];
+            if is_done(done, i) || op.invoked_at > horizon {
+                continue;
+            }
+            let next = match (op.call.written(), op.read()) {
+                (Some(written), _) => written.cloned(),
+                (None, Some(read)) if *read == state => state.clone(),
+                _ => continue,
+            };
+            done[i / 64] |= 1 << (i % 64);
+            if self.step(done, next, remaining - usize::from(op.succeeded())) {
+                return true;
+            }
+            done[i / 64] &= !(1 << (i % 64));
+        }
+        false
+    }
+}
+
+/// 操作之间的因果关系图
+struct CausalGraph {
+    ops: Vec<Operation>,
+    /// 每个操作的直接前驱
+    parents: Vec<Vec<usize>>,
+    ancestors: HashMap<usize, HashSet<usize>>,
+}
+
+impl CausalGraph {
+    fn new(operations: Vec<Operation>) -> Self {
+        // 结果未知的读取不参与检查；结果未知的写入只可能作为读取的来源
+        let ops: Vec<Operation> = operations
+            .into_iter()
+            .filter(|op| op.succeeded() || op.is_write())
+            .collect();
+        let mut parents = vec![Vec::new(); ops.len()];
+        let mut last: HashMap<&str, usize> = HashMap::new();
+        let mut order: Vec<usize> = (0..ops.len()).collect();
+        order.sort_by_key(|&i| ops[i].invoked_at);
+        for i in order.into_iter().filter(|&i| ops[i].succeeded()) {
+            if let Some(previous) = last.insert(&ops[i].client, i) {
+                parents[i].push(previous);
+            }
+        }
+        for (i, op) in ops.iter().enumerate() {
+            if let Some(Some(value)) = op.read() {
+                if let [source] = Self::writers(&ops, &op.key, value)[..] {
+                    parents[i].push(source);
+                }
+            }
+        }
+        Self {
+            ops,
+            parents,
+            ancestors: HashMap::new(),
+        }
+    }
+
+    fn writers(ops: &[Operation], key: &Bytes, value: &Bytes) -> Vec<usize> {
+        (0..ops.len())
+            .filter(|&i| ops[i].key == *key && ops[i].call == Call::Put(value.clone()))
+            .collect()
+    }
+
+    fn ancestors(&mut self, node: usize) -> &HashSet<usize> {
+        if !self.ancestors.contains_key(&node) {
+            let mut seen = HashSet::new();
+            let mut stack = self.parents[node].clone();
+            while let Some(i) = stack.pop() {
+                if seen.insert(i) {
+                    stack.extend(self.parents[i].iter().copied());
+                }
+            }
+            self.ancestors.insert(node, seen);
+        }
+        &self.ancestors[&node]
+    }
+
+    /// `a` 是否因果上先于 `b`
+    fn precedes(&mut self, a: usize, b: usize) -> bool {
+        self.ancestors(b).contains(&a)
+    }
+
+    fn check(mut self) -> Vec<Anomaly> {
+        let mut anomalies = Vec::new();
+        for r in 0..self.ops.len() {
+            let Some(read) = self.ops[r].read().cloned() else {
+                continue;
+            };
+            let key = self.ops[r].key.clone();
+            let ancestors: Vec<usize> = self.ancestors(r).iter().copied().collect();
+            let past: Vec<usize> = ancestors
+                .into_iter()
+                .filter(|&w| self.ops[w].key == key && self.ops[w].is_write())
+                .collect();
+            let anomaly = match read {
+                Some(value) => match Self::writers(&self.ops, &key, &value).as_slice() {
+                    [] => Some(("读到了从未写入的值", vec![r])),
+                    &[source] if self.precedes(r, source) => Some(("读到了因果上之后的写入", vec![source, r])),
+                    &[source] => past
+                        .iter()
+                        .find(|&&w| w != source && self.precedes(source, w))
+                        .map(|&w| ("读到的值在因果上已被覆盖", vec![source, w, r])),
+                    // 同一个值被多次写入时无法确定来源，不检查
+                    _ => None,
+                },
+                None => {
+                    let puts: Vec<usize> = past.iter().copied().filter(|&w| matches!(self.ops[w].call, Call::Put(_))).collect();
+                    let deletes: Vec<usize> = (0..self.ops.len())
+                        .filter(|&d| self.ops[d].key == key && self.ops[d].call == Call::Delete)
+                        .collect();
+                    let visible = deletes
+                        .into_iter()
+                        .any(|d| !self.precedes(r, d) && !past.iter().any(|&w| w != d && self.precedes(d, w)));
+                    match puts.first() {
+                        Some(&put) if !visible => Some(("读到空值，但因果上更早的写入未被删除", vec![put, r])),
+                        _ => None,
+                    }
+                }
+            };
+            if let Some((description, ops)) = anomaly {
+                anomalies.push(Anomaly {
+                    key,
+                    description: description.to_string(),
+                    operations: ops.into_iter().map(|i| self.ops[i].clone()).collect(),
+                });
+            }
+        }
+        anomalies
+    }
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// 单次请求的一致性级别，覆盖 `[replication]` 中配置的读写法定数
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ConsistencyLevel {
+    /// 任意一个副本确认
+    One,
+    /// 多数副本（`factor / 2 + 1`）确认
+    Quorum,
+    /// 全部 `factor` 个副本确认
+    All,
+    /// 与协调节点同一可用区的副本中多数确认；协调节点未声明可用区时等同于 `Quorum`
+    LocalQuorum,
+}
+
+impl ConsistencyLevel {
+    /// 在 `replicas` 个副本中需要的确认数
+    pub fn required(self, replicas: usize) -> usize {
+        match self {
+            ConsistencyLevel::One => 1,
+            ConsistencyLevel::Quorum | ConsistencyLevel::LocalQuorum => replicas / 2 + 1,
+            ConsistencyLevel::All => replicas,
+        }
+    }
+}
+
+/// 请求结果及返回时已确认的副本数
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Acknowledged<T> {
+    pub value: T,
+    /// 协调者返回结果时已成功响应的副本数，不含达到级别后仍在后台进行的请求
+    pub acknowledged: usize,
+}
+
+impl<T> Acknowledged<T> {
+    pub fn new(value: T, acknowledged: usize) -> Self {
+        Self { value, acknowledged }
+    }
+}
@@ -0,0 +1,160 @@
+use super::raft::is_internal;
+use super::vclock::Version;
+use crate::distribution::{HashFunction, PartitionRange};
+use crate::storage::StorageEngine;
+use crate::Result;
+use bytes::Bytes;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Merkle 树的最大深度，叶子数为 `2^depth`
+pub const MAX_TREE_DEPTH: u32 = 16;
+
+/// 一个分区内数据的 Merkle 树
+///
+/// key 按哈希值的低 `depth` 位分到叶子，叶子的哈希覆盖其中全部 key 及值的摘要，
+/// 内部节点为两个子节点哈希的哈希。两个副本的根相同即认为该分区数据一致。
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleTree {
+    depth: u32,
+    /// 第 0 层为根，最后一层为叶子
+    levels: Vec<Vec<u64>>,
+}
+
+impl MerkleTree {
+    /// 由按 key 排序的 (key, 摘要) 构建
+    pub fn build<'a>(depth: u32, entries: impl IntoIterator<Item = (&'a [u8], u64)>) -> Self {
+        let depth = depth.min(MAX_TREE_DEPTH);
+        let mut leaves: Vec<Vec<u8>> = vec![Vec::new(); 1 << depth];
+        for (key, digest) in entries {
+            let leaf = &mut leaves[leaf_of(key, depth)];
+            leaf.extend_from_slice(&(key.len() as u64).to_le_bytes());
+            leaf.extend_from_slice(key);
+            leaf.extend_from_slice(&digest.to_le_bytes());
+        }
+        let mut level: Vec<u64> = leaves
+            .iter()
+            .map(|leaf| if leaf.is_empty() { 0 } else { hash(leaf) })
+            .collect();
+        let mut levels = vec![level.clone()];
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match (pair[0], pair[1]) {
+                    (0, 0) => 0,
+                    (left, right) => hash(&[left.to_le_bytes(), right.to_le_bytes()].concat()),
+                })
+                .collect();
+            levels.push(level.clone());
+        }
+        levels.reverse();
+        Self { depth, levels }
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    pub fn root(&self) -> u64 {
+        self.levels[0][0]
+    }
+
+    /// 与另一棵树哈希不同的叶子下标，只沿不同的子树向下比较
+    ///
+    /// 深度不同时无法逐层比较，返回全部叶子。
+    pub fn diff(&self, other: &MerkleTree) -> Vec<usize> {
+        if self.depth != other.depth {
+            return (0..1 << self.depth).collect();
+        }
+        if self.root() == other.root() {
+            return Vec::new();
+        }
+        let mut differing = vec![0];
+        for level in 1..self.levels.len() {
+            let (ours, theirs) = (&self.levels[level], &other.levels[level]);
+            differing = differing
+                .into_iter()
+                .flat_map(|i| [2 * i, 2 * i + 1])
+                .filter(|&i| ours[i] != theirs[i])
+                .collect();
+        }
+        differing
+    }
+}
+
+/// key 所在的叶子
+///
+/// 取哈希的低位：同一个哈希分区内的 key 高位基本相同，低位才能均匀分散。
+pub fn leaf_of(key: &[u8], depth: u32) -> usize {
+    (hash(key) & ((1u64 << depth) - 1)) as usize
+}
+
+/// 存储值的摘要
+///
+/// 版本列表与顺序无关：各副本合并版本的顺序可能不同，但包含的版本相同时摘要相同。
+pub fn digest(value: &[u8]) -> u64 {
+    match serde_json::from_slice::<Vec<Version>>(value) {
+        Ok(versions) => versions.iter().fold(0u64, |acc, version| {
+            let encoded = serde_json::to_vec(version).unwrap_or_default();
+            acc.wrapping_add(hash(&encoded))
+        }),
+        Err(_) => hash(value),
+    }
+}
+
+/// 本地存储中落在 `range` 内、且位于 `leaves`（`None` 为全部）中的 key 及其摘要，按 key 排序
+///
+/// 提示移交和 Raft 的内部数据不属于任何分区，不参与比较。
+pub async fn range_digests(
+    storage: &dyn StorageEngine,
+    range: &PartitionRange,
+    depth: u32,
+    leaves: Option<&[usize]>,
+) -> Result<Vec<(Bytes, u64)>> {
+    let depth = depth.min(MAX_TREE_DEPTH);
+    let leaves: Option<HashSet<usize>> = leaves.map(|leaves| leaves.iter().copied().collect());
+    let mut digests = bucket_digests(storage, &[range]).await?.pop().unwrap_or_default();
+    if let Some(leaves) = leaves {
+        digests.retain(|(key, _)| leaves.contains(&leaf_of(key, depth)));
+    }
+    Ok(digests)
+}
+
+/// 只扫描一遍本地存储，把 key 及其摘要分到各自所在的分区，结果与 `ranges` 一一对应、各自按 key 排序
+///
+/// 分区互不重叠；都是 key 范围时按范围扫描，否则扫描全部数据后按哈希分配。
+pub async fn bucket_digests(storage: &dyn StorageEngine, ranges: &[&PartitionRange]) -> Result<Vec<Vec<(Bytes, u64)>>> {
+    let mut buckets = vec![Vec::new(); ranges.len()];
+    let bounds = match ranges {
+        [PartitionRange::Key(range)] => (range.start.clone(), range.end.clone()),
+        _ => (Bytes::new(), None),
+    };
+    let mut items = storage.scan(&bounds.0, bounds.1.as_deref(), None).await?;
+    while let Some(kv) = items.next().await {
+        let kv = kv?;
+        if is_internal(&kv.key) {
+            continue;
+        }
+        if let Some(idx) = ranges.iter().position(|range| range.contains(&kv.key)) {
+            let digest = digest(&kv.value);
+            buckets[idx].push((kv.key, digest));
+        }
+    }
+    Ok(buckets)
+}
+
+/// 本地存储中 `range` 的 Merkle 树
+pub async fn range_tree(storage: &dyn StorageEngine, range: &PartitionRange, depth: u32) -> Result<MerkleTree> {
+    let digests = range_digests(storage, range, depth, None).await?;
+    Ok(tree_of(&digests, depth))
+}
+
+/// 由按 key 排序的摘要构建 Merkle 树
+pub fn tree_of(digests: &[(Bytes, u64)], depth: u32) -> MerkleTree {
+    MerkleTree::build(depth, digests.iter().map(|(key, digest)| (&key[..], *digest)))
+}
+
+fn hash(data: &[u8]) -> u64 {
+    HashFunction::default().hash(data)
+}
@@ -1,14 +1,74 @@
 
+mod anti_entropy;
+mod crdt;
+mod events;
+mod handoff;
+pub mod history;
+mod level;
+mod merkle;
+mod quorum;
+mod raft;
+mod repair;
+mod replica;
+mod resolver;
+mod retry;
+mod session;
+mod stamped;
+mod strong;
+mod vclock;
+
 use async_trait::async_trait;
 use bytes::Bytes;
+use crate::error::Error;
 use crate::Result;
 
+pub use anti_entropy::{AntiEntropyService, AntiEntropyStats};
+pub use crdt::{Crdt, CrdtOp, GCounter, LwwMap, LwwRegister, OrSet, PnCounter, CRDT_TAG};
+pub use events::{EventBus, EventFilter, DEFAULT_EVENT_BUFFER};
+pub use handoff::{HandoffService, Hint, HintStore, NodeHealth, HINT_PREFIX};
+pub use level::{Acknowledged, ConsistencyLevel};
+pub use merkle::{MerkleTree, MAX_TREE_DEPTH};
+pub use quorum::{QuorumConsistencyManager, DEFAULT_MAX_DEPENDENCIES, DEFAULT_REPLICA_TIMEOUT, DEFAULT_REQUEST_TIMEOUT};
+pub(crate) use raft::{check_user_key, is_internal};
+pub(crate) use replica::merge_stored;
+pub use raft::{
+    Command, LogEntry, RaftClient, RaftGroup, RaftNode, RaftRole, RaftStatus, RAFT_PREFIX,
+};
+pub use replica::{
+    replica_topic, ReplicaClient, ReplicaOp, ReplicaReply, ReplicaRequest, ReplicaResponse,
+    ReplicaServer,
+};
+pub use resolver::{
+    ConflictResolver, HighestVectorClock, LastWriterWins, MergeFn, ResolverRegistry,
+};
+pub use retry::RetryPolicy;
+pub use session::SessionToken;
+pub use stamped::{Stamped, STAMPED_TAG};
+pub use strong::StrongConsistencyManager;
+pub use vclock::{reconcile, CausalContext, Causality, Siblings, VectorClock, Version};
+
 /// 一致性管理事件
 #[derive(Debug, Clone)]
 pub enum ConsistencyEvent {
+    /// 写入达到法定数
     WriteCommitted { key: Bytes, value: Bytes },
+    /// 读取时发现互相并发的版本
     WriteConflict { key: Bytes, old: Bytes, new: Bytes },
     ReadRepair { key: Bytes, repaired_value: Bytes },
+    /// 订阅者处理过慢，之前有 `missed` 个事件被丢弃
+    Lagged { missed: u64 },
+}
+
+impl ConsistencyEvent {
+    /// 事件涉及的 key
+    pub fn key(&self) -> Option<&Bytes> {
+        match self {
+            ConsistencyEvent::WriteCommitted { key, .. }
+            | ConsistencyEvent::WriteConflict { key, .. }
+            | ConsistencyEvent::ReadRepair { key, .. } => Some(key),
+            ConsistencyEvent::Lagged { .. } => None,
+        }
+    }
 }
 
 /// 一致性管理 trait
@@ -23,6 +83,68 @@ pub trait ConsistencyManager: Send + Sync + 'static {
     /// 删除数据
     async fn delete(&self, key: &[u8]) -> Result<()>;
 
+    /// 按请求指定的一致性级别写入，返回确认的副本数
+    ///
+    /// 不区分级别的实现（如单机或由 Raft 提交的写入）忽略 `level`，确认数记为 1。
+    async fn put_with_level(&self, key: &[u8], value: &[u8], _level: ConsistencyLevel) -> Result<Acknowledged<()>> {
+        self.put(key, value).await?;
+        Ok(Acknowledged::new((), 1))
+    }
+
+    async fn get_with_level(&self, key: &[u8], _level: ConsistencyLevel) -> Result<Acknowledged<Option<Bytes>>> {
+        Ok(Acknowledged::new(self.get(key).await?, 1))
+    }
+
+    async fn delete_with_level(&self, key: &[u8], _level: ConsistencyLevel) -> Result<Acknowledged<()>> {
+        self.delete(key).await?;
+        Ok(Acknowledged::new((), 1))
+    }
+
+    /// 读取全部并发版本（siblings）及其因果上下文
+    ///
+    /// 不支持版本的实现最多返回一个值，上下文为空。
+    async fn get_versioned(&self, key: &[u8]) -> Result<Siblings> {
+        Ok(Siblings {
+            values: self.get(key).await?.into_iter().collect(),
+            context: CausalContext::default(),
+        })
+    }
+
+    /// 携带读取时得到的因果上下文写入，新值覆盖上下文中的全部版本
+    async fn put_with_context(&self, key: &[u8], value: &[u8], _context: &CausalContext) -> Result<()> {
+        self.put(key, value).await
+    }
+
+    /// 因果一致读取：等会话令牌中的依赖都已应用后读取，返回值和更新后的令牌
+    ///
+    /// 不支持因果一致的实现直接读取，令牌原样返回。
+    async fn get_causal(&self, key: &[u8], token: &SessionToken) -> Result<(Option<Bytes>, SessionToken)> {
+        Ok((self.get(key).await?, token.clone()))
+    }
+
+    /// 因果一致写入，返回记录了本次写入的令牌
+    async fn put_causal(&self, key: &[u8], value: &[u8], token: &SessionToken) -> Result<SessionToken> {
+        self.put(key, value).await?;
+        Ok(token.clone())
+    }
+
+    async fn delete_causal(&self, key: &[u8], token: &SessionToken) -> Result<SessionToken> {
+        self.delete(key).await?;
+        Ok(token.clone())
+    }
+
+    /// 读取 CRDT 状态，各副本的状态合并后返回
+    ///
+    /// 不支持 CRDT 的实现返回错误。
+    async fn get_crdt(&self, _key: &[u8]) -> Result<Option<Crdt>> {
+        Err(Error::Consistency("当前一致性模式不支持 CRDT".to_string()))
+    }
+
+    /// 对 CRDT 执行一次更新，返回更新后的状态
+    async fn update_crdt(&self, _key: &[u8], _op: CrdtOp) -> Result<Crdt> {
+        Err(Error::Consistency("当前一致性模式不支持 CRDT".to_string()))
+    }
+
     /// 触发读修复
     async fn read_repair(&self, key: &[u8]) -> Result<()>;
 
@@ -30,6 +152,16 @@ pub trait ConsistencyManager: Send + Sync + 'static {
     async fn watch_events(
         &self,
     ) -> Result<futures::stream::BoxStream<'static, ConsistencyEvent>>;
+
+    /// 只监听符合 `filter` 的事件
+    async fn subscribe_events(
+        &self,
+        filter: EventFilter,
+    ) -> Result<futures::stream::BoxStream<'static, ConsistencyEvent>> {
+        use futures::StreamExt;
+        let events = self.watch_events().await?;
+        Ok(Box::pin(events.filter(move |event| futures::future::ready(filter.matches(event)))))
+    }
 }
 
 /// 一个简单的占位实现（可用于测试）
@@ -0,0 +1,876 @@
+use super::handoff::NodeHealth;
+use super::level::{Acknowledged, ConsistencyLevel};
+use super::crdt::{Crdt, CrdtOp};
+use super::events::{EventBus, EventFilter};
+use super::repair::{PendingCalls, ReadRepairer, Resolved};
+use super::replica::{ReplicaClient, ReplicaOp, ReplicaReply};
+use super::resolver::ResolverRegistry;
+use super::retry::RetryPolicy;
+use super::session::SessionToken;
+use super::stamped::Stamped;
+use super::vclock::{reconcile, CausalContext, Siblings, VectorClock, Version};
+use super::{check_user_key, ConsistencyEvent, ConsistencyManager};
+use crate::config::{HotKeyMitigation, ReadRepairMode, ReplicationConfig};
+use crate::distribution::{HotKeyCache, HotKeyDetector, LoadTracker, SharedRing};
+use crate::error::Error;
+use crate::messaging::MessageBroker;
+use crate::utils::HybridClock;
+use crate::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::StreamExt;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+
+/// 副本请求的默认超时
+pub const DEFAULT_REPLICA_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 一次读写的默认截止时间
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 因果写入默认最多随新版本保存的依赖数
+pub const DEFAULT_MAX_DEPENDENCIES: usize = 32;
+
+/// 法定数个成功响应（附带副本 id）以及其余仍在进行的请求
+struct Responses {
+    replies: Vec<(String, ReplicaReply)>,
+    /// 计入一致性级别的确认数
+    acknowledged: usize,
+    pending: PendingCalls,
+}
+
+/// Dynamo 风格的法定副本数（quorum）协调者
+///
+/// 按分布环选出 `factor` 个副本，通过消息层并发发送请求：
+/// 写入在 `write_quorum` 个副本确认后返回，读取在收到 `read_quorum` 个响应后返回，
+/// 否则返回 [`Error::QuorumNotMet`]。达到法定数后其余副本的请求在后台继续完成。
+/// 未启用向量时钟时读取返回时间戳最晚的值，并写回时间戳较早的副本（读修复）。
+///
+/// 启用向量时钟后每次写入都生成一个新版本，副本保留互相并发的版本，
+/// 读取时合并各副本的版本并通过 [`ConsistencyManager::get_versioned`] 返回全部 siblings；
+/// key 所在命名空间配置了冲突解决策略时只返回解决后的一个值。
+/// 读取后把最终版本写回落后的副本（读修复）。写入达到法定数、读取发现并发版本以及读修复时
+/// 通过 [`ConsistencyManager::subscribe_events`] 发出事件。
+///
+/// 设置 [`NodeHealth`] 后使用宽松法定数（sloppy quorum）：读取跳过宕机的副本；
+/// 写入时宕机副本的请求改发给偏好列表中后续的健康节点，由其暂存为提示（hint），
+/// 原副本恢复后回放，提示写入的确认同样计入写法定数，但不计入 [`ConsistencyLevel::All`]。
+///
+/// CRDT 的更新由 key 的第一个可用副本以自己的 id 作为执行者执行，更新后的状态再合并到各副本，
+/// 写入法定数个副本确认后返回；读取合并法定数个副本的状态，并把合并结果写回状态不同的副本。
+///
+/// 带 [`ConsistencyLevel`] 的请求按级别而不是配置的 R/W 决定需要的确认数；
+/// `LocalQuorum` 只统计与协调节点同一可用区的副本（提示按其目标副本统计）。
+///
+/// 每个副本请求最多等待副本超时，失败后按 [`RetryPolicy`] 重试；整个请求在截止时间内
+/// 未凑齐确认时返回 [`Error::Timeout`]，所有副本请求都已结束仍不足时返回 [`Error::QuorumNotMet`]。
+/// 启用对冲读取后读取先只发给需要数量的副本，在对冲延迟内未凑齐或有副本失败时再向下一个副本发送。
+///
+/// 设置 [`HotKeyDetector`] 后每个请求都经过检测器：缓解方式为 [`HotKeyMitigation::SpreadReads`] 时，
+/// 热点 key 的读取只发给需要数量的副本，起始副本由 [`HotKeyDetector::read_replica`] 轮流选择；
+/// 为 [`HotKeyMitigation::Cache`] 时 [`ConsistencyManager::get`] 在本节点缓存热点 key 的值，
+/// 经本协调者的写入使缓存失效。
+pub struct QuorumConsistencyManager {
+    ring: Arc<SharedRing>,
+    client: Arc<ReplicaClient>,
+    replication: ReplicationConfig,
+    timeout: Duration,
+    request_timeout: Duration,
+    retry: RetryPolicy,
+    hedge_after: Option<Duration>,
+    hedged: AtomicU64,
+    vector_clocks: bool,
+    resolvers: ResolverRegistry,
+    read_repair: ReadRepairMode,
+    repairer: ReadRepairer,
+    health: Option<Arc<NodeHealth>>,
+    hot_keys: Option<Arc<HotKeyDetector>>,
+    hot_cache: Option<HotKeyCache>,
+    load: Option<Arc<LoadTracker>>,
+    clock: Arc<HybridClock>,
+    events: EventBus,
+    /// 本协调者在向量时钟中的计数，以启动时的微秒时间为起点，重启后仍然递增
+    counter: AtomicU64,
+    max_dependencies: usize,
+}
+
+impl QuorumConsistencyManager {
+    pub async fn start(
+        node_id: impl Into<String>,
+        ring: Arc<SharedRing>,
+        broker: Arc<dyn MessageBroker>,
+        replication: ReplicationConfig,
+    ) -> Result<Self> {
+        let valid = |quorum: usize| quorum >= 1 && quorum <= replication.factor;
+        if !valid(replication.read_quorum) || !valid(replication.write_quorum) {
+            return Err(Error::Configuration(format!(
+                "读写法定数必须在 1 到副本数 {} 之间: R={}, W={}",
+                replication.factor, replication.read_quorum, replication.write_quorum
+            )));
+        }
+        let client = Arc::new(ReplicaClient::start(node_id, broker).await?);
+        let events = EventBus::default();
+        let requests = &replication.requests;
+        let timeout = Duration::from_millis(requests.replica_timeout_ms);
+        Ok(Self {
+            ring,
+            repairer: ReadRepairer::new(client.clone(), timeout, events.clone()),
+            events,
+            read_repair: ReadRepairMode::default(),
+            client,
+            timeout,
+            request_timeout: Duration::from_millis(requests.request_timeout_ms),
+            retry: RetryPolicy::from_config(&requests.retry),
+            hedge_after: (requests.hedge_after_ms > 0).then(|| Duration::from_millis(requests.hedge_after_ms)),
+            hedged: AtomicU64::new(0),
+            replication,
+            vector_clocks: false,
+            resolvers: ResolverRegistry::new(),
+            health: None,
+            hot_keys: None,
+            hot_cache: None,
+            load: None,
+            clock: Arc::new(HybridClock::default()),
+            counter: AtomicU64::new(now_micros()),
+            max_dependencies: DEFAULT_MAX_DEPENDENCIES,
+        })
+    }
+
+    /// 是否为每次写入附加向量时钟（对应 `ConsistencyConfig::vector_clock_enabled`）
+    pub fn with_vector_clocks(mut self, enabled: bool) -> Self {
+        self.vector_clocks = enabled;
+        self
+    }
+
+    /// 因果写入随新版本保存的依赖数上限（对应 `ConsistencyConfig::max_causal_dependencies`）
+    pub fn with_max_dependencies(mut self, max: usize) -> Self {
+        self.max_dependencies = max;
+        self
+    }
+
+    /// 并发版本的冲突解决策略
+    pub fn with_resolvers(mut self, resolvers: ResolverRegistry) -> Self {
+        self.resolvers = resolvers;
+        self
+    }
+
+    /// 读修复方式
+    pub fn with_read_repair(mut self, mode: ReadRepairMode) -> Self {
+        self.read_repair = mode;
+        self
+    }
+
+    /// 节点健康状态，用于宽松法定数和提示移交
+    pub fn with_health(mut self, health: Arc<NodeHealth>) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// 热点 key 检测器（对应 `DistributionConfig::hot_keys`），按其配置的缓解方式处理热点 key
+    pub fn with_hot_keys(mut self, detector: Arc<HotKeyDetector>) -> Self {
+        let config = detector.config();
+        self.hot_cache = (config.mitigation == HotKeyMitigation::Cache)
+            .then(|| HotKeyCache::new(Duration::from_millis(config.cache_ttl_ms), config.capacity));
+        self.hot_keys = Some(detector);
+        self
+    }
+
+    /// 按分区统计本协调者处理的请求（对应 `DistributionConfig::load_aware`）
+    pub fn with_load_tracker(mut self, tracker: Arc<LoadTracker>) -> Self {
+        self.load = Some(tracker);
+        self
+    }
+
+    /// 为写入（包括墓碑）生成最后写入者胜出时间戳的时钟，通常与消息层共用
+    pub fn with_clock(mut self, clock: Arc<HybridClock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// 每个事件订阅者最多缓冲的事件数（对应 `ConsistencyConfig::event_buffer`）
+    pub fn with_event_buffer(mut self, capacity: usize) -> Self {
+        self.events = EventBus::new(capacity);
+        self.repairer = self.repairer.with_events(self.events.clone());
+        self
+    }
+
+    /// 设置单个副本请求的超时
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self.repairer = self.repairer.with_timeout(timeout);
+        self
+    }
+
+    /// 设置一次读写（包括重试和对冲）的截止时间
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// 副本请求失败后的重试策略
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// 读取在 `after` 内未凑齐确认时向额外的副本发送请求，`None` 表示不对冲
+    pub fn with_hedging(mut self, after: Option<Duration>) -> Self {
+        self.hedge_after = after;
+        self
+    }
+
+    /// 因响应慢而额外发出的读取请求数
+    pub fn hedged_reads(&self) -> u64 {
+        self.hedged.load(Ordering::Relaxed)
+    }
+
+    /// 每个目标节点及发给它的请求
+    ///
+    /// 没有健康信息时即 key 的全部副本；否则跳过宕机的副本，
+    /// 写入时（且启用提示移交）改为向偏好列表中后续的健康节点发送提示。
+    fn targets(&self, key: &[u8], op: &ReplicaOp, write: bool) -> Vec<(String, ReplicaOp)> {
+        let ring = self.ring.snapshot();
+        let replicas = ring.get_replicas(key, self.replication.factor);
+        let Some(health) = &self.health else {
+            return replicas.into_iter().map(|r| (r.id, op.clone())).collect();
+        };
+        let mut spares = ring
+            .get_replicas(key, ring.strategy.all_nodes().len())
+            .into_iter()
+            .filter(|n| !replicas.iter().any(|r| r.id == n.id) && !health.is_down(&n.id));
+        let handoff = write && self.replication.hinted_handoff.enabled;
+        let mut targets = Vec::with_capacity(replicas.len());
+        for replica in &replicas {
+            if !health.is_down(&replica.id) {
+                targets.push((replica.id.clone(), op.clone()));
+            } else if let Some(spare) = spares.next().filter(|_| handoff) {
+                let hint = ReplicaOp::PutHint {
+                    target: replica.id.clone(),
+                    op: Box::new(op.clone()),
+                };
+                targets.push((spare.id, hint));
+            }
+        }
+        targets
+    }
+
+    /// 需要的确认数，以及只统计其确认的副本（`None` 表示全部副本都计入）
+    fn requirement(&self, key: &[u8], level: Option<ConsistencyLevel>, write: bool) -> (usize, Option<HashSet<String>>) {
+        let factor = self.replication.factor;
+        let level = match level {
+            Some(level) => level,
+            None if write => return (self.replication.write_quorum, None),
+            None => return (self.replication.read_quorum, None),
+        };
+        if level != ConsistencyLevel::LocalQuorum {
+            return (level.required(factor), None);
+        }
+        let ring = self.ring.snapshot();
+        let nodes = ring.strategy.all_nodes();
+        let node_id = self.client.node_id();
+        let Some(zone) = nodes.iter().find(|n| n.id == node_id).and_then(|n| n.zone.clone()) else {
+            return (level.required(factor), None);
+        };
+        let local: HashSet<String> = ring
+            .get_replicas(key, factor)
+            .into_iter()
+            .filter(|r| r.zone.as_ref() == Some(&zone))
+            .map(|r| r.id)
+            .collect();
+        (level.required(local.len()), Some(local))
+    }
+
+    /// 向 key 的所有副本发送 `op`，收到一致性级别要求的成功响应即返回
+    ///
+    /// `level` 为 `None` 时按配置的读写法定数。
+    async fn fan_out(&self, key: &[u8], op: ReplicaOp, level: Option<ConsistencyLevel>) -> Result<Responses> {
+        self.fan_out_until(key, op, level, Instant::now() + self.request_timeout)
+            .await
+    }
+
+    /// 同 [`Self::fan_out`]，在给定的截止时间前完成
+    async fn fan_out_until(
+        &self,
+        key: &[u8],
+        op: ReplicaOp,
+        level: Option<ConsistencyLevel>,
+        deadline: Instant,
+    ) -> Result<Responses> {
+        check_user_key(key)?;
+        let write = !matches!(
+            op,
+            ReplicaOp::Get { .. }
+                | ReplicaOp::GetVersions { .. }
+                | ReplicaOp::GetVersionsAfter { .. }
+                | ReplicaOp::GetCrdt { .. }
+        );
+        let hot = self.hot_keys.as_ref().is_some_and(|detector| detector.record(key));
+        if let Some(tracker) = &self.load {
+            tracker.record(key);
+        }
+        // 按 key 范围分区时范围按读写负载分裂
+        if write {
+            self.ring.record_write(key, written_bytes(&op));
+        } else {
+            self.ring.record_read(key);
+        }
+        if write {
+            if let Some(cache) = &self.hot_cache {
+                cache.invalidate(key);
+            }
+        }
+        let (required, counted) = self.requirement(key, level, write);
+        // 本可用区没有副本时无法满足，不必发送请求
+        if counted.as_ref().is_some_and(|c| c.is_empty()) {
+            return Err(Error::QuorumNotMet {
+                required: 1,
+                acknowledged: 0,
+            });
+        }
+        let mut targets = self.targets(key, &op, write);
+        let spread = !write && hot && self.spread_reads(key, &mut targets);
+        // 提示按其目标副本判断是否计入；`All` 要求每个副本都真正写入，提示不计入
+        let all = level == Some(ConsistencyLevel::All);
+        let uncounted: HashSet<String> = targets
+            .iter()
+            .filter(|(replica, op)| {
+                let owner = match op {
+                    ReplicaOp::PutHint { .. } if all => return true,
+                    ReplicaOp::PutHint { target, .. } => target,
+                    _ => replica,
+                };
+                counted.as_ref().is_some_and(|c| !c.contains(owner))
+            })
+            .map(|(replica, _)| replica.clone())
+            .collect();
+        // 对冲读取和分散的热点读取先只发给需要数量的副本，其余按偏好顺序备用
+        let hedge_after = self.hedge_after.filter(|_| !write);
+        let mut spares = if (hedge_after.is_some() || spread) && targets.len() > required {
+            targets.split_off(required)
+        } else {
+            Vec::new()
+        };
+        spares.reverse();
+
+        let mut calls: PendingCalls = targets
+            .into_iter()
+            .map(|(replica, op)| self.spawn_call(replica, op, deadline))
+            .collect();
+        let expired = tokio::time::sleep_until(deadline);
+        let hedge = tokio::time::sleep_until(hedge_after.map_or(deadline, |after| Instant::now() + after));
+        tokio::pin!(expired, hedge);
+
+        let mut replies = Vec::with_capacity(required);
+        let mut acknowledged = 0;
+        loop {
+            // 按固定顺序检查各分支，模拟运行时结果只取决于种子
+            tokio::select! {
+                biased;
+                result = calls.next() => {
+                    let Some(result) = result else {
+                        break;
+                    };
+                    match result {
+                        Ok((replica, Ok(reply))) => {
+                            acknowledged += usize::from(!uncounted.contains(&replica));
+                            replies.push((replica, reply));
+                            if acknowledged >= required {
+                                return Ok(Responses {
+                                    replies,
+                                    acknowledged,
+                                    pending: calls,
+                                });
+                            }
+                            continue;
+                        }
+                        Ok((_, Err(e))) => tracing::debug!("副本请求失败: {}", e),
+                        Err(e) => tracing::debug!("副本请求任务异常: {}", e),
+                    }
+                    // 失败的副本立即由备用副本替代
+                    if let Some((replica, op)) = spares.pop() {
+                        calls.push(self.spawn_call(replica, op, deadline));
+                    }
+                }
+                _ = &mut hedge, if hedge_after.is_some() && !spares.is_empty() => {
+                    if let Some((replica, op)) = spares.pop() {
+                        tracing::debug!("副本响应慢，向 {} 发出对冲读取", replica);
+                        self.hedged.fetch_add(1, Ordering::Relaxed);
+                        calls.push(self.spawn_call(replica, op, deadline));
+                    }
+                    if let Some(after) = hedge_after {
+                        hedge.as_mut().reset(Instant::now() + after);
+                    }
+                }
+                _ = &mut expired => {
+                    return Err(Error::Timeout(format!(
+                        "{:?} 内只收到 {} 个确认，需要 {} 个",
+                        self.request_timeout, acknowledged, required
+                    )));
+                }
+            }
+        }
+        Err(Error::QuorumNotMet { required, acknowledged })
+    }
+
+    /// 缓解方式为分散读取时把热点 key 的读取目标轮转为从 [`HotKeyDetector::read_replica`]
+    /// 选出的副本开始，返回是否分散
+    fn spread_reads(&self, key: &[u8], targets: &mut [(String, ReplicaOp)]) -> bool {
+        let spreading = |detector: &&Arc<HotKeyDetector>| detector.config().mitigation == HotKeyMitigation::SpreadReads;
+        let Some(detector) = self.hot_keys.as_ref().filter(spreading) else {
+            return false;
+        };
+        let replicas = self.ring.snapshot().get_replicas(key, self.replication.factor);
+        let first = detector.read_replica(key, &replicas);
+        if let Some(idx) = first.and_then(|first| targets.iter().position(|(replica, _)| *replica == first.id)) {
+            targets.rotate_left(idx);
+        }
+        true
+    }
+
+    /// 在单独的任务中执行副本请求，保证协调者提前返回后请求仍会完成
+    fn spawn_call(
+        &self,
+        replica: String,
+        op: ReplicaOp,
+        deadline: Instant,
+    ) -> tokio::task::JoinHandle<(String, Result<ReplicaReply>)> {
+        let client = self.client.clone();
+        let retry = self.retry.clone();
+        let timeout = self.timeout;
+        tokio::spawn(async move {
+            let result = retry.call(&client, &replica, op, timeout, deadline).await;
+            (replica, result)
+        })
+    }
+
+    /// 法定数读取并解决冲突，随后按配置进行读修复
+    ///
+    /// 给出 `after` 时副本等到其版本覆盖该时钟后才响应（因果一致读取）。
+    async fn read_versions(
+        &self,
+        key: &[u8],
+        after: Option<&VectorClock>,
+        repair: ReadRepairMode,
+        level: Option<ConsistencyLevel>,
+    ) -> Result<Acknowledged<Vec<Version>>> {
+        let key = Bytes::copy_from_slice(key);
+        let op = match after {
+            // 副本的等待时间短于请求超时，保证超时前能收到副本的结果
+            Some(after) => ReplicaOp::GetVersionsAfter {
+                key: key.clone(),
+                after: after.clone(),
+                wait_ms: (self.timeout * 3 / 4).as_millis() as u64,
+            },
+            None => ReplicaOp::GetVersions { key: key.clone() },
+        };
+        let responses = self.fan_out(&key, op, level).await?;
+        let acknowledged = responses.acknowledged;
+        let versions = responses.replies.iter().flat_map(|(_, reply)| match reply {
+            ReplicaReply::Versions(versions) => versions.clone(),
+            _ => Vec::new(),
+        });
+        let versions = reconcile(versions);
+        self.report_conflicts(&key, &versions);
+        let winners = self.resolvers.resolve(&key, versions);
+        self.repairer
+            .after_read(repair, key, Resolved::Versions(winners.clone()), responses.replies, responses.pending)
+            .await;
+        Ok(Acknowledged::new(winners, acknowledged))
+    }
+
+    /// 读取到互相并发的多个值时，以第一个值为基准为其余每个值发出冲突事件
+    fn report_conflicts(&self, key: &Bytes, versions: &[Version]) {
+        let mut values = versions.iter().filter_map(|v| v.value.as_ref());
+        let Some(old) = values.next() else {
+            return;
+        };
+        for new in values {
+            self.events.publish(ConsistencyEvent::WriteConflict {
+                key: key.clone(),
+                old: old.clone(),
+                new: new.clone(),
+            });
+        }
+    }
+
+    fn committed(&self, key: &[u8], value: Option<Bytes>) {
+        if let Some(value) = value {
+            self.events.publish(ConsistencyEvent::WriteCommitted {
+                key: Bytes::copy_from_slice(key),
+                value,
+            });
+        }
+    }
+
+    /// 在上下文的基础上递增本协调者的计数，写入新版本，返回新版本的时钟
+    async fn write_version(
+        &self,
+        key: &[u8],
+        value: Option<Bytes>,
+        context: &CausalContext,
+        deps: BTreeMap<Bytes, VectorClock>,
+        level: Option<ConsistencyLevel>,
+    ) -> Result<Acknowledged<VectorClock>> {
+        let mut clock = context.clock().clone();
+        let node_id = self.client.node_id();
+        let floor = clock.get(node_id);
+        let previous = self
+            .counter
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |c| Some(c.max(floor) + 1))
+            .unwrap_or_default();
+        clock.advance(node_id, previous.max(floor) + 1);
+
+        let op = ReplicaOp::PutVersion {
+            key: Bytes::copy_from_slice(key),
+            version: Version {
+                clock: clock.clone(),
+                value: value.clone(),
+                timestamp: self.clock.now().as_u64(),
+                deps,
+            },
+        };
+        let responses = self.fan_out(key, op, level).await?;
+        self.committed(key, value);
+        Ok(Acknowledged::new(clock, responses.acknowledged))
+    }
+
+    /// 携带会话依赖写入，新版本覆盖会话已观察到的该 key 的版本
+    ///
+    /// 新版本只保存尚未确认已应用的依赖；超过 `max_dependencies` 时，
+    /// 多出的依赖先写回足够多的副本使之已应用，不再随版本保存。
+    async fn write_causal(&self, key: &[u8], value: Option<Bytes>, token: &SessionToken) -> Result<SessionToken> {
+        let mut token = token.clone();
+        let mut deps = token.pending_dependencies_except(key);
+        while deps.len() > self.max_dependencies {
+            let Some((dep, clock)) = deps.pop_first() else {
+                break;
+            };
+            self.stabilize(&dep, &clock).await?;
+            token.mark_applied(&dep, &clock);
+        }
+        let context = CausalContext::new(token.dependency(key).cloned().unwrap_or_default());
+        let clock = self.write_version(key, value, &context, deps, None).await?.value;
+        token.observe(key, &clock);
+        if self.stable_level().is_none() {
+            token.mark_applied(key, &clock);
+        }
+        Ok(token)
+    }
+
+    /// 使写入对任何法定数读取可见所需的级别：读写法定数相交时为写法定数（`None`），否则为全部副本
+    fn stable_level(&self) -> Option<ConsistencyLevel> {
+        let replication = &self.replication;
+        (replication.read_quorum + replication.write_quorum <= replication.factor).then_some(ConsistencyLevel::All)
+    }
+
+    /// 读取 `key` 覆盖 `clock` 的版本，再把它们写回 [`Self::stable_level`] 要求的副本数
+    async fn stabilize(&self, key: &[u8], clock: &VectorClock) -> Result<()> {
+        let versions = self
+            .read_versions(key, Some(clock), ReadRepairMode::Disabled, None)
+            .await?
+            .value;
+        for version in versions {
+            let op = ReplicaOp::PutVersion {
+                key: Bytes::copy_from_slice(key),
+                version,
+            };
+            self.fan_out(key, op, self.stable_level()).await?;
+        }
+        Ok(())
+    }
+
+    /// 启用向量时钟时为不带上下文的写入，与其他写入并发时产生 siblings
+    async fn put_at(&self, key: &[u8], value: &[u8], level: Option<ConsistencyLevel>) -> Result<Acknowledged<()>> {
+        let value = Bytes::copy_from_slice(value);
+        if self.vector_clocks {
+            let written = self
+                .write_version(key, Some(value), &CausalContext::default(), BTreeMap::new(), level)
+                .await?;
+            return Ok(Acknowledged::new((), written.acknowledged));
+        }
+        let op = ReplicaOp::Put {
+            key: Bytes::copy_from_slice(key),
+            value: value.clone(),
+            timestamp: self.clock.now().as_u64(),
+        };
+        let responses = self.fan_out(key, op, level).await?;
+        self.committed(key, Some(value));
+        Ok(Acknowledged::new((), responses.acknowledged))
+    }
+
+    /// 返回各响应中时间戳最晚的值，最晚的是墓碑时返回 `None`
+    ///
+    /// 启用向量时钟时若存在多个并发版本，返回字节序最小的一个。
+    async fn get_at(&self, key: &[u8], level: Option<ConsistencyLevel>) -> Result<Acknowledged<Option<Bytes>>> {
+        if self.vector_clocks {
+            let read = self.read_versions(key, None, self.read_repair, level).await?;
+            let value = Siblings::from_versions(&read.value).values.into_iter().next();
+            return Ok(Acknowledged::new(value, read.acknowledged));
+        }
+        let read = self.read_stamped(key, self.read_repair, level).await?;
+        Ok(Acknowledged::new(read.value.value, read.acknowledged))
+    }
+
+    /// 法定数读取时间戳最晚的值，并把它写回被其覆盖的副本
+    async fn read_stamped(
+        &self,
+        key: &[u8],
+        repair: ReadRepairMode,
+        level: Option<ConsistencyLevel>,
+    ) -> Result<Acknowledged<Stamped>> {
+        let key = Bytes::copy_from_slice(key);
+        let op = ReplicaOp::Get { key: key.clone() };
+        let responses = self.fan_out(&key, op, level).await?;
+        let latest = responses
+            .replies
+            .iter()
+            .filter_map(|(_, reply)| match reply {
+                ReplicaReply::Value(stamped) => Some(stamped),
+                _ => None,
+            })
+            .fold(Stamped::default(), |latest, stamped| {
+                if stamped.supersedes(&latest) {
+                    stamped.clone()
+                } else {
+                    latest
+                }
+            });
+        self.repairer
+            .after_read(repair, key, Resolved::Stamped(latest.clone()), responses.replies, responses.pending)
+            .await;
+        Ok(Acknowledged::new(latest, responses.acknowledged))
+    }
+
+    /// 启用向量时钟时写入覆盖当前所有可见版本的墓碑，读取和写入都按同一级别
+    async fn delete_at(&self, key: &[u8], level: Option<ConsistencyLevel>) -> Result<Acknowledged<()>> {
+        if self.vector_clocks {
+            let read = self.read_versions(key, None, self.read_repair, level).await?;
+            let context = CausalContext::from_versions(&read.value);
+            let written = self.write_version(key, None, &context, BTreeMap::new(), level).await?;
+            return Ok(Acknowledged::new((), written.acknowledged));
+        }
+        let op = ReplicaOp::Delete {
+            key: Bytes::copy_from_slice(key),
+            timestamp: self.clock.now().as_u64(),
+        };
+        let responses = self.fan_out(key, op, level).await?;
+        Ok(Acknowledged::new((), responses.acknowledged))
+    }
+}
+
+fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64
+}
+
+#[async_trait]
+impl ConsistencyManager for QuorumConsistencyManager {
+    async fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.put_at(key, value, None).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        let (Some(detector), Some(cache)) = (&self.hot_keys, &self.hot_cache) else {
+            return Ok(self.get_at(key, None).await?.value);
+        };
+        if let Some(value) = cache.get(key) {
+            detector.record(key);
+            return Ok(value);
+        }
+        let value = self.get_at(key, None).await?.value;
+        if detector.is_hot(key) {
+            cache.insert(key, value.clone());
+        }
+        Ok(value)
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<()> {
+        self.delete_at(key, None).await?;
+        Ok(())
+    }
+
+    async fn put_with_level(&self, key: &[u8], value: &[u8], level: ConsistencyLevel) -> Result<Acknowledged<()>> {
+        self.put_at(key, value, Some(level)).await
+    }
+
+    async fn get_with_level(&self, key: &[u8], level: ConsistencyLevel) -> Result<Acknowledged<Option<Bytes>>> {
+        self.get_at(key, Some(level)).await
+    }
+
+    async fn delete_with_level(&self, key: &[u8], level: ConsistencyLevel) -> Result<Acknowledged<()>> {
+        self.delete_at(key, Some(level)).await
+    }
+
+    async fn get_versioned(&self, key: &[u8]) -> Result<Siblings> {
+        if !self.vector_clocks {
+            return Ok(Siblings {
+                values: self.get(key).await?.into_iter().collect(),
+                context: CausalContext::default(),
+            });
+        }
+        let versions = self.read_versions(key, None, self.read_repair, None).await?.value;
+        Ok(Siblings::from_versions(&versions))
+    }
+
+    async fn put_with_context(&self, key: &[u8], value: &[u8], context: &CausalContext) -> Result<()> {
+        if !self.vector_clocks {
+            return self.put(key, value).await;
+        }
+        self.write_version(key, Some(Bytes::copy_from_slice(value)), context, BTreeMap::new(), None)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_crdt(&self, key: &[u8]) -> Result<Option<Crdt>> {
+        let key = Bytes::copy_from_slice(key);
+        let op = ReplicaOp::GetCrdt { key: key.clone() };
+        let responses = self.fan_out(&key, op, None).await?;
+        let mut merged: Option<Crdt> = None;
+        for (_, reply) in &responses.replies {
+            let ReplicaReply::Crdt(Some(state)) = reply else {
+                continue;
+            };
+            match &mut merged {
+                Some(merged) => merged.merge(state)?,
+                None => merged = Some(state.clone()),
+            }
+        }
+        if let Some(merged) = &merged {
+            self.repairer
+                .after_read(
+                    self.read_repair,
+                    key,
+                    Resolved::Crdt(merged.clone()),
+                    responses.replies,
+                    responses.pending,
+                )
+                .await;
+        }
+        Ok(merged)
+    }
+
+    /// 依次尝试各副本执行更新，第一个成功的副本的结果再合并到全部副本
+    ///
+    /// 更新带有操作 id，同一副本按重试策略重试时不会重复执行。超时的更新可能已在该副本执行，
+    /// 换到下一个副本会以另一个执行者再计一次，因此只在请求未能发出时才尝试下一个副本，
+    /// 其余错误直接返回。整个更新（包括合并）在请求截止时间前完成，否则返回超时。
+    async fn update_crdt(&self, key: &[u8], op: CrdtOp) -> Result<Crdt> {
+        check_user_key(key)?;
+        let key = Bytes::copy_from_slice(key);
+        let update = ReplicaOp::UpdateCrdt {
+            key: key.clone(),
+            op,
+            timestamp: self.clock.now().as_u64(),
+            op_id: crate::utils::generate_id(),
+        };
+        let deadline = Instant::now() + self.request_timeout;
+        let mut state = None;
+        for (replica, update) in self.targets(&key, &update, false) {
+            let call = self.spawn_call(replica.clone(), update, deadline);
+            let result = match tokio::time::timeout_at(deadline, call).await {
+                Ok(Ok((_, result))) => result,
+                Ok(Err(e)) => Err(Error::Consistency(format!("副本请求任务异常: {}", e))),
+                Err(_) => {
+                    return Err(Error::Timeout(format!(
+                        "{:?} 内副本 {} 未完成 CRDT 更新",
+                        self.request_timeout, replica
+                    )))
+                }
+            };
+            match result {
+                Ok(ReplicaReply::Crdt(Some(updated))) => {
+                    state = Some(updated);
+                    break;
+                }
+                Ok(_) => tracing::debug!("副本 {} 返回了意外的 CRDT 响应", replica),
+                Err(e @ Error::Communication(_)) => tracing::debug!("无法向副本 {} 发送 CRDT 更新: {}", replica, e),
+                Err(e) => return Err(e),
+            }
+        }
+        let state = state.ok_or(Error::QuorumNotMet {
+            required: 1,
+            acknowledged: 0,
+        })?;
+        let merge = ReplicaOp::MergeCrdt {
+            key: key.clone(),
+            state: state.clone(),
+        };
+        self.fan_out_until(&key, merge, None, deadline).await?;
+        Ok(state)
+    }
+
+    /// 副本等到会话依赖都已应用后才响应；未启用向量时钟时退化为普通读取
+    async fn get_causal(&self, key: &[u8], token: &SessionToken) -> Result<(Option<Bytes>, SessionToken)> {
+        if !self.vector_clocks {
+            return Ok((self.get(key).await?, token.clone()));
+        }
+        let versions = self
+            .read_versions(key, token.dependency(key), self.read_repair, None)
+            .await?
+            .value;
+        let mut token = token.clone();
+        for version in &versions {
+            token.observe_version(key, version);
+        }
+        let value = Siblings::from_versions(&versions).values.into_iter().next();
+        Ok((value, token))
+    }
+
+    async fn put_causal(&self, key: &[u8], value: &[u8], token: &SessionToken) -> Result<SessionToken> {
+        if !self.vector_clocks {
+            self.put(key, value).await?;
+            return Ok(token.clone());
+        }
+        self.write_causal(key, Some(Bytes::copy_from_slice(value)), token).await
+    }
+
+    /// 墓碑覆盖会话读到的全部版本
+    async fn delete_causal(&self, key: &[u8], token: &SessionToken) -> Result<SessionToken> {
+        if !self.vector_clocks {
+            self.delete(key).await?;
+            return Ok(token.clone());
+        }
+        let (_, token) = self.get_causal(key, token).await?;
+        self.write_causal(key, None, &token).await
+    }
+
+    /// 读取 key 并同步修复已响应的副本
+    ///
+    /// 未启用向量时钟时按时间戳判断副本新旧。
+    async fn read_repair(&self, key: &[u8]) -> Result<()> {
+        if self.vector_clocks {
+            self.read_versions(key, None, ReadRepairMode::Sync, None).await?;
+        } else {
+            self.read_stamped(key, ReadRepairMode::Sync, None).await?;
+        }
+        Ok(())
+    }
+
+    async fn watch_events(
+        &self,
+    ) -> Result<futures::stream::BoxStream<'static, ConsistencyEvent>> {
+        Ok(self.events.subscribe(EventFilter::all()))
+    }
+
+    async fn subscribe_events(
+        &self,
+        filter: EventFilter,
+    ) -> Result<futures::stream::BoxStream<'static, ConsistencyEvent>> {
+        Ok(self.events.subscribe(filter))
+    }
+}
+
+/// 写入操作携带的数据量
+fn written_bytes(op: &ReplicaOp) -> usize {
+    match op {
+        ReplicaOp::Put { key, value, .. } => key.len() + value.len(),
+        ReplicaOp::PutVersion { key, version } => key.len() + version.value.as_ref().map_or(0, Bytes::len),
+        _ => 0,
+    }
+}
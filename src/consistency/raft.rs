@@ -0,0 +1,1308 @@
+use super::handoff::HINT_PREFIX;
+use crate::config::RaftConfig;
+use crate::distribution::{PartitionMap, PartitionRange};
+use crate::error::Error;
+use crate::messaging::MessageBroker;
+use crate::storage::StorageEngine;
+use crate::utils::SeededRng;
+use crate::Result;
+use bytes::Bytes;
+use dashmap::DashMap;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, watch};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// 存储引擎中保存 Raft 状态和日志的命名空间（key 前缀）
+pub const RAFT_PREFIX: &[u8] = b"__raft/";
+
+fn raft_topic(group: &str, node_id: &str) -> String {
+    format!("coretex.raft.{}.{}", group, node_id)
+}
+
+fn reply_topic(node_id: &str) -> String {
+    format!("coretex.raft.reply.{}.{}", node_id, crate::utils::generate_id())
+}
+
+/// 同一组副本负责的全部分区共用一个 Raft 组
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RaftGroup {
+    /// 由组内起始位置最小的分区得出，成员变化时不变
+    pub id: String,
+    /// 按 id 排序的成员
+    pub members: Vec<String>,
+    pub ranges: Vec<PartitionRange>,
+}
+
+impl RaftGroup {
+    /// 按副本集合划分分区映射中的分区，副本集合相同的分区属于同一组
+    ///
+    /// 组 id 取自组内第一个分区的起始位置：节点加入或离开后，仍从该位置开始的分区沿用原来的组
+    /// （及其日志），只是成员改变。各节点按交换后的同一份映射划分，得到相同的组。
+    pub fn from_map(map: &PartitionMap) -> Vec<RaftGroup> {
+        let mut groups: BTreeMap<Vec<String>, Vec<PartitionRange>> = BTreeMap::new();
+        for owned in &map.partitions {
+            let mut members = owned.replicas.clone();
+            members.sort();
+            groups.entry(members).or_default().push(owned.range.clone());
+        }
+        let mut groups: Vec<RaftGroup> = groups
+            .into_iter()
+            .map(|(members, mut ranges)| {
+                ranges.sort();
+                RaftGroup {
+                    id: group_id(map, &ranges[0]),
+                    members,
+                    ranges,
+                }
+            })
+            .collect();
+        groups.sort_by(|a, b| a.id.cmp(&b.id));
+        groups
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.ranges.iter().any(|range| range.contains(key))
+    }
+
+    /// 两组的分区是否有交集
+    pub fn overlaps(&self, other: &RaftGroup) -> bool {
+        self.ranges.iter().any(|a| {
+            other.ranges.iter().any(|b| match (a, b) {
+                (PartitionRange::Hash(a), PartitionRange::Hash(b)) => {
+                    a.end.is_none_or(|end| b.start < end) && b.end.is_none_or(|end| a.start < end)
+                }
+                (PartitionRange::Key(a), PartitionRange::Key(b)) => a.intersect(b).is_some(),
+                _ => false,
+            })
+        })
+    }
+
+    /// 本组的分区是否都落在 `ranges` 之内（允许跨越多个首尾相接的分区）
+    pub fn covered_by(&self, ranges: &[PartitionRange]) -> bool {
+        self.ranges.iter().all(|range| match range {
+            PartitionRange::Hash(range) => {
+                let mut at = range.start;
+                loop {
+                    let Some(end) = ranges.iter().find_map(|r| match r {
+                        PartitionRange::Hash(r) if r.contains_hash(at) => Some(r.end),
+                        _ => None,
+                    }) else {
+                        return false;
+                    };
+                    match end {
+                        Some(end) if range.end.is_none_or(|e| end < e) => at = end,
+                        _ => return true,
+                    }
+                }
+            }
+            PartitionRange::Key(range) => {
+                let mut at = range.start.clone();
+                loop {
+                    let Some(end) = ranges.iter().find_map(|r| match r {
+                        PartitionRange::Key(r) if r.contains(&at) => Some(r.end.clone()),
+                        _ => None,
+                    }) else {
+                        return false;
+                    };
+                    match end {
+                        Some(end) if range.end.as_ref().is_none_or(|e| end < *e) => at = end,
+                        _ => return true,
+                    }
+                }
+            }
+        })
+    }
+
+    fn quorum(&self) -> usize {
+        self.members.len() / 2 + 1
+    }
+}
+
+/// 分区的起始位置对应的组 id
+fn group_id(map: &PartitionMap, range: &PartitionRange) -> String {
+    match range {
+        PartitionRange::Hash(range) => format!("h{:016x}", range.start),
+        PartitionRange::Key(range) => format!("k{:016x}", map.hash_function.hash(&range.start)),
+    }
+}
+
+/// 复制到日志中的状态机命令
+///
+/// 读取不写入日志：leader 记下当前的提交位置，经一轮心跳确认自己仍是 leader，
+/// 应用到该位置后直接读取存储引擎（ReadIndex），同样是线性一致的。
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Command {
+    /// leader 当选后写入的空日志，用于提交之前任期的日志
+    Noop,
+    Put { key: Bytes, value: Bytes },
+    Delete { key: Bytes },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub term: u64,
+    pub index: u64,
+    pub command: Command,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RaftRole {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+/// 某个节点上一个 Raft 组的当前状态
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RaftStatus {
+    pub role: RaftRole,
+    pub term: u64,
+    pub leader: Option<String>,
+    pub commit_index: u64,
+    pub last_applied: u64,
+    /// 最近一次快照覆盖到的日志位置
+    pub snapshot_index: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum RaftMessage {
+    RequestVote {
+        term: u64,
+        last_log_index: u64,
+        last_log_term: u64,
+    },
+    Vote {
+        term: u64,
+        granted: bool,
+    },
+    /// `round` 为 leader 的心跳轮次，follower 在响应中原样带回，用于确认读取
+    AppendEntries {
+        term: u64,
+        prev_log_index: u64,
+        prev_log_term: u64,
+        entries: Vec<LogEntry>,
+        leader_commit: u64,
+        round: u64,
+    },
+    /// 失败时 `match_index` 为 follower 建议的重试位置
+    AppendResult {
+        term: u64,
+        success: bool,
+        match_index: u64,
+        round: u64,
+    },
+    /// 快照即存储引擎中该组分区内的全部数据
+    InstallSnapshot {
+        term: u64,
+        last_index: u64,
+        last_term: u64,
+        data: Vec<(Bytes, Bytes)>,
+    },
+    Propose {
+        id: u64,
+        reply_to: String,
+        command: Command,
+    },
+    /// `key` 为 `None` 时只返回确认后的读取位置（8 字节大端）
+    Read {
+        id: u64,
+        reply_to: String,
+        key: Option<Bytes>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    from: String,
+    message: RaftMessage,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum ProposeError {
+    NotLeader(Option<String>),
+    Failed(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ProposeReply {
+    id: u64,
+    result: std::result::Result<Option<Bytes>, ProposeError>,
+}
+
+/// 持久化的 Raft 状态
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct HardState {
+    term: u64,
+    voted_for: Option<String>,
+    snapshot_index: u64,
+    snapshot_term: u64,
+    applied: u64,
+}
+
+/// 本节点上某个 Raft 组的成员
+///
+/// 日志和投票状态保存在存储引擎的 [`RAFT_PREFIX`] 命名空间下，状态机即存储引擎本身：
+/// 已提交的写入直接应用到对应的 key 上。已应用的日志超过 `snapshot_threshold` 后截断，
+/// 落后于截断位置的 follower 通过 `InstallSnapshot` 接收该组分区内的全部数据。
+/// 节点之间的消息经过 [`MessageBroker`] 发送。
+///
+/// 分区映射变化后由 [`RaftNode::reconfigure`] 原地更新组的成员和分区，新成员从 leader 复制日志或快照。
+pub struct RaftNode {
+    status: watch::Receiver<RaftStatus>,
+    group: watch::Sender<RaftGroup>,
+    storage: Arc<dyn StorageEngine>,
+    task: JoinHandle<()>,
+}
+
+impl RaftNode {
+    pub async fn start(
+        group: RaftGroup,
+        node_id: impl Into<String>,
+        storage: Arc<dyn StorageEngine>,
+        broker: Arc<dyn MessageBroker>,
+        config: RaftConfig,
+    ) -> Result<Self> {
+        Self::spawn(group, node_id.into(), storage, broker, config, 0).await
+    }
+
+    /// 本节点已保存该组全部分区的数据（之前就是这些分区的副本）时加入新组
+    ///
+    /// `applied` 为本节点在之前负责这些分区的各组中已应用的日志位置之和。没有持久化状态时以它
+    /// （至少为 1）作为快照位置：应用得更多的成员日志较新，优先当选 leader；没有数据或数据较旧的成员
+    /// 通过 `InstallSnapshot` 从 leader 获得数据。
+    pub async fn start_with_data(
+        group: RaftGroup,
+        node_id: impl Into<String>,
+        storage: Arc<dyn StorageEngine>,
+        broker: Arc<dyn MessageBroker>,
+        config: RaftConfig,
+        applied: u64,
+    ) -> Result<Self> {
+        Self::spawn(group, node_id.into(), storage, broker, config, applied.max(1)).await
+    }
+
+    async fn spawn(
+        group: RaftGroup,
+        node_id: String,
+        storage: Arc<dyn StorageEngine>,
+        broker: Arc<dyn MessageBroker>,
+        config: RaftConfig,
+        seed: u64,
+    ) -> Result<Self> {
+        let mut inbox = broker.subscribe(&raft_topic(&group.id, &node_id)).await?;
+        let (group_tx, mut groups) = watch::channel(group.clone());
+        let mut core = Core::recover(group, node_id, storage.clone(), broker, config, seed).await?;
+        let status = core.status.subscribe();
+        let task = tokio::spawn(async move {
+            loop {
+                let deadline = match core.role {
+                    RaftRole::Leader => core.heartbeat_deadline,
+                    _ => core.election_deadline,
+                };
+                tokio::select! {
+                    message = inbox.next() => match message {
+                        Some(Ok(message)) => match serde_json::from_slice::<Envelope>(&message.data) {
+                            Ok(envelope) => core.handle(envelope.from, envelope.message).await,
+                            Err(e) => tracing::warn!("无法解析 Raft 消息: {}", e),
+                        },
+                        Some(Err(e)) => tracing::warn!("Raft 消息错误: {}", e),
+                        None => break,
+                    },
+                    _ = tokio::time::sleep_until(deadline) => core.on_timeout().await,
+                    Ok(()) = groups.changed() => {
+                        let group = groups.borrow_and_update().clone();
+                        core.reconfigure(group).await;
+                    }
+                }
+                core.publish_status();
+            }
+        });
+        Ok(Self {
+            status,
+            group: group_tx,
+            storage,
+            task,
+        })
+    }
+
+    /// 更新组的成员和分区，组 id 不变
+    pub fn reconfigure(&self, group: RaftGroup) {
+        self.group.send_if_modified(|current| {
+            let changed = *current != group;
+            *current = group;
+            changed
+        });
+    }
+
+    /// 本节点离开该组：停止运行并删除持久化的日志和状态
+    ///
+    /// 以后再加入同一 id 的组时从空日志开始，由 leader 补齐，避免旧日志在新的组中被当作最新日志。
+    pub async fn remove(mut self) -> Result<()> {
+        self.task.abort();
+        let _ = (&mut self.task).await;
+        let prefix = group_prefix(&self.group.borrow().id);
+        let mut end = prefix.clone();
+        end.push(0xff);
+        let mut items = self.storage.scan(&prefix, Some(&end), None).await?;
+        let mut keys = Vec::new();
+        while let Some(kv) = items.next().await {
+            keys.push(kv?.key);
+        }
+        for key in keys {
+            self.storage.delete(&key).await?;
+        }
+        Ok(())
+    }
+
+    pub fn status(&self) -> RaftStatus {
+        self.status.borrow().clone()
+    }
+
+    /// 订阅状态变化
+    pub fn watch_status(&self) -> watch::Receiver<RaftStatus> {
+        self.status.clone()
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.status.borrow().role == RaftRole::Leader
+    }
+}
+
+impl Drop for RaftNode {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct Pending {
+    id: u64,
+    reply_to: String,
+    term: u64,
+}
+
+/// 等待确认的读取：多数成员响应了第 `round` 轮心跳、且已应用到 `index` 后读取
+struct PendingRead {
+    pending: Pending,
+    key: Option<Bytes>,
+    index: u64,
+    round: u64,
+}
+
+struct Core {
+    group: RaftGroup,
+    id: String,
+    storage: Arc<dyn StorageEngine>,
+    broker: Arc<dyn MessageBroker>,
+    config: RaftConfig,
+    hard: HardState,
+    /// 快照之后的日志，`log[i]` 的位置为 `snapshot_index + i + 1`
+    log: Vec<LogEntry>,
+    commit_index: u64,
+    role: RaftRole,
+    leader: Option<String>,
+    votes: HashSet<String>,
+    next_index: HashMap<String, u64>,
+    match_index: HashMap<String, u64>,
+    pending: HashMap<u64, Pending>,
+    reads: Vec<PendingRead>,
+    /// 当前任期的第一条日志（leader 当选时写入的空日志）
+    term_start: u64,
+    /// 心跳轮次，每次向全部成员发送 `AppendEntries` 时加一
+    round: u64,
+    /// 各成员在当前任期响应过的最大轮次
+    acked: HashMap<String, u64>,
+    election_deadline: Instant,
+    heartbeat_deadline: Instant,
+    /// 选举超时的抖动来源
+    jitter: SeededRng,
+    status: watch::Sender<RaftStatus>,
+}
+
+impl Core {
+    async fn recover(
+        group: RaftGroup,
+        id: String,
+        storage: Arc<dyn StorageEngine>,
+        broker: Arc<dyn MessageBroker>,
+        config: RaftConfig,
+        seed: u64,
+    ) -> Result<Self> {
+        let prefix = group_prefix(&group.id);
+        let hard: HardState = match storage.get(&state_key(&prefix)).await? {
+            Some(data) => serde_json::from_slice(&data)?,
+            // 以本地已有的数据作为快照加入新组
+            None if seed > 0 => {
+                let hard = HardState {
+                    snapshot_index: seed,
+                    applied: seed,
+                    ..Default::default()
+                };
+                storage.put(&state_key(&prefix), &serde_json::to_vec(&hard)?).await?;
+                hard
+            }
+            None => HardState::default(),
+        };
+        let log_prefix = [&prefix[..], b"log/"].concat();
+        let mut end = log_prefix.clone();
+        end.push(0xff);
+        let mut items = storage.scan(&log_prefix, Some(&end), None).await?;
+        let mut log = Vec::new();
+        while let Some(kv) = items.next().await {
+            let entry: LogEntry = serde_json::from_slice(&kv?.value)?;
+            if entry.index > hard.snapshot_index {
+                log.push(entry);
+            }
+        }
+        let status = watch::channel(RaftStatus {
+            role: RaftRole::Follower,
+            term: hard.term,
+            leader: None,
+            commit_index: hard.applied,
+            last_applied: hard.applied,
+            snapshot_index: hard.snapshot_index,
+        })
+        .0;
+        // 同一节点上的各组使用不同的序列，避免同时超时
+        let seed = config
+            .election_seed
+            .unwrap_or_else(|| uuid::Uuid::new_v4().as_u128() as u64);
+        let seed = group.id.bytes().fold(seed, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+        let mut core = Self {
+            group,
+            id,
+            storage,
+            broker,
+            commit_index: hard.applied,
+            hard,
+            log,
+            role: RaftRole::Follower,
+            leader: None,
+            votes: HashSet::new(),
+            next_index: HashMap::new(),
+            match_index: HashMap::new(),
+            pending: HashMap::new(),
+            reads: Vec::new(),
+            term_start: 0,
+            round: 0,
+            acked: HashMap::new(),
+            election_deadline: Instant::now(),
+            heartbeat_deadline: Instant::now(),
+            jitter: SeededRng::new(seed),
+            status,
+            config,
+        };
+        core.reset_election_deadline();
+        Ok(core)
+    }
+
+    fn publish_status(&self) {
+        let status = RaftStatus {
+            role: self.role,
+            term: self.hard.term,
+            leader: self.leader.clone(),
+            commit_index: self.commit_index,
+            last_applied: self.hard.applied,
+            snapshot_index: self.hard.snapshot_index,
+        };
+        self.status.send_if_modified(|current| {
+            let changed = *current != status;
+            *current = status;
+            changed
+        });
+    }
+
+    fn last_index(&self) -> u64 {
+        self.hard.snapshot_index + self.log.len() as u64
+    }
+
+    fn last_term(&self) -> u64 {
+        self.log.last().map_or(self.hard.snapshot_term, |e| e.term)
+    }
+
+    /// 位置 `index` 的日志任期，已被快照截断的位置返回 `None`
+    fn term_at(&self, index: u64) -> Option<u64> {
+        if index == self.hard.snapshot_index {
+            return Some(self.hard.snapshot_term);
+        }
+        if index < self.hard.snapshot_index {
+            return None;
+        }
+        self.entry(index).map(|e| e.term)
+    }
+
+    fn entry(&self, index: u64) -> Option<&LogEntry> {
+        let offset = index.checked_sub(self.hard.snapshot_index + 1)?;
+        self.log.get(offset as usize)
+    }
+
+    fn peers(&self) -> impl Iterator<Item = &String> {
+        self.group.members.iter().filter(move |m| **m != self.id)
+    }
+
+    fn reset_election_deadline(&mut self) {
+        let min = self.config.election_timeout_min_ms;
+        let span = self.config.election_timeout_max_ms.saturating_sub(min).max(1);
+        let jitter = self.jitter.below(span);
+        self.election_deadline = Instant::now() + Duration::from_millis(min + jitter);
+    }
+
+    async fn send(&self, to: &str, message: RaftMessage) {
+        let envelope = Envelope {
+            from: self.id.clone(),
+            message,
+        };
+        let sent = match serde_json::to_vec(&envelope) {
+            Ok(data) => self.broker.publish(&raft_topic(&self.group.id, to), data).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = sent {
+            tracing::debug!("发送 Raft 消息到 {} 失败: {}", to, e);
+        }
+    }
+
+    async fn reply(&self, pending: Pending, result: std::result::Result<Option<Bytes>, ProposeError>) {
+        let reply = ProposeReply {
+            id: pending.id,
+            result,
+        };
+        let sent = match serde_json::to_vec(&reply) {
+            Ok(data) => self.broker.publish(&pending.reply_to, data).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = sent {
+            tracing::debug!("发送 Raft 响应失败: {}", e);
+        }
+    }
+
+    async fn persist_state(&self) {
+        let prefix = group_prefix(&self.group.id);
+        let written = match serde_json::to_vec(&self.hard) {
+            Ok(data) => self.storage.put(&state_key(&prefix), &data).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = written {
+            tracing::warn!("保存 Raft 状态失败: {}", e);
+        }
+    }
+
+    async fn persist_entries(&self, entries: &[LogEntry]) {
+        let prefix = group_prefix(&self.group.id);
+        for entry in entries {
+            let written = match serde_json::to_vec(entry) {
+                Ok(data) => self.storage.put(&log_key(&prefix, entry.index), &data).await,
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = written {
+                tracing::warn!("保存 Raft 日志失败: {}", e);
+            }
+        }
+    }
+
+    async fn delete_entries(&self, from: u64, to: u64) {
+        let prefix = group_prefix(&self.group.id);
+        for index in from..=to {
+            if let Err(e) = self.storage.delete(&log_key(&prefix, index)).await {
+                tracing::warn!("删除 Raft 日志失败: {}", e);
+            }
+        }
+    }
+
+    async fn become_follower(&mut self, term: u64, leader: Option<String>) {
+        if term > self.hard.term {
+            self.hard.term = term;
+            self.hard.voted_for = None;
+            self.persist_state().await;
+        }
+        if self.role == RaftRole::Leader {
+            for (_, pending) in std::mem::take(&mut self.pending) {
+                self.reply(pending, Err(ProposeError::NotLeader(leader.clone()))).await;
+            }
+            for read in std::mem::take(&mut self.reads) {
+                self.reply(read.pending, Err(ProposeError::NotLeader(leader.clone()))).await;
+            }
+        }
+        self.role = RaftRole::Follower;
+        self.leader = leader;
+    }
+
+    async fn on_timeout(&mut self) {
+        if self.role == RaftRole::Leader {
+            self.broadcast_append().await;
+            return;
+        }
+        self.hard.term += 1;
+        self.hard.voted_for = Some(self.id.clone());
+        self.persist_state().await;
+        self.role = RaftRole::Candidate;
+        self.leader = None;
+        self.votes = HashSet::from([self.id.clone()]);
+        self.reset_election_deadline();
+        if self.votes.len() >= self.group.quorum() {
+            self.become_leader().await;
+            return;
+        }
+        let request = RaftMessage::RequestVote {
+            term: self.hard.term,
+            last_log_index: self.last_index(),
+            last_log_term: self.last_term(),
+        };
+        let peers: Vec<String> = self.peers().cloned().collect();
+        for peer in peers {
+            self.send(&peer, request.clone()).await;
+        }
+    }
+
+    async fn become_leader(&mut self) {
+        self.role = RaftRole::Leader;
+        self.leader = Some(self.id.clone());
+        let next = self.last_index() + 1;
+        let peers: Vec<String> = self.peers().cloned().collect();
+        self.next_index = peers.iter().map(|p| (p.clone(), next)).collect();
+        self.match_index = peers.into_iter().map(|p| (p, 0)).collect();
+        self.acked.clear();
+        self.term_start = self.append(Command::Noop).await;
+        self.broadcast_append().await;
+        self.advance_commit().await;
+    }
+
+    async fn append(&mut self, command: Command) -> u64 {
+        let entry = LogEntry {
+            term: self.hard.term,
+            index: self.last_index() + 1,
+            command,
+        };
+        self.persist_entries(std::slice::from_ref(&entry)).await;
+        self.log.push(entry);
+        self.last_index()
+    }
+
+    async fn broadcast_append(&mut self) {
+        self.round += 1;
+        self.heartbeat_deadline = Instant::now() + Duration::from_millis(self.config.heartbeat_interval_ms);
+        let peers: Vec<String> = self.peers().cloned().collect();
+        for peer in peers {
+            self.send_append(&peer).await;
+        }
+    }
+
+    async fn send_append(&self, peer: &str) {
+        let next = self.next_index.get(peer).copied().unwrap_or(1);
+        let message = if next <= self.hard.snapshot_index {
+            // 存储中的数据对应已应用的位置，而不是上次截断的位置
+            let last_index = self.hard.applied;
+            match self.snapshot_data().await {
+                Ok(data) => RaftMessage::InstallSnapshot {
+                    term: self.hard.term,
+                    last_index,
+                    last_term: self.term_at(last_index).unwrap_or(self.hard.snapshot_term),
+                    data,
+                },
+                Err(e) => {
+                    tracing::warn!("读取快照失败: {}", e);
+                    return;
+                }
+            }
+        } else {
+            let prev_log_index = next - 1;
+            let entries = (next..=self.last_index())
+                .take(self.config.max_append_entries.max(1))
+                .filter_map(|index| self.entry(index).cloned())
+                .collect();
+            RaftMessage::AppendEntries {
+                term: self.hard.term,
+                prev_log_index,
+                prev_log_term: self.term_at(prev_log_index).unwrap_or_default(),
+                entries,
+                leader_commit: self.commit_index,
+                round: self.round,
+            }
+        };
+        self.send(peer, message).await;
+    }
+
+    async fn handle(&mut self, from: String, message: RaftMessage) {
+        let term = match &message {
+            RaftMessage::RequestVote { term, .. }
+            | RaftMessage::Vote { term, .. }
+            | RaftMessage::AppendEntries { term, .. }
+            | RaftMessage::AppendResult { term, .. }
+            | RaftMessage::InstallSnapshot { term, .. } => Some(*term),
+            RaftMessage::Propose { .. } | RaftMessage::Read { .. } => None,
+        };
+        if let Some(term) = term {
+            if term > self.hard.term {
+                self.become_follower(term, None).await;
+            }
+        }
+        match message {
+            RaftMessage::RequestVote {
+                term,
+                last_log_index,
+                last_log_term,
+            } => {
+                let up_to_date = (last_log_term, last_log_index) >= (self.last_term(), self.last_index());
+                let free = self.hard.voted_for.as_ref().is_none_or(|v| *v == from);
+                let granted = term == self.hard.term && free && up_to_date;
+                if granted {
+                    self.hard.voted_for = Some(from.clone());
+                    self.persist_state().await;
+                    self.reset_election_deadline();
+                }
+                let reply = RaftMessage::Vote {
+                    term: self.hard.term,
+                    granted,
+                };
+                self.send(&from, reply).await;
+            }
+            RaftMessage::Vote { term, granted } => {
+                if self.role == RaftRole::Candidate && term == self.hard.term && granted {
+                    self.votes.insert(from);
+                    if self.votes.len() >= self.group.quorum() {
+                        self.become_leader().await;
+                    }
+                }
+            }
+            RaftMessage::AppendEntries {
+                term,
+                prev_log_index,
+                prev_log_term,
+                entries,
+                leader_commit,
+                round,
+            } => {
+                let (success, match_index) = self
+                    .on_append(from.clone(), term, prev_log_index, prev_log_term, entries, leader_commit)
+                    .await;
+                let reply = RaftMessage::AppendResult {
+                    term: self.hard.term,
+                    success,
+                    match_index,
+                    round,
+                };
+                self.send(&from, reply).await;
+            }
+            RaftMessage::AppendResult {
+                term,
+                success,
+                match_index,
+                round,
+            } => {
+                if self.role != RaftRole::Leader || term != self.hard.term {
+                    return;
+                }
+                let acked = self.acked.entry(from.clone()).or_default();
+                *acked = (*acked).max(round);
+                if success {
+                    let matched = self.match_index.entry(from.clone()).or_default();
+                    *matched = (*matched).max(match_index);
+                    let matched = *matched;
+                    self.next_index.insert(from.clone(), matched + 1);
+                    self.advance_commit().await;
+                    if matched < self.last_index() {
+                        self.send_append(&from).await;
+                    }
+                } else {
+                    let next = self.next_index.get(&from).copied().unwrap_or(1);
+                    self.next_index
+                        .insert(from.clone(), (match_index + 1).min(next.saturating_sub(1)).max(1));
+                    self.send_append(&from).await;
+                }
+                self.serve_reads().await;
+            }
+            RaftMessage::InstallSnapshot {
+                term,
+                last_index,
+                last_term,
+                data,
+            } => {
+                let reply = self.on_snapshot(from.clone(), term, last_index, last_term, data).await;
+                self.send(&from, reply).await;
+            }
+            RaftMessage::Propose {
+                id,
+                reply_to,
+                command,
+            } => {
+                let pending = Pending {
+                    id,
+                    reply_to,
+                    term: self.hard.term,
+                };
+                if self.role != RaftRole::Leader {
+                    let leader = self.leader.clone();
+                    self.reply(pending, Err(ProposeError::NotLeader(leader))).await;
+                    return;
+                }
+                let index = self.append(command).await;
+                self.pending.insert(index, pending);
+                self.broadcast_append().await;
+                self.advance_commit().await;
+            }
+            RaftMessage::Read { id, reply_to, key } => {
+                let pending = Pending {
+                    id,
+                    reply_to,
+                    term: self.hard.term,
+                };
+                if self.role != RaftRole::Leader {
+                    let leader = self.leader.clone();
+                    self.reply(pending, Err(ProposeError::NotLeader(leader))).await;
+                    return;
+                }
+                // 当前任期的空日志提交之前，提交位置可能落后于之前任期已提交的日志
+                let index = self.commit_index.max(self.term_start);
+                self.broadcast_append().await;
+                self.reads.push(PendingRead {
+                    pending,
+                    key,
+                    index,
+                    round: self.round,
+                });
+                self.serve_reads().await;
+            }
+        }
+    }
+
+    /// 回复已确认且已应用到读取位置的读取
+    async fn serve_reads(&mut self) {
+        if self.role != RaftRole::Leader || self.reads.is_empty() {
+            return;
+        }
+        let quorum = self.group.quorum();
+        let mut waiting = Vec::new();
+        for read in std::mem::take(&mut self.reads) {
+            let confirmed = 1 + self.peers().filter(|p| self.acked.get(*p).is_some_and(|r| *r >= read.round)).count();
+            if confirmed < quorum || self.hard.applied < read.index {
+                waiting.push(read);
+                continue;
+            }
+            let result = match &read.key {
+                Some(key) => self.storage.get(key).await,
+                None => Ok(Some(Bytes::copy_from_slice(&read.index.to_be_bytes()))),
+            };
+            let result = result.map_err(|e| ProposeError::Failed(e.to_string()));
+            self.reply(read.pending, result).await;
+        }
+        self.reads = waiting;
+    }
+
+    /// 应用新的成员和分区；成员变化时 leader 开始向新成员复制
+    async fn reconfigure(&mut self, group: RaftGroup) {
+        let changed = group.members != self.group.members;
+        self.group = group;
+        if !changed {
+            return;
+        }
+        let members: HashSet<String> = self.group.members.iter().cloned().collect();
+        self.votes.retain(|m| members.contains(m));
+        self.acked.retain(|m, _| members.contains(m));
+        self.next_index.retain(|m, _| members.contains(m));
+        self.match_index.retain(|m, _| members.contains(m));
+        if self.role != RaftRole::Leader {
+            return;
+        }
+        let next = self.last_index() + 1;
+        let peers: Vec<String> = self.peers().cloned().collect();
+        for peer in peers {
+            self.next_index.entry(peer.clone()).or_insert(next);
+            self.match_index.entry(peer).or_insert(0);
+        }
+        self.broadcast_append().await;
+        self.advance_commit().await;
+        self.serve_reads().await;
+    }
+
+    /// 处理 `AppendEntries`，返回是否成功及匹配位置（失败时为建议的重试位置）
+    async fn on_append(
+        &mut self,
+        from: String,
+        term: u64,
+        prev_log_index: u64,
+        prev_log_term: u64,
+        entries: Vec<LogEntry>,
+        leader_commit: u64,
+    ) -> (bool, u64) {
+        if term < self.hard.term {
+            return (false, 0);
+        }
+        self.become_follower(term, Some(from)).await;
+        self.reset_election_deadline();
+
+        if prev_log_index > self.last_index() {
+            return (false, self.last_index());
+        }
+        // 已被快照截断的位置必然已提交，与 leader 一致
+        if let Some(term) = self.term_at(prev_log_index) {
+            if term != prev_log_term {
+                return (false, self.commit_index);
+            }
+        }
+
+        let last_new = prev_log_index + entries.len() as u64;
+        let mut appended = Vec::new();
+        for entry in entries {
+            if entry.index <= self.hard.snapshot_index {
+                continue;
+            }
+            match self.term_at(entry.index) {
+                Some(term) if term == entry.term => continue,
+                Some(_) => {
+                    // 与 leader 冲突的日志及其后的全部日志作废
+                    let last = self.last_index();
+                    self.delete_entries(entry.index, last).await;
+                    self.log.truncate((entry.index - self.hard.snapshot_index - 1) as usize);
+                }
+                None => {}
+            }
+            appended.push(entry.clone());
+            self.log.push(entry);
+        }
+        self.persist_entries(&appended).await;
+
+        if leader_commit > self.commit_index {
+            self.commit_index = leader_commit.min(last_new).max(self.commit_index);
+            self.apply().await;
+        }
+        (true, last_new)
+    }
+
+    async fn on_snapshot(
+        &mut self,
+        from: String,
+        term: u64,
+        last_index: u64,
+        last_term: u64,
+        data: Vec<(Bytes, Bytes)>,
+    ) -> RaftMessage {
+        if term < self.hard.term {
+            return RaftMessage::AppendResult {
+                term: self.hard.term,
+                success: false,
+                match_index: 0,
+                round: 0,
+            };
+        }
+        self.become_follower(term, Some(from)).await;
+        self.reset_election_deadline();
+        if last_index <= self.commit_index {
+            return RaftMessage::AppendResult {
+                term: self.hard.term,
+                success: true,
+                match_index: self.commit_index,
+                round: 0,
+            };
+        }
+        if let Err(e) = self.restore(&data).await {
+            tracing::warn!("安装快照失败: {}", e);
+            return RaftMessage::AppendResult {
+                term: self.hard.term,
+                success: false,
+                match_index: self.commit_index,
+                round: 0,
+            };
+        }
+        let old_last = self.last_index();
+        if self.term_at(last_index) == Some(last_term) {
+            self.log.drain(..(last_index - self.hard.snapshot_index) as usize);
+            self.delete_entries(self.hard.snapshot_index + 1, last_index).await;
+        } else {
+            self.log.clear();
+            self.delete_entries(self.hard.snapshot_index + 1, old_last).await;
+        }
+        self.hard.snapshot_index = last_index;
+        self.hard.snapshot_term = last_term;
+        self.hard.applied = last_index;
+        self.commit_index = last_index;
+        self.persist_state().await;
+        RaftMessage::AppendResult {
+            term: self.hard.term,
+            success: true,
+            match_index: last_index,
+            round: 0,
+        }
+    }
+
+    /// 多数成员已复制的当前任期日志即为已提交
+    async fn advance_commit(&mut self) {
+        let mut matched: Vec<u64> = self.match_index.values().copied().collect();
+        matched.push(self.last_index());
+        matched.sort_unstable_by(|a, b| b.cmp(a));
+        let candidate = matched[self.group.quorum() - 1];
+        if candidate > self.commit_index && self.term_at(candidate) == Some(self.hard.term) {
+            self.commit_index = candidate;
+            self.apply().await;
+        }
+    }
+
+    async fn apply(&mut self) {
+        let start = self.hard.applied;
+        while self.hard.applied < self.commit_index {
+            let index = self.hard.applied + 1;
+            let Some(entry) = self.entry(index).cloned() else {
+                break;
+            };
+            let result = self.execute(&entry.command).await;
+            self.hard.applied = index;
+            if let Some(pending) = self.pending.remove(&index) {
+                let result = if pending.term == entry.term {
+                    result.map_err(|e| ProposeError::Failed(e.to_string()))
+                } else {
+                    Err(ProposeError::NotLeader(self.leader.clone()))
+                };
+                self.reply(pending, result).await;
+            }
+        }
+        if self.hard.applied > start {
+            if self.hard.applied - self.hard.snapshot_index >= self.config.snapshot_threshold.max(1) {
+                self.compact().await;
+            }
+            self.persist_state().await;
+            self.serve_reads().await;
+        }
+    }
+
+    async fn execute(&self, command: &Command) -> Result<Option<Bytes>> {
+        match command {
+            Command::Noop => Ok(None),
+            Command::Put { key, value } => {
+                self.storage.put(key, value).await?;
+                Ok(None)
+            }
+            Command::Delete { key } => {
+                self.storage.delete(key).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// 以已应用位置为快照，截断之前的日志
+    async fn compact(&mut self) {
+        let index = self.hard.applied;
+        let Some(term) = self.term_at(index) else {
+            return;
+        };
+        let old = self.hard.snapshot_index;
+        self.log.drain(..(index - old) as usize);
+        self.hard.snapshot_index = index;
+        self.hard.snapshot_term = term;
+        self.persist_state().await;
+        self.delete_entries(old + 1, index).await;
+    }
+
+    async fn snapshot_data(&self) -> Result<Vec<(Bytes, Bytes)>> {
+        let mut items = self.storage.scan(b"", None, None).await?;
+        let mut data = Vec::new();
+        while let Some(kv) = items.next().await {
+            let kv = kv?;
+            if !is_internal(&kv.key) && self.group.contains(&kv.key) {
+                data.push((kv.key, kv.value));
+            }
+        }
+        Ok(data)
+    }
+
+    /// 用快照替换本地该组分区内的数据
+    async fn restore(&self, data: &[(Bytes, Bytes)]) -> Result<()> {
+        for (key, _) in self.snapshot_data().await? {
+            self.storage.delete(&key).await?;
+        }
+        for (key, value) in data {
+            self.storage.put(key, value).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Raft 和提示移交的内部数据，不属于任何分区
+pub(crate) fn is_internal(key: &[u8]) -> bool {
+    key.starts_with(RAFT_PREFIX) || key.starts_with(HINT_PREFIX)
+}
+
+/// 拒绝落在内部命名空间中的用户 key，避免覆盖 Raft 日志或提示
+pub(crate) fn check_user_key(key: &[u8]) -> Result<()> {
+    if is_internal(key) {
+        return Err(Error::Consistency(format!(
+            "key 不能使用内部前缀: {}",
+            String::from_utf8_lossy(key)
+        )));
+    }
+    Ok(())
+}
+
+fn group_prefix(group: &str) -> Vec<u8> {
+    [RAFT_PREFIX, group.as_bytes(), b"/"].concat()
+}
+
+fn state_key(prefix: &[u8]) -> Vec<u8> {
+    [prefix, b"state"].concat()
+}
+
+fn log_key(prefix: &[u8], index: u64) -> Vec<u8> {
+    [prefix, format!("log/{:020}", index).as_bytes()].concat()
+}
+
+/// 发给 leader 的请求
+#[derive(Clone)]
+enum Request {
+    Propose(Command),
+    Read(Option<Bytes>),
+}
+
+impl Request {
+    fn message(&self, id: u64, reply_to: String) -> RaftMessage {
+        match self {
+            Request::Propose(command) => RaftMessage::Propose {
+                id,
+                reply_to,
+                command: command.clone(),
+            },
+            Request::Read(key) => RaftMessage::Read {
+                id,
+                reply_to,
+                key: key.clone(),
+            },
+        }
+    }
+}
+
+/// 向 Raft 组提交命令和读取的客户端
+///
+/// 优先发给记录的 leader，收到 `NotLeader` 时按提示或依次尝试其他成员，直到总超时。
+pub struct RaftClient {
+    node_id: String,
+    reply_to: String,
+    broker: Arc<dyn MessageBroker>,
+    next_id: AtomicU64,
+    pending: Arc<DashMap<u64, oneshot::Sender<ProposeReply>>>,
+    leaders: DashMap<String, String>,
+    task: JoinHandle<()>,
+}
+
+impl RaftClient {
+    pub async fn start(node_id: impl Into<String>, broker: Arc<dyn MessageBroker>) -> Result<Self> {
+        let node_id = node_id.into();
+        let reply_to = reply_topic(&node_id);
+        let mut replies = broker.subscribe(&reply_to).await?;
+        let pending: Arc<DashMap<u64, oneshot::Sender<ProposeReply>>> = Arc::new(DashMap::new());
+        let task = {
+            let pending = pending.clone();
+            tokio::spawn(async move {
+                while let Some(message) = replies.next().await {
+                    let reply = message.and_then(|m| Ok(serde_json::from_slice::<ProposeReply>(&m.data)?));
+                    match reply {
+                        Ok(reply) => {
+                            if let Some((_, tx)) = pending.remove(&reply.id) {
+                                let _ = tx.send(reply);
+                            }
+                        }
+                        Err(e) => tracing::warn!("无法解析 Raft 响应: {}", e),
+                    }
+                }
+            })
+        };
+        Ok(Self {
+            node_id,
+            reply_to,
+            broker,
+            next_id: AtomicU64::new(0),
+            pending,
+            leaders: DashMap::new(),
+            task,
+        })
+    }
+
+    /// 提交命令并等待其被应用
+    ///
+    /// 超时返回 [`Error::Consistency`]：此时命令可能已经提交，也可能没有。
+    pub async fn propose(&self, group: &RaftGroup, command: Command, timeout: Duration) -> Result<()> {
+        self.request(group, Request::Propose(command), timeout).await?;
+        Ok(())
+    }
+
+    /// 线性一致地读取 key，不写入日志
+    pub async fn read(&self, group: &RaftGroup, key: &[u8], timeout: Duration) -> Result<Option<Bytes>> {
+        self.request(group, Request::Read(Some(Bytes::copy_from_slice(key))), timeout).await
+    }
+
+    /// leader 确认任期后的提交位置：在此之前完成的写入都不晚于该位置
+    pub async fn read_index(&self, group: &RaftGroup, timeout: Duration) -> Result<u64> {
+        let index = self.request(group, Request::Read(None), timeout).await?;
+        index
+            .and_then(|index| <[u8; 8]>::try_from(index.as_ref()).ok())
+            .map(u64::from_be_bytes)
+            .ok_or_else(|| Error::Consistency("无效的读取位置".to_string()))
+    }
+
+    async fn request(&self, group: &RaftGroup, request: Request, timeout: Duration) -> Result<Option<Bytes>> {
+        let deadline = Instant::now() + timeout;
+        let mut candidate = self.leaders.get(&group.id).map(|l| l.clone());
+        let mut attempt = 0usize;
+        while Instant::now() < deadline {
+            let target = candidate
+                .take()
+                .unwrap_or_else(|| group.members[attempt % group.members.len()].clone());
+            attempt += 1;
+            match self.call(group, &target, &request, deadline).await {
+                Some(Ok(value)) => {
+                    self.leaders.insert(group.id.clone(), target);
+                    return Ok(value);
+                }
+                Some(Err(ProposeError::Failed(e))) => return Err(Error::Consistency(e)),
+                Some(Err(ProposeError::NotLeader(leader))) => {
+                    self.leaders.remove(&group.id);
+                    match leader {
+                        Some(leader) if leader != target => candidate = Some(leader),
+                        // 选举尚未完成，稍后重试
+                        _ => tokio::time::sleep(Duration::from_millis(20)).await,
+                    }
+                }
+                None => {
+                    self.leaders.remove(&group.id);
+                }
+            }
+        }
+        Err(Error::Consistency(format!(
+            "Raft 组 {} 在 {:?} 内未能完成请求",
+            group.id, timeout
+        )))
+    }
+
+    /// 单次请求，每个成员最多等待剩余时间的四分之一，超时返回 `None`
+    async fn call(
+        &self,
+        group: &RaftGroup,
+        target: &str,
+        request: &Request,
+        deadline: Instant,
+    ) -> Option<std::result::Result<Option<Bytes>, ProposeError>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.insert(id, tx);
+        let envelope = Envelope {
+            from: self.node_id.clone(),
+            message: request.message(id, self.reply_to.clone()),
+        };
+        let data = serde_json::to_vec(&envelope).ok()?;
+        if self.broker.publish(&raft_topic(&group.id, target), data).await.is_err() {
+            self.pending.remove(&id);
+            return None;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let wait = (remaining / 4).max(Duration::from_millis(50)).min(remaining);
+        let reply = tokio::time::timeout(wait, rx).await;
+        self.pending.remove(&id);
+        reply.ok()?.ok().map(|reply| reply.result)
+    }
+}
+
+impl Drop for RaftClient {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,167 @@
+use super::crdt::Crdt;
+use super::replica::{ReplicaClient, ReplicaOp, ReplicaReply};
+use super::stamped::Stamped;
+use super::vclock::Version;
+use super::events::EventBus;
+use super::ConsistencyEvent;
+use crate::config::ReadRepairMode;
+use crate::Result;
+use bytes::Bytes;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// 尚未返回的副本请求，结果附带副本 id
+pub(crate) type PendingCalls = FuturesUnordered<JoinHandle<(String, Result<ReplicaReply>)>>;
+
+/// 读取得出的最终结果
+#[derive(Clone)]
+pub(crate) enum Resolved {
+    /// 解决冲突后仍互相并发的版本
+    Versions(Vec<Version>),
+    /// 各副本状态合并后的 CRDT
+    Crdt(Crdt),
+    /// 未启用向量时钟时时间戳最晚的值或墓碑
+    Stamped(Stamped),
+}
+
+/// 把读取得出的最终结果写回落后的副本
+///
+/// 带向量时钟的版本写回不被副本版本覆盖的最终版本，CRDT 写回合并后的状态；
+/// 未启用向量时钟时把时间戳最晚的值（或墓碑）写回被其 [`Stamped::supersedes`] 的副本。
+/// 未加时间戳的旧数据（时间戳为 0）无法判断新旧，不做修复。
+#[derive(Clone)]
+pub(crate) struct ReadRepairer {
+    client: Arc<ReplicaClient>,
+    timeout: Duration,
+    events: EventBus,
+}
+
+impl ReadRepairer {
+    pub(crate) fn new(
+        client: Arc<ReplicaClient>,
+        timeout: Duration,
+        events: EventBus,
+    ) -> Self {
+        Self {
+            client,
+            timeout,
+            events,
+        }
+    }
+
+    pub(crate) fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub(crate) fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 按 `mode` 修复一次读取涉及的副本
+    ///
+    /// 已响应的副本在 [`ReadRepairMode::Sync`] 下立即修复，否则在后台修复；
+    /// 读取返回时仍未响应的副本总是在后台等待其响应后修复。
+    pub(crate) async fn after_read(
+        &self,
+        mode: ReadRepairMode,
+        key: Bytes,
+        resolved: Resolved,
+        replies: Vec<(String, ReplicaReply)>,
+        mut pending: PendingCalls,
+    ) {
+        if mode == ReadRepairMode::Disabled {
+            return;
+        }
+        if mode == ReadRepairMode::Sync {
+            self.repair(&key, &resolved, &replies).await;
+        }
+        let repairer = self.clone();
+        tokio::spawn(async move {
+            if mode == ReadRepairMode::Background {
+                repairer.repair(&key, &resolved, &replies).await;
+            }
+            let mut late = Vec::new();
+            while let Some(call) = pending.next().await {
+                if let Ok((replica, Ok(reply))) = call {
+                    late.push((replica, reply));
+                }
+            }
+            repairer.repair(&key, &resolved, &late).await;
+        });
+    }
+
+    /// 修复 `replies` 中与最终结果不一致的副本，返回修复的副本数
+    pub(crate) async fn repair(&self, key: &Bytes, resolved: &Resolved, replies: &[(String, ReplicaReply)]) -> usize {
+        let mut repaired = 0;
+        let mut values = HashSet::new();
+        for (replica, reply) in replies {
+            let ops = stale_ops(key, resolved, reply);
+            if ops.is_empty() {
+                continue;
+            }
+            let mut ok = true;
+            for op in ops {
+                let value = match &op {
+                    ReplicaOp::PutVersion { version, .. } => version.value.clone(),
+                    ReplicaOp::MergeCrdt { state, .. } => state.encode().ok().map(Bytes::from),
+                    ReplicaOp::Put { value, .. } => Some(value.clone()),
+                    _ => None,
+                };
+                match self.client.call(replica, op, self.timeout).await {
+                    Ok(_) => values.extend(value),
+                    Err(e) => {
+                        tracing::debug!("读修复副本 {} 失败: {}", replica, e);
+                        ok = false;
+                    }
+                }
+            }
+            if ok {
+                repaired += 1;
+            }
+        }
+        for value in values {
+            self.events.publish(ConsistencyEvent::ReadRepair {
+                key: key.clone(),
+                repaired_value: value,
+            });
+        }
+        repaired
+    }
+}
+
+/// 使副本包含全部最终版本所需的写入，墓碑也会写回；CRDT 状态不同时写回合并后的状态
+fn stale_ops(key: &Bytes, resolved: &Resolved, reply: &ReplicaReply) -> Vec<ReplicaOp> {
+    let (winners, versions) = match (resolved, reply) {
+        (Resolved::Stamped(latest), ReplicaReply::Value(stamped))
+            if latest.timestamp > 0 && latest.supersedes(stamped) =>
+        {
+            let (key, timestamp) = (key.clone(), latest.timestamp);
+            return vec![match latest.value.clone() {
+                Some(value) => ReplicaOp::Put { key, value, timestamp },
+                None => ReplicaOp::Delete { key, timestamp },
+            }];
+        }
+        (Resolved::Versions(winners), ReplicaReply::Versions(versions)) => (winners, versions),
+        (Resolved::Crdt(merged), ReplicaReply::Crdt(state)) if state.as_ref() != Some(merged) => {
+            return vec![ReplicaOp::MergeCrdt {
+                key: key.clone(),
+                state: merged.clone(),
+            }];
+        }
+        _ => return Vec::new(),
+    };
+    winners
+        .iter()
+        .filter(|w| !versions.iter().any(|v| v.clock.descends(&w.clock)))
+        .map(|w| ReplicaOp::PutVersion {
+            key: key.clone(),
+            version: w.clone(),
+        })
+        .collect()
+}
@@ -0,0 +1,490 @@
+use super::crdt::{Crdt, CrdtOp};
+use super::handoff::HintStore;
+use super::merkle::{range_digests, range_tree, MerkleTree};
+use super::stamped::Stamped;
+use super::vclock::{reconcile, CausalContext, VectorClock, Version};
+use crate::config::HintedHandoffConfig;
+use crate::distribution::PartitionRange;
+use crate::error::Error;
+use crate::messaging::MessageBroker;
+use crate::storage::StorageEngine;
+use crate::Result;
+use bytes::Bytes;
+use dashmap::DashMap;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// 副本记住的最近执行过的 CRDT 更新数
+const APPLIED_CRDT_OPS: usize = 4096;
+
+/// 最近执行过的 CRDT 更新及其结果，用于对重试或重复投递的更新去重
+#[derive(Default)]
+struct AppliedOps {
+    results: HashMap<String, Crdt>,
+    order: VecDeque<String>,
+}
+
+impl AppliedOps {
+    fn get(&self, op_id: &str) -> Option<Crdt> {
+        self.results.get(op_id).cloned()
+    }
+
+    fn insert(&mut self, op_id: String, state: Crdt) {
+        if self.order.len() >= APPLIED_CRDT_OPS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.results.remove(&oldest);
+            }
+        }
+        self.order.push_back(op_id.clone());
+        self.results.insert(op_id, state);
+    }
+}
+
+/// 发往某个副本节点的请求 topic
+pub fn replica_topic(node_id: &str) -> String {
+    format!("coretex.replica.{}", node_id)
+}
+
+/// 发回协调节点的响应 topic，同一节点上的每个协调者各自独立
+fn reply_topic(node_id: &str) -> String {
+    format!("coretex.reply.{}.{}", node_id, crate::utils::generate_id())
+}
+
+/// 副本上执行的操作
+///
+/// `Get` / `Put` / `Delete` 读写带时间戳的单个值（[`Stamped`]），副本保留时间戳较晚的写入；
+/// 启用向量时钟时使用 `GetVersions` / `PutVersion`，
+/// 副本上保存的是序列化后的并发版本列表。同一个 key 不应混用两种方式。
+/// CRDT 类型的 key 只通过 `*Crdt` 操作读写，`GetVersions` 读到 CRDT 时返回 [`ReplicaReply::Crdt`]。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ReplicaOp {
+    Get { key: Bytes },
+    /// `timestamp` 为协调者写入时的混合逻辑时钟时间戳
+    Put {
+        key: Bytes,
+        value: Bytes,
+        #[serde(default)]
+        timestamp: u64,
+    },
+    /// 写入带时间戳的墓碑
+    Delete {
+        key: Bytes,
+        #[serde(default)]
+        timestamp: u64,
+    },
+    GetVersions { key: Bytes },
+    /// 与已有版本合并，只保留未被因果覆盖的版本
+    PutVersion { key: Bytes, version: Version },
+    /// 等到本副本的版本覆盖 `after`（最多 `wait_ms` 毫秒）后再返回全部版本
+    GetVersionsAfter {
+        key: Bytes,
+        after: VectorClock,
+        wait_ms: u64,
+    },
+    /// 代替宕机的 `target` 暂存写入，待其恢复后回放
+    PutHint { target: String, op: Box<ReplicaOp> },
+    /// 副本上 `range` 的 Merkle 树，用于反熵比较
+    GetTree { range: PartitionRange, depth: u32 },
+    /// `range` 中位于 `leaves` 的 key 及其值的摘要
+    GetDigests {
+        range: PartitionRange,
+        depth: u32,
+        leaves: Vec<usize>,
+    },
+    GetCrdt { key: Bytes },
+    /// 以本副本为执行者在本地状态上执行 `op`，返回更新后的状态
+    ///
+    /// `op_id` 由协调者生成，副本重复收到同一个更新时不再执行，直接返回之前的结果。
+    UpdateCrdt {
+        key: Bytes,
+        op: CrdtOp,
+        timestamp: u64,
+        op_id: String,
+    },
+    /// 把 `state` 合并进本地状态
+    MergeCrdt { key: Bytes, state: Crdt },
+    /// 把其他副本上保存的原始值合并进本地，用于分区迁移和反熵同步
+    Merge { entries: Vec<(Bytes, Bytes)> },
+    /// 副本上保存的原始值，不论其格式
+    GetStored { key: Bytes },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ReplicaReply {
+    Value(Stamped),
+    Versions(Vec<Version>),
+    Tree(MerkleTree),
+    Digests(Vec<(Bytes, u64)>),
+    Crdt(Option<Crdt>),
+    Stored(Option<Bytes>),
+    Ack,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplicaRequest {
+    pub id: u64,
+    /// 响应发往的 topic
+    pub reply_to: String,
+    pub op: ReplicaOp,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplicaResponse {
+    pub id: u64,
+    pub replica: String,
+    pub result: std::result::Result<ReplicaReply, String>,
+}
+
+/// 在本节点的存储引擎上执行其他协调节点发来的副本请求
+///
+/// 请求按到达顺序依次执行，同一协调节点对同一副本的操作不会乱序。
+pub struct ReplicaServer {
+    hints: Arc<HintStore>,
+    task: JoinHandle<()>,
+}
+
+impl ReplicaServer {
+    /// 使用默认的提示移交参数，提示保存在同一个存储引擎的 [`HINT_PREFIX`](super::HINT_PREFIX) 命名空间下
+    pub async fn start(
+        node_id: impl Into<String>,
+        storage: Arc<dyn StorageEngine>,
+        broker: Arc<dyn MessageBroker>,
+    ) -> Result<Self> {
+        let hints = HintStore::open(storage.clone(), &HintedHandoffConfig::default()).await?;
+        Self::start_with_hints(node_id, storage, Arc::new(hints), broker).await
+    }
+
+    pub async fn start_with_hints(
+        node_id: impl Into<String>,
+        storage: Arc<dyn StorageEngine>,
+        hints: Arc<HintStore>,
+        broker: Arc<dyn MessageBroker>,
+    ) -> Result<Self> {
+        let node_id = node_id.into();
+        let mut requests = broker.subscribe(&replica_topic(&node_id)).await?;
+        let task_hints = hints.clone();
+        let task = tokio::spawn(async move {
+            // 每次写入版本后唤醒等待因果依赖的读取
+            let applied = Arc::new(Notify::new());
+            let mut crdt_ops = AppliedOps::default();
+            while let Some(message) = requests.next().await {
+                let request = message
+                    .and_then(|m| Ok(serde_json::from_slice::<ReplicaRequest>(&m.data)?));
+                let request = match request {
+                    Ok(request) => request,
+                    Err(e) => {
+                        tracing::warn!("无法解析副本请求: {}", e);
+                        continue;
+                    }
+                };
+                let ReplicaRequest { id, reply_to, op } = request;
+                if let ReplicaOp::GetVersionsAfter { key, after, wait_ms } = op {
+                    // 等待期间不能阻塞后续请求，被等待的写入正是由后续请求带来的
+                    let (storage, broker, node_id, applied) =
+                        (storage.clone(), broker.clone(), node_id.clone(), applied.clone());
+                    tokio::spawn(async move {
+                        let wait = Duration::from_millis(wait_ms);
+                        let result = versions_after(storage.as_ref(), &applied, &key, &after, wait)
+                            .await
+                            .map_err(|e| e.to_string());
+                        respond(broker.as_ref(), &node_id, id, &reply_to, result).await;
+                    });
+                    continue;
+                }
+                let write = matches!(op, ReplicaOp::PutVersion { .. } | ReplicaOp::Merge { .. });
+                let result = execute(&node_id, storage.as_ref(), &task_hints, &mut crdt_ops, op)
+                    .await
+                    .map_err(|e| e.to_string());
+                if write {
+                    applied.notify_waiters();
+                }
+                respond(broker.as_ref(), &node_id, id, &reply_to, result).await;
+            }
+        });
+        Ok(Self { hints, task })
+    }
+
+    /// 本节点暂存的提示
+    pub fn hints(&self) -> Arc<HintStore> {
+        self.hints.clone()
+    }
+}
+
+impl Drop for ReplicaServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn respond(
+    broker: &dyn MessageBroker,
+    node_id: &str,
+    id: u64,
+    reply_to: &str,
+    result: std::result::Result<ReplicaReply, String>,
+) {
+    let response = ReplicaResponse {
+        id,
+        replica: node_id.to_string(),
+        result,
+    };
+    let sent = match serde_json::to_vec(&response) {
+        Ok(data) => broker.publish(reply_to, data).await,
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = sent {
+        tracing::warn!("发送副本响应失败: {}", e);
+    }
+}
+
+/// 等到本副本上 `key` 的版本覆盖 `after` 后返回这些版本，超过 `wait` 仍未覆盖则返回错误
+async fn versions_after(
+    storage: &dyn StorageEngine,
+    applied: &Notify,
+    key: &[u8],
+    after: &VectorClock,
+    wait: Duration,
+) -> Result<ReplicaReply> {
+    let deadline = Instant::now() + wait;
+    loop {
+        // 先登记再检查，避免错过检查与等待之间的写入
+        let notified = applied.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        let versions = load_versions(storage, key).await?;
+        if CausalContext::from_versions(&versions).clock().descends(after) {
+            return Ok(ReplicaReply::Versions(versions));
+        }
+        if tokio::time::timeout_at(deadline, notified).await.is_err() {
+            return Err(Error::Consistency("等待因果依赖超时".to_string()));
+        }
+    }
+}
+
+async fn execute(
+    node_id: &str,
+    storage: &dyn StorageEngine,
+    hints: &HintStore,
+    crdt_ops: &mut AppliedOps,
+    op: ReplicaOp,
+) -> Result<ReplicaReply> {
+    match op {
+        ReplicaOp::Get { key } => Ok(ReplicaReply::Value(Stamped::from_stored(storage.get(&key).await?))),
+        ReplicaOp::Put { key, value, timestamp } => {
+            put_stamped(storage, &key, Stamped::new(timestamp, Some(value))).await?;
+            Ok(ReplicaReply::Ack)
+        }
+        ReplicaOp::Delete { key, timestamp } => {
+            put_stamped(storage, &key, Stamped::new(timestamp, None)).await?;
+            Ok(ReplicaReply::Ack)
+        }
+        ReplicaOp::GetVersions { key } | ReplicaOp::GetVersionsAfter { key, .. } => {
+            let data = storage.get(&key).await?;
+            if let Some(crdt) = data.as_deref().and_then(Crdt::decode) {
+                return Ok(ReplicaReply::Crdt(Some(crdt)));
+            }
+            Ok(ReplicaReply::Versions(parse_versions(data)?))
+        }
+        ReplicaOp::PutVersion { key, version } => {
+            let mut versions = load_versions(storage, &key).await?;
+            versions.push(version);
+            let versions = reconcile(versions);
+            storage.put(&key, &serde_json::to_vec(&versions)?).await?;
+            Ok(ReplicaReply::Ack)
+        }
+        ReplicaOp::PutHint { target, op } => {
+            hints.add(&target, *op).await?;
+            Ok(ReplicaReply::Ack)
+        }
+        ReplicaOp::GetTree { range, depth } => Ok(ReplicaReply::Tree(range_tree(storage, &range, depth).await?)),
+        ReplicaOp::GetDigests { range, depth, leaves } => Ok(ReplicaReply::Digests(
+            range_digests(storage, &range, depth, Some(&leaves)).await?,
+        )),
+        ReplicaOp::GetCrdt { key } => Ok(ReplicaReply::Crdt(load_crdt(storage, &key).await?)),
+        ReplicaOp::UpdateCrdt {
+            key,
+            op,
+            timestamp,
+            op_id,
+        } => {
+            if let Some(state) = crdt_ops.get(&op_id) {
+                return Ok(ReplicaReply::Crdt(Some(state)));
+            }
+            let state = Crdt::apply(load_crdt(storage, &key).await?, node_id, op, timestamp)?;
+            storage.put(&key, &state.encode()?).await?;
+            crdt_ops.insert(op_id, state.clone());
+            Ok(ReplicaReply::Crdt(Some(state)))
+        }
+        ReplicaOp::MergeCrdt { key, state } => {
+            let merged = match load_crdt(storage, &key).await? {
+                Some(mut local) => {
+                    local.merge(&state)?;
+                    local
+                }
+                None => state,
+            };
+            storage.put(&key, &merged.encode()?).await?;
+            Ok(ReplicaReply::Ack)
+        }
+        ReplicaOp::Merge { entries } => {
+            for (key, value) in entries {
+                if let Some(value) = merge_stored(storage, &key, value).await? {
+                    storage.put(&key, &value).await?;
+                }
+            }
+            Ok(ReplicaReply::Ack)
+        }
+        ReplicaOp::GetStored { key } => Ok(ReplicaReply::Stored(storage.get(&key).await?)),
+    }
+}
+
+/// 写入 `stamped`，本地已有时间戳更晚的值时忽略
+async fn put_stamped(storage: &dyn StorageEngine, key: &[u8], stamped: Stamped) -> Result<()> {
+    let current = Stamped::from_stored(storage.get(key).await?);
+    if current.supersedes(&stamped) {
+        return Ok(());
+    }
+    storage.put(key, &stamped.encode()).await
+}
+
+/// 把另一个副本上 `key` 的原始值 `value` 合并到本地的值，返回需要写入的值，本地的值已包含 `value` 时返回 `None`
+///
+/// CRDT 状态合并、并发版本列表按因果关系合并，带时间戳的值保留较晚的一个；
+/// 未加时间戳的值无法判断先后，本地已有值时保留本地的值。
+pub(crate) async fn merge_stored(storage: &dyn StorageEngine, key: &[u8], value: Bytes) -> Result<Option<Bytes>> {
+    let Some(existing) = storage.get(key).await? else {
+        return Ok(Some(value));
+    };
+    if let (Some(mut local), Some(incoming)) = (Crdt::decode(&existing), Crdt::decode(&value)) {
+        if local.merge(&incoming).is_err() {
+            return Ok(None);
+        }
+        let local = Bytes::from(local.encode()?);
+        return Ok((local != existing).then_some(local));
+    }
+    let versions = |data: &Bytes| serde_json::from_slice::<Vec<Version>>(data).ok();
+    if let (Some(local), Some(incoming)) = (versions(&existing), versions(&value)) {
+        // 合并的版本都已被本地的版本因果覆盖时无需写入
+        if incoming.iter().all(|v| local.iter().any(|l| l.clock.descends(&v.clock))) {
+            return Ok(None);
+        }
+        let merged = reconcile(local.into_iter().chain(incoming));
+        return Ok(Some(Bytes::from(serde_json::to_vec(&merged)?)));
+    }
+    if let (Some(local), Some(incoming)) = (Stamped::decode(&existing), Stamped::decode(&value)) {
+        return Ok(incoming.supersedes(&local).then_some(value));
+    }
+    Ok(None)
+}
+
+pub(crate) async fn load_versions(storage: &dyn StorageEngine, key: &[u8]) -> Result<Vec<Version>> {
+    parse_versions(storage.get(key).await?)
+}
+
+fn parse_versions(data: Option<Bytes>) -> Result<Vec<Version>> {
+    match data {
+        Some(data) => Ok(serde_json::from_slice(&data)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// 本地保存的 CRDT 状态，key 上保存的不是 CRDT 时返回错误
+pub(crate) async fn load_crdt(storage: &dyn StorageEngine, key: &[u8]) -> Result<Option<Crdt>> {
+    match storage.get(key).await? {
+        Some(data) => Crdt::decode(&data)
+            .map(Some)
+            .ok_or_else(|| Error::Consistency("key 上保存的不是 CRDT 值".to_string())),
+        None => Ok(None),
+    }
+}
+
+/// 协调节点一侧的副本请求客户端
+///
+/// 通过消息层发送 [`ReplicaRequest`]，按请求 id 匹配从响应 topic 收到的结果。
+pub struct ReplicaClient {
+    node_id: String,
+    reply_to: String,
+    broker: Arc<dyn MessageBroker>,
+    next_id: AtomicU64,
+    pending: Arc<DashMap<u64, oneshot::Sender<ReplicaResponse>>>,
+    task: JoinHandle<()>,
+}
+
+impl ReplicaClient {
+    pub async fn start(node_id: impl Into<String>, broker: Arc<dyn MessageBroker>) -> Result<Self> {
+        let node_id = node_id.into();
+        let reply_to = reply_topic(&node_id);
+        let mut replies = broker.subscribe(&reply_to).await?;
+        let pending: Arc<DashMap<u64, oneshot::Sender<ReplicaResponse>>> = Arc::new(DashMap::new());
+        let task = {
+            let pending = pending.clone();
+            tokio::spawn(async move {
+                while let Some(message) = replies.next().await {
+                    let response = message
+                        .and_then(|m| Ok(serde_json::from_slice::<ReplicaResponse>(&m.data)?));
+                    match response {
+                        Ok(response) => {
+                            if let Some((_, tx)) = pending.remove(&response.id) {
+                                let _ = tx.send(response);
+                            }
+                        }
+                        Err(e) => tracing::warn!("无法解析副本响应: {}", e),
+                    }
+                }
+            })
+        };
+        Ok(Self {
+            node_id,
+            reply_to,
+            broker,
+            next_id: AtomicU64::new(0),
+            pending,
+            task,
+        })
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    /// 在 `replica` 上执行 `op`，超过 `timeout` 未收到响应时返回超时错误
+    pub async fn call(&self, replica: &str, op: ReplicaOp, timeout: Duration) -> Result<ReplicaReply> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.insert(id, tx);
+        let request = ReplicaRequest {
+            id,
+            reply_to: self.reply_to.clone(),
+            op,
+        };
+        let sent = match serde_json::to_vec(&request) {
+            Ok(data) => self.broker.publish(&replica_topic(replica), data).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = sent {
+            self.pending.remove(&id);
+            return Err(e);
+        }
+
+        let response = tokio::time::timeout(timeout, rx).await;
+        self.pending.remove(&id);
+        match response {
+            Ok(Ok(response)) => response.result.map_err(Error::Storage),
+            Ok(Err(_)) => Err(Error::Communication("副本响应通道已关闭".to_string())),
+            Err(_) => Err(Error::Timeout(format!("副本 {} 响应超时", replica))),
+        }
+    }
+}
+
+impl Drop for ReplicaClient {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
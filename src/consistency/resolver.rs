@@ -0,0 +1,146 @@
+use super::vclock::{VectorClock, Version};
+use crate::config::{ConflictResolution, ConsistencyConfig};
+use bytes::Bytes;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// 并发版本的冲突解决策略
+pub trait ConflictResolver: Send + Sync + 'static {
+    /// 从互相并发的版本（至少两个）中得出最终值，`None` 表示删除
+    fn resolve(&self, key: &[u8], versions: &[Version]) -> Option<Bytes>;
+}
+
+/// 时间戳最大的版本胜出，时间戳相同时比较值的字节序（墓碑最小）
+pub struct LastWriterWins;
+
+impl ConflictResolver for LastWriterWins {
+    fn resolve(&self, _key: &[u8], versions: &[Version]) -> Option<Bytes> {
+        versions
+            .iter()
+            .max_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.value.cmp(&b.value)))
+            .and_then(|v| v.value.clone())
+    }
+}
+
+/// 向量时钟计数之和最大的版本胜出，相同时退化为最后写入者胜出
+pub struct HighestVectorClock;
+
+impl ConflictResolver for HighestVectorClock {
+    fn resolve(&self, _key: &[u8], versions: &[Version]) -> Option<Bytes> {
+        versions
+            .iter()
+            .max_by(|a, b| {
+                a.clock
+                    .total()
+                    .cmp(&b.clock.total())
+                    .then_with(|| a.timestamp.cmp(&b.timestamp))
+                    .then_with(|| a.value.cmp(&b.value))
+            })
+            .and_then(|v| v.value.clone())
+    }
+}
+
+/// 由应用提供的合并函数，参数为全部并发版本的值（墓碑为 `None`）
+pub struct MergeFn<F>(pub F);
+
+impl<F> ConflictResolver for MergeFn<F>
+where
+    F: Fn(&[u8], &[Option<Bytes>]) -> Option<Bytes> + Send + Sync + 'static,
+{
+    fn resolve(&self, key: &[u8], versions: &[Version]) -> Option<Bytes> {
+        let values: Vec<Option<Bytes>> = versions.iter().map(|v| v.value.clone()).collect();
+        (self.0)(key, &values)
+    }
+}
+
+/// 命名空间对应的策略，`None` 表示保留 siblings
+type Namespace = (Vec<u8>, Option<Arc<dyn ConflictResolver>>);
+
+/// 按命名空间（key 前缀）选择冲突解决策略
+///
+/// 最长前缀优先；没有匹配的命名空间时使用默认策略，默认策略为 `None` 时保留全部 siblings。
+/// 协调者在读取时使用，副本之间的反熵同步也应使用同一份配置。
+#[derive(Clone, Default)]
+pub struct ResolverRegistry {
+    default: Option<Arc<dyn ConflictResolver>>,
+    namespaces: Vec<Namespace>,
+}
+
+impl ResolverRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_config(config: &ConsistencyConfig) -> Self {
+        let mut registry = Self::new();
+        registry.default = builtin(config.conflict_resolution);
+        for (prefix, resolution) in &config.namespaces {
+            registry.set(prefix.as_bytes(), builtin(*resolution));
+        }
+        registry
+    }
+
+    pub fn with_default(mut self, resolver: Arc<dyn ConflictResolver>) -> Self {
+        self.default = Some(resolver);
+        self
+    }
+
+    /// 为以 `prefix` 开头的 key 注册策略
+    pub fn register(&mut self, prefix: impl AsRef<[u8]>, resolver: Arc<dyn ConflictResolver>) {
+        self.set(prefix.as_ref(), Some(resolver));
+    }
+
+    /// 以 `prefix` 开头的 key 保留全部 siblings
+    pub fn keep_siblings(&mut self, prefix: impl AsRef<[u8]>) {
+        self.set(prefix.as_ref(), None);
+    }
+
+    pub fn resolver_for(&self, key: &[u8]) -> Option<&Arc<dyn ConflictResolver>> {
+        self.namespaces
+            .iter()
+            .filter(|(prefix, _)| key.starts_with(prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default.as_ref(), |(_, resolver)| resolver.as_ref())
+    }
+
+    /// 把多个并发版本合并为一个版本，其时钟覆盖全部输入版本
+    ///
+    /// 只有一个版本或 key 没有对应策略时原样返回。
+    pub fn resolve(&self, key: &[u8], versions: Vec<Version>) -> Vec<Version> {
+        if versions.len() < 2 {
+            return versions;
+        }
+        let Some(resolver) = self.resolver_for(key) else {
+            return versions;
+        };
+        let value = resolver.resolve(key, &versions);
+        let mut clock = VectorClock::new();
+        for version in &versions {
+            clock.merge(&version.clock);
+        }
+        let timestamp = versions.iter().map(|v| v.timestamp).max().unwrap_or_default();
+        let mut deps: BTreeMap<Bytes, VectorClock> = BTreeMap::new();
+        for (dep, dep_clock) in versions.iter().flat_map(|v| &v.deps) {
+            deps.entry(dep.clone()).or_default().merge(dep_clock);
+        }
+        vec![Version {
+            clock,
+            value,
+            timestamp,
+            deps,
+        }]
+    }
+
+    fn set(&mut self, prefix: &[u8], resolver: Option<Arc<dyn ConflictResolver>>) {
+        self.namespaces.retain(|(p, _)| p != prefix);
+        self.namespaces.push((prefix.to_vec(), resolver));
+    }
+}
+
+fn builtin(resolution: ConflictResolution) -> Option<Arc<dyn ConflictResolver>> {
+    match resolution {
+        ConflictResolution::Siblings => None,
+        ConflictResolution::LastWriterWins => Some(Arc::new(LastWriterWins)),
+        ConflictResolution::HighestVectorClock => Some(Arc::new(HighestVectorClock)),
+    }
+}
@@ -0,0 +1,103 @@
+use super::replica::{ReplicaClient, ReplicaOp, ReplicaReply};
+use crate::config::RetryConfig;
+use crate::error::Error;
+use crate::Result;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// 副本请求超时或无法送达时的重试策略
+///
+/// 第 n 次重试前等待 `initial_backoff * multiplier^(n-1)`，不超过 `max_backoff`。
+/// 只重试 [`Error::Timeout`] 和 [`Error::Communication`]，副本返回的存储错误重试也不会成功。
+/// 所有副本操作都可以重试：普通写入带混合逻辑时钟时间戳，迟到的重试会被副本丢弃；
+/// 版本写入按向量时钟合并，CRDT 更新按操作 id 去重。
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// 每个副本最多尝试的次数，包括第一次
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::from_config(&RetryConfig::default())
+    }
+}
+
+impl RetryPolicy {
+    /// 最多尝试 `max_attempts` 次，退避从 `initial_backoff` 开始翻倍
+    pub fn new(max_attempts: u32, initial_backoff: Duration) -> Self {
+        Self {
+            max_attempts,
+            initial_backoff,
+            ..Self::default()
+        }
+    }
+
+    /// 不重试
+    pub fn none() -> Self {
+        Self::new(1, Duration::ZERO)
+    }
+
+    pub fn from_config(config: &RetryConfig) -> Self {
+        Self {
+            max_attempts: config.max_attempts,
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+            max_backoff: Duration::from_millis(config.max_backoff_ms),
+            multiplier: config.multiplier,
+        }
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// 第 `retry` 次重试（从 1 开始）前的等待时间
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = self.multiplier.max(1.0).powi(retry.saturating_sub(1) as i32);
+        self.initial_backoff.mul_f64(factor).min(self.max_backoff)
+    }
+
+    /// 该错误是否可能在重试后消失
+    pub fn is_retryable(error: &Error) -> bool {
+        matches!(error, Error::Timeout(_) | Error::Communication(_))
+    }
+
+    /// 在 `replica` 上执行 `op`，每次尝试最多等待 `timeout`
+    ///
+    /// 不在 `deadline` 之后开始新的尝试，最后一次尝试仍会等满 `timeout`。
+    pub(crate) async fn call(
+        &self,
+        client: &ReplicaClient,
+        replica: &str,
+        op: ReplicaOp,
+        timeout: Duration,
+        deadline: Instant,
+    ) -> Result<ReplicaReply> {
+        let attempts = self.max_attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let result = client.call(replica, op.clone(), timeout).await;
+            match result {
+                Err(e) if attempt < attempts && Self::is_retryable(&e) => {
+                    let backoff = self.backoff(attempt);
+                    if Instant::now() + backoff >= deadline {
+                        return Err(e);
+                    }
+                    tracing::debug!("副本 {} 请求失败，{:?} 后重试: {}", replica, backoff, e);
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use super::vclock::{VectorClock, Version};
+use crate::Result;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 因果一致模式下客户端会话携带的令牌（依赖向量）
+///
+/// 记录会话读写过的每个 key 的版本时钟，以及读到的版本所依赖的其他 key 的时钟。
+/// 每次读写都返回新的令牌，客户端在下一次请求时带上，即使换了协调节点，
+/// 副本也会等到这些依赖都已应用后才响应读取，从而保证读己之写和单调读。
+///
+/// 令牌还记录已确认应用到足够多副本、任何法定数读取都能读到的时钟，
+/// 这些依赖不必再随新版本保存。
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionToken {
+    #[serde(with = "super::vclock::dependencies")]
+    deps: BTreeMap<Bytes, VectorClock>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty", with = "super::vclock::dependencies")]
+    applied: BTreeMap<Bytes, VectorClock>,
+}
+
+impl SessionToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 会话对 `key` 的依赖，读取结果必须包含该时钟之前的全部版本
+    pub fn dependency(&self, key: &[u8]) -> Option<&VectorClock> {
+        self.deps.get(key)
+    }
+
+    /// 除 `key` 自身之外的全部依赖，随写入保存在新版本中
+    pub fn dependencies_except(&self, key: &[u8]) -> BTreeMap<Bytes, VectorClock> {
+        self.deps
+            .iter()
+            .filter(|(dep, _)| dep.as_ref() != key)
+            .map(|(dep, clock)| (dep.clone(), clock.clone()))
+            .collect()
+    }
+
+    /// 除 `key` 自身之外尚未确认已应用的依赖，写入时只需保存这些依赖
+    pub fn pending_dependencies_except(&self, key: &[u8]) -> BTreeMap<Bytes, VectorClock> {
+        self.deps
+            .iter()
+            .filter(|(dep, clock)| dep.as_ref() != key && !self.is_applied(dep, clock))
+            .map(|(dep, clock)| (dep.clone(), clock.clone()))
+            .collect()
+    }
+
+    /// `key` 的 `clock` 是否已确认应用，空时钟不构成依赖
+    pub fn is_applied(&self, key: &[u8], clock: &VectorClock) -> bool {
+        clock.is_empty() || self.applied.get(key).is_some_and(|applied| applied.descends(clock))
+    }
+
+    /// 记录 `key` 的 `clock` 已应用到足够多的副本，任何法定数读取都能读到
+    pub fn mark_applied(&mut self, key: &[u8], clock: &VectorClock) {
+        self.applied
+            .entry(Bytes::copy_from_slice(key))
+            .or_default()
+            .merge(clock);
+    }
+
+    /// 记录会话观察到 `key` 的 `clock`
+    pub fn observe(&mut self, key: &[u8], clock: &VectorClock) {
+        self.deps
+            .entry(Bytes::copy_from_slice(key))
+            .or_default()
+            .merge(clock);
+    }
+
+    /// 记录读到的版本及其携带的依赖
+    pub fn observe_version(&mut self, key: &[u8], version: &Version) {
+        self.observe(key, &version.clock);
+        for (dep, clock) in &version.deps {
+            self.observe(dep, clock);
+        }
+    }
+
+    /// 合并另一个令牌，用于同一客户端的并发请求
+    pub fn merge(&mut self, other: &SessionToken) {
+        for (key, clock) in &other.deps {
+            self.observe(key, clock);
+        }
+        for (key, clock) in &other.applied {
+            self.mark_applied(key, clock);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.deps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deps.is_empty()
+    }
+
+    /// 编码为客户端可以原样保存和回传的字节
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
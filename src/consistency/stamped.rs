@@ -0,0 +1,59 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+/// 存储中带时间戳的普通值的前缀，用于与未加时间戳的值、版本列表和 CRDT 区分
+pub const STAMPED_TAG: &[u8] = b"\x00lww:";
+
+/// 不启用向量时钟时副本上保存的值
+///
+/// 附带协调者写入时的混合逻辑时钟时间戳（[`crate::utils::HybridTimestamp`]），
+/// 删除保存为 `value` 为 `None` 的墓碑。副本只保留时间戳较晚的写入，
+/// 迟到或重试的 `Put` / `Delete` 不会覆盖之后的写入。墓碑不会被清理。
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stamped {
+    pub timestamp: u64,
+    pub value: Option<Bytes>,
+}
+
+impl Stamped {
+    pub fn new(timestamp: u64, value: Option<Bytes>) -> Self {
+        Self { timestamp, value }
+    }
+
+    /// 是否应覆盖 `other`
+    ///
+    /// 先比较时间戳，时间戳相同时按值比较（墓碑最小），各副本对同一组写入得出相同结果。
+    pub fn supersedes(&self, other: &Stamped) -> bool {
+        (self.timestamp, &self.value) > (other.timestamp, &other.value)
+    }
+
+    /// 带 [`STAMPED_TAG`] 前缀的存储格式：8 字节大端时间戳、1 字节是否有值，之后是值本身
+    pub fn encode(&self) -> Vec<u8> {
+        let value = self.value.as_deref().unwrap_or_default();
+        let mut data = Vec::with_capacity(STAMPED_TAG.len() + 9 + value.len());
+        data.extend_from_slice(STAMPED_TAG);
+        data.extend_from_slice(&self.timestamp.to_be_bytes());
+        data.push(self.value.is_some() as u8);
+        data.extend_from_slice(value);
+        data
+    }
+
+    /// 解析存储中的值，不带 [`STAMPED_TAG`] 前缀时返回 `None`
+    pub fn decode(data: &[u8]) -> Option<Stamped> {
+        let data = data.strip_prefix(STAMPED_TAG)?;
+        let (timestamp, rest) = data.split_first_chunk::<8>()?;
+        let (present, value) = rest.split_first()?;
+        Some(Stamped {
+            timestamp: u64::from_be_bytes(*timestamp),
+            value: (*present != 0).then(|| Bytes::copy_from_slice(value)),
+        })
+    }
+
+    /// 解析存储中的值，未加时间戳的值视为时间戳为 0 的写入
+    pub fn from_stored(data: Option<Bytes>) -> Stamped {
+        match data {
+            Some(data) => Stamped::decode(&data).unwrap_or(Stamped::new(0, Some(data))),
+            None => Stamped::default(),
+        }
+    }
+}
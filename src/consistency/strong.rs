@@ -0,0 +1,314 @@
+use super::raft::{check_user_key, Command, RaftClient, RaftGroup, RaftNode, RaftStatus};
+use super::{Acknowledged, ConsistencyEvent, ConsistencyLevel, ConsistencyManager, EventBus, EventFilter};
+use crate::config::RaftConfig;
+use crate::distribution::{PartitionMap, PartitionRange};
+use crate::error::Error;
+use crate::messaging::MessageBroker;
+use crate::storage::StorageEngine;
+use crate::Result;
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// 强一致（线性一致）模式，对应 `ConsistencyMode::Strong`
+///
+/// 按节点之间交换的分区映射（[`PartitionMapExchange::subscribe`](crate::distribution::PartitionMapExchange::subscribe)）
+/// 把分区划分为 Raft 组（副本集合相同的分区共用一组），本节点参与的组各运行一个 [`RaftNode`]。
+/// 写入作为命令提交到 key 所在组的日志，读取由 leader 确认任期后直接读取（不写入日志）；
+/// 非成员节点同样可以作为协调者转发请求。
+///
+/// 收到更新的映射后重新划分：沿用原 id 的组原地更新成员和分区，本节点新加入的组启动新的成员，
+/// 离开的组停止运行并删除本地的日志。新组中之前就是这些分区副本的成员先应用完原来各组已提交的日志，
+/// 以此作为初始快照，没有数据的成员从 leader 获得快照。映射中不足副本数个节点（例如通过种子节点加入、尚未发现其他成员）时
+/// 不据此划分：启动时等待，超过 `startup_timeout_ms` 仍不足则拒绝启动，避免各节点各自组成成员不足的 Raft 组。
+///
+/// 每个请求都是线性一致的，指定 [`ConsistencyLevel`] 时忽略级别，
+/// 确认数为提交日志所需的组内多数。
+pub struct StrongConsistencyManager {
+    groups: Arc<Groups>,
+    timeout: Duration,
+    events: EventBus,
+    task: JoinHandle<()>,
+}
+
+impl StrongConsistencyManager {
+    pub async fn start(
+        node_id: impl Into<String>,
+        mut maps: watch::Receiver<Arc<PartitionMap>>,
+        storage: Arc<dyn StorageEngine>,
+        broker: Arc<dyn MessageBroker>,
+        config: RaftConfig,
+    ) -> Result<Self> {
+        let node_id = node_id.into();
+        let map = wait_for_nodes(&mut maps, Duration::from_millis(config.startup_timeout_ms)).await?;
+        let timeout = Duration::from_millis(config.request_timeout_ms);
+        let groups = Arc::new(Groups {
+            client: RaftClient::start(node_id.clone(), broker.clone()).await?,
+            node_id,
+            storage,
+            broker,
+            timeout,
+            config,
+            groups: ArcSwap::from_pointee(Vec::new()),
+            nodes: Mutex::new(BTreeMap::new()),
+        });
+        groups.apply(&map).await?;
+        let task = {
+            let groups = groups.clone();
+            tokio::spawn(async move {
+                while maps.changed().await.is_ok() {
+                    let map = maps.borrow_and_update().clone();
+                    if !enough_nodes(&map) {
+                        tracing::warn!("分区映射中只有 {} 个节点，不据此调整 Raft 组", map.nodes.len());
+                        continue;
+                    }
+                    if let Err(e) = groups.apply(&map).await {
+                        tracing::warn!("调整 Raft 组失败: {}", e);
+                    }
+                }
+            })
+        };
+        Ok(Self {
+            groups,
+            timeout,
+            events: EventBus::default(),
+            task,
+        })
+    }
+
+    /// 每个事件订阅者最多缓冲的事件数
+    pub fn with_event_buffer(mut self, capacity: usize) -> Self {
+        self.events = EventBus::new(capacity);
+        self
+    }
+
+    /// 按当前分区映射划分的 Raft 组
+    pub fn groups(&self) -> Arc<Vec<RaftGroup>> {
+        self.groups.groups.load_full()
+    }
+
+    /// 本节点参与的各 Raft 组的状态
+    pub fn raft_status(&self) -> Vec<(String, RaftStatus)> {
+        self.groups
+            .nodes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(group, node)| (group.clone(), node.status()))
+            .collect()
+    }
+
+    fn group_for(&self, key: &[u8]) -> Result<RaftGroup> {
+        check_user_key(key)?;
+        self.groups
+            .groups
+            .load()
+            .iter()
+            .find(|group| group.contains(key))
+            .cloned()
+            .ok_or_else(|| Error::Consistency("没有负责该 key 的 Raft 组".to_string()))
+    }
+
+    async fn propose(&self, key: &[u8], command: Command) -> Result<()> {
+        let group = self.group_for(key)?;
+        self.groups.client.propose(&group, command, self.timeout).await
+    }
+
+    /// 提交日志时确认的副本数
+    fn majority(&self, key: &[u8]) -> Result<usize> {
+        Ok(self.group_for(key)?.members.len() / 2 + 1)
+    }
+}
+
+impl Drop for StrongConsistencyManager {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// 当前的 Raft 组及本节点参与的组的成员
+struct Groups {
+    node_id: String,
+    client: RaftClient,
+    storage: Arc<dyn StorageEngine>,
+    broker: Arc<dyn MessageBroker>,
+    timeout: Duration,
+    config: RaftConfig,
+    groups: ArcSwap<Vec<RaftGroup>>,
+    nodes: Mutex<BTreeMap<String, RaftNode>>,
+}
+
+impl Groups {
+    /// 按分区映射重新划分 Raft 组
+    async fn apply(&self, map: &PartitionMap) -> Result<()> {
+        let groups = RaftGroup::from_map(map);
+        if groups == **self.groups.load() {
+            return Ok(());
+        }
+        // 本节点之前参与的组，其分区的数据已在本地存储中
+        let previous: Vec<(RaftGroup, watch::Receiver<RaftStatus>)> = {
+            let nodes = self.nodes.lock().unwrap();
+            self.groups
+                .load()
+                .iter()
+                .filter_map(|g| nodes.get(&g.id).map(|node| (g.clone(), node.watch_status())))
+                .collect()
+        };
+        let held: Vec<PartitionRange> = previous.iter().flat_map(|(g, _)| g.ranges.iter().cloned()).collect();
+        let joined: Vec<RaftGroup> = {
+            let nodes = self.nodes.lock().unwrap();
+            let mut joined = Vec::new();
+            for group in groups.iter().filter(|g| g.members.contains(&self.node_id)) {
+                match nodes.get(&group.id) {
+                    Some(node) => node.reconfigure(group.clone()),
+                    None => joined.push(group.clone()),
+                }
+            }
+            joined
+        };
+        for group in joined {
+            let id = group.id.clone();
+            let (node_id, storage, broker, config) = (
+                self.node_id.clone(),
+                self.storage.clone(),
+                self.broker.clone(),
+                self.config.clone(),
+            );
+            let node = if !held.is_empty() && group.covered_by(&held) {
+                let mut applied = 0;
+                for (old, status) in previous.iter().filter(|(g, _)| g.overlaps(&group)) {
+                    applied += self.catch_up(old, status.clone()).await;
+                }
+                RaftNode::start_with_data(group, node_id, storage, broker, config, applied).await?
+            } else {
+                RaftNode::start(group, node_id, storage, broker, config).await?
+            };
+            self.nodes.lock().unwrap().insert(id, node);
+        }
+        let left: Vec<RaftNode> = {
+            let mut nodes = self.nodes.lock().unwrap();
+            let member_of = |id: &String| {
+                groups
+                    .iter()
+                    .any(|g| g.id == *id && g.members.contains(&self.node_id))
+            };
+            let ids: Vec<String> = nodes.keys().filter(|id| !member_of(id)).cloned().collect();
+            ids.iter().filter_map(|id| nodes.remove(id)).collect()
+        };
+        for node in left {
+            node.remove().await?;
+        }
+        self.groups.store(Arc::new(groups));
+        Ok(())
+    }
+
+    /// 等待本节点应用完 `group` 中已提交的日志，返回已应用的位置
+    ///
+    /// 以此作为新组的初始快照位置，保证新组的 leader 拥有变更前已确认的全部写入。
+    async fn catch_up(&self, group: &RaftGroup, mut status: watch::Receiver<RaftStatus>) -> u64 {
+        let caught_up = async {
+            let index = self.client.read_index(group, self.timeout).await?;
+            tokio::time::timeout(self.timeout, status.wait_for(|s| s.last_applied >= index))
+                .await
+                .map_err(|_| Error::Consistency(format!("未能在 {:?} 内应用到位置 {}", self.timeout, index)))?
+                .map_err(|_| Error::Consistency("Raft 组已停止".to_string()))?;
+            Ok::<_, Error>(())
+        };
+        if let Err(e) = caught_up.await {
+            tracing::warn!("等待 Raft 组 {} 的日志失败: {}", group.id, e);
+        }
+        let applied = status.borrow().last_applied;
+        applied
+    }
+}
+
+#[async_trait]
+impl ConsistencyManager for StrongConsistencyManager {
+    async fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let key = Bytes::copy_from_slice(key);
+        let value = Bytes::copy_from_slice(value);
+        let command = Command::Put {
+            key: key.clone(),
+            value: value.clone(),
+        };
+        self.propose(&key, command).await?;
+        self.events.publish(ConsistencyEvent::WriteCommitted { key, value });
+        Ok(())
+    }
+
+    async fn get(&self, key: &[u8]) -> Result<Option<Bytes>> {
+        let group = self.group_for(key)?;
+        self.groups.client.read(&group, key, self.timeout).await
+    }
+
+    async fn delete(&self, key: &[u8]) -> Result<()> {
+        let command = Command::Delete {
+            key: Bytes::copy_from_slice(key),
+        };
+        self.propose(key, command).await?;
+        Ok(())
+    }
+
+    async fn put_with_level(&self, key: &[u8], value: &[u8], _level: ConsistencyLevel) -> Result<Acknowledged<()>> {
+        self.put(key, value).await?;
+        Ok(Acknowledged::new((), self.majority(key)?))
+    }
+
+    async fn get_with_level(&self, key: &[u8], _level: ConsistencyLevel) -> Result<Acknowledged<Option<Bytes>>> {
+        let value = self.get(key).await?;
+        Ok(Acknowledged::new(value, self.majority(key)?))
+    }
+
+    async fn delete_with_level(&self, key: &[u8], _level: ConsistencyLevel) -> Result<Acknowledged<()>> {
+        self.delete(key).await?;
+        Ok(Acknowledged::new((), self.majority(key)?))
+    }
+
+    /// 副本由 Raft 日志保持一致，无需读修复
+    async fn read_repair(&self, _key: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    async fn watch_events(
+        &self,
+    ) -> Result<futures::stream::BoxStream<'static, ConsistencyEvent>> {
+        Ok(self.events.subscribe(EventFilter::all()))
+    }
+
+    async fn subscribe_events(
+        &self,
+        filter: EventFilter,
+    ) -> Result<futures::stream::BoxStream<'static, ConsistencyEvent>> {
+        Ok(self.events.subscribe(filter))
+    }
+}
+
+/// 映射中的节点数不少于副本数
+fn enough_nodes(map: &PartitionMap) -> bool {
+    map.nodes.len() >= map.replica_count
+}
+
+/// 等待分区映射中至少有副本数个节点，超时返回错误
+async fn wait_for_nodes(maps: &mut watch::Receiver<Arc<PartitionMap>>, timeout: Duration) -> Result<Arc<PartitionMap>> {
+    let ready = tokio::time::timeout(timeout, async {
+        maps.wait_for(|map| enough_nodes(map)).await.map(|map| map.clone())
+    })
+    .await;
+    match ready {
+        Ok(Ok(map)) => Ok(map),
+        Ok(Err(_)) => Err(Error::Membership("分区映射已关闭".to_string())),
+        Err(_) => {
+            let map = maps.borrow();
+            Err(Error::Consistency(format!(
+                "强一致模式需要分区映射中至少 {} 个节点，当前只有 {} 个",
+                map.replica_count,
+                map.nodes.len()
+            )))
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::Result;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 两个向量时钟之间的因果关系
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Causality {
+    /// 前者发生在后者之前
+    Before,
+    /// 前者发生在后者之后
+    After,
+    Equal,
+    /// 互不包含，即并发
+    Concurrent,
+}
+
+/// 向量时钟：节点 id 到该节点写入计数的映射
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VectorClock {
+    entries: BTreeMap<String, u64>,
+}
+
+impl VectorClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, node_id: &str) -> u64 {
+        self.entries.get(node_id).copied().unwrap_or(0)
+    }
+
+    /// 把 `node_id` 的计数设为 `counter`（只增不减）
+    pub fn advance(&mut self, node_id: &str, counter: u64) {
+        let entry = self.entries.entry(node_id.to_string()).or_insert(0);
+        *entry = (*entry).max(counter);
+    }
+
+    pub fn increment(&mut self, node_id: &str) -> u64 {
+        let counter = self.get(node_id) + 1;
+        self.advance(node_id, counter);
+        counter
+    }
+
+    /// 逐项取最大值
+    pub fn merge(&mut self, other: &VectorClock) {
+        for (node_id, counter) in &other.entries {
+            self.advance(node_id, *counter);
+        }
+    }
+
+    pub fn compare(&self, other: &VectorClock) -> Causality {
+        let mut less = false;
+        let mut greater = false;
+        for node_id in self.entries.keys().chain(other.entries.keys()) {
+            let (a, b) = (self.get(node_id), other.get(node_id));
+            less |= a < b;
+            greater |= a > b;
+        }
+        match (less, greater) {
+            (false, false) => Causality::Equal,
+            (true, false) => Causality::Before,
+            (false, true) => Causality::After,
+            (true, true) => Causality::Concurrent,
+        }
+    }
+
+    /// 是否包含 `other` 的全部历史（相等也算）
+    pub fn descends(&self, other: &VectorClock) -> bool {
+        matches!(self.compare(other), Causality::After | Causality::Equal)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+
+    /// 各节点计数之和
+    pub fn total(&self) -> u64 {
+        self.entries.values().sum()
+    }
+}
+
+/// 带向量时钟的一个版本，`value` 为 `None` 表示删除（墓碑）
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Version {
+    pub clock: VectorClock,
+    pub value: Option<Bytes>,
+    /// 协调者写入时的混合逻辑时钟时间戳（[`crate::utils::HybridTimestamp`]），用于最后写入者胜出
+    #[serde(default)]
+    pub timestamp: u64,
+    /// 因果一致模式下写入时会话已观察到的其他 key 的版本，读到该版本的会话同样依赖它们
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty", with = "dependencies")]
+    pub deps: BTreeMap<Bytes, VectorClock>,
+}
+
+/// 依赖表按 `(key, clock)` 列表编码，JSON 对象的键只能是字符串
+pub(crate) mod dependencies {
+    use super::VectorClock;
+    use bytes::Bytes;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer>(deps: &BTreeMap<Bytes, VectorClock>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(deps.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<Bytes, VectorClock>, D::Error> {
+        Ok(Vec::<(Bytes, VectorClock)>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
+impl Version {
+    pub fn is_tombstone(&self) -> bool {
+        self.value.is_none()
+    }
+}
+
+/// 去掉被其他版本因果覆盖的版本，只保留互相并发的版本（siblings）
+///
+/// 时钟相同的版本只保留先出现的一个。
+pub fn reconcile(versions: impl IntoIterator<Item = Version>) -> Vec<Version> {
+    let mut kept: Vec<Version> = Vec::new();
+    for version in versions {
+        if kept.iter().any(|k| k.clock.descends(&version.clock)) {
+            continue;
+        }
+        kept.retain(|k| !version.clock.descends(&k.clock));
+        kept.push(version);
+    }
+    kept
+}
+
+/// 读取时返回给客户端的因果上下文
+///
+/// 下一次写入带上该上下文时，新版本会覆盖上下文中已知的全部版本。
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CausalContext {
+    clock: VectorClock,
+}
+
+impl CausalContext {
+    pub fn new(clock: VectorClock) -> Self {
+        Self { clock }
+    }
+
+    /// 合并多个版本的时钟
+    pub fn from_versions<'a>(versions: impl IntoIterator<Item = &'a Version>) -> Self {
+        let mut clock = VectorClock::new();
+        for version in versions {
+            clock.merge(&version.clock);
+        }
+        Self { clock }
+    }
+
+    pub fn clock(&self) -> &VectorClock {
+        &self.clock
+    }
+
+    /// 编码为客户端可以原样保存和回传的字节
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
+
+/// 一个 key 当前的全部并发值及其因果上下文
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Siblings {
+    /// 互相并发的值，不含墓碑，按字节序排列；为空表示 key 不存在或已删除
+    pub values: Vec<Bytes>,
+    pub context: CausalContext,
+}
+
+impl Siblings {
+    pub fn from_versions(versions: &[Version]) -> Self {
+        let mut values: Vec<Bytes> = versions.iter().filter_map(|v| v.value.clone()).collect();
+        values.sort();
+        values.dedup();
+        Self {
+            values,
+            context: CausalContext::from_versions(versions),
+        }
+    }
+
+    pub fn is_conflict(&self) -> bool {
+        self.values.len() > 1
+    }
+}
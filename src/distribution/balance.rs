@@ -0,0 +1,515 @@
+use super::{PartitionMap, PartitionRange, RingSnapshot, SharedRing};
+use crate::config::{BalanceMetric, LoadAwareConfig};
+use crate::consistency::is_internal;
+use crate::messaging::MessageBroker;
+use crate::storage::StorageEngine;
+use crate::Result;
+use arc_swap::ArcSwap;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// 按分区统计请求次数
+///
+/// 统计基于某个版本的分区映射；分布环更新后调用 [`LoadTracker::reset`] 切换到新版本。
+pub struct LoadTracker {
+    state: ArcSwap<TrackerState>,
+}
+
+struct TrackerState {
+    map: PartitionMap,
+    requests: Vec<AtomicU64>,
+    since: Instant,
+}
+
+impl LoadTracker {
+    pub fn new(map: PartitionMap) -> Self {
+        Self {
+            state: ArcSwap::from_pointee(TrackerState::new(map)),
+        }
+    }
+
+    /// 记录一次对 `key` 的请求
+    pub fn record(&self, key: &[u8]) {
+        let state = self.state.load();
+        let hit = state.map.route_index(key).and_then(|idx| state.requests.get(idx));
+        if let Some(counter) = hit {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 切换到新的分区映射并清零统计
+    pub fn reset(&self, map: PartitionMap) {
+        self.state.store(Arc::new(TrackerState::new(map)));
+    }
+
+    /// 各分区的请求速率（次/秒），顺序与分区映射一致
+    fn rates(&self) -> (Arc<TrackerState>, Vec<f64>) {
+        let state = self.state.load_full();
+        let secs = state.since.elapsed().as_secs_f64().max(1e-3);
+        let rates = state
+            .requests
+            .iter()
+            .map(|c| c.load(Ordering::Relaxed) as f64 / secs)
+            .collect();
+        (state, rates)
+    }
+}
+
+impl TrackerState {
+    fn new(map: PartitionMap) -> Self {
+        let requests = map.partitions.iter().map(|_| AtomicU64::new(0)).collect();
+        Self {
+            map,
+            requests,
+            since: Instant::now(),
+        }
+    }
+}
+
+/// 单个节点的负载
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodeLoad {
+    pub node_id: String,
+    /// 节点权重，代表容量
+    pub weight: u64,
+    /// 负载系数，见 [`adjust_load_factors`]
+    pub load_factor: f64,
+    /// 节点上存储的 key 数（含副本）
+    pub keys: u64,
+    pub bytes: u64,
+    /// 该节点作为主节点承担的请求速率
+    pub requests_per_sec: f64,
+}
+
+/// 单个分区的负载（按主副本统计）
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RangeLoad {
+    pub range: PartitionRange,
+    pub replicas: Vec<String>,
+    pub keys: u64,
+    pub bytes: u64,
+    pub requests_per_sec: f64,
+}
+
+/// 某项指标相对理想分布（按权重比例分摊）的偏差
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Deviation {
+    pub total: f64,
+    /// 各节点实际值与理想值之差的标准差
+    pub stddev: f64,
+    /// 标准差与节点平均值之比
+    pub relative: f64,
+    /// 实际值与理想值之比的最大值
+    pub max_over_ideal: f64,
+}
+
+/// 负载均衡报告
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BalanceReport {
+    pub epoch: u64,
+    pub nodes: Vec<NodeLoad>,
+    pub ranges: Vec<RangeLoad>,
+    pub keys: Deviation,
+    pub bytes: Deviation,
+    pub requests: Deviation,
+}
+
+/// 单个节点的负载统计，由 [`LoadBalancer`] 在节点之间交换后汇总成 [`BalanceReport`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodeStats {
+    pub node_id: String,
+    /// 节点上存储的 key 数（含副本）
+    pub keys: u64,
+    pub bytes: u64,
+    /// 按本节点的分区映射统计：本节点作为主副本存储的 key 数和数据量，以及经本节点协调的请求速率
+    pub ranges: Vec<RangeLoad>,
+}
+
+impl NodeStats {
+    /// 扫描本节点的存储，并读取本节点作为协调者记录的请求统计
+    ///
+    /// `tracker` 基于的分区映射与 `map` 版本不同时不计入请求速率。
+    pub async fn collect(
+        node_id: &str,
+        storage: &dyn StorageEngine,
+        map: &PartitionMap,
+        tracker: Option<&LoadTracker>,
+    ) -> Result<Self> {
+        let mut stats = Self {
+            node_id: node_id.to_string(),
+            keys: 0,
+            bytes: 0,
+            ranges: range_loads(map),
+        };
+        let mut items = storage.scan(b"", None, None).await?;
+        while let Some(kv) = items.next().await {
+            let kv = kv?;
+            // Raft 状态和提示不是分区数据
+            if is_internal(&kv.key) {
+                continue;
+            }
+            let size = (kv.key.len() + kv.value.len()) as u64;
+            stats.keys += 1;
+            stats.bytes += size;
+            if let Some(range) = map.route_index(&kv.key).map(|idx| &mut stats.ranges[idx]) {
+                if range.replicas.first().map(String::as_str) == Some(node_id) {
+                    range.keys += 1;
+                    range.bytes += size;
+                }
+            }
+        }
+        if let Some(tracker) = tracker {
+            stats.add_requests(map, tracker);
+        }
+        Ok(stats)
+    }
+
+    /// 计入 `tracker` 中基于 `map` 同一版本的请求速率
+    fn add_requests(&mut self, map: &PartitionMap, tracker: &LoadTracker) {
+        let (tracked, rates) = tracker.rates();
+        if tracked.map.epoch == map.epoch && tracked.map.partitions.len() == self.ranges.len() {
+            for (range, rate) in self.ranges.iter_mut().zip(rates) {
+                range.requests_per_sec += rate;
+            }
+        }
+    }
+}
+
+fn range_loads(map: &PartitionMap) -> Vec<RangeLoad> {
+    map.partitions
+        .iter()
+        .map(|p| RangeLoad {
+            range: p.range.clone(),
+            replicas: p.replicas.clone(),
+            keys: 0,
+            bytes: 0,
+            requests_per_sec: 0.0,
+        })
+        .collect()
+}
+
+impl BalanceReport {
+    /// 扫描同一进程内各节点的存储引擎并结合请求统计生成报告
+    ///
+    /// `engines` 中缺失的节点其 key 数与数据量按 0 计。节点分布在多个进程时由
+    /// [`LoadBalancer`] 交换各节点的 [`NodeStats`] 后通过 [`BalanceReport::from_stats`] 生成。
+    pub async fn collect(
+        ring: &SharedRing,
+        engines: &HashMap<String, Arc<dyn StorageEngine>>,
+        tracker: &LoadTracker,
+        replica_count: usize,
+    ) -> Result<Self> {
+        let snapshot = ring.snapshot();
+        let map = snapshot.partition_map(replica_count);
+        let mut stats = Vec::with_capacity(engines.len());
+        for (node_id, engine) in engines {
+            stats.push(NodeStats::collect(node_id, engine.as_ref(), &map, None).await?);
+        }
+        // 请求统计不属于任何一个节点的存储，单独作为一份只含请求速率的统计计入
+        let mut requests = NodeStats {
+            node_id: String::new(),
+            keys: 0,
+            bytes: 0,
+            ranges: range_loads(&map),
+        };
+        requests.add_requests(&map, tracker);
+        stats.push(requests);
+        Ok(Self::build(&snapshot, &map, &stats))
+    }
+
+    /// 汇总各节点的统计生成报告
+    ///
+    /// 各节点的统计按其本地分区映射计算，只计入与本节点当前映射中相同的分区；
+    /// 缺少统计的节点其负载按 0 计。
+    pub fn from_stats(ring: &SharedRing, stats: &[NodeStats], replica_count: usize) -> Self {
+        let snapshot = ring.snapshot();
+        let map = snapshot.partition_map(replica_count);
+        Self::build(&snapshot, &map, stats)
+    }
+
+    fn build(snapshot: &RingSnapshot, map: &PartitionMap, stats: &[NodeStats]) -> Self {
+        let mut ranges = range_loads(map);
+        let mut nodes: Vec<NodeLoad> = map
+            .nodes
+            .iter()
+            .map(|n| NodeLoad {
+                node_id: n.node.id.clone(),
+                weight: n.node.weight,
+                load_factor: snapshot.strategy.load_factor(&n.node.id),
+                keys: 0,
+                bytes: 0,
+                requests_per_sec: 0.0,
+            })
+            .collect();
+        let index: HashMap<String, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.node_id.clone(), i))
+            .collect();
+        let range_index: HashMap<&PartitionRange, usize> =
+            map.partitions.iter().enumerate().map(|(i, p)| (&p.range, i)).collect();
+
+        for node_stats in stats {
+            if let Some(&idx) = index.get(&node_stats.node_id) {
+                nodes[idx].keys += node_stats.keys;
+                nodes[idx].bytes += node_stats.bytes;
+            }
+            for load in &node_stats.ranges {
+                if let Some(&idx) = range_index.get(&load.range) {
+                    ranges[idx].keys += load.keys;
+                    ranges[idx].bytes += load.bytes;
+                    ranges[idx].requests_per_sec += load.requests_per_sec;
+                }
+            }
+        }
+        for range in &ranges {
+            if let Some(&idx) = range.replicas.first().and_then(|id| index.get(id)) {
+                nodes[idx].requests_per_sec += range.requests_per_sec;
+            }
+        }
+
+        let keys = deviation(&nodes, |n| n.keys as f64);
+        let bytes = deviation(&nodes, |n| n.bytes as f64);
+        let requests = deviation(&nodes, |n| n.requests_per_sec);
+        Self {
+            epoch: map.epoch,
+            nodes,
+            ranges,
+            keys,
+            bytes,
+            requests,
+        }
+    }
+
+    pub fn deviation(&self, metric: BalanceMetric) -> &Deviation {
+        match metric {
+            BalanceMetric::Keys => &self.keys,
+            BalanceMetric::Bytes => &self.bytes,
+            BalanceMetric::Requests => &self.requests,
+        }
+    }
+
+    fn value(node: &NodeLoad, metric: BalanceMetric) -> f64 {
+        match metric {
+            BalanceMetric::Keys => node.keys as f64,
+            BalanceMetric::Bytes => node.bytes as f64,
+            BalanceMetric::Requests => node.requests_per_sec,
+        }
+    }
+}
+
+fn deviation(nodes: &[NodeLoad], value: impl Fn(&NodeLoad) -> f64) -> Deviation {
+    if nodes.is_empty() {
+        return Deviation::default();
+    }
+    let total: f64 = nodes.iter().map(&value).sum();
+    let total_weight: f64 = nodes.iter().map(|n| n.weight.max(1) as f64).sum();
+    let mut sum_sq = 0.0;
+    let mut max_over_ideal: f64 = 0.0;
+    for node in nodes {
+        let ideal = total * node.weight.max(1) as f64 / total_weight;
+        let actual = value(node);
+        sum_sq += (actual - ideal).powi(2);
+        if ideal > 0.0 {
+            max_over_ideal = max_over_ideal.max(actual / ideal);
+        }
+    }
+    let stddev = (sum_sq / nodes.len() as f64).sqrt();
+    let mean = total / nodes.len() as f64;
+    Deviation {
+        total,
+        stddev,
+        relative: if mean > 0.0 { stddev / mean } else { 0.0 },
+        max_over_ideal,
+    }
+}
+
+/// 一次负载系数调整
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LoadFactorChange {
+    pub node_id: String,
+    pub from: f64,
+    pub to: f64,
+}
+
+/// 根据报告调整节点的负载系数（一致性哈希环即调整 token 数量，rendezvous 即调整打分权重）
+///
+/// 节点权重代表容量保持不变；负载高于按权重计算的理想值的节点降低系数，反之提高，
+/// 单次变化受 `max_step` 限制。返回实际发生的调整；有调整时分布环发布新版本。
+/// 不支持负载系数的策略（jump、range）不做任何调整。
+pub fn adjust_load_factors(
+    ring: &SharedRing,
+    report: &BalanceReport,
+    config: &LoadAwareConfig,
+) -> Vec<LoadFactorChange> {
+    let deviation = report.deviation(config.metric);
+    if deviation.relative <= config.tolerance || deviation.total <= 0.0 {
+        return Vec::new();
+    }
+    let total_weight: f64 = report.nodes.iter().map(|n| n.weight.max(1) as f64).sum();
+    let changes: Vec<LoadFactorChange> = report
+        .nodes
+        .iter()
+        .filter_map(|node| {
+            let ideal = deviation.total * node.weight.max(1) as f64 / total_weight;
+            let actual = BalanceReport::value(node, config.metric);
+            let ratio = if actual > 0.0 { ideal / actual } else { 1.0 + config.max_step };
+            let ratio = ratio.clamp(1.0 - config.max_step, 1.0 + config.max_step);
+            let to = node.load_factor * ratio;
+            ((to - node.load_factor).abs() > f64::EPSILON).then(|| LoadFactorChange {
+                node_id: node.node_id.clone(),
+                from: node.load_factor,
+                to,
+            })
+        })
+        .collect();
+    apply_load_factors(ring, changes)
+}
+
+/// 把节点的负载系数设为调整的目标值，返回实际生效的调整
+fn apply_load_factors(ring: &SharedRing, mut changes: Vec<LoadFactorChange>) -> Vec<LoadFactorChange> {
+    if changes.is_empty() {
+        return changes;
+    }
+    ring.update(|strategy| {
+        changes.retain(|change| {
+            (strategy.load_factor(&change.node_id) - change.to).abs() > f64::EPSILON
+                && strategy.set_load_factor(&change.node_id, change.to)
+        });
+        !changes.is_empty()
+    });
+    changes
+}
+
+/// 节点之间交换负载统计和调整的 topic
+pub const LOAD_TOPIC: &str = "coretex.load";
+
+#[derive(Serialize, Deserialize)]
+enum LoadMessage {
+    Stats(NodeStats),
+    Adjust(Vec<LoadFactorChange>),
+}
+
+/// 在节点之间交换负载统计并按负载调整负载系数（对应 `DistributionConfig::load_aware`）
+///
+/// 各节点每隔 `interval_ms` 扫描本地存储、读取本节点协调的请求统计（[`LoadTracker`]），
+/// 把 [`NodeStats`] 广播给所有节点后清零请求统计。分布环中标识最小的节点在收到环中每个节点
+/// 最近的统计后汇总成 [`BalanceReport`]，调整本地的负载系数并把生效的调整广播出去，
+/// 其他节点应用同样的目标值，各节点的分布环因此保持一致；随后的数据迁移由各节点的
+/// [`RebalanceService`](super::RebalanceService) 完成。
+pub struct LoadBalancer {
+    task: JoinHandle<()>,
+}
+
+impl LoadBalancer {
+    pub async fn start(
+        node_id: impl Into<String>,
+        ring: Arc<SharedRing>,
+        storage: Arc<dyn StorageEngine>,
+        tracker: Arc<LoadTracker>,
+        broker: Arc<dyn MessageBroker>,
+        replica_count: usize,
+        config: LoadAwareConfig,
+    ) -> Result<Self> {
+        let node_id = node_id.into();
+        let mut incoming = broker.subscribe(LOAD_TOPIC).await?;
+        let mut epochs = ring.subscribe();
+        let interval = Duration::from_millis(config.interval_ms.max(1));
+        let task = tokio::spawn(async move {
+            // 各节点最近一次上报的统计及收到的时间
+            let mut latest: HashMap<String, (NodeStats, Instant)> = HashMap::new();
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    changed = epochs.changed() => {
+                        if changed.is_err() {
+                            break;
+                        }
+                        // 请求统计按分区记录，分区随分布环变化
+                        tracker.reset(ring.snapshot().partition_map(replica_count));
+                    }
+                    message = incoming.next() => {
+                        let Some(message) = message else {
+                            break;
+                        };
+                        match message.and_then(|m| Ok(serde_json::from_slice::<LoadMessage>(&m.data)?)) {
+                            Ok(LoadMessage::Stats(stats)) => {
+                                latest.insert(stats.node_id.clone(), (stats, Instant::now()));
+                            }
+                            Ok(LoadMessage::Adjust(changes)) => {
+                                let applied = apply_load_factors(&ring, changes);
+                                if !applied.is_empty() {
+                                    tracing::info!("按负载调整负载系数: {:?}", applied);
+                                }
+                            }
+                            Err(e) => tracing::warn!("无法解析负载消息: {}", e),
+                        }
+                    }
+                    _ = ticker.tick() => {
+                        let map = ring.snapshot().partition_map(replica_count);
+                        match NodeStats::collect(&node_id, storage.as_ref(), &map, Some(&tracker)).await {
+                            Ok(stats) => {
+                                tracker.reset(map);
+                                publish(broker.as_ref(), &LoadMessage::Stats(stats)).await;
+                            }
+                            Err(e) => tracing::warn!("统计本节点负载失败: {}", e),
+                        }
+                        if let Some(changes) = coordinate(&node_id, &ring, &latest, interval, replica_count, &config) {
+                            publish(broker.as_ref(), &LoadMessage::Adjust(changes)).await;
+                        }
+                    }
+                }
+            }
+        });
+        Ok(Self { task })
+    }
+}
+
+impl Drop for LoadBalancer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// 本节点是环中标识最小的节点且收到了环中每个节点最近两个周期内的统计时，汇总报告并调整负载系数
+fn coordinate(
+    node_id: &str,
+    ring: &SharedRing,
+    latest: &HashMap<String, (NodeStats, Instant)>,
+    interval: Duration,
+    replica_count: usize,
+    config: &LoadAwareConfig,
+) -> Option<Vec<LoadFactorChange>> {
+    let nodes = ring.snapshot().strategy.all_nodes();
+    if nodes.iter().map(|n| n.id.as_str()).min() != Some(node_id) {
+        return None;
+    }
+    let mut stats = Vec::with_capacity(nodes.len());
+    for node in &nodes {
+        let (node_stats, received) = latest.get(&node.id)?;
+        if received.elapsed() > interval * 2 {
+            return None;
+        }
+        stats.push(node_stats.clone());
+    }
+    let report = BalanceReport::from_stats(ring, &stats, replica_count);
+    let changes = adjust_load_factors(ring, &report, config);
+    (!changes.is_empty()).then_some(changes)
+}
+
+async fn publish(broker: &dyn MessageBroker, message: &LoadMessage) {
+    let result = match serde_json::to_vec(message) {
+        Ok(data) => broker.publish(LOAD_TOPIC, data).await,
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = result {
+        tracing::warn!("广播负载消息失败: {}", e);
+    }
+}
@@ -0,0 +1,170 @@
+use super::{DistributionNode, DistributionStrategy, RingSnapshot, SharedRing};
+use crate::membership::{MembershipEvent, MembershipManager, Node, NodeState};
+use crate::Result;
+use futures::StreamExt;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// 节点处于某个状态时在分布环中的处理方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeAction {
+    /// 节点保留在环中，继续承担数据
+    Include,
+    /// 节点移出环，其数据由后继节点接管
+    Exclude,
+}
+
+/// 非 Active 状态节点的处理策略，Active 节点总是在环中
+#[derive(Clone, Debug)]
+pub struct RingPolicy {
+    pub joining: NodeAction,
+    /// 默认保留宕机节点，视为临时故障，避免一次故障引发大规模数据迁移
+    pub down: NodeAction,
+    pub leaving: NodeAction,
+}
+
+impl Default for RingPolicy {
+    fn default() -> Self {
+        Self {
+            joining: NodeAction::Exclude,
+            down: NodeAction::Include,
+            leaving: NodeAction::Exclude,
+        }
+    }
+}
+
+impl RingPolicy {
+    fn action(&self, state: &NodeState) -> NodeAction {
+        match state {
+            NodeState::Active => NodeAction::Include,
+            NodeState::Joining => self.joining,
+            NodeState::Down => self.down,
+            NodeState::Leaving => self.leaving,
+        }
+    }
+}
+
+/// 由成员事件自动维护的共享分布环
+///
+/// 后台任务订阅 `watch_nodes()`，成员变化时通过 [`SharedRing`] 写时复制地发布新版本；
+/// 读取方通过 [`ClusterRing::snapshot`] 无锁地获得某个版本的环。
+pub struct ClusterRing {
+    shared: Arc<SharedRing>,
+    task: JoinHandle<()>,
+}
+
+impl ClusterRing {
+    pub async fn start(
+        membership: Arc<dyn MembershipManager>,
+        strategy: Box<dyn DistributionStrategy>,
+        policy: RingPolicy,
+    ) -> Result<Self> {
+        // 先订阅再读取当前成员，避免两者之间的事件丢失
+        let mut events = membership.watch_nodes().await?;
+        let nodes = membership.get_nodes().await?;
+
+        let shared = Arc::new(SharedRing::new(strategy));
+        let mut sync = RingSync {
+            known: nodes.into_iter().map(|n| (n.id.clone(), n)).collect(),
+            policy,
+            shared: shared.clone(),
+        };
+        sync.publish();
+
+        let task = tokio::spawn(async move {
+            while let Some(event) = events.next().await {
+                match event {
+                    Ok(event) => sync.apply(event),
+                    Err(e) => tracing::warn!("成员事件错误: {}", e),
+                }
+            }
+        });
+
+        Ok(Self { shared, task })
+    }
+
+    /// 底层的共享分布环
+    pub fn shared(&self) -> Arc<SharedRing> {
+        self.shared.clone()
+    }
+
+    /// 当前版本的分布环
+    pub fn snapshot(&self) -> Arc<RingSnapshot> {
+        self.shared.snapshot()
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.shared.epoch()
+    }
+
+    /// 订阅分布环版本变化
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.shared.subscribe()
+    }
+
+    /// 等待分布环版本达到 `epoch`
+    pub async fn wait_for_epoch(&self, epoch: u64) -> Result<()> {
+        self.shared.wait_for_epoch(epoch).await
+    }
+}
+
+impl Drop for ClusterRing {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct RingSync {
+    known: HashMap<String, Node>,
+    policy: RingPolicy,
+    shared: Arc<SharedRing>,
+}
+
+impl RingSync {
+    fn apply(&mut self, event: MembershipEvent) {
+        match event {
+            MembershipEvent::NodeJoined(node) => {
+                self.known.insert(node.id.clone(), node);
+            }
+            MembershipEvent::NodeStateChanged { id, state } => {
+                if let Some(node) = self.known.get_mut(&id) {
+                    node.state = state;
+                }
+            }
+            MembershipEvent::NodeLeft(id) => {
+                self.known.remove(&id);
+            }
+        }
+        self.publish();
+    }
+
+    /// 环中节点与期望不一致时发布下一个版本
+    fn publish(&mut self) {
+        let desired: BTreeSet<&str> = self
+            .known
+            .values()
+            .filter(|n| self.policy.action(&n.state) == NodeAction::Include)
+            .map(|n| n.id.as_str())
+            .collect();
+        let known = &self.known;
+        self.shared.update(|strategy| {
+            let present: Vec<DistributionNode> = strategy.all_nodes();
+            let unchanged = present.len() == desired.len()
+                && present.iter().all(|n| desired.contains(n.id.as_str()));
+            if unchanged {
+                return false;
+            }
+            for node in present.iter().filter(|n| !desired.contains(n.id.as_str())) {
+                strategy.remove_node(&node.id);
+            }
+            for id in &desired {
+                if !present.iter().any(|n| n.id == *id) {
+                    strategy.add_node(DistributionNode::from(&known[*id]));
+                }
+            }
+            true
+        });
+    }
+}
@@ -0,0 +1,156 @@
+use super::{PartitionMap, SharedRing};
+use crate::messaging::MessageBroker;
+use crate::utils::HybridClock;
+use crate::Result;
+use futures::StreamExt;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// 节点之间交换分区映射的 topic
+pub const PARTITION_MAP_TOPIC: &str = "coretex.partition_map";
+
+/// 通过消息层在节点之间交换分区映射
+///
+/// 本地分布环每发布一个新版本就广播一次分区映射，并打上本节点混合逻辑时钟的时间戳。
+/// 各节点的 epoch 互不相关，收到映射时按时间戳比较新旧，替换较旧的本地缓存；
+/// 客户端下载的即为 [`PartitionMapExchange::latest`]，由 [`PartitionMapExchange::serve`] 提供；
+/// 强一致模式按 [`PartitionMapExchange::subscribe`] 收到的映射划分 Raft 组。
+pub struct PartitionMapExchange {
+    latest: Arc<watch::Sender<Arc<PartitionMap>>>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl PartitionMapExchange {
+    pub async fn start(
+        ring: Arc<SharedRing>,
+        broker: Arc<dyn MessageBroker>,
+        clock: Arc<HybridClock>,
+        replica_count: usize,
+    ) -> Result<Self> {
+        let mut incoming = broker.subscribe(PARTITION_MAP_TOPIC).await?;
+        let mut epochs = ring.subscribe();
+
+        let stamp = {
+            let clock = clock.clone();
+            move |map: PartitionMap| PartitionMap {
+                version: clock.now(),
+                ..map
+            }
+        };
+        let initial = stamp(ring.snapshot().partition_map(replica_count));
+        broker.publish(PARTITION_MAP_TOPIC, initial.to_bytes()?).await?;
+        let latest = Arc::new(watch::channel(Arc::new(initial)).0);
+
+        let receiver = {
+            let latest = latest.clone();
+            tokio::spawn(async move {
+                while let Some(message) = incoming.next().await {
+                    // 先推进本地时钟，之后本节点广播的映射一定晚于已收到的映射
+                    let map = message
+                        .and_then(|m| PartitionMap::from_bytes(&m.data))
+                        .and_then(|map| clock.update(map.version).map(|_| map));
+                    match map {
+                        Ok(map) => {
+                            accept(&latest, map);
+                        }
+                        Err(e) => tracing::warn!("无法解析分区映射: {}", e),
+                    }
+                }
+            })
+        };
+
+        let publisher = {
+            let latest = latest.clone();
+            tokio::spawn(async move {
+                while epochs.changed().await.is_ok() {
+                    let map = stamp(ring.snapshot().partition_map(replica_count));
+                    let bytes = match map.to_bytes() {
+                        Ok(bytes) => bytes,
+                        Err(e) => {
+                            tracing::warn!("无法序列化分区映射: {}", e);
+                            continue;
+                        }
+                    };
+                    if accept(&latest, map) {
+                        if let Err(e) = broker.publish(PARTITION_MAP_TOPIC, bytes).await {
+                            tracing::warn!("广播分区映射失败: {}", e);
+                        }
+                    }
+                }
+            })
+        };
+
+        Ok(Self {
+            latest,
+            tasks: Mutex::new(vec![receiver, publisher]),
+        })
+    }
+
+    /// 已知的最新分区映射
+    pub fn latest(&self) -> Arc<PartitionMap> {
+        self.latest.borrow().clone()
+    }
+
+    /// 订阅已知的最新分区映射，接受更新的映射时通知
+    pub fn subscribe(&self) -> watch::Receiver<Arc<PartitionMap>> {
+        self.latest.subscribe()
+    }
+
+    /// 在 `listener` 上向客户端提供最新的分区映射（[`Client::partition_map`](crate::api::client::Client::partition_map)）
+    ///
+    /// 简单协议：请求为 "MAP "，响应为 4 字节大端长度加序列化后的映射。
+    pub fn serve(&self, listener: TcpListener) {
+        let latest = self.latest.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        tracing::warn!("接受分区映射下载连接失败: {}", e);
+                        continue;
+                    }
+                };
+                let latest = latest.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = respond(stream, &latest).await {
+                        tracing::debug!("分区映射下载失败: {}", e);
+                    }
+                });
+            }
+        });
+        self.tasks.lock().unwrap().push(task);
+    }
+}
+
+impl Drop for PartitionMapExchange {
+    fn drop(&mut self) {
+        for task in self.tasks.lock().unwrap().iter() {
+            task.abort();
+        }
+    }
+}
+
+async fn respond(mut stream: TcpStream, latest: &watch::Sender<Arc<PartitionMap>>) -> std::io::Result<()> {
+    let mut command = [0u8; 4];
+    stream.read_exact(&mut command).await?;
+    if &command != b"MAP " {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "未知的请求"));
+    }
+    let data = latest.borrow().to_bytes().map_err(std::io::Error::other)?;
+    stream.write_all(&(data.len() as u32).to_be_bytes()).await?;
+    stream.write_all(&data).await
+}
+
+/// 仅接受时间戳更晚的映射
+fn accept(latest: &watch::Sender<Arc<PartitionMap>>, map: PartitionMap) -> bool {
+    latest.send_if_modified(|current| {
+        let accepted = map.version > current.version;
+        if accepted {
+            *current = Arc::new(map);
+        }
+        accepted
+    })
+}
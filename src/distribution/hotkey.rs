@@ -0,0 +1,258 @@
+use super::DistributionNode;
+use crate::config::{HotKeyConfig, HotKeyMitigation};
+use arc_swap::ArcSwap;
+use bytes::Bytes;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// 一个热点 key 及其估算的请求速率
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HotKey {
+    pub key: Bytes,
+    pub requests_per_sec: f64,
+}
+
+/// 热点检测统计
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HotKeyStats {
+    /// 经过检测器的请求总数
+    pub requests: u64,
+    /// 其中被采样的请求数
+    pub sampled: u64,
+    /// 当前的热点 key，按请求速率从高到低排列
+    pub hot_keys: Vec<HotKey>,
+}
+
+/// 基于采样的热点 key 检测器
+///
+/// 每 `sample_every` 个请求采样一次，在固定窗口内用 Space-Saving 算法统计出现最多的 key；
+/// 估算速率达到阈值的 key 立即标记为热点，窗口结束时按该窗口的统计重新计算热点集合，
+/// 因此一个 key 冷却后最多再保持一个窗口的热点状态。
+pub struct HotKeyDetector {
+    config: HotKeyConfig,
+    requests: AtomicU64,
+    sampled: AtomicU64,
+    window: Mutex<Window>,
+    hot: ArcSwap<HashMap<Bytes, f64>>,
+    next_replica: AtomicUsize,
+}
+
+struct Window {
+    started: Instant,
+    counts: HashMap<Bytes, u64>,
+}
+
+impl HotKeyDetector {
+    pub fn new(config: HotKeyConfig) -> Self {
+        Self {
+            config,
+            requests: AtomicU64::new(0),
+            sampled: AtomicU64::new(0),
+            window: Mutex::new(Window {
+                started: Instant::now(),
+                counts: HashMap::new(),
+            }),
+            hot: ArcSwap::from_pointee(HashMap::new()),
+            next_replica: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn config(&self) -> &HotKeyConfig {
+        &self.config
+    }
+
+    /// 记录一次对 `key` 的请求，返回 `key` 当前是否为热点
+    pub fn record(&self, key: &[u8]) -> bool {
+        let seq = self.requests.fetch_add(1, Ordering::Relaxed);
+        if seq.is_multiple_of(self.config.sample_every.max(1)) {
+            self.sampled.fetch_add(1, Ordering::Relaxed);
+            self.sample(key);
+        }
+        self.is_hot(key)
+    }
+
+    pub fn is_hot(&self, key: &[u8]) -> bool {
+        self.hot.load().contains_key(key)
+    }
+
+    /// 立即结束当前窗口并重新计算热点集合
+    pub fn rotate(&self) {
+        let mut window = self.window.lock().unwrap();
+        self.finish_window(&mut window);
+    }
+
+    pub fn stats(&self) -> HotKeyStats {
+        let mut hot_keys: Vec<HotKey> = self
+            .hot
+            .load()
+            .iter()
+            .map(|(key, rate)| HotKey {
+                key: key.clone(),
+                requests_per_sec: *rate,
+            })
+            .collect();
+        hot_keys.sort_by(|a, b| {
+            b.requests_per_sec
+                .total_cmp(&a.requests_per_sec)
+                .then_with(|| a.key.cmp(&b.key))
+        });
+        HotKeyStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            sampled: self.sampled.load(Ordering::Relaxed),
+            hot_keys,
+        }
+    }
+
+    /// 为一次读请求选择副本
+    ///
+    /// 启用 [`HotKeyMitigation::SpreadReads`] 时热点 key 的读请求在所有副本之间轮流分发，
+    /// 其余情况总是选择主节点。
+    pub fn read_replica(&self, key: &[u8], replicas: &[DistributionNode]) -> Option<DistributionNode> {
+        if replicas.len() > 1
+            && self.config.mitigation == HotKeyMitigation::SpreadReads
+            && self.is_hot(key)
+        {
+            let idx = self.next_replica.fetch_add(1, Ordering::Relaxed) % replicas.len();
+            return Some(replicas[idx].clone());
+        }
+        replicas.first().cloned()
+    }
+
+    fn sample(&self, key: &[u8]) {
+        let mut window = self.window.lock().unwrap();
+        if window.started.elapsed() >= self.window_len() {
+            self.finish_window(&mut window);
+        }
+
+        let capacity = self.config.capacity.max(1);
+        let tracked = window.counts.len();
+        let count = match window.counts.get_mut(key) {
+            Some(count) => {
+                *count += 1;
+                *count
+            }
+            None if tracked < capacity => {
+                window.counts.insert(Bytes::copy_from_slice(key), 1);
+                1
+            }
+            None => {
+                // Space-Saving：替换计数最小的候选，新 key 继承其计数
+                let (victim, min) = window
+                    .counts
+                    .iter()
+                    .min_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                    .map(|(k, c)| (k.clone(), *c))
+                    .expect("容量至少为 1");
+                window.counts.remove(&victim);
+                window.counts.insert(Bytes::copy_from_slice(key), min + 1);
+                min + 1
+            }
+        };
+
+        let rate = self.rate(count);
+        if rate >= self.config.threshold && !self.is_hot(key) {
+            self.hot.rcu(|hot| {
+                let mut hot = HashMap::clone(hot);
+                hot.insert(Bytes::copy_from_slice(key), rate);
+                hot
+            });
+        }
+    }
+
+    fn finish_window(&self, window: &mut Window) {
+        let hot: HashMap<Bytes, f64> = window
+            .counts
+            .drain()
+            .map(|(key, count)| (key, self.rate(count)))
+            .filter(|(_, rate)| *rate >= self.config.threshold)
+            .collect();
+        self.hot.store(Arc::new(hot));
+        window.started = Instant::now();
+    }
+
+    /// 由窗口内的采样次数估算请求速率（次/秒）
+    fn rate(&self, count: u64) -> f64 {
+        let secs = self.window_len().as_secs_f64().max(1e-3);
+        (count * self.config.sample_every.max(1)) as f64 / secs
+    }
+
+    fn window_len(&self) -> Duration {
+        Duration::from_millis(self.config.window_ms)
+    }
+}
+
+/// 协调节点上热点 key 的短期缓存
+///
+/// 只应缓存被 [`HotKeyDetector`] 判定为热点的 key；本地写入时需调用
+/// [`HotKeyCache::invalidate`]，其他节点的写入最多在 `ttl` 之后可见。
+/// 最多保存 `capacity` 个 key：已满时先清理过期的条目，仍然满则淘汰最早缓存的条目，
+/// 冷却后不再被读取的 key 不会一直留在缓存中。
+pub struct HotKeyCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: DashMap<Bytes, (Option<Bytes>, Instant)>,
+}
+
+impl HotKeyCache {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity: capacity.max(1),
+            entries: DashMap::new(),
+        }
+    }
+
+    /// 未过期的缓存值；外层 `None` 表示未命中，内层 `None` 表示 key 不存在
+    pub fn get(&self, key: &[u8]) -> Option<Option<Bytes>> {
+        let entry = self.entries.get(key)?;
+        let (value, cached_at) = entry.value();
+        if cached_at.elapsed() < self.ttl {
+            return Some(value.clone());
+        }
+        drop(entry);
+        self.entries
+            .remove_if(key, |_, (_, cached_at)| cached_at.elapsed() >= self.ttl);
+        None
+    }
+
+    pub fn insert(&self, key: &[u8], value: Option<Bytes>) {
+        if !self.entries.contains_key(key) && self.entries.len() >= self.capacity {
+            self.purge_expired();
+            if self.entries.len() >= self.capacity {
+                let oldest = self
+                    .entries
+                    .iter()
+                    .min_by_key(|entry| entry.value().1)
+                    .map(|entry| entry.key().clone());
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
+                }
+            }
+        }
+        self.entries
+            .insert(Bytes::copy_from_slice(key), (value, Instant::now()));
+    }
+
+    /// 删除过期的条目
+    pub fn purge_expired(&self) {
+        self.entries
+            .retain(|_, (_, cached_at)| cached_at.elapsed() < self.ttl);
+    }
+
+    pub fn invalidate(&self, key: &[u8]) {
+        self.entries.remove(key);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
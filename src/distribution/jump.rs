@@ -0,0 +1,124 @@
+use super::{
+    fxhash, partition_of, partition_range, placement, DistributionNode, DistributionStrategy,
+    PartitionRange, RangeOwnership, DEFAULT_PARTITIONS,
+};
+use crate::config::DistributionKind;
+
+/// Jump 一致性哈希分布策略
+///
+/// key 先映射到固定数量的分区，再用 jump hash 将分区映射到桶（节点）。
+/// 桶按节点 id 排序，各节点不论以什么顺序加入、移除节点都得出相同的放置。
+/// 新节点的 id 排在末尾时迁移量最小；插入或移除中间的节点会使其后的桶位依次移动，
+/// 因此会额外迁移一部分数据。jump hash 不支持权重，`weight` 被忽略。
+#[derive(Clone)]
+pub struct JumpHashing {
+    buckets: Vec<DistributionNode>,
+    partitions: usize,
+}
+
+impl JumpHashing {
+    pub fn new() -> Self {
+        Self::with_partitions(DEFAULT_PARTITIONS)
+    }
+
+    /// 指定分区数量
+    pub fn with_partitions(partitions: usize) -> Self {
+        Self {
+            buckets: Vec::new(),
+            partitions: partitions.max(1),
+        }
+    }
+
+    /// Lamping & Veach 的 jump consistent hash
+    fn jump(mut key: u64, buckets: usize) -> usize {
+        let mut b: i64 = -1;
+        let mut j: i64 = 0;
+        while j < buckets as i64 {
+            b = j;
+            key = key.wrapping_mul(2862933555777941757).wrapping_add(1);
+            j = ((b + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+        }
+        b as usize
+    }
+
+    /// 偏好列表：依次对分区做再哈希取桶，最后按桶顺序兜底
+    fn candidates(&self, key: &[u8]) -> impl Iterator<Item = &DistributionNode> {
+        self.candidates_for(partition_of(fxhash::hash64(key), self.partitions))
+    }
+
+    fn candidates_for(&self, partition: u64) -> impl Iterator<Item = &DistributionNode> {
+        let n = self.buckets.len();
+        let first = Self::jump(partition, n);
+        let probes = (1..n as u64).map(move |i| {
+            let mut data = partition.to_be_bytes().to_vec();
+            data.extend_from_slice(&i.to_be_bytes());
+            Self::jump(fxhash::hash64(&data), n)
+        });
+        std::iter::once(first)
+            .chain(probes)
+            .chain((0..n).map(move |i| (first + i) % n))
+            .map(move |idx| &self.buckets[idx])
+    }
+}
+
+impl Default for JumpHashing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DistributionStrategy for JumpHashing {
+    fn add_node(&mut self, node: DistributionNode) {
+        match self.buckets.binary_search_by(|b| b.id.cmp(&node.id)) {
+            Ok(idx) => self.buckets[idx] = node,
+            Err(idx) => self.buckets.insert(idx, node),
+        }
+    }
+
+    fn remove_node(&mut self, node_id: &str) {
+        if let Ok(idx) = self.buckets.binary_search_by(|b| b.id.as_str().cmp(node_id)) {
+            self.buckets.remove(idx);
+        }
+    }
+
+    fn get_primary(&self, key: &[u8]) -> Option<DistributionNode> {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        self.candidates(key).next().cloned()
+    }
+
+    fn get_replicas(&self, key: &[u8], replica_count: usize) -> Vec<DistributionNode> {
+        if self.buckets.is_empty() {
+            return Vec::new();
+        }
+        placement::select_replicas(self.candidates(key), replica_count)
+    }
+
+    fn ownership(&self, replica_count: usize) -> Vec<RangeOwnership> {
+        if self.buckets.is_empty() {
+            return Vec::new();
+        }
+        (0..self.partitions as u64)
+            .map(|p| RangeOwnership {
+                range: PartitionRange::Hash(partition_range(p, self.partitions)),
+                replicas: placement::select_replicas(self.candidates_for(p), replica_count)
+                    .into_iter()
+                    .map(|n| n.id)
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn clone_box(&self) -> Box<dyn DistributionStrategy> {
+        Box::new(self.clone())
+    }
+
+    fn kind(&self) -> DistributionKind {
+        DistributionKind::Jump
+    }
+
+    fn all_nodes(&self) -> Vec<DistributionNode> {
+        self.buckets.clone()
+    }
+}
@@ -0,0 +1,94 @@
+use super::{fxhash, DistributionNode, PartitionRange, RangeOwnership};
+use crate::config::DistributionKind;
+use crate::utils::HybridTimestamp;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// 分区映射使用的 key 哈希函数
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashFunction {
+    /// 密钥为 0 的 SipHash-1-3
+    #[default]
+    SipHash13,
+}
+
+impl HashFunction {
+    pub fn hash(&self, data: &[u8]) -> u64 {
+        match self {
+            HashFunction::SipHash13 => fxhash::hash64(data),
+        }
+    }
+}
+
+/// 分区映射中的节点及其 token
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionMapNode {
+    pub node: DistributionNode,
+    pub tokens: Vec<u64>,
+}
+
+/// 可序列化、带版本的分区映射
+///
+/// 由 [`DistributionStrategy::partition_map`](super::DistributionStrategy::partition_map) 生成，
+/// 在节点之间交换，也可交给客户端直接把请求路由到副本节点。
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartitionMap {
+    /// 生成该映射的节点本地的分布环版本，只在同一节点内可比较
+    pub epoch: u64,
+    /// 广播时打上的混合逻辑时钟时间戳，节点之间按它判断映射的新旧
+    #[serde(default)]
+    pub version: HybridTimestamp,
+    pub strategy: DistributionKind,
+    pub hash_function: HashFunction,
+    pub replica_count: usize,
+    pub nodes: Vec<PartitionMapNode>,
+    /// 按起始位置排序、首尾相接的分区
+    pub partitions: Vec<RangeOwnership>,
+}
+
+impl PartitionMap {
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        Ok(serde_json::from_slice(data)?)
+    }
+
+    /// key 所在的分区
+    pub fn route(&self, key: &[u8]) -> Option<&RangeOwnership> {
+        self.route_index(key).map(|idx| &self.partitions[idx])
+    }
+
+    /// key 所在分区在 `partitions` 中的下标
+    pub fn route_index(&self, key: &[u8]) -> Option<usize> {
+        let hash = self.hash_function.hash(key);
+        let idx = self.partitions.partition_point(|p| match &p.range {
+            PartitionRange::Hash(r) => r.start <= hash,
+            PartitionRange::Key(r) => r.start.as_ref() <= key,
+        });
+        let idx = idx.checked_sub(1)?;
+        let contains = match &self.partitions[idx].range {
+            PartitionRange::Hash(r) => r.contains_hash(hash),
+            PartitionRange::Key(r) => r.contains(key),
+        };
+        contains.then_some(idx)
+    }
+
+    pub fn node(&self, node_id: &str) -> Option<&DistributionNode> {
+        self.nodes.iter().map(|n| &n.node).find(|n| n.id == node_id)
+    }
+
+    /// key 的副本节点，首个为主节点
+    pub fn replicas(&self, key: &[u8]) -> Vec<&DistributionNode> {
+        self.route(key)
+            .map(|p| p.replicas.iter().filter_map(|id| self.node(id)).collect())
+            .unwrap_or_default()
+    }
+
+    /// key 主节点的服务地址
+    pub fn primary_address(&self, key: &[u8]) -> Option<SocketAddr> {
+        self.replicas(key).first().and_then(|n| n.address)
+    }
+}
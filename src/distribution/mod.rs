@@ -1,103 +1,295 @@
+mod balance;
+mod cluster;
+mod exchange;
+mod hotkey;
+mod jump;
+mod map;
+mod partition;
+mod placement;
+mod range;
+mod rebalance;
+mod rendezvous;
+mod ring;
+mod shared;
 
-use async_trait::async_trait;
-use std::collections::HashMap;
+use crate::config::{DistributionConfig, DistributionKind};
+use bytes::Bytes;
+use crate::membership::Node;
+use partition::partition_range;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+pub use balance::{
+    adjust_load_factors, BalanceReport, Deviation, LoadBalancer, LoadFactorChange, LoadTracker, NodeLoad,
+    NodeStats, RangeLoad, LOAD_TOPIC,
+};
+pub use cluster::{ClusterRing, NodeAction, RingPolicy};
+pub use exchange::{PartitionMapExchange, PARTITION_MAP_TOPIC};
+pub use hotkey::{HotKey, HotKeyCache, HotKeyDetector, HotKeyStats};
+pub use jump::JumpHashing;
+pub use map::{HashFunction, PartitionMap, PartitionMapNode};
+pub use partition::{HashRange, PartitionRange, RangeOwnership};
+pub use placement::select_replicas;
+pub use range::{
+    KeyRange, RangePartitioner, RangeSplitService, RangeStats, RangeUsage, ScanRoute, SplitPolicy, RANGE_TOPIC,
+};
+pub use rebalance::{
+    RebalanceExecutor, RebalancePlan, RebalancePlanner, RebalanceProgress, RebalanceService, Throttle,
+    Transfer,
+};
+pub use rendezvous::RendezvousHashing;
+pub use ring::{ConsistentHashRing, DEFAULT_VIRTUAL_NODES};
+pub use shared::{RingSnapshot, SharedRing};
+
+/// 节点元数据中表示可用区的键
+pub const ZONE_METADATA_KEY: &str = "zone";
+/// 节点元数据中表示机架的键
+pub const RACK_METADATA_KEY: &str = "rack";
+/// 节点元数据中表示分布权重的键
+pub const WEIGHT_METADATA_KEY: &str = "weight";
+/// 未指定权重时的默认值
+pub const DEFAULT_WEIGHT: u64 = 100;
+/// 基于分区的策略（rendezvous、jump）默认的分区数量
+pub const DEFAULT_PARTITIONS: usize = 1024;
 
 /// 分布式哈希环节点信息
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DistributionNode {
     pub id: String,
     pub weight: u64,
+    /// 所在可用区
+    pub zone: Option<String>,
+    /// 所在机架（在可用区内唯一即可）
+    pub rack: Option<String>,
+    /// 节点的服务地址，供客户端直连路由
+    pub address: Option<SocketAddr>,
+}
+
+impl DistributionNode {
+    pub fn new(id: impl Into<String>, weight: u64) -> Self {
+        Self {
+            id: id.into(),
+            weight,
+            zone: None,
+            rack: None,
+            address: None,
+        }
+    }
+
+    pub fn with_zone(mut self, zone: impl Into<String>) -> Self {
+        self.zone = Some(zone.into());
+        self
+    }
+
+    pub fn with_rack(mut self, rack: impl Into<String>) -> Self {
+        self.rack = Some(rack.into());
+        self
+    }
+
+    pub fn with_address(mut self, address: SocketAddr) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// 机架的全局标识（可用区 + 机架）
+    fn rack_key(&self) -> Option<String> {
+        self.rack.as_ref().map(|rack| match &self.zone {
+            Some(zone) => format!("{}/{}", zone, rack),
+            None => rack.clone(),
+        })
+    }
+}
+
+/// 从成员信息构建分布节点，zone/rack/weight 取自节点元数据
+impl From<&Node> for DistributionNode {
+    fn from(node: &Node) -> Self {
+        let weight = node
+            .metadata
+            .get(WEIGHT_METADATA_KEY)
+            .and_then(|w| w.parse().ok())
+            .unwrap_or(DEFAULT_WEIGHT);
+        Self {
+            id: node.id.clone(),
+            weight,
+            zone: node.metadata.get(ZONE_METADATA_KEY).cloned(),
+            rack: node.metadata.get(RACK_METADATA_KEY).cloned(),
+            address: Some(node.address),
+        }
+    }
 }
 
 /// 数据分布策略 trait
-#[async_trait]
+///
+/// 查询均为同步操作；`add_node`/`remove_node` 只作用于尚未发布的私有副本，
+/// 共享的环通过 [`SharedRing`] 写时复制并原子替换，读取方无需加锁。
 pub trait DistributionStrategy: Send + Sync + 'static {
     /// 添加节点到分布环
-    async fn add_node(&mut self, node: DistributionNode);
+    fn add_node(&mut self, node: DistributionNode);
 
     /// 移除节点
-    async fn remove_node(&mut self, node_id: &str);
+    fn remove_node(&mut self, node_id: &str);
 
     /// 根据 key 选择主节点
-    async fn get_primary(&self, key: &[u8]) -> Option<DistributionNode>;
+    fn get_primary(&self, key: &[u8]) -> Option<DistributionNode>;
 
     /// 根据 key 选择副本节点（含主节点）
-    async fn get_replicas(&self, key: &[u8], replica_count: usize) -> Vec<DistributionNode>;
+    ///
+    /// 返回互不相同的物理节点，并尽量分散在不同可用区/机架，
+    /// 节点数不足时返回的数量少于 `replica_count`。
+    fn get_replicas(&self, key: &[u8], replica_count: usize) -> Vec<DistributionNode>;
 
-    /// 获取所有节点
-    fn all_nodes(&self) -> Vec<DistributionNode>;
-}
+    /// 当前各分区的副本归属，所有分区首尾相接覆盖整个 key 空间
+    fn ownership(&self, replica_count: usize) -> Vec<RangeOwnership>;
 
-/// 一致性哈希分布策略（简单实现）
-pub struct ConsistentHashRing {
-    nodes: HashMap<String, DistributionNode>,
-    ring: Vec<String>,
-}
+    /// 复制当前策略状态，用于写时复制地更新共享的分布环
+    fn clone_box(&self) -> Box<dyn DistributionStrategy>;
 
-impl ConsistentHashRing {
-    pub fn new() -> Self {
-        Self {
-            nodes: HashMap::new(),
-            ring: Vec::new(),
-        }
+    /// 策略类型
+    fn kind(&self) -> DistributionKind;
+
+    /// 设置节点的负载系数，在权重（容量）之外按实际负载微调其承担的数据比例
+    ///
+    /// 不支持的策略返回 `false`。
+    fn set_load_factor(&mut self, _node_id: &str, _factor: f64) -> bool {
+        false
     }
-}
 
-#[async_trait]
-impl DistributionStrategy for ConsistentHashRing {
-    async fn add_node(&mut self, node: DistributionNode) {
-        self.ring.push(node.id.clone());
-        self.nodes.insert(node.id.clone(), node);
-        self.ring.sort(); // 简单排序，实际可用 hash 排序
+    /// 节点当前的负载系数，默认为 1
+    fn load_factor(&self, _node_id: &str) -> f64 {
+        1.0
     }
 
-    async fn remove_node(&mut self, node_id: &str) {
-        self.ring.retain(|id| id != node_id);
-        self.nodes.remove(node_id);
+    /// 记录一次对 `key` 的写入，`size` 为写入的字节数；按负载分裂范围的策略（range）据此决定分裂点
+    ///
+    /// 请求路径通过共享的快照调用（[`SharedRing::record_write`]），实现需自行处理并发。
+    fn record_write(&self, _key: &[u8], _size: usize) {}
+
+    /// 记录一次对 `key` 的读取
+    fn record_read(&self, _key: &[u8]) {}
+
+    /// 取出本节点新记录、尚未交给其他节点的负载
+    fn drain_usage(&self) -> Vec<RangeUsage> {
+        Vec::new()
     }
 
-    async fn get_primary(&self, key: &[u8]) -> Option<DistributionNode> {
-        if self.ring.is_empty() {
-            return None;
-        }
-        let hash = fxhash::hash64(key);
-        let idx = (hash as usize) % self.ring.len();
-        let node_id = &self.ring[idx];
-        self.nodes.get(node_id).cloned()
+    /// 计入其他节点记录的负载
+    fn absorb_usage(&self, _usage: &[RangeUsage]) {}
+
+    /// 按记录的负载分裂/合并分区，返回是否有变化；不支持的策略返回 `false`
+    fn split_and_merge(&mut self) -> bool {
+        false
     }
 
-    async fn get_replicas(&self, key: &[u8], replica_count: usize) -> Vec<DistributionNode> {
-        let mut result = Vec::new();
-        if self.ring.is_empty() {
-            return result;
-        }
-        let hash = fxhash::hash64(key);
-        let mut idx = (hash as usize) % self.ring.len();
-        for _ in 0..replica_count {
-            let node_id = &self.ring[idx % self.ring.len()];
-            if let Some(node) = self.nodes.get(node_id) {
-                result.push(node.clone());
-            }
-            idx += 1;
-        }
-        result
+    /// 采用其他节点决定的 key 范围划分，返回是否有变化；不按 key 范围分区的策略返回 `false`
+    fn assign_ranges(&mut self, _layout: &[(KeyRange, String)]) -> bool {
+        false
     }
 
-    fn all_nodes(&self) -> Vec<DistributionNode> {
-        self.ring
-            .iter()
-            .filter_map(|id| self.nodes.get(id).cloned())
+    /// 将 `[start, end)` 的扫描拆分为各节点上的子范围
+    ///
+    /// 默认每个节点都可能持有该范围内的 key，需要扫描所有节点；按 key 范围分区的策略只返回相关节点。
+    fn route_scan(&self, start: &[u8], end: Option<&[u8]>) -> Vec<ScanRoute> {
+        let range = KeyRange::new(Bytes::copy_from_slice(start), end.map(Bytes::copy_from_slice));
+        self.all_nodes()
+            .into_iter()
+            .map(|node| ScanRoute {
+                range: range.clone(),
+                node,
+            })
             .collect()
     }
+
+    /// 节点在环上的 token，不使用 token 的策略返回空
+    fn tokens(&self, _node_id: &str) -> Vec<u64> {
+        Vec::new()
+    }
+
+    /// 生成可序列化的分区映射（版本号为 0，由 [`RingSnapshot`] 填入实际版本，
+    /// 交换时由 [`PartitionMapExchange`] 打上时间戳）
+    fn partition_map(&self, replica_count: usize) -> PartitionMap {
+        PartitionMap {
+            epoch: 0,
+            version: Default::default(),
+            strategy: self.kind(),
+            hash_function: HashFunction::default(),
+            replica_count,
+            nodes: self
+                .all_nodes()
+                .into_iter()
+                .map(|node| PartitionMapNode {
+                    tokens: self.tokens(&node.id),
+                    node,
+                })
+                .collect(),
+            partitions: self.ownership(replica_count),
+        }
+    }
+
+    /// 获取所有节点
+    fn all_nodes(&self) -> Vec<DistributionNode>;
+}
+
+/// 根据配置构建分布策略
+pub fn from_config(config: &DistributionConfig) -> Box<dyn DistributionStrategy> {
+    match config.strategy {
+        DistributionKind::ConsistentHash => {
+            Box::new(ConsistentHashRing::with_virtual_nodes(config.virtual_nodes))
+        }
+        DistributionKind::Rendezvous => Box::new(RendezvousHashing::with_partitions(config.partitions)),
+        DistributionKind::Jump => Box::new(JumpHashing::with_partitions(config.partitions)),
+        DistributionKind::Range => Box::new(RangePartitioner::with_policy(SplitPolicy::from_config(&config.range))),
+    }
+}
+
+/// 将 64 位哈希值映射到 `[0, partitions)` 的分区，每个分区对应一段连续的哈希区间
+fn partition_of(hash: u64, partitions: usize) -> u64 {
+    ((hash as u128 * partitions as u128) >> 64) as u64
 }
 
 // 简单 hash 函数依赖
+//
+// 分区映射会在节点与客户端之间共享，因此哈希必须跨进程、跨编译器版本稳定。
+// 这里显式实现密钥为 0 的 SipHash-1-3（与当前标准库 `DefaultHasher::new()` 的结果一致），
+// 不依赖标准库未承诺稳定的实现细节。
 mod fxhash {
     pub fn hash64(data: &[u8]) -> u64 {
-        use std::hash::Hasher;
-        use std::collections::hash_map::DefaultHasher;
-        let mut hasher = DefaultHasher::new();
-        hasher.write(data);
-        hasher.finish()
+        let mut v0: u64 = 0x736f6d6570736575;
+        let mut v1: u64 = 0x646f72616e646f6d;
+        let mut v2: u64 = 0x6c7967656e657261;
+        let mut v3: u64 = 0x7465646279746573;
+
+        fn round(v0: &mut u64, v1: &mut u64, v2: &mut u64, v3: &mut u64) {
+            *v0 = v0.wrapping_add(*v1);
+            *v1 = v1.rotate_left(13) ^ *v0;
+            *v0 = v0.rotate_left(32);
+            *v2 = v2.wrapping_add(*v3);
+            *v3 = v3.rotate_left(16) ^ *v2;
+            *v0 = v0.wrapping_add(*v3);
+            *v3 = v3.rotate_left(21) ^ *v0;
+            *v2 = v2.wrapping_add(*v1);
+            *v1 = v1.rotate_left(17) ^ *v2;
+            *v2 = v2.rotate_left(32);
+        }
+
+        let mut chunks = data.chunks_exact(8);
+        for chunk in &mut chunks {
+            let m = u64::from_le_bytes(chunk.try_into().expect("8 bytes"));
+            v3 ^= m;
+            round(&mut v0, &mut v1, &mut v2, &mut v3);
+            v0 ^= m;
+        }
+        let mut last = (data.len() as u64 & 0xff) << 56;
+        for (i, byte) in chunks.remainder().iter().enumerate() {
+            last |= (*byte as u64) << (8 * i);
+        }
+        v3 ^= last;
+        round(&mut v0, &mut v1, &mut v2, &mut v3);
+        v0 ^= last;
+
+        v2 ^= 0xff;
+        for _ in 0..3 {
+            round(&mut v0, &mut v1, &mut v2, &mut v3);
+        }
+        v0 ^ v1 ^ v2 ^ v3
     }
 }
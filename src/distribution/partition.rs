@@ -0,0 +1,57 @@
+use super::{fxhash, KeyRange};
+use serde::{Deserialize, Serialize};
+
+/// key 哈希值上的左闭右开区间 `[start, end)`，`end` 为 `None` 表示直到 `u64::MAX`（含）
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct HashRange {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl HashRange {
+    pub fn new(start: u64, end: Option<u64>) -> Self {
+        Self { start, end }
+    }
+
+    pub fn contains_hash(&self, hash: u64) -> bool {
+        hash >= self.start && self.end.is_none_or(|end| hash < end)
+    }
+}
+
+/// 数据分区：哈希类策略按 key 的哈希值划分，范围策略按 key 本身划分
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PartitionRange {
+    Hash(HashRange),
+    Key(KeyRange),
+}
+
+impl PartitionRange {
+    /// 判断 key 是否落在该分区
+    pub fn contains(&self, key: &[u8]) -> bool {
+        match self {
+            PartitionRange::Hash(range) => range.contains_hash(fxhash::hash64(key)),
+            PartitionRange::Key(range) => range.contains(key),
+        }
+    }
+}
+
+/// 某个分区的副本归属（按偏好顺序，首个为主节点）
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeOwnership {
+    pub range: PartitionRange,
+    pub replicas: Vec<String>,
+}
+
+/// 第 `partition` 个分区（共 `partitions` 个）对应的哈希区间，与 `partition_of` 互逆
+pub(crate) fn partition_range(partition: u64, partitions: usize) -> HashRange {
+    let start_of = |p: u64| -> u64 {
+        let scaled = (p as u128) << 64;
+        scaled.div_ceil(partitions as u128) as u64
+    };
+    let end = if partition + 1 >= partitions as u64 {
+        None
+    } else {
+        Some(start_of(partition + 1))
+    };
+    HashRange::new(start_of(partition), end)
+}
@@ -0,0 +1,55 @@
+use super::DistributionNode;
+use std::collections::HashSet;
+
+/// 按故障域挑选副本节点
+///
+/// `candidates` 为按偏好排序的候选节点（可能包含重复节点），返回最多 `count` 个互不相同的物理节点。
+/// 挑选分三轮进行：
+/// 1. 优先选择可用区（zone）和机架（rack）都未被占用的节点；
+/// 2. 可用区不足时，退而选择机架未被占用的节点；
+/// 3. 机架也不足时，按偏好顺序补齐剩余的不同节点。
+///
+/// 未声明 zone/rack 的节点视为独占一个故障域。
+pub fn select_replicas<'a, I>(candidates: I, count: usize) -> Vec<DistributionNode>
+where
+    I: IntoIterator<Item = &'a DistributionNode>,
+{
+    let mut seen = HashSet::new();
+    let candidates: Vec<&DistributionNode> = candidates
+        .into_iter()
+        .filter(|node| seen.insert(node.id.as_str()))
+        .collect();
+
+    let mut chosen: Vec<&DistributionNode> = Vec::with_capacity(count.min(candidates.len()));
+    let mut used_zones = HashSet::new();
+    let mut used_racks = HashSet::new();
+
+    for pass in 0..3 {
+        for node in candidates.iter() {
+            if chosen.len() >= count {
+                break;
+            }
+            if chosen.iter().any(|c| c.id == node.id) {
+                continue;
+            }
+            let zone_free = node.zone.as_ref().is_none_or(|z| !used_zones.contains(z));
+            let rack_free = node.rack_key().is_none_or(|r| !used_racks.contains(&r));
+            let eligible = match pass {
+                0 => zone_free && rack_free,
+                1 => rack_free,
+                _ => true,
+            };
+            if eligible {
+                if let Some(zone) = &node.zone {
+                    used_zones.insert(zone.clone());
+                }
+                if let Some(rack) = node.rack_key() {
+                    used_racks.insert(rack);
+                }
+                chosen.push(node);
+            }
+        }
+    }
+
+    chosen.into_iter().cloned().collect()
+}
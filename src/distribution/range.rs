@@ -0,0 +1,719 @@
+use super::{placement, DistributionNode, DistributionStrategy, PartitionRange, RangeOwnership, SharedRing};
+use crate::config::{DistributionKind, RangeConfig};
+use crate::messaging::MessageBroker;
+use crate::Result;
+use bytes::Bytes;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// 每个范围保留的采样 key 数量，用于估算分裂点
+const SAMPLE_SIZE: usize = 64;
+
+/// 左闭右开的 key 范围 `[start, end)`，`end` 为 `None` 表示无上界
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct KeyRange {
+    pub start: Bytes,
+    pub end: Option<Bytes>,
+}
+
+impl KeyRange {
+    pub fn new(start: impl Into<Bytes>, end: Option<Bytes>) -> Self {
+        Self {
+            start: start.into(),
+            end,
+        }
+    }
+
+    /// 覆盖整个 key 空间的范围
+    pub fn full() -> Self {
+        Self::new(Bytes::new(), None)
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        key >= self.start.as_ref() && self.end.as_ref().is_none_or(|end| key < end.as_ref())
+    }
+
+    /// 与另一个范围的交集
+    pub fn intersect(&self, other: &KeyRange) -> Option<KeyRange> {
+        let start = self.start.clone().max(other.start.clone());
+        let end = match (&self.end, &other.end) {
+            (Some(a), Some(b)) => Some(a.clone().min(b.clone())),
+            (Some(a), None) => Some(a.clone()),
+            (None, Some(b)) => Some(b.clone()),
+            (None, None) => None,
+        };
+        if end.as_ref().is_some_and(|end| *end <= start) {
+            return None;
+        }
+        Some(KeyRange { start, end })
+    }
+}
+
+/// 范围的大小与负载统计
+#[derive(Clone, Debug, Default)]
+pub struct RangeStats {
+    /// 估算的数据量（字节）
+    pub bytes: u64,
+    /// 读写请求次数
+    pub load: u64,
+}
+
+/// 范围分裂与合并策略
+#[derive(Clone, Debug)]
+pub struct SplitPolicy {
+    /// 数据量超过该值时分裂
+    pub max_bytes: u64,
+    /// 负载超过该值时分裂
+    pub max_load: u64,
+    /// 相邻两个范围合计数据量与负载都低于该比例的阈值时合并
+    pub merge_ratio: f64,
+}
+
+impl Default for SplitPolicy {
+    fn default() -> Self {
+        Self::from_config(&RangeConfig::default())
+    }
+}
+
+impl SplitPolicy {
+    pub fn from_config(config: &RangeConfig) -> Self {
+        Self {
+            max_bytes: config.max_bytes,
+            max_load: config.max_load,
+            merge_ratio: config.merge_ratio,
+        }
+    }
+}
+
+/// 某个节点上记录的一个范围的负载，交给决定分裂/合并的节点汇总
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeUsage {
+    /// 记录时所在范围的起始 key
+    pub start: Bytes,
+    pub bytes: u64,
+    pub load: u64,
+    /// 采样的 key
+    pub samples: Vec<Bytes>,
+}
+
+/// 扫描路由：需要到 `node` 上扫描的子范围
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanRoute {
+    pub range: KeyRange,
+    pub node: DistributionNode,
+}
+
+#[derive(Clone, Debug)]
+struct RangeEntry {
+    end: Option<Bytes>,
+    owner: String,
+}
+
+/// 一个范围上的负载统计
+#[derive(Clone, Debug, Default)]
+struct Usage {
+    stats: RangeStats,
+    /// 哈希值最小的若干 key（bottom-k 采样），用于确定分裂点
+    samples: BTreeMap<u64, Bytes>,
+}
+
+impl Usage {
+    fn sample(&mut self, key: &[u8]) {
+        let hash = super::fxhash::hash64(key);
+        if self.samples.len() < SAMPLE_SIZE {
+            self.samples.insert(hash, Bytes::copy_from_slice(key));
+        } else if self.samples.last_key_value().is_some_and(|(max, _)| hash < *max) {
+            self.samples.insert(hash, Bytes::copy_from_slice(key));
+            self.samples.pop_last();
+        }
+    }
+}
+
+/// 负载统计：`stats` 用于本节点的分裂/合并决定，`pending` 是尚未交给其他节点的新增负载
+///
+/// 均以范围起始 key 为键。请求路径通过共享的快照记录负载，因此放在锁内。
+#[derive(Clone, Debug, Default)]
+struct UsageLog {
+    stats: HashMap<Bytes, Usage>,
+    pending: HashMap<Bytes, Usage>,
+}
+
+/// 按 key 范围分区的分布策略
+///
+/// 相邻 key 落在同一范围内，范围扫描只需访问少数节点。
+/// 范围可按数据量或负载分裂、合并，并在节点间均衡数量；范围少于节点时按 key 空间的中点分裂，
+/// 每个节点都能分到范围。多个节点各自维护分布环时由 [`RangeSplitService`] 保持范围划分一致。
+pub struct RangePartitioner {
+    nodes: HashMap<String, DistributionNode>,
+    /// 范围起始 key -> 范围信息，所有范围首尾相接覆盖整个 key 空间
+    ranges: BTreeMap<Bytes, RangeEntry>,
+    usage: Mutex<UsageLog>,
+    policy: SplitPolicy,
+}
+
+impl Clone for RangePartitioner {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            ranges: self.ranges.clone(),
+            usage: Mutex::new(self.usage.lock().unwrap().clone()),
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+impl RangePartitioner {
+    pub fn new() -> Self {
+        Self::with_policy(SplitPolicy::default())
+    }
+
+    pub fn with_policy(policy: SplitPolicy) -> Self {
+        Self {
+            nodes: HashMap::new(),
+            ranges: BTreeMap::new(),
+            usage: Mutex::new(UsageLog::default()),
+            policy,
+        }
+    }
+
+    fn entry_for(&self, key: &[u8]) -> Option<(&Bytes, &RangeEntry)> {
+        self.ranges
+            .range::<[u8], _>((Bound::Unbounded, Bound::Included(key)))
+            .next_back()
+    }
+
+    /// 所有范围及其归属节点
+    pub fn ranges(&self) -> Vec<(KeyRange, String)> {
+        self.ranges
+            .iter()
+            .map(|(start, entry)| (KeyRange::new(start.clone(), entry.end.clone()), entry.owner.clone()))
+            .collect()
+    }
+
+    /// 某个 key 所在范围的统计信息
+    pub fn stats(&self, key: &[u8]) -> Option<RangeStats> {
+        let (start, _) = self.entry_for(key)?;
+        let usage = self.usage.lock().unwrap();
+        Some(usage.stats.get(start).map(|u| u.stats.clone()).unwrap_or_default())
+    }
+
+    /// 记录一次写入，`size` 为写入的字节数
+    pub fn record_write(&self, key: &[u8], size: usize) {
+        self.record(key, size as u64);
+    }
+
+    /// 记录一次读取
+    pub fn record_read(&self, key: &[u8]) {
+        self.record(key, 0);
+    }
+
+    fn record(&self, key: &[u8], bytes: u64) {
+        let Some((start, _)) = self.entry_for(key) else {
+            return;
+        };
+        let mut log = self.usage.lock().unwrap();
+        let log = &mut *log;
+        for usage in [&mut log.stats, &mut log.pending] {
+            let usage = usage.entry(start.clone()).or_default();
+            usage.stats.bytes += bytes;
+            usage.stats.load += 1;
+            usage.sample(key);
+        }
+    }
+
+    /// 取出尚未交给其他节点的负载记录
+    pub fn drain_usage(&self) -> Vec<RangeUsage> {
+        let pending = std::mem::take(&mut self.usage.lock().unwrap().pending);
+        pending
+            .into_iter()
+            .map(|(start, usage)| RangeUsage {
+                start,
+                bytes: usage.stats.bytes,
+                load: usage.stats.load,
+                samples: usage.samples.into_values().collect(),
+            })
+            .collect()
+    }
+
+    /// 计入其他节点记录的负载，按记录时范围的起始 key 归入当前所在的范围
+    pub fn absorb_usage(&self, usage: &[RangeUsage]) {
+        let mut log = self.usage.lock().unwrap();
+        for item in usage {
+            let Some((start, _)) = self.entry_for(&item.start) else {
+                continue;
+            };
+            let target = log.stats.entry(start.clone()).or_default();
+            target.stats.bytes += item.bytes;
+            target.stats.load += item.load;
+            for key in &item.samples {
+                target.sample(key);
+            }
+        }
+    }
+
+    /// 在 `at` 处分裂其所在范围，新范围 `[at, end)` 暂由原节点持有
+    ///
+    /// 统计信息按采样 key 的比例分配。`at` 恰为范围起点时不做任何操作。
+    pub fn split_range(&mut self, at: &[u8]) -> bool {
+        let Some((start, _)) = self.entry_for(at) else {
+            return false;
+        };
+        if start.as_ref() == at {
+            return false;
+        }
+        let start = start.clone();
+        let at = Bytes::copy_from_slice(at);
+        let log = self.usage.get_mut().unwrap();
+        let usage = log.stats.entry(start.clone()).or_default();
+        let total = usage.samples.len().max(1) as u64;
+        let right_samples: BTreeMap<u64, Bytes> = usage
+            .samples
+            .iter()
+            .filter(|(_, key)| **key >= at)
+            .map(|(h, k)| (*h, k.clone()))
+            .collect();
+        let ratio = |v: u64| v * right_samples.len() as u64 / total;
+        let right_stats = RangeStats {
+            bytes: ratio(usage.stats.bytes),
+            load: ratio(usage.stats.load),
+        };
+        usage.samples.retain(|_, key| *key < at);
+        usage.stats.bytes -= right_stats.bytes;
+        usage.stats.load -= right_stats.load;
+        log.stats.insert(
+            at.clone(),
+            Usage {
+                stats: right_stats,
+                samples: right_samples,
+            },
+        );
+
+        let entry = self.ranges.get_mut(&start).expect("range exists");
+        let right = RangeEntry {
+            end: entry.end.take(),
+            owner: entry.owner.clone(),
+        };
+        entry.end = Some(at.clone());
+        self.ranges.insert(at, right);
+        true
+    }
+
+    /// 合并起始于 `start` 的范围与其右侧相邻范围
+    ///
+    /// 两个范围由不同节点持有时不合并，否则右侧范围的数据会在不迁移的情况下改变归属。
+    pub fn merge_with_next(&mut self, start: &[u8]) -> bool {
+        let Some(entry) = self.ranges.get(start) else {
+            return false;
+        };
+        let Some(next_start) = entry.end.clone() else {
+            return false;
+        };
+        if self.ranges.get(&next_start).is_none_or(|next| next.owner != entry.owner) {
+            return false;
+        }
+        let next = self.ranges.remove(&next_start).expect("range exists");
+        self.ranges.get_mut(start).expect("range exists").end = next.end;
+        let log = self.usage.get_mut().unwrap();
+        if let Some(next) = log.stats.remove(&next_start) {
+            let usage = log.stats.entry(Bytes::copy_from_slice(start)).or_default();
+            usage.stats.bytes += next.stats.bytes;
+            usage.stats.load += next.stats.load;
+            usage.samples.extend(next.samples);
+            while usage.samples.len() > SAMPLE_SIZE {
+                usage.samples.pop_last();
+            }
+        }
+        true
+    }
+
+    /// 按策略分裂过大/过热的范围、合并过小的相邻范围并均衡归属，返回是否有变化
+    pub fn split_and_merge(&mut self) -> bool {
+        let mut changed = false;
+
+        let stats = self.usage.get_mut().unwrap().stats.clone();
+        let usage = |start: &Bytes| stats.get(start).cloned().unwrap_or_default();
+        let split_points: Vec<Bytes> = self
+            .ranges
+            .keys()
+            .map(usage)
+            .filter(|u| u.stats.bytes > self.policy.max_bytes || u.stats.load > self.policy.max_load)
+            .filter_map(|u| {
+                let mut keys: Vec<&Bytes> = u.samples.values().collect();
+                keys.sort();
+                keys.get(keys.len() / 2).map(|k| (*k).clone())
+            })
+            .collect();
+        for at in split_points {
+            changed |= self.split_range(&at);
+        }
+
+        let small_bytes = (self.policy.max_bytes as f64 * self.policy.merge_ratio) as u64;
+        let small_load = (self.policy.max_load as f64 * self.policy.merge_ratio) as u64;
+        let starts: Vec<Bytes> = self.ranges.keys().cloned().collect();
+        for start in starts {
+            let Some(next) = self.ranges.get(&start).and_then(|entry| entry.end.clone()) else {
+                continue;
+            };
+            if !self.ranges.contains_key(&next) {
+                continue;
+            }
+            let stats = &self.usage.get_mut().unwrap().stats;
+            let load = |start: &Bytes| stats.get(start).map(|u| u.stats.clone()).unwrap_or_default();
+            let (left, right) = (load(&start), load(&next));
+            if left.bytes + right.bytes < small_bytes && left.load + right.load < small_load {
+                changed |= self.merge_with_next(&start);
+            }
+        }
+        changed |= !self.balance_owners().is_empty();
+        changed
+    }
+
+    /// 采用其他节点决定的范围划分和归属，返回是否有变化
+    ///
+    /// `layout` 必须按起始 key 排序且首尾相接覆盖整个 key 空间，否则不做任何修改。
+    /// 归属于本地未知节点的范围重新分配给已知节点。
+    pub fn assign_ranges(&mut self, layout: &[(KeyRange, String)]) -> bool {
+        let contiguous = layout.first().is_some_and(|(r, _)| r.start.is_empty())
+            && layout.last().is_some_and(|(r, _)| r.end.is_none())
+            && layout.windows(2).all(|w| w[0].0.end.as_ref() == Some(&w[1].0.start));
+        if !contiguous || self.nodes.is_empty() || self.ranges() == layout {
+            return false;
+        }
+        self.ranges = layout
+            .iter()
+            .map(|(range, owner)| {
+                let entry = RangeEntry {
+                    end: range.end.clone(),
+                    owner: owner.clone(),
+                };
+                (range.start.clone(), entry)
+            })
+            .collect();
+        let ranges = &self.ranges;
+        self.usage.get_mut().unwrap().stats.retain(|start, _| ranges.contains_key(start));
+        self.balance_owners();
+        true
+    }
+
+    /// 范围少于节点时把每个范围在 key 空间的中点一分为二，直到每个节点都能分到范围
+    ///
+    /// 只依赖当前的范围划分，各节点对同样的划分得到同样的结果。
+    fn split_for_nodes(&mut self) {
+        while self.ranges.len() < self.nodes.len() {
+            let points: Vec<Bytes> = self
+                .ranges
+                .iter()
+                .filter_map(|(start, entry)| midpoint(start, entry.end.as_deref()))
+                .collect();
+            let mut split = false;
+            for at in points {
+                split |= self.split_range(&at);
+            }
+            if !split {
+                break;
+            }
+        }
+    }
+
+    /// 将 `[start, end)` 的扫描拆分为各节点上的子范围，按 key 顺序返回
+    pub fn route_scan(&self, start: &[u8], end: Option<&[u8]>) -> Vec<ScanRoute> {
+        let query = KeyRange::new(Bytes::copy_from_slice(start), end.map(Bytes::copy_from_slice));
+        let first = self
+            .entry_for(start)
+            .map(|(s, _)| s.clone())
+            .unwrap_or_default();
+        self.ranges
+            .range(first..)
+            .take_while(|(s, _)| end.is_none_or(|end| s.as_ref() < end))
+            .filter_map(|(s, entry)| {
+                let range = KeyRange::new(s.clone(), entry.end.clone()).intersect(&query)?;
+                let node = self.nodes.get(&entry.owner)?.clone();
+                Some(ScanRoute { range, node })
+            })
+            .collect()
+    }
+
+    /// 按范围数量在节点间均衡归属，返回发生迁移的范围及新旧节点
+    pub fn balance_owners(&mut self) -> Vec<(KeyRange, String, String)> {
+        let mut moves = Vec::new();
+        if self.nodes.is_empty() {
+            return moves;
+        }
+        let mut counts: HashMap<String, usize> =
+            self.nodes.keys().map(|id| (id.clone(), 0)).collect();
+        for entry in self.ranges.values() {
+            *counts.entry(entry.owner.clone()).or_default() += 1;
+        }
+        // 重复直到持有范围最多与最少的节点相差不超过 1，每次迁移都使分布更均匀，循环必然结束
+        loop {
+            let before = moves.len();
+            for (start, entry) in self.ranges.iter_mut() {
+                let owner_count = counts.get(&entry.owner).copied().unwrap_or(0);
+                let orphaned = !self.nodes.contains_key(&entry.owner);
+                let (least, least_count) = counts
+                    .iter()
+                    .filter(|(id, _)| self.nodes.contains_key(*id))
+                    .min_by(|a, b| a.1.cmp(b.1).then_with(|| a.0.cmp(b.0)))
+                    .map(|(id, c)| (id.clone(), *c))
+                    .expect("at least one node");
+                if !orphaned && least_count + 1 >= owner_count {
+                    continue;
+                }
+                if let Some(c) = counts.get_mut(&entry.owner) {
+                    *c -= 1;
+                }
+                *counts.get_mut(&least).expect("node exists") += 1;
+                let from = std::mem::replace(&mut entry.owner, least.clone());
+                moves.push((KeyRange::new(start.clone(), entry.end.clone()), from, least));
+            }
+            if moves.len() == before {
+                return moves;
+            }
+        }
+    }
+
+    /// 副本从归属节点开始，按节点 id 顺序依次向后选择
+    fn replicas_of(&self, owner: &str, replica_count: usize) -> Vec<DistributionNode> {
+        let nodes = self.sorted_nodes();
+        let Some(pos) = nodes.iter().position(|n| n.id == owner) else {
+            return Vec::new();
+        };
+        let ordered = nodes[pos..].iter().chain(nodes[..pos].iter()).copied();
+        placement::select_replicas(ordered, replica_count)
+    }
+
+    /// 节点按 id 排序，作为副本偏好顺序的基础
+    fn sorted_nodes(&self) -> Vec<&DistributionNode> {
+        let mut nodes: Vec<_> = self.nodes.values().collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        nodes
+    }
+}
+
+impl Default for RangePartitioner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `[start, end)` 在 key 空间中的中点，把 key 视为大端小数计算；没有严格位于两者之间的 key 时返回 `None`
+fn midpoint(start: &[u8], end: Option<&[u8]>) -> Option<Bytes> {
+    let len = start.len().max(end.map_or(0, <[u8]>::len)) + 1;
+    // 无上界时按全 0xFF 计算
+    let digit = |key: Option<&[u8]>, i: usize| match key {
+        Some(key) => key.get(i).copied().unwrap_or(0) as u16,
+        None => 0xFF,
+    };
+    let mut sum = vec![0u16; len];
+    let mut carry = 0u16;
+    for i in (0..len).rev() {
+        let total = digit(Some(start), i) + digit(end, i) + carry;
+        sum[i] = total & 0xFF;
+        carry = total >> 8;
+    }
+    let mut mid = Vec::with_capacity(len);
+    let mut rem = carry;
+    for digit in sum {
+        let value = rem * 256 + digit;
+        mid.push((value / 2) as u8);
+        rem = value % 2;
+    }
+    while mid.last() == Some(&0) {
+        mid.pop();
+    }
+    let inside = mid.as_slice() > start && end.is_none_or(|end| mid.as_slice() < end);
+    inside.then(|| Bytes::from(mid))
+}
+
+impl DistributionStrategy for RangePartitioner {
+    fn add_node(&mut self, node: DistributionNode) {
+        let id = node.id.clone();
+        self.nodes.insert(id.clone(), node);
+        if self.ranges.is_empty() {
+            self.ranges.insert(Bytes::new(), RangeEntry { end: None, owner: id });
+        }
+        self.split_for_nodes();
+        self.balance_owners();
+    }
+
+    fn remove_node(&mut self, node_id: &str) {
+        if self.nodes.remove(node_id).is_none() {
+            return;
+        }
+        if self.nodes.is_empty() {
+            self.ranges.clear();
+            *self.usage.get_mut().unwrap() = UsageLog::default();
+        } else {
+            self.balance_owners();
+        }
+    }
+
+    fn get_primary(&self, key: &[u8]) -> Option<DistributionNode> {
+        let (_, entry) = self.entry_for(key)?;
+        self.nodes.get(&entry.owner).cloned()
+    }
+
+    fn get_replicas(&self, key: &[u8], replica_count: usize) -> Vec<DistributionNode> {
+        match self.entry_for(key) {
+            Some((_, entry)) => self.replicas_of(&entry.owner, replica_count),
+            None => Vec::new(),
+        }
+    }
+
+    fn ownership(&self, replica_count: usize) -> Vec<RangeOwnership> {
+        self.ranges
+            .iter()
+            .map(|(start, entry)| RangeOwnership {
+                range: PartitionRange::Key(KeyRange::new(start.clone(), entry.end.clone())),
+                replicas: self
+                    .replicas_of(&entry.owner, replica_count)
+                    .into_iter()
+                    .map(|n| n.id)
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn clone_box(&self) -> Box<dyn DistributionStrategy> {
+        Box::new(self.clone())
+    }
+
+    fn kind(&self) -> DistributionKind {
+        DistributionKind::Range
+    }
+
+    fn record_write(&self, key: &[u8], size: usize) {
+        RangePartitioner::record_write(self, key, size);
+    }
+
+    fn record_read(&self, key: &[u8]) {
+        RangePartitioner::record_read(self, key);
+    }
+
+    fn drain_usage(&self) -> Vec<RangeUsage> {
+        RangePartitioner::drain_usage(self)
+    }
+
+    fn absorb_usage(&self, usage: &[RangeUsage]) {
+        RangePartitioner::absorb_usage(self, usage);
+    }
+
+    fn split_and_merge(&mut self) -> bool {
+        RangePartitioner::split_and_merge(self)
+    }
+
+    fn assign_ranges(&mut self, layout: &[(KeyRange, String)]) -> bool {
+        RangePartitioner::assign_ranges(self, layout)
+    }
+
+    fn route_scan(&self, start: &[u8], end: Option<&[u8]>) -> Vec<ScanRoute> {
+        RangePartitioner::route_scan(self, start, end)
+    }
+
+    fn all_nodes(&self) -> Vec<DistributionNode> {
+        self.sorted_nodes().into_iter().cloned().collect()
+    }
+}
+
+/// 节点之间交换范围负载和范围划分的 topic
+pub const RANGE_TOPIC: &str = "coretex.ranges";
+
+#[derive(Serialize, Deserialize)]
+enum RangeMessage {
+    Usage(Vec<RangeUsage>),
+    Layout(Vec<(KeyRange, String)>),
+}
+
+/// 按负载分裂/合并范围，并在节点之间保持同样的范围划分（对应 `DistributionConfig::range`）
+///
+/// 协调者在读写路径上把负载记录到本节点的分布环（[`SharedRing::record_write`]）。分布环中标识最小的节点
+/// 负责决定：其他节点每隔 `interval_ms` 把新增的负载发给它，它汇总后分裂/合并范围并均衡归属，
+/// 再把范围划分广播给所有节点采用。划分每个周期都会广播，暂时不一致的节点在下一个周期恢复一致；
+/// 归属变化后的数据迁移由各节点的 [`RebalanceService`](super::RebalanceService) 完成。
+pub struct RangeSplitService {
+    task: JoinHandle<()>,
+}
+
+impl RangeSplitService {
+    pub async fn start(
+        node_id: impl Into<String>,
+        ring: Arc<SharedRing>,
+        broker: Arc<dyn MessageBroker>,
+        interval: Duration,
+    ) -> Result<Self> {
+        let node_id = node_id.into();
+        let mut incoming = broker.subscribe(RANGE_TOPIC).await?;
+        let task = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval.max(Duration::from_millis(1)));
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    message = incoming.next() => {
+                        let Some(message) = message else {
+                            break;
+                        };
+                        let deciding = deciding(&node_id, &ring);
+                        match message.and_then(|m| Ok(serde_json::from_slice::<RangeMessage>(&m.data)?)) {
+                            Ok(RangeMessage::Usage(usage)) if deciding => {
+                                ring.snapshot().strategy.absorb_usage(&usage);
+                            }
+                            Ok(RangeMessage::Layout(layout)) if !deciding => {
+                                ring.update(|strategy| strategy.assign_ranges(&layout));
+                            }
+                            Ok(_) => {}
+                            Err(e) => tracing::warn!("无法解析范围消息: {}", e),
+                        }
+                    }
+                    _ = ticker.tick() => {
+                        let usage = ring.snapshot().strategy.drain_usage();
+                        let message = if deciding(&node_id, &ring) {
+                            // 本节点记录的负载已计入自己的统计
+                            ring.split_and_merge();
+                            RangeMessage::Layout(layout(&ring))
+                        } else if !usage.is_empty() {
+                            RangeMessage::Usage(usage)
+                        } else {
+                            continue;
+                        };
+                        let result = match serde_json::to_vec(&message) {
+                            Ok(data) => broker.publish(RANGE_TOPIC, data).await,
+                            Err(e) => Err(e.into()),
+                        };
+                        if let Err(e) = result {
+                            tracing::warn!("广播范围消息失败: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+        Ok(Self { task })
+    }
+}
+
+impl Drop for RangeSplitService {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// 本节点是否为分布环中标识最小的节点
+fn deciding(node_id: &str, ring: &SharedRing) -> bool {
+    ring.snapshot().strategy.all_nodes().iter().map(|n| n.id.as_str()).min() == Some(node_id)
+}
+
+/// 当前的范围划分及各范围的归属节点
+fn layout(ring: &SharedRing) -> Vec<(KeyRange, String)> {
+    ring.snapshot()
+        .ownership(1)
+        .into_iter()
+        .filter_map(|ownership| match ownership.range {
+            PartitionRange::Key(range) => Some((range, ownership.replicas.into_iter().next()?)),
+            PartitionRange::Hash(_) => None,
+        })
+        .collect()
+}
@@ -0,0 +1,527 @@
+use super::{HashRange, KeyRange, PartitionRange, RangeOwnership, SharedRing};
+use crate::config::RebalanceConfig;
+use crate::consistency::{is_internal, merge_stored, ReplicaClient, ReplicaOp};
+use crate::error::Error;
+use crate::storage::{StorageEngine, WriteOperation};
+use crate::Result;
+use bytes::Bytes;
+use futures::{StreamExt, TryStreamExt};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// 一次数据迁移：把 `range` 内的数据从 `from` 复制到 `to`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transfer {
+    pub range: PartitionRange,
+    pub from: String,
+    pub to: String,
+}
+
+/// 再均衡计划
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RebalancePlan {
+    /// 需要执行的数据迁移
+    pub transfers: Vec<Transfer>,
+    /// 迁移完成后各节点不再负责、可以清理的分区
+    pub releases: Vec<(PartitionRange, String)>,
+}
+
+impl RebalancePlan {
+    pub fn is_empty(&self) -> bool {
+        self.transfers.is_empty() && self.releases.is_empty()
+    }
+}
+
+/// 比较两个分布状态，生成分区迁移计划
+///
+/// 典型用法：在 `add_node`/`remove_node` 前后各取一次 `ownership`，再调用 [`RebalancePlanner::diff`]。
+pub struct RebalancePlanner;
+
+impl RebalancePlanner {
+    pub fn diff(before: &[RangeOwnership], after: &[RangeOwnership]) -> Result<RebalancePlan> {
+        if before.is_empty() || after.is_empty() {
+            // 集群从无到有或全部下线时没有可迁移的数据
+            return Ok(RebalancePlan::default());
+        }
+
+        let mut hash = (Vec::new(), Vec::new());
+        let mut key = (Vec::new(), Vec::new());
+        for (side, list) in [(0, before), (1, after)] {
+            for own in list {
+                match &own.range {
+                    PartitionRange::Hash(r) => {
+                        let target = if side == 0 { &mut hash.0 } else { &mut hash.1 };
+                        target.push((r.start, r.end, own.replicas.clone()));
+                    }
+                    PartitionRange::Key(r) => {
+                        let target = if side == 0 { &mut key.0 } else { &mut key.1 };
+                        target.push((r.start.clone(), r.end.clone(), own.replicas.clone()));
+                    }
+                }
+            }
+        }
+
+        let segments: Vec<(PartitionRange, Vec<String>, Vec<String>)> = match (
+            hash.0.is_empty(),
+            hash.1.is_empty(),
+            key.0.is_empty(),
+            key.1.is_empty(),
+        ) {
+            (false, false, true, true) => refine(hash.0, hash.1)
+                .into_iter()
+                .map(|(s, e, b, a)| (PartitionRange::Hash(HashRange::new(s, e)), b, a))
+                .collect(),
+            (true, true, false, false) => refine(key.0, key.1)
+                .into_iter()
+                .map(|(s, e, b, a)| (PartitionRange::Key(KeyRange::new(s, e)), b, a))
+                .collect(),
+            _ => {
+                return Err(Error::Configuration(
+                    "无法在哈希分区与范围分区之间生成迁移计划".to_string(),
+                ))
+            }
+        };
+
+        let mut plan = RebalancePlan::default();
+        for (range, before, after) in segments {
+            // 优先从迁移后仍然保留该分区的旧副本读取数据
+            let source = before
+                .iter()
+                .find(|id| after.contains(id))
+                .or_else(|| before.first());
+            if let Some(source) = source {
+                for to in after.iter().filter(|id| !before.contains(id)) {
+                    push_transfer(&mut plan.transfers, range.clone(), source, to);
+                }
+            }
+            for old in before.iter().filter(|id| !after.contains(id)) {
+                push_release(&mut plan.releases, range.clone(), old);
+            }
+        }
+        Ok(plan)
+    }
+}
+
+/// 将两组首尾相接的区间按双方的边界细分，返回每段区间在前后两个状态下的副本
+#[allow(clippy::type_complexity)]
+fn refine<B: Ord + Clone>(
+    before: Vec<(B, Option<B>, Vec<String>)>,
+    after: Vec<(B, Option<B>, Vec<String>)>,
+) -> Vec<(B, Option<B>, Vec<String>, Vec<String>)> {
+    let before: BTreeMap<B, Vec<String>> = before.into_iter().map(|(s, _, r)| (s, r)).collect();
+    let after: BTreeMap<B, Vec<String>> = after.into_iter().map(|(s, _, r)| (s, r)).collect();
+    let bounds: Vec<B> = before
+        .keys()
+        .chain(after.keys())
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let lookup = |map: &BTreeMap<B, Vec<String>>, at: &B| -> Vec<String> {
+        map.range(..=at.clone())
+            .next_back()
+            .map(|(_, r)| r.clone())
+            .unwrap_or_default()
+    };
+
+    bounds
+        .iter()
+        .enumerate()
+        .map(|(i, start)| {
+            let end = bounds.get(i + 1).cloned();
+            (start.clone(), end, lookup(&before, start), lookup(&after, start))
+        })
+        .collect()
+}
+
+/// 与上一个相同来源/目标的迁移首尾相接时合并为一个
+fn push_transfer(transfers: &mut Vec<Transfer>, range: PartitionRange, from: &str, to: &str) {
+    if let Some(prev) = transfers
+        .iter_mut()
+        .rev()
+        .find(|t| t.from == from && t.to == to)
+    {
+        if let Some(merged) = join(&prev.range, &range) {
+            prev.range = merged;
+            return;
+        }
+    }
+    transfers.push(Transfer {
+        range,
+        from: from.to_string(),
+        to: to.to_string(),
+    });
+}
+
+fn push_release(releases: &mut Vec<(PartitionRange, String)>, range: PartitionRange, node: &str) {
+    if let Some(prev) = releases.iter_mut().rev().find(|(_, n)| n == node) {
+        if let Some(merged) = join(&prev.0, &range) {
+            prev.0 = merged;
+            return;
+        }
+    }
+    releases.push((range, node.to_string()));
+}
+
+/// 两个相邻区间拼接成一个
+fn join(left: &PartitionRange, right: &PartitionRange) -> Option<PartitionRange> {
+    match (left, right) {
+        (PartitionRange::Hash(l), PartitionRange::Hash(r)) if l.end == Some(r.start) => {
+            Some(PartitionRange::Hash(HashRange::new(l.start, r.end)))
+        }
+        (PartitionRange::Key(l), PartitionRange::Key(r)) if l.end.as_ref() == Some(&r.start) => {
+            Some(PartitionRange::Key(KeyRange::new(l.start.clone(), r.end.clone())))
+        }
+        _ => None,
+    }
+}
+
+/// 迁移限流参数
+#[derive(Clone, Debug)]
+pub struct Throttle {
+    /// 同时进行迁移的源节点数量，同一源节点上的迁移依次执行
+    pub max_concurrent: usize,
+    /// 所有迁移合计的带宽上限（字节/秒），`None` 表示不限速
+    pub bytes_per_sec: Option<u64>,
+    /// 每批写入目标节点的 key 数量
+    pub batch_size: usize,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::from_config(&RebalanceConfig::default())
+    }
+}
+
+impl Throttle {
+    pub fn from_config(config: &RebalanceConfig) -> Self {
+        Self {
+            max_concurrent: config.max_concurrent,
+            bytes_per_sec: Some(config.bytes_per_sec).filter(|rate| *rate > 0),
+            batch_size: config.batch_size,
+        }
+    }
+}
+
+/// 迁移执行结果
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RebalanceProgress {
+    pub transfers_completed: usize,
+    pub keys_copied: u64,
+    pub bytes_copied: u64,
+    pub keys_released: u64,
+}
+
+impl RebalanceProgress {
+    fn add(&mut self, other: &RebalanceProgress) {
+        self.transfers_completed += other.transfers_completed;
+        self.keys_copied += other.keys_copied;
+        self.bytes_copied += other.bytes_copied;
+        self.keys_released += other.keys_released;
+    }
+}
+
+/// 执行迁移计划
+///
+/// 迁移期间集群保持在线：目标节点上已存在的 key 与迁移的数据合并（CRDT 状态合并、
+/// 并发版本列表按因果关系合并，带时间戳的值保留较晚的一个），未加时间戳的值无法判断先后时保留目标节点上的值。
+/// 所有迁移成功后才清理旧副本上的数据，清理前重新扫描旧副本，把迁移期间写入旧副本的数据补到新副本。
+///
+/// 源节点和被清理的节点必须在本进程内（`engines`）；目标节点不在本进程时，
+/// 通过 [`with_remote`](Self::with_remote) 设置的副本客户端把数据发给目标节点的副本服务合并。
+pub struct RebalanceExecutor {
+    engines: HashMap<String, Arc<dyn StorageEngine>>,
+    remote: Option<(Arc<ReplicaClient>, Duration)>,
+    throttle: Throttle,
+    limiter: Mutex<(Instant, u64)>,
+}
+
+/// 接收迁移数据的节点
+enum Target<'a> {
+    Local(&'a Arc<dyn StorageEngine>),
+    Remote(&'a ReplicaClient, Duration),
+}
+
+impl RebalanceExecutor {
+    pub fn new(engines: HashMap<String, Arc<dyn StorageEngine>>, throttle: Throttle) -> Self {
+        Self {
+            engines,
+            remote: None,
+            throttle,
+            limiter: Mutex::new((Instant::now(), 0)),
+        }
+    }
+
+    /// 通过 `client` 向不在本进程的目标节点发送迁移数据，每批最多等待 `timeout`
+    pub fn with_remote(mut self, client: Arc<ReplicaClient>, timeout: Duration) -> Self {
+        self.remote = Some((client, timeout));
+        self
+    }
+
+    fn engine(&self, node_id: &str) -> Result<&Arc<dyn StorageEngine>> {
+        self.engines
+            .get(node_id)
+            .ok_or_else(|| Error::Storage(format!("节点 {} 没有对应的存储引擎", node_id)))
+    }
+
+    fn target(&self, node_id: &str) -> Result<Target<'_>> {
+        if let Some(engine) = self.engines.get(node_id) {
+            return Ok(Target::Local(engine));
+        }
+        match &self.remote {
+            Some((client, timeout)) => Ok(Target::Remote(client.as_ref(), *timeout)),
+            None => Err(Error::Storage(format!("节点 {} 没有对应的存储引擎", node_id))),
+        }
+    }
+
+    pub async fn execute(&self, plan: &RebalancePlan) -> Result<RebalanceProgress> {
+        self.execute_for(plan, None).await
+    }
+
+    /// 只执行以 `node_id` 为源的迁移和 `node_id` 上的清理
+    ///
+    /// 各节点在自己的分布环变化后计算同样的计划，各自执行其中属于自己的部分。
+    pub async fn execute_local(&self, plan: &RebalancePlan, node_id: &str) -> Result<RebalanceProgress> {
+        self.execute_for(plan, Some(node_id)).await
+    }
+
+    async fn execute_for(&self, plan: &RebalancePlan, node_id: Option<&str>) -> Result<RebalanceProgress> {
+        let local = |id: &str| node_id.is_none_or(|node_id| node_id == id);
+        let mut sources: BTreeMap<&str, Vec<&Transfer>> = BTreeMap::new();
+        for transfer in plan.transfers.iter().filter(|t| local(&t.from)) {
+            sources.entry(transfer.from.as_str()).or_default().push(transfer);
+        }
+        let mut tasks = Vec::new();
+        for (from, transfers) in sources {
+            tasks.push(self.transfer_from(from, transfers));
+        }
+        let results: Vec<RebalanceProgress> = futures::stream::iter(tasks)
+            .buffer_unordered(self.throttle.max_concurrent.max(1))
+            .try_collect()
+            .await?;
+
+        let mut progress = RebalanceProgress::default();
+        for result in &results {
+            progress.add(result);
+        }
+        let mut releases: BTreeMap<&str, Vec<&PartitionRange>> = BTreeMap::new();
+        for (range, node) in plan.releases.iter().filter(|(_, node)| local(node)) {
+            releases.entry(node.as_str()).or_default().push(range);
+        }
+        let transfers: Vec<&Transfer> = plan.transfers.iter().collect();
+        for (node, ranges) in releases {
+            progress.keys_released += self.release(node, &ranges, &transfers).await?;
+        }
+        Ok(progress)
+    }
+
+    /// 执行同一源节点的全部迁移，共用对源节点的扫描
+    async fn transfer_from(&self, from: &str, transfers: Vec<&Transfer>) -> Result<RebalanceProgress> {
+        let ranges: Vec<&PartitionRange> = transfers.iter().map(|t| &t.range).collect();
+        let (keys, bytes, _) = self.forward(self.engine(from)?.as_ref(), &ranges, &transfers, false).await?;
+        Ok(RebalanceProgress {
+            transfers_completed: transfers.len(),
+            keys_copied: keys,
+            bytes_copied: bytes,
+            keys_released: 0,
+        })
+    }
+
+    /// 删除 `node_id` 上 `ranges` 内的数据，删除前把每个 key 合并到接收它的新副本上
+    async fn release(&self, node_id: &str, ranges: &[&PartitionRange], transfers: &[&Transfer]) -> Result<u64> {
+        let engine = self.engine(node_id)?;
+        let (_, _, keys) = self.forward(engine.as_ref(), ranges, transfers, true).await?;
+        let count = keys.len() as u64;
+        let ops: Vec<WriteOperation> = keys.into_iter().map(|key| WriteOperation::Delete { key }).collect();
+        for chunk in ops.chunks(self.throttle.batch_size.max(1)) {
+            engine.batch_write(chunk.to_vec()).await?;
+        }
+        Ok(count)
+    }
+
+    /// 扫描 `source` 上落在 `ranges` 内的数据，发给覆盖各 key 的迁移的目标节点
+    ///
+    /// 返回目标节点实际写入的 key 数和字节数，`keep_keys` 时还返回扫描到的 key。
+    async fn forward(
+        &self,
+        source: &dyn StorageEngine,
+        ranges: &[&PartitionRange],
+        transfers: &[&Transfer],
+        keep_keys: bool,
+    ) -> Result<(u64, u64, Vec<Bytes>)> {
+        let batch_size = self.throttle.batch_size.max(1);
+        let (mut keys, mut bytes) = (0u64, 0u64);
+        let mut seen = Vec::new();
+        let mut batches: HashMap<&str, Vec<(Bytes, Bytes)>> = HashMap::new();
+        for scan in scans(ranges) {
+            let mut items = scan_range(source, &scan).await?;
+            while let Some(kv) = items.next().await {
+                let kv = kv?;
+                if is_internal(&kv.key) || !ranges.iter().any(|r| r.contains(&kv.key)) {
+                    continue;
+                }
+                for transfer in transfers.iter().filter(|t| t.range.contains(&kv.key)) {
+                    let batch = batches.entry(transfer.to.as_str()).or_default();
+                    batch.push((kv.key.clone(), kv.value.clone()));
+                    if batch.len() >= batch_size {
+                        let (k, b) = self.deliver(&transfer.to, std::mem::take(batch)).await?;
+                        keys += k;
+                        bytes += b;
+                    }
+                }
+                if keep_keys {
+                    seen.push(kv.key);
+                }
+            }
+        }
+        for (to, batch) in batches.into_iter().filter(|(_, batch)| !batch.is_empty()) {
+            let (k, b) = self.deliver(to, batch).await?;
+            keys += k;
+            bytes += b;
+        }
+        Ok((keys, bytes, seen))
+    }
+
+    /// 把一批数据合并到节点 `to`，返回写入的 key 数和字节数
+    async fn deliver(&self, to: &str, entries: Vec<(Bytes, Bytes)>) -> Result<(u64, u64)> {
+        let (keys, bytes) = match self.target(to)? {
+            Target::Local(engine) => {
+                let mut ops = Vec::with_capacity(entries.len());
+                let mut bytes = 0u64;
+                for (key, value) in entries {
+                    if let Some(value) = merge_stored(engine.as_ref(), &key, value).await? {
+                        bytes += (key.len() + value.len()) as u64;
+                        ops.push(WriteOperation::Put { key, value });
+                    }
+                }
+                let keys = ops.len() as u64;
+                if !ops.is_empty() {
+                    engine.batch_write(ops).await?;
+                }
+                (keys, bytes)
+            }
+            Target::Remote(client, timeout) => {
+                let keys = entries.len() as u64;
+                let bytes = entries.iter().map(|(k, v)| (k.len() + v.len()) as u64).sum();
+                client.call(to, ReplicaOp::Merge { entries }, timeout).await?;
+                (keys, bytes)
+            }
+        };
+        self.throttle(bytes).await;
+        Ok((keys, bytes))
+    }
+
+    /// 按带宽上限计算累计发送量所需的时间，不足则等待
+    async fn throttle(&self, bytes: u64) {
+        let Some(rate) = self.throttle.bytes_per_sec.filter(|r| *r > 0) else {
+            return;
+        };
+        let wait = {
+            let mut limiter = self.limiter.lock().unwrap();
+            limiter.1 += bytes;
+            let due = Duration::from_secs_f64(limiter.1 as f64 / rate as f64);
+            due.saturating_sub(limiter.0.elapsed())
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// 迁移失败后重试的间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 订阅分布环的版本变化，为每次变化生成迁移计划并执行本节点负责的部分
+///
+/// 各节点比较自己的分布环相邻两个版本的分区归属，得到同样的计划：本节点作为源的迁移把数据
+/// 发给新副本，本节点不再负责的分区在把数据转交新副本后清理。迁移失败时保留旧版本，
+/// 稍后与最新版本重新比较并重试，合并是幂等的，已完成的部分重复执行不会出错。
+pub struct RebalanceService {
+    progress: Arc<Mutex<RebalanceProgress>>,
+    task: JoinHandle<()>,
+}
+
+impl RebalanceService {
+    pub fn start(
+        node_id: impl Into<String>,
+        ring: Arc<SharedRing>,
+        storage: Arc<dyn StorageEngine>,
+        client: Arc<ReplicaClient>,
+        replica_count: usize,
+        throttle: Throttle,
+        timeout: Duration,
+    ) -> Self {
+        let node_id = node_id.into();
+        let engines = HashMap::from([(node_id.clone(), storage)]);
+        let executor = RebalanceExecutor::new(engines, throttle).with_remote(client, timeout);
+        let progress = Arc::new(Mutex::new(RebalanceProgress::default()));
+        let task_progress = progress.clone();
+        let mut epochs = ring.subscribe();
+        let task = tokio::spawn(async move {
+            let mut previous = ring.snapshot();
+            loop {
+                let current = ring.snapshot();
+                if current.epoch != previous.epoch {
+                    let plan = RebalancePlanner::diff(
+                        &previous.ownership(replica_count),
+                        &current.ownership(replica_count),
+                    );
+                    match plan {
+                        Ok(plan) => match executor.execute_local(&plan, &node_id).await {
+                            Ok(done) => {
+                                task_progress.lock().unwrap().add(&done);
+                                previous = current;
+                            }
+                            Err(e) => {
+                                tracing::warn!("分布环版本 {} 的数据迁移失败，稍后重试: {}", current.epoch, e);
+                                tokio::time::sleep(RETRY_INTERVAL).await;
+                                continue;
+                            }
+                        },
+                        Err(e) => {
+                            tracing::warn!("无法为分布环版本 {} 生成迁移计划: {}", current.epoch, e);
+                            previous = current;
+                        }
+                    }
+                }
+                if epochs.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+        Self { progress, task }
+    }
+
+    /// 本节点累计完成的迁移
+    pub fn progress(&self) -> RebalanceProgress {
+        self.progress.lock().unwrap().clone()
+    }
+}
+
+impl Drop for RebalanceService {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// 覆盖 `ranges` 所需的扫描：key 范围各自扫描，哈希范围共用一次全量扫描
+fn scans(ranges: &[&PartitionRange]) -> Vec<PartitionRange> {
+    if ranges.iter().any(|r| matches!(r, PartitionRange::Hash(_))) {
+        return vec![PartitionRange::Hash(HashRange::new(0, None))];
+    }
+    ranges.iter().map(|r| (*r).clone()).collect()
+}
+
+/// 扫描分区数据：key 范围直接按范围扫描，哈希范围需要全量扫描后按哈希过滤
+async fn scan_range(
+    engine: &dyn StorageEngine,
+    range: &PartitionRange,
+) -> Result<std::pin::Pin<Box<dyn futures::Stream<Item = Result<crate::storage::KeyValue>> + Send>>> {
+    match range {
+        PartitionRange::Key(r) => engine.scan(&r.start, r.end.as_deref(), None).await,
+        PartitionRange::Hash(_) => engine.scan(b"", None, None).await,
+    }
+}
@@ -0,0 +1,123 @@
+use super::{
+    fxhash, partition_of, partition_range, placement, DistributionNode, DistributionStrategy,
+    PartitionRange, RangeOwnership, DEFAULT_PARTITIONS,
+};
+use crate::config::DistributionKind;
+use std::collections::HashMap;
+
+/// 最高随机权重（HRW / Rendezvous）分布策略
+///
+/// key 先映射到固定数量的分区，每个分区对所有节点打分，
+/// 分数最高者为主节点，其余按分数降序构成偏好列表。
+/// 增删节点时只有归属于该节点的分区会迁移。
+#[derive(Clone)]
+pub struct RendezvousHashing {
+    nodes: HashMap<String, DistributionNode>,
+    partitions: usize,
+    load_factors: HashMap<String, f64>,
+}
+
+impl RendezvousHashing {
+    pub fn new() -> Self {
+        Self::with_partitions(DEFAULT_PARTITIONS)
+    }
+
+    /// 指定分区数量
+    pub fn with_partitions(partitions: usize) -> Self {
+        Self {
+            nodes: HashMap::new(),
+            partitions: partitions.max(1),
+            load_factors: HashMap::new(),
+        }
+    }
+
+    /// 加权打分：score = -weight / ln(h)，h 为 (0, 1] 上的均匀哈希值
+    fn score(&self, node: &DistributionNode, partition: u64) -> f64 {
+        let mut data = node.id.as_bytes().to_vec();
+        data.extend_from_slice(&partition.to_be_bytes());
+        let hash = fxhash::hash64(&data);
+        let unit = ((hash >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        -(node.weight.max(1) as f64 * self.load_factor(&node.id)) / unit.ln()
+    }
+
+    /// 按分数降序排列的候选节点
+    fn ranked(&self, key: &[u8]) -> Vec<&DistributionNode> {
+        self.ranked_partition(partition_of(fxhash::hash64(key), self.partitions))
+    }
+
+    fn ranked_partition(&self, partition: u64) -> Vec<&DistributionNode> {
+        let mut scored: Vec<(f64, &DistributionNode)> = self
+            .nodes
+            .values()
+            .map(|node| (self.score(node, partition), node))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.id.cmp(&b.1.id)));
+        scored.into_iter().map(|(_, node)| node).collect()
+    }
+}
+
+impl Default for RendezvousHashing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DistributionStrategy for RendezvousHashing {
+    fn add_node(&mut self, node: DistributionNode) {
+        self.nodes.insert(node.id.clone(), node);
+    }
+
+    fn remove_node(&mut self, node_id: &str) {
+        self.nodes.remove(node_id);
+        self.load_factors.remove(node_id);
+    }
+
+    fn get_primary(&self, key: &[u8]) -> Option<DistributionNode> {
+        self.ranked(key).first().map(|node| (*node).clone())
+    }
+
+    fn get_replicas(&self, key: &[u8], replica_count: usize) -> Vec<DistributionNode> {
+        placement::select_replicas(self.ranked(key), replica_count)
+    }
+
+    fn ownership(&self, replica_count: usize) -> Vec<RangeOwnership> {
+        if self.nodes.is_empty() {
+            return Vec::new();
+        }
+        (0..self.partitions as u64)
+            .map(|p| RangeOwnership {
+                range: PartitionRange::Hash(partition_range(p, self.partitions)),
+                replicas: placement::select_replicas(self.ranked_partition(p), replica_count)
+                    .into_iter()
+                    .map(|n| n.id)
+                    .collect(),
+            })
+            .collect()
+    }
+
+    fn clone_box(&self) -> Box<dyn DistributionStrategy> {
+        Box::new(self.clone())
+    }
+
+    fn set_load_factor(&mut self, node_id: &str, factor: f64) -> bool {
+        if !self.nodes.contains_key(node_id) {
+            return false;
+        }
+        self.load_factors.insert(node_id.to_string(), factor);
+        true
+    }
+
+    fn load_factor(&self, node_id: &str) -> f64 {
+        self.load_factors.get(node_id).copied().unwrap_or(1.0)
+    }
+
+    fn kind(&self) -> DistributionKind {
+        DistributionKind::Rendezvous
+    }
+
+    fn all_nodes(&self) -> Vec<DistributionNode> {
+        let mut nodes: Vec<_> = self.nodes.values().cloned().collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        nodes
+    }
+}
@@ -0,0 +1,173 @@
+use super::{
+    fxhash, placement, DistributionNode, DistributionStrategy, HashRange, PartitionRange,
+    RangeOwnership, DEFAULT_WEIGHT,
+};
+use crate::config::DistributionKind;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// 每个默认权重节点在环上的虚拟节点数
+pub const DEFAULT_VIRTUAL_NODES: usize = 64;
+
+/// 一致性哈希分布策略
+///
+/// 每个物理节点按权重在环上放置若干虚拟节点（token），
+/// key 顺时针找到的第一个 token 的节点为主节点。
+#[derive(Clone)]
+pub struct ConsistentHashRing {
+    nodes: HashMap<String, DistributionNode>,
+    ring: BTreeMap<u64, String>,
+    virtual_nodes: usize,
+    load_factors: HashMap<String, f64>,
+}
+
+impl ConsistentHashRing {
+    pub fn new() -> Self {
+        Self::with_virtual_nodes(DEFAULT_VIRTUAL_NODES)
+    }
+
+    /// 指定默认权重节点的虚拟节点数
+    pub fn with_virtual_nodes(virtual_nodes: usize) -> Self {
+        Self {
+            nodes: HashMap::new(),
+            ring: BTreeMap::new(),
+            virtual_nodes: virtual_nodes.max(1),
+            load_factors: HashMap::new(),
+        }
+    }
+
+    fn token_count(&self, node: &DistributionNode) -> usize {
+        let factor = self.load_factor(&node.id);
+        let scaled = self.virtual_nodes as f64 * node.weight as f64 / DEFAULT_WEIGHT as f64 * factor;
+        (scaled.round() as usize).max(1)
+    }
+
+    fn insert_tokens(&mut self, node: &DistributionNode) {
+        for token in self.tokens_for(node) {
+            // token 冲突时保留 id 较小的节点，使环的形态与添加顺序无关
+            let owner = self.ring.entry(token).or_insert_with(|| node.id.clone());
+            if node.id < *owner {
+                *owner = node.id.clone();
+            }
+        }
+    }
+
+    fn tokens_for(&self, node: &DistributionNode) -> Vec<u64> {
+        (0..self.token_count(node))
+            .map(|i| fxhash::hash64(format!("{}#{}", node.id, i).as_bytes()))
+            .collect()
+    }
+
+    /// 从 key 的哈希位置开始顺时针遍历环上的节点
+    fn walk(&self, key: &[u8]) -> impl Iterator<Item = &DistributionNode> {
+        self.walk_from(fxhash::hash64(key))
+    }
+
+    /// 从哈希位置开始顺时针遍历，每个物理节点只出现一次，遍历完所有节点即停止
+    fn walk_from(&self, hash: u64) -> impl Iterator<Item = &DistributionNode> {
+        let mut seen = HashSet::new();
+        self.ring
+            .range(hash..)
+            .chain(self.ring.range(..hash))
+            .filter(move |(_, id)| seen.insert(id.as_str()))
+            .take(self.nodes.len())
+            .filter_map(|(_, id)| self.nodes.get(id))
+    }
+}
+
+impl Default for ConsistentHashRing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DistributionStrategy for ConsistentHashRing {
+    fn add_node(&mut self, node: DistributionNode) {
+        self.ring.retain(|_, id| *id != node.id);
+        self.insert_tokens(&node);
+        self.nodes.insert(node.id.clone(), node);
+    }
+
+    fn remove_node(&mut self, node_id: &str) {
+        self.load_factors.remove(node_id);
+        if self.nodes.remove(node_id).is_some() {
+            self.ring.retain(|_, id| id != node_id);
+        }
+    }
+
+    fn get_primary(&self, key: &[u8]) -> Option<DistributionNode> {
+        self.walk(key).next().cloned()
+    }
+
+    fn get_replicas(&self, key: &[u8], replica_count: usize) -> Vec<DistributionNode> {
+        placement::select_replicas(self.walk(key), replica_count)
+    }
+
+    fn ownership(&self, replica_count: usize) -> Vec<RangeOwnership> {
+        let replicas_at = |token: u64| -> Vec<String> {
+            placement::select_replicas(self.walk_from(token), replica_count)
+                .into_iter()
+                .map(|n| n.id)
+                .collect()
+        };
+        let tokens: Vec<u64> = self.ring.keys().copied().collect();
+        let Some(&first) = tokens.first() else {
+            return Vec::new();
+        };
+
+        // token t_i 负责 (t_{i-1}, t_i]，即 [t_{i-1} + 1, t_i + 1)；末尾一段绕回首个 token
+        let mut result = Vec::with_capacity(tokens.len() + 1);
+        let mut start = 0u64;
+        for &token in &tokens {
+            let end = token.checked_add(1);
+            result.push(RangeOwnership {
+                range: PartitionRange::Hash(HashRange::new(start, end)),
+                replicas: replicas_at(token),
+            });
+            match end {
+                Some(end) => start = end,
+                None => return result,
+            }
+        }
+        result.push(RangeOwnership {
+            range: PartitionRange::Hash(HashRange::new(start, None)),
+            replicas: replicas_at(first),
+        });
+        result
+    }
+
+    fn clone_box(&self) -> Box<dyn DistributionStrategy> {
+        Box::new(self.clone())
+    }
+
+    fn set_load_factor(&mut self, node_id: &str, factor: f64) -> bool {
+        let Some(node) = self.nodes.get(node_id).cloned() else {
+            return false;
+        };
+        self.load_factors.insert(node.id.clone(), factor);
+        self.ring.retain(|_, id| id != node_id);
+        self.insert_tokens(&node);
+        true
+    }
+
+    fn load_factor(&self, node_id: &str) -> f64 {
+        self.load_factors.get(node_id).copied().unwrap_or(1.0)
+    }
+
+    fn kind(&self) -> DistributionKind {
+        DistributionKind::ConsistentHash
+    }
+
+    fn tokens(&self, node_id: &str) -> Vec<u64> {
+        self.ring
+            .iter()
+            .filter(|(_, id)| id.as_str() == node_id)
+            .map(|(token, _)| *token)
+            .collect()
+    }
+
+    fn all_nodes(&self) -> Vec<DistributionNode> {
+        let mut nodes: Vec<_> = self.nodes.values().cloned().collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+        nodes
+    }
+}
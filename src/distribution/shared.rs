@@ -0,0 +1,143 @@
+use super::{DistributionNode, DistributionStrategy, PartitionMap, RangeOwnership, ScanRoute};
+use crate::error::Error;
+use crate::Result;
+use arc_swap::ArcSwap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// 某个版本（epoch）的分布环，一经发布不再修改
+///
+/// 一次请求应持有同一个快照完成全部路由，保证所有副本选择基于同一版本的环。
+pub struct RingSnapshot {
+    pub epoch: u64,
+    pub strategy: Box<dyn DistributionStrategy>,
+}
+
+impl RingSnapshot {
+    pub fn get_primary(&self, key: &[u8]) -> Option<DistributionNode> {
+        self.strategy.get_primary(key)
+    }
+
+    pub fn get_replicas(&self, key: &[u8], replica_count: usize) -> Vec<DistributionNode> {
+        self.strategy.get_replicas(key, replica_count)
+    }
+
+    pub fn ownership(&self, replica_count: usize) -> Vec<RangeOwnership> {
+        self.strategy.ownership(replica_count)
+    }
+
+    /// 带版本号的可序列化分区映射
+    pub fn partition_map(&self, replica_count: usize) -> PartitionMap {
+        PartitionMap {
+            epoch: self.epoch,
+            ..self.strategy.partition_map(replica_count)
+        }
+    }
+
+    /// `[start, end)` 的扫描需要访问的节点及子范围
+    pub fn route_scan(&self, start: &[u8], end: Option<&[u8]>) -> Vec<ScanRoute> {
+        self.strategy.route_scan(start, end)
+    }
+
+    pub fn contains(&self, node_id: &str) -> bool {
+        self.strategy.all_nodes().iter().any(|n| n.id == node_id)
+    }
+}
+
+/// 写时复制的共享分布环
+///
+/// 读取方通过 [`SharedRing::snapshot`] 无锁地获取当前版本；
+/// 写入方在私有副本上修改后原子替换，写入之间串行执行。
+pub struct SharedRing {
+    current: ArcSwap<RingSnapshot>,
+    writer: Mutex<()>,
+    epochs: watch::Sender<u64>,
+}
+
+impl SharedRing {
+    pub fn new(strategy: Box<dyn DistributionStrategy>) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(RingSnapshot { epoch: 0, strategy }),
+            writer: Mutex::new(()),
+            epochs: watch::channel(0).0,
+        }
+    }
+
+    /// 当前版本的分布环
+    pub fn snapshot(&self) -> Arc<RingSnapshot> {
+        self.current.load_full()
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.current.load().epoch
+    }
+
+    /// 复制当前环并交给 `f` 修改，`f` 返回 `true` 时发布为新版本
+    ///
+    /// 返回修改后的当前版本号。
+    pub fn update<F>(&self, f: F) -> u64
+    where
+        F: FnOnce(&mut dyn DistributionStrategy) -> bool,
+    {
+        let _guard = self.writer.lock().unwrap();
+        let current = self.current.load_full();
+        let mut strategy = current.strategy.clone_box();
+        if !f(strategy.as_mut()) {
+            return current.epoch;
+        }
+        let epoch = current.epoch + 1;
+        self.current.store(Arc::new(RingSnapshot { epoch, strategy }));
+        self.epochs.send_replace(epoch);
+        epoch
+    }
+
+    pub fn add_node(&self, node: DistributionNode) -> u64 {
+        self.update(|strategy| {
+            strategy.add_node(node);
+            true
+        })
+    }
+
+    pub fn remove_node(&self, node_id: &str) -> u64 {
+        self.update(|strategy| {
+            let present = strategy.all_nodes().iter().any(|n| n.id == node_id);
+            strategy.remove_node(node_id);
+            present
+        })
+    }
+
+    /// 在当前版本上记录一次写入（[`DistributionStrategy::record_write`]），不发布新版本
+    pub fn record_write(&self, key: &[u8], size: usize) {
+        self.current.load().strategy.record_write(key, size);
+    }
+
+    /// 在当前版本上记录一次读取
+    pub fn record_read(&self, key: &[u8]) {
+        self.current.load().strategy.record_read(key);
+    }
+
+    /// 按记录的负载分裂/合并分区，有变化时发布新版本，返回是否有变化
+    pub fn split_and_merge(&self) -> bool {
+        let mut changed = false;
+        self.update(|strategy| {
+            changed = strategy.split_and_merge();
+            changed
+        });
+        changed
+    }
+
+    /// 订阅版本变化
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.epochs.subscribe()
+    }
+
+    /// 等待版本达到 `epoch`
+    pub async fn wait_for_epoch(&self, epoch: u64) -> Result<()> {
+        let mut epochs = self.epochs.subscribe();
+        epochs
+            .wait_for(|current| *current >= epoch)
+            .await
+            .map(|_| ())
+            .map_err(|_| Error::Membership("分布环已关闭".to_string()))
+    }
+}
@@ -17,6 +17,17 @@ pub enum Error {
     #[error("一致性错误: {0}")]
     Consistency(String),
 
+    /// 在截止时间前未收到足够的响应，操作可能已经生效也可能没有
+    #[error("请求超时: {0}")]
+    Timeout(String),
+
+    /// 所有副本请求都已结束（成功、失败或各自超时），成功的数量不足
+    #[error("未达到法定副本数: 需要 {required} 个，实际 {acknowledged} 个")]
+    QuorumNotMet { required: usize, acknowledged: usize },
+
+    #[error("时钟偏差过大: 超前 {offset_ms}ms，允许 {max_offset_ms}ms")]
+    ClockSkew { offset_ms: u64, max_offset_ms: u64 },
+
     #[error("节点成员错误: {0}")]
     Membership(String),
 
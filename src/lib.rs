@@ -5,10 +5,23 @@ pub mod distribution;
 pub mod error;
 pub mod membership;
 pub mod messaging;
+#[cfg(feature = "simulation")]
+pub mod simulation;
 pub mod storage;
 pub mod utils;
 
+use config::{ConsistencyMode, DistributionKind};
+use consistency::{
+    AntiEntropyService, ConsistencyManager, HandoffService, HintStore, NodeHealth, QuorumConsistencyManager,
+    ReplicaClient, ReplicaServer, ResolverRegistry, StrongConsistencyManager,
+};
+use distribution::{
+    ClusterRing, HotKeyDetector, LoadBalancer, LoadTracker, PartitionMapExchange, RangeSplitService, RebalanceService,
+    RingPolicy, Throttle,
+};
 use std::sync::Arc;
+use std::time::Duration;
+use utils::HybridClock;
 
 pub use error::Error;
 pub type Result<T> = std::result::Result<T, Error>;
@@ -20,6 +33,197 @@ pub struct Coretex {
     pub membership: Arc<dyn membership::MembershipManager>,
     pub messaging: Arc<dyn messaging::MessageBroker>,
     pub consistency: Arc<dyn consistency::ConsistencyManager>,
+    pub ring: Arc<distribution::ClusterRing>,
+    /// 协调者读写路径上的热点 key 检测器，未配置 `[distribution.hot_keys]` 或强一致模式下为 `None`
+    pub hot_keys: Option<Arc<HotKeyDetector>>,
+    /// 与其他节点交换的分区映射，可通过 [`PartitionMapExchange::serve`] 提供给客户端下载
+    pub partition_map: Arc<PartitionMapExchange>,
+}
+
+/// 节点的后台服务（副本服务、提示移交、反熵、数据迁移、负载调整、范围分裂），drop 时停止
+pub struct NodeServices {
+    _replica_server: ReplicaServer,
+    _handoff: HandoffService,
+    _anti_entropy: Option<AntiEntropyService>,
+    _rebalance: Option<RebalanceService>,
+    _load_balancer: Option<LoadBalancer>,
+    _range_split: Option<RangeSplitService>,
+}
+
+impl Coretex {
+    /// 在给定的存储、成员管理和通信层上组装节点 `node_id`
+    ///
+    /// `node_id` 应已在 `membership` 中注册；`messaging` 通常是用同一个 `clock`
+    /// 包装的 [`messaging::ClockedBroker`]，使消息和写入的时间戳来自同一个混合逻辑时钟。
+    pub async fn assemble(
+        config: Arc<config::Config>,
+        node_id: &str,
+        storage: Arc<dyn storage::StorageEngine>,
+        membership: Arc<dyn membership::MembershipManager>,
+        messaging: Arc<dyn messaging::MessageBroker>,
+        clock: Arc<HybridClock>,
+    ) -> Result<(Self, NodeServices)> {
+        // 分布环随成员变化自动更新
+        let ring = Arc::new(
+            ClusterRing::start(
+                membership.clone(),
+                distribution::from_config(&config.distribution),
+                RingPolicy::default(),
+            )
+            .await?,
+        );
+        let hints = Arc::new(
+            HintStore::open(storage.clone(), &config.replication.hinted_handoff)
+                .await?
+                .with_clock(clock.clone()),
+        );
+        let replica_server =
+            ReplicaServer::start_with_hints(node_id, storage.clone(), hints.clone(), messaging.clone()).await?;
+        let replica_client = Arc::new(ReplicaClient::start(node_id, messaging.clone()).await?);
+        let replica_timeout = Duration::from_millis(config.replication.requests.replica_timeout_ms);
+        let handoff = HandoffService::start(
+            hints,
+            replica_client.clone(),
+            membership.clone(),
+            replica_timeout,
+            Duration::from_millis(config.replication.hinted_handoff.retry_interval_ms),
+        )
+        .await?;
+        // 因果一致依赖版本时钟，该模式下总是启用向量时钟
+        let vector_clocks =
+            config.consistency.vector_clock_enabled || matches!(config.consistency.mode, ConsistencyMode::Causal);
+        // 强一致模式下副本之间由 Raft 日志保持一致，其余模式总是运行反熵同步
+        let anti_entropy = (!matches!(config.consistency.mode, ConsistencyMode::Strong)).then(|| {
+            AntiEntropyService::start(
+                storage.clone(),
+                ring.shared(),
+                replica_client.clone(),
+                config.replication.factor,
+                &config.consistency.anti_entropy,
+                replica_timeout,
+            )
+        });
+        // 强一致模式下分区数据随 Raft 日志复制，不在节点之间直接迁移
+        let rebalance = (config.distribution.rebalance.enabled
+            && !matches!(config.consistency.mode, ConsistencyMode::Strong))
+        .then(|| {
+            RebalanceService::start(
+                node_id,
+                ring.shared(),
+                storage.clone(),
+                replica_client.clone(),
+                config.replication.factor,
+                Throttle::from_config(&config.distribution.rebalance),
+                replica_timeout,
+            )
+        });
+        let partition_map = Arc::new(
+            PartitionMapExchange::start(ring.shared(), messaging.clone(), clock.clone(), config.replication.factor)
+                .await?,
+        );
+        // 请求统计来自一致性层的协调者，强一致模式下不按负载调整
+        let mut load_tracker = None;
+        let mut load_balancer = None;
+        if let Some(load_aware) = config
+            .distribution
+            .load_aware
+            .clone()
+            .filter(|_| !matches!(config.consistency.mode, ConsistencyMode::Strong))
+        {
+            let tracker = Arc::new(LoadTracker::new(ring.shared().snapshot().partition_map(config.replication.factor)));
+            load_balancer = Some(
+                LoadBalancer::start(
+                    node_id,
+                    ring.shared(),
+                    storage.clone(),
+                    tracker.clone(),
+                    messaging.clone(),
+                    config.replication.factor,
+                    load_aware,
+                )
+                .await?,
+            );
+            load_tracker = Some(tracker);
+        }
+        // range 策略下各节点按同样的范围划分路由，由环中标识最小的节点按负载分裂/合并
+        // 强一致模式下不按负载分裂：副本组改变时数据随 Raft 快照复制，不宜频繁变动
+        let range_split = if matches!(config.distribution.strategy, DistributionKind::Range)
+            && !matches!(config.consistency.mode, ConsistencyMode::Strong)
+        {
+            Some(
+                RangeSplitService::start(
+                    node_id,
+                    ring.shared(),
+                    messaging.clone(),
+                    Duration::from_millis(config.distribution.range.interval_ms),
+                )
+                .await?,
+            )
+        } else {
+            None
+        };
+        let mut hot_keys = None;
+        let consistency: Arc<dyn ConsistencyManager> = match config.consistency.mode {
+            // 强一致模式下写入经过各副本组的 Raft 日志，副本组按交换的分区映射划分
+            ConsistencyMode::Strong => Arc::new(
+                StrongConsistencyManager::start(
+                    node_id,
+                    partition_map.subscribe(),
+                    storage.clone(),
+                    messaging.clone(),
+                    config.consistency.raft.clone(),
+                )
+                .await?
+                .with_event_buffer(config.consistency.event_buffer),
+            ),
+            ConsistencyMode::Eventual | ConsistencyMode::Causal => {
+                let health = Arc::new(NodeHealth::start(membership.clone()).await?);
+                let mut manager = QuorumConsistencyManager::start(
+                    node_id,
+                    ring.shared(),
+                    messaging.clone(),
+                    config.replication.clone(),
+                )
+                .await?
+                .with_vector_clocks(vector_clocks)
+                .with_resolvers(ResolverRegistry::from_config(&config.consistency))
+                .with_read_repair(config.consistency.read_repair)
+                .with_health(health)
+                .with_clock(clock)
+                .with_event_buffer(config.consistency.event_buffer)
+                .with_max_dependencies(config.consistency.max_causal_dependencies);
+                // 热点 key 在协调者上检测，读取按配置分散到各副本或在本节点缓存
+                if let Some(hot_config) = &config.distribution.hot_keys {
+                    let detector = Arc::new(HotKeyDetector::new(hot_config.clone()));
+                    manager = manager.with_hot_keys(detector.clone());
+                    hot_keys = Some(detector);
+                }
+                if let Some(tracker) = load_tracker {
+                    manager = manager.with_load_tracker(tracker);
+                }
+                Arc::new(manager)
+            }
+        };
+        let coretex = Coretex {
+            config,
+            storage,
+            membership,
+            messaging,
+            consistency,
+            ring,
+            hot_keys,
+            partition_map,
+        };
+        let services = NodeServices {
+            _replica_server: replica_server,
+            _handoff: handoff,
+            _anti_entropy: anti_entropy,
+            _rebalance: rebalance,
+            _load_balancer: load_balancer,
+            _range_split: range_split,
+        };
+        Ok((coretex, services))
+    }
 }
 
 pub async fn start(_config_path: &str) -> Result<Coretex> {
@@ -1,8 +1,11 @@
 use coretex::config::{FileConfigProvider, ConfigProvider};
-use coretex::membership::InMemoryMembership;
+use coretex::membership::{NodeState, SwimMembership, UdpTransport};
+use coretex::messaging::{ClockedBroker, TcpBroker};
+use coretex::utils::HybridClock;
 use coretex::storage::InMemoryEngine;
 use coretex::{Coretex, Result};
 use std::sync::Arc;
+use std::time::Duration;
 use std::env;
 
 #[tokio::main]
@@ -32,21 +35,40 @@ async fn main() -> Result<()> {
         }
     };
 
-    // 初始化成员管理
-    let membership: Arc<dyn coretex::membership::MembershipManager> = Arc::new(InMemoryMembership::new());
+    // 初始化成员管理：在监听地址上通过 UDP 与种子节点交换成员信息
+    // 节点标识在重启后保持不变，其他节点不会留下重启前的成员
+    let transport = Arc::new(UdpTransport::bind(config.node.bind_address).await?);
+    let membership: Arc<dyn coretex::membership::MembershipManager> = Arc::new(
+        SwimMembership::start(
+            config.node.resolve_node_id()?,
+            transport,
+            config.node.seed_nodes.clone(),
+            config.membership.clone(),
+        )
+        .await?,
+    );
 
-    // 初始化通信层和一致性层（此处为占位）
-    let messaging = Arc::new(coretex::messaging::memory::InMemoryBroker::new("main"));
-    let consistency = Arc::new(coretex::consistency::DummyConsistencyManager);
+    // 注册本节点，携带配置的可用区和机架供副本放置使用
+    let node_id = membership
+        .register_node(config.node.bind_address, config.node.metadata())
+        .await?;
+    membership.update_node_state(&node_id, NodeState::Active).await?;
 
-    // 构建 Coretex 实例
-    let _coretex = Coretex {
-        config,
-        storage,
-        membership,
-        messaging,
-        consistency,
-    };
+    // 初始化通信层：在监听地址上通过 TCP 与其他成员收发消息
+    // 每条消息都携带混合逻辑时钟时间戳，收发时推进本节点的时钟
+    let clock = Arc::new(HybridClock::new(Duration::from_millis(config.clock.max_offset_ms)));
+    let broker = TcpBroker::start(node_id.clone(), config.node.bind_address, membership.clone()).await?;
+    let messaging: Arc<dyn coretex::messaging::MessageBroker> =
+        Arc::new(ClockedBroker::new(Arc::new(broker), clock.clone()));
+
+    // 构建 Coretex 实例：分布环、副本服务和一致性层
+    let client_address = config.node.client_address;
+    let (coretex, _services) = Coretex::assemble(config, &node_id, storage, membership, messaging, clock).await?;
+
+    // 客户端从这里下载分区映射后直接把请求路由到副本节点
+    if let Some(address) = client_address {
+        coretex.partition_map.serve(tokio::net::TcpListener::bind(address).await?);
+    }
 
     println!("Coretex 启动完成。");
     // 这里可以启动服务监听、节点注册等
@@ -23,6 +23,18 @@ impl InMemoryMembership {
         }
     }
 
+    /// 以指定 id 注册节点（如模拟测试中需要稳定的节点 id），状态为 `Joining`
+    pub fn register_node_with_id(&self, id: impl Into<String>, address: SocketAddr, metadata: HashMap<String, String>) {
+        let node = Node {
+            id: id.into(),
+            address,
+            state: NodeState::Joining,
+            metadata,
+        };
+        self.nodes.insert(node.id.clone(), node.clone());
+        self.notify(MembershipEvent::NodeJoined(node));
+    }
+
     fn notify(&self, event: MembershipEvent) {
         let subscribers = self.event_subscribers.lock().unwrap();
         for tx in subscribers.iter() {
@@ -31,6 +43,12 @@ impl InMemoryMembership {
     }
 }
 
+impl Default for InMemoryMembership {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[async_trait]
 impl MembershipManager for InMemoryMembership {
     async fn register_node(
@@ -39,14 +57,7 @@ impl MembershipManager for InMemoryMembership {
         metadata: HashMap<String, String>,
     ) -> Result<String> {
         let id = Uuid::new_v4().to_string();
-        let node = Node {
-            id: id.clone(),
-            address,
-            state: NodeState::Joining,
-            metadata,
-        };
-        self.nodes.insert(id.clone(), node.clone());
-        self.notify(MembershipEvent::NodeJoined(node));
+        self.register_node_with_id(id.clone(), address, metadata);
         Ok(id)
     }
 
@@ -1,4 +1,6 @@
 mod memory;
+mod swim;
+mod transport;
 // 预留 raft 共识实现
 // mod raft;
 
@@ -12,6 +14,8 @@ use std::net::SocketAddr;
 use crate::Result;
 
 pub use memory::InMemoryMembership;
+pub use swim::SwimMembership;
+pub use transport::{BrokerTransport, GossipTransport, UdpTransport};
 // pub use raft::RaftBasedMembership;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -0,0 +1,662 @@
+use super::transport::GossipTransport;
+use super::{MembershipEvent, MembershipManager, Node, NodeState};
+use crate::config::MembershipConfig;
+use crate::error::Error;
+use crate::utils::SeededRng;
+use crate::Result;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use futures::{stream, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+
+/// 成员的存活状态，化身号相同时后者覆盖前者
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+enum Liveness {
+    Alive,
+    Suspect,
+    Dead,
+    Left,
+}
+
+/// 关于一个成员的变更：节点自己公布的信息、化身号和存活状态
+///
+/// 只有节点自己会递增化身号，被怀疑时以更大的化身号反驳。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct Update {
+    node: Node,
+    incarnation: u64,
+    liveness: Liveness,
+}
+
+impl Update {
+    fn supersedes(&self, other: &Update) -> bool {
+        (self.incarnation, self.liveness) > (other.incarnation, other.liveness)
+    }
+
+    /// 对外呈现的状态，宕机的成员为 `Down`，其余为节点自己公布的状态
+    fn node(&self) -> Node {
+        let mut node = self.node.clone();
+        if self.liveness == Liveness::Dead {
+            node.state = NodeState::Down;
+        }
+        node
+    }
+
+    /// 仍在集群中且可以探测
+    fn reachable(&self) -> bool {
+        matches!(self.liveness, Liveness::Alive | Liveness::Suspect)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+enum Payload {
+    Ping { seq: u64 },
+    Ack { seq: u64 },
+    /// 请接收者代为探测 `target`，收到确认后以同一个 `seq` 回复
+    PingReq { seq: u64, target: SocketAddr },
+    /// 完整成员表，`reply` 为真时接收者回复自己的成员表
+    Sync { members: Vec<Update>, reply: bool },
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    from: SocketAddr,
+    payload: Payload,
+    /// 捎带传播的成员变更
+    updates: Vec<Update>,
+}
+
+struct State {
+    /// 包括本节点；离开的成员保留为墓碑，防止旧消息使其复活
+    members: BTreeMap<String, Update>,
+    /// 被怀疑的成员及其判定为宕机的时间
+    suspicions: BTreeMap<String, Instant>,
+    /// 已宕机的成员及其从成员表中移除的时间
+    dead: BTreeMap<String, Instant>,
+    /// 待传播的变更及已捎带的次数
+    broadcasts: BTreeMap<String, (Update, u32)>,
+    probe_order: Vec<String>,
+    rng: SeededRng,
+    left: bool,
+}
+
+struct Inner {
+    id: String,
+    addr: SocketAddr,
+    seeds: Vec<SocketAddr>,
+    config: MembershipConfig,
+    transport: Arc<dyn GossipTransport>,
+    state: Mutex<State>,
+    acks: DashMap<u64, oneshot::Sender<()>>,
+    next_seq: AtomicU64,
+    subscribers: Mutex<Vec<UnboundedSender<Result<MembershipEvent>>>>,
+}
+
+/// 基于 SWIM 协议的成员管理
+///
+/// 每个探测周期按随机轮转的顺序直接探测一个成员，超时后委托其他成员间接探测，
+/// 仍无确认时将其标记为被怀疑；被怀疑的成员在超时前未以更大的化身号反驳即判定为宕机（`Down`）。
+/// 宕机超过 `dead_member_timeout_ms` 的成员从成员表中移除，如同离开集群。
+/// 成员变更捎带在探测消息中传播，每条变更随 O(log n) 条消息发出。
+/// 启动时向种子节点交换完整成员表加入集群，之后定期与随机成员（包括已宕机的成员和种子节点）
+/// 交换成员表，分区恢复后两侧据此重新发现对方。
+///
+/// 只能注册、修改和注销本节点，其他节点的状态由它们自己公布或由故障检测决定。
+pub struct SwimMembership {
+    inner: Arc<Inner>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl SwimMembership {
+    /// 以 `node_id` 加入 `seeds` 所在的集群，本节点的初始状态为 `Joining`
+    ///
+    /// 本节点的地址为传输层的地址，`seeds` 中的本节点地址被忽略，为空时等待其他节点加入。
+    pub async fn start(
+        node_id: impl Into<String>,
+        transport: Arc<dyn GossipTransport>,
+        seeds: Vec<SocketAddr>,
+        config: MembershipConfig,
+    ) -> Result<Self> {
+        let id = node_id.into();
+        let addr = transport.local_addr();
+        let local = Update {
+            node: Node {
+                id: id.clone(),
+                address: addr,
+                state: NodeState::Joining,
+                metadata: HashMap::new(),
+            },
+            incarnation: 0,
+            liveness: Liveness::Alive,
+        };
+        // 随机选择只取决于节点 id，模拟中可以复现
+        let mut hasher = DefaultHasher::new();
+        id.hash(&mut hasher);
+        let state = State {
+            members: BTreeMap::from([(id.clone(), local)]),
+            suspicions: BTreeMap::new(),
+            dead: BTreeMap::new(),
+            broadcasts: BTreeMap::new(),
+            probe_order: Vec::new(),
+            rng: SeededRng::new(hasher.finish()),
+            left: false,
+        };
+        let inner = Arc::new(Inner {
+            id,
+            addr,
+            seeds: seeds.into_iter().filter(|seed| *seed != addr).collect(),
+            config,
+            transport,
+            state: Mutex::new(state),
+            acks: DashMap::new(),
+            next_seq: AtomicU64::new(1),
+            subscribers: Mutex::new(Vec::new()),
+        });
+        let tasks = vec![
+            tokio::spawn(inner.clone().receive()),
+            tokio::spawn(inner.clone().probe_loop()),
+            tokio::spawn(inner.clone().sync_loop()),
+        ];
+        Ok(Self {
+            inner,
+            tasks: Mutex::new(tasks),
+        })
+    }
+
+    pub fn node_id(&self) -> &str {
+        &self.inner.id
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.inner.addr
+    }
+
+    fn check_local(&self, node_id: &str) -> Result<()> {
+        if node_id == self.inner.id {
+            Ok(())
+        } else {
+            Err(Error::Membership(format!(
+                "只能修改本节点 {}，节点 {} 的状态由其自身公布或由故障检测决定",
+                self.inner.id, node_id
+            )))
+        }
+    }
+}
+
+impl Drop for SwimMembership {
+    fn drop(&mut self) {
+        for task in self.tasks.lock().unwrap().iter() {
+            task.abort();
+        }
+    }
+}
+
+impl Inner {
+    fn notify(&self, events: Vec<MembershipEvent>) {
+        if events.is_empty() {
+            return;
+        }
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|tx| events.iter().all(|event| tx.send(Ok(event.clone())).is_ok()));
+    }
+
+    fn enqueue(state: &mut State, update: Update) {
+        state.broadcasts.insert(update.node.id.clone(), (update, 0));
+    }
+
+    /// 合并一条变更，返回由此产生的成员事件
+    ///
+    /// 其他成员判定的宕机只作为怀疑，本节点的怀疑超时后才判定，给被误判的成员留出反驳的时间。
+    fn apply(&self, state: &mut State, mut update: Update) -> Vec<MembershipEvent> {
+        let reachable = state.members.get(&update.node.id).is_some_and(Update::reachable);
+        if update.liveness == Liveness::Dead && reachable {
+            update.liveness = Liveness::Suspect;
+        }
+        self.record(state, update)
+    }
+
+    fn record(&self, state: &mut State, update: Update) -> Vec<MembershipEvent> {
+        let id = update.node.id.clone();
+        if id == self.id {
+            self.refute(state, &update);
+            return Vec::new();
+        }
+        let previous = state.members.get(&id);
+        if previous.is_some_and(|previous| !update.supersedes(previous)) {
+            return Vec::new();
+        }
+        let previous = previous.filter(|p| p.liveness != Liveness::Left).map(|p| p.node().state);
+        let mut events = Vec::new();
+        match (update.liveness, previous) {
+            (Liveness::Left, None) => {}
+            (Liveness::Left, Some(_)) => events.push(MembershipEvent::NodeLeft(id.clone())),
+            (_, None) => events.push(MembershipEvent::NodeJoined(update.node())),
+            (_, Some(state)) if state != update.node().state => events.push(MembershipEvent::NodeStateChanged {
+                id: id.clone(),
+                state: update.node().state,
+            }),
+            _ => {}
+        }
+        if update.liveness == Liveness::Suspect {
+            let timeout = Duration::from_millis(self.config.suspicion_timeout_ms);
+            state.suspicions.entry(id.clone()).or_insert_with(|| Instant::now() + timeout);
+        } else {
+            state.suspicions.remove(&id);
+        }
+        if update.liveness == Liveness::Dead {
+            let timeout = Duration::from_millis(self.config.dead_member_timeout_ms);
+            state.dead.entry(id.clone()).or_insert_with(|| Instant::now() + timeout);
+        } else {
+            state.dead.remove(&id);
+        }
+        state.members.insert(id, update.clone());
+        Self::enqueue(state, update);
+        events
+    }
+
+    /// 其他成员认为本节点被怀疑或宕机时，以更大的化身号宣告存活
+    fn refute(&self, state: &mut State, update: &Update) {
+        if state.left || update.liveness == Liveness::Alive {
+            return;
+        }
+        let local = state.members.get_mut(&self.id).expect("本节点");
+        if update.incarnation >= local.incarnation {
+            local.incarnation = update.incarnation + 1;
+            let local = local.clone();
+            tracing::debug!("节点 {} 以化身号 {} 反驳 {:?}", self.id, local.incarnation, update.liveness);
+            Self::enqueue(state, local);
+        }
+    }
+
+    /// 修改本节点公布的信息并以新的化身号传播
+    fn update_local(&self, change: impl FnOnce(&mut Update)) -> Update {
+        let mut state = self.state.lock().unwrap();
+        let local = state.members.get_mut(&self.id).expect("本节点");
+        local.incarnation += 1;
+        change(local);
+        let local = local.clone();
+        Self::enqueue(&mut state, local.clone());
+        local
+    }
+
+    fn merge(&self, updates: Vec<Update>) {
+        let events = {
+            let mut state = self.state.lock().unwrap();
+            updates
+                .into_iter()
+                .flat_map(|update| self.apply(&mut state, update))
+                .collect()
+        };
+        self.notify(events);
+    }
+
+    /// 取出传播次数最少的若干条变更，传播次数达到上限的变更不再发送
+    fn piggyback(&self, state: &mut State) -> Vec<Update> {
+        let members = state.members.values().filter(|m| m.liveness != Liveness::Left).count();
+        let limit = self.config.retransmit_mult.max(1) * (usize::BITS - members.leading_zeros()).max(1);
+        let mut queued: Vec<(&String, u32)> = state.broadcasts.iter().map(|(id, (_, sent))| (id, *sent)).collect();
+        queued.sort_by_key(|(_, sent)| *sent);
+        let chosen: Vec<String> = queued
+            .into_iter()
+            .take(self.config.max_piggyback)
+            .map(|(id, _)| id.clone())
+            .collect();
+        let mut updates = Vec::with_capacity(chosen.len());
+        for id in chosen {
+            let Some((update, sent)) = state.broadcasts.get_mut(&id) else {
+                continue;
+            };
+            *sent += 1;
+            updates.push(update.clone());
+            if *sent >= limit {
+                state.broadcasts.remove(&id);
+            }
+        }
+        updates
+    }
+
+    async fn send(&self, to: SocketAddr, payload: Payload) {
+        let updates = self.piggyback(&mut self.state.lock().unwrap());
+        let envelope = Envelope {
+            from: self.addr,
+            payload,
+            updates,
+        };
+        let result = match serde_json::to_vec(&envelope) {
+            Ok(data) => self.transport.send(to, data).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = result {
+            tracing::debug!("向 {} 发送成员消息失败: {}", to, e);
+        }
+    }
+
+    fn snapshot(&self) -> Vec<Update> {
+        self.state.lock().unwrap().members.values().cloned().collect()
+    }
+
+    async fn receive(self: Arc<Self>) {
+        while let Some(data) = self.transport.recv().await {
+            let envelope: Envelope = match serde_json::from_slice(&data) {
+                Ok(envelope) => envelope,
+                Err(e) => {
+                    tracing::debug!("无法解析成员消息: {}", e);
+                    continue;
+                }
+            };
+            self.merge(envelope.updates);
+            match envelope.payload {
+                Payload::Ping { seq } => self.send(envelope.from, Payload::Ack { seq }).await,
+                Payload::Ack { seq } => {
+                    if let Some((_, ack)) = self.acks.remove(&seq) {
+                        let _ = ack.send(());
+                    }
+                }
+                Payload::PingReq { seq, target } => {
+                    let inner = self.clone();
+                    tokio::spawn(async move {
+                        if inner.probe(target).await {
+                            inner.send(envelope.from, Payload::Ack { seq }).await;
+                        }
+                    });
+                }
+                Payload::Sync { members, reply } => {
+                    self.merge(members);
+                    if reply {
+                        let members = self.snapshot();
+                        self.send(envelope.from, Payload::Sync { members, reply: false }).await;
+                    }
+                }
+            }
+        }
+    }
+
+    fn register_ack(&self) -> (u64, oneshot::Receiver<()>) {
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.acks.insert(seq, tx);
+        (seq, rx)
+    }
+
+    /// 直接探测 `target`，在探测超时内收到确认时返回 `true`
+    async fn probe(&self, target: SocketAddr) -> bool {
+        let (seq, ack) = self.register_ack();
+        self.send(target, Payload::Ping { seq }).await;
+        let timeout = Duration::from_millis(self.config.probe_timeout_ms);
+        let acked = matches!(tokio::time::timeout(timeout, ack).await, Ok(Ok(())));
+        self.acks.remove(&seq);
+        acked
+    }
+
+    async fn probe_loop(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(Duration::from_millis(self.config.probe_interval_ms));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if self.state.lock().unwrap().left {
+                return;
+            }
+            self.expire_suspicions();
+            if self.peers(|m| m.reachable()).is_empty() {
+                // 还没有加入集群或其他成员都已宕机，向种子节点请求成员表
+                for seed in self.seeds.clone() {
+                    let members = self.snapshot();
+                    self.send(seed, Payload::Sync { members, reply: true }).await;
+                }
+                continue;
+            }
+            if let Some(target) = self.next_target() {
+                self.probe_member(target).await;
+            }
+        }
+    }
+
+    /// 满足条件的其他成员
+    fn peers(&self, filter: impl Fn(&Update) -> bool) -> Vec<Update> {
+        let state = self.state.lock().unwrap();
+        state
+            .members
+            .values()
+            .filter(|m| m.node.id != self.id && filter(m))
+            .cloned()
+            .collect()
+    }
+
+    /// 按随机轮转顺序选出下一个探测目标，每一轮重新打乱顺序
+    fn next_target(&self) -> Option<Update> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.probe_order.is_empty() {
+                let mut order: Vec<String> = state
+                    .members
+                    .values()
+                    .filter(|m| m.node.id != self.id && m.reachable())
+                    .map(|m| m.node.id.clone())
+                    .collect();
+                if order.is_empty() {
+                    return None;
+                }
+                for i in (1..order.len()).rev() {
+                    let j = state.rng.below(i as u64 + 1) as usize;
+                    order.swap(i, j);
+                }
+                state.probe_order = order;
+            }
+            let id = state.probe_order.pop()?;
+            if let Some(member) = state.members.get(&id).filter(|m| m.reachable()) {
+                return Some(member.clone());
+            }
+        }
+    }
+
+    async fn probe_member(&self, target: Update) {
+        let address = target.node.address;
+        if self.probe(address).await {
+            return;
+        }
+        let helpers = {
+            let mut candidates = self.peers(|m| m.liveness == Liveness::Alive && m.node.id != target.node.id);
+            let mut state = self.state.lock().unwrap();
+            let mut helpers = Vec::new();
+            while helpers.len() < self.config.indirect_probes && !candidates.is_empty() {
+                let i = state.rng.below(candidates.len() as u64) as usize;
+                helpers.push(candidates.swap_remove(i).node.address);
+            }
+            helpers
+        };
+        let (seq, ack) = self.register_ack();
+        for helper in helpers {
+            self.send(helper, Payload::PingReq { seq, target: address }).await;
+        }
+        let wait = self
+            .config
+            .probe_interval_ms
+            .saturating_sub(self.config.probe_timeout_ms)
+            .max(self.config.probe_timeout_ms);
+        let acked = matches!(tokio::time::timeout(Duration::from_millis(wait), ack).await, Ok(Ok(())));
+        self.acks.remove(&seq);
+        if acked {
+            return;
+        }
+        let events = {
+            let mut state = self.state.lock().unwrap();
+            // 探测期间可能已收到更新的变更
+            let Some(current) = state.members.get(&target.node.id).cloned() else {
+                return;
+            };
+            if current.incarnation != target.incarnation || current.liveness != Liveness::Alive {
+                return;
+            }
+            tracing::debug!("节点 {} 怀疑 {} 已宕机", self.id, target.node.id);
+            let suspect = Update {
+                liveness: Liveness::Suspect,
+                ..current
+            };
+            self.apply(&mut state, suspect)
+        };
+        self.notify(events);
+    }
+
+    /// 怀疑超时的成员判定为宕机，宕机超时的成员视为离开
+    ///
+    /// 移除的成员保留为墓碑；以同一标识重启的节点收到墓碑后以更大的化身号反驳，重新加入。
+    fn expire_suspicions(&self) {
+        let events = {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            let reaped: Vec<String> = state
+                .dead
+                .iter()
+                .filter(|(_, deadline)| **deadline <= now)
+                .map(|(id, _)| id.clone())
+                .collect();
+            let mut events = Vec::new();
+            for id in reaped {
+                let Some(member) = state.members.get(&id).cloned() else {
+                    state.dead.remove(&id);
+                    continue;
+                };
+                tracing::info!("节点 {} 将宕机超时的 {} 移出成员表", self.id, id);
+                let left = Update {
+                    liveness: Liveness::Left,
+                    ..member
+                };
+                events.extend(self.record(&mut state, left));
+            }
+            let expired: Vec<String> = state
+                .suspicions
+                .iter()
+                .filter(|(_, deadline)| **deadline <= now)
+                .map(|(id, _)| id.clone())
+                .collect();
+            for id in expired {
+                let Some(member) = state.members.get(&id).cloned() else {
+                    state.suspicions.remove(&id);
+                    continue;
+                };
+                tracing::info!("节点 {} 判定 {} 已宕机", self.id, id);
+                let dead = Update {
+                    liveness: Liveness::Dead,
+                    ..member
+                };
+                events.extend(self.record(&mut state, dead));
+            }
+            events
+        };
+        self.notify(events);
+    }
+
+    /// 定期与随机成员或种子节点交换完整成员表
+    async fn sync_loop(self: Arc<Self>) {
+        let period = Duration::from_millis(self.config.sync_interval_ms);
+        let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if self.state.lock().unwrap().left {
+                return;
+            }
+            let mut candidates: Vec<SocketAddr> = self
+                .peers(|m| m.liveness != Liveness::Left)
+                .into_iter()
+                .map(|m| m.node.address)
+                .collect();
+            for seed in &self.seeds {
+                if !candidates.contains(seed) {
+                    candidates.push(*seed);
+                }
+            }
+            if candidates.is_empty() {
+                continue;
+            }
+            let i = self.state.lock().unwrap().rng.below(candidates.len() as u64) as usize;
+            let members = self.snapshot();
+            self.send(candidates[i], Payload::Sync { members, reply: true }).await;
+        }
+    }
+
+    /// 通知所有成员本节点离开，之后不再探测和同步
+    async fn leave(&self) {
+        let local = self.update_local(|local| local.liveness = Liveness::Left);
+        self.state.lock().unwrap().left = true;
+        for peer in self.peers(|m| m.reachable()) {
+            let members = vec![local.clone()];
+            self.send(peer.node.address, Payload::Sync { members, reply: false }).await;
+        }
+    }
+}
+
+#[async_trait]
+impl MembershipManager for SwimMembership {
+    /// 设置本节点公布的元数据，`address` 必须是本节点的地址
+    async fn register_node(&self, address: SocketAddr, metadata: HashMap<String, String>) -> Result<String> {
+        if address != self.inner.addr {
+            return Err(Error::Membership(format!(
+                "只能注册本节点 {}，其他节点通过种子节点加入: {}",
+                self.inner.addr, address
+            )));
+        }
+        self.inner.update_local(|local| local.node.metadata = metadata);
+        Ok(self.inner.id.clone())
+    }
+
+    async fn update_node_state(&self, node_id: &str, state: NodeState) -> Result<()> {
+        self.check_local(node_id)?;
+        self.inner.update_local(|local| local.node.state = state.clone());
+        self.inner.notify(vec![MembershipEvent::NodeStateChanged {
+            id: node_id.to_string(),
+            state,
+        }]);
+        Ok(())
+    }
+
+    /// 离开集群：通知其他成员后停止后台任务
+    async fn unregister_node(&self, node_id: &str) -> Result<()> {
+        self.check_local(node_id)?;
+        self.inner.leave().await;
+        for task in self.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+        self.inner.notify(vec![MembershipEvent::NodeLeft(node_id.to_string())]);
+        Ok(())
+    }
+
+    async fn get_nodes(&self) -> Result<Vec<Node>> {
+        let state = self.inner.state.lock().unwrap();
+        Ok(state
+            .members
+            .values()
+            .filter(|m| m.liveness != Liveness::Left)
+            .map(Update::node)
+            .collect())
+    }
+
+    async fn get_node(&self, node_id: &str) -> Result<Option<Node>> {
+        let state = self.inner.state.lock().unwrap();
+        Ok(state
+            .members
+            .get(node_id)
+            .filter(|m| m.liveness != Liveness::Left)
+            .map(Update::node))
+    }
+
+    async fn watch_nodes(&self) -> Result<Pin<Box<dyn Stream<Item = Result<MembershipEvent>> + Send>>> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        self.inner.subscribers.lock().unwrap().push(tx);
+        Ok(Box::pin(stream::unfold(rx, |mut rx| async {
+            rx.recv().await.map(|event| (event, rx))
+        })))
+    }
+}
@@ -0,0 +1,110 @@
+use crate::error::Error;
+use crate::messaging::{Message, MessageBroker};
+use crate::Result;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+use tokio::sync::Mutex;
+
+/// UDP 数据报的最大长度
+const MAX_DATAGRAM: usize = 65_507;
+
+/// 成员协议收发消息的方式，按地址寻址，消息可能丢失或乱序
+#[async_trait]
+pub trait GossipTransport: Send + Sync + 'static {
+    /// 其他节点向本节点发送消息的地址
+    fn local_addr(&self) -> SocketAddr;
+
+    async fn send(&self, to: SocketAddr, data: Vec<u8>) -> Result<()>;
+
+    /// 下一条收到的消息，传输层关闭时返回 `None`
+    async fn recv(&self) -> Option<Vec<u8>>;
+}
+
+/// 基于 UDP 的传输，用于跨机器部署
+pub struct UdpTransport {
+    socket: UdpSocket,
+    addr: SocketAddr,
+}
+
+impl UdpTransport {
+    /// 绑定 `addr`，端口为 0 时由系统分配；应绑定其他节点可达的具体地址
+    pub async fn bind(addr: SocketAddr) -> Result<Self> {
+        let socket = UdpSocket::bind(addr).await?;
+        let addr = socket.local_addr()?;
+        Ok(Self { socket, addr })
+    }
+}
+
+#[async_trait]
+impl GossipTransport for UdpTransport {
+    fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    async fn send(&self, to: SocketAddr, data: Vec<u8>) -> Result<()> {
+        if data.len() > MAX_DATAGRAM {
+            return Err(Error::Communication(format!("成员消息过大: {} 字节", data.len())));
+        }
+        self.socket.send_to(&data, to).await?;
+        Ok(())
+    }
+
+    async fn recv(&self) -> Option<Vec<u8>> {
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+        loop {
+            match self.socket.recv_from(&mut buf).await {
+                Ok((len, _)) => return Some(buf[..len].to_vec()),
+                // 对端端口不可达等错误只影响单条消息
+                Err(e) => tracing::debug!("接收成员消息失败: {}", e),
+            }
+        }
+    }
+}
+
+/// 通过消息层按地址对应的 topic 收发，用于进程内测试和模拟网络
+pub struct BrokerTransport {
+    broker: Arc<dyn MessageBroker>,
+    addr: SocketAddr,
+    messages: Mutex<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>>,
+}
+
+impl BrokerTransport {
+    /// 以 `addr` 的身份订阅发往该地址的消息
+    pub async fn start(broker: Arc<dyn MessageBroker>, addr: SocketAddr) -> Result<Self> {
+        let messages = broker.subscribe(&gossip_topic(addr)).await?;
+        Ok(Self {
+            broker,
+            addr,
+            messages: Mutex::new(messages),
+        })
+    }
+}
+
+fn gossip_topic(addr: SocketAddr) -> String {
+    format!("membership/gossip/{}", addr)
+}
+
+#[async_trait]
+impl GossipTransport for BrokerTransport {
+    fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    async fn send(&self, to: SocketAddr, data: Vec<u8>) -> Result<()> {
+        self.broker.publish(&gossip_topic(to), data).await
+    }
+
+    async fn recv(&self) -> Option<Vec<u8>> {
+        let mut messages = self.messages.lock().await;
+        loop {
+            match messages.next().await? {
+                Ok(message) => return Some(message.data.to_vec()),
+                Err(e) => tracing::debug!("接收成员消息失败: {}", e),
+            }
+        }
+    }
+}
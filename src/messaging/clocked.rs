@@ -0,0 +1,79 @@
+use super::{Message, MessageBroker};
+use crate::error::Error;
+use crate::utils::{HybridClock, HybridTimestamp};
+use crate::Result;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// 时间戳在消息中占用的字节数
+const HEADER_LEN: usize = 8;
+
+/// 在收发消息时推进混合逻辑时钟的 broker 包装
+///
+/// 发送时在消息前附加 [`HybridClock::now`] 的时间戳，收到消息时去掉时间戳并调用
+/// [`HybridClock::update`]。时钟偏差超出上限的消息被丢弃并记录警告。
+/// 集群中所有节点都需要使用该包装，否则无法解析对方的消息。
+pub struct ClockedBroker {
+    inner: Arc<dyn MessageBroker>,
+    clock: Arc<HybridClock>,
+}
+
+impl ClockedBroker {
+    pub fn new(inner: Arc<dyn MessageBroker>, clock: Arc<HybridClock>) -> Self {
+        Self { inner, clock }
+    }
+
+    pub fn clock(&self) -> Arc<HybridClock> {
+        self.clock.clone()
+    }
+}
+
+/// 去掉时间戳并推进时钟，返回 `None` 表示消息应被丢弃
+fn receive(clock: &HybridClock, mut msg: Message) -> Option<Result<Message>> {
+    if msg.data.len() < HEADER_LEN {
+        tracing::warn!("丢弃缺少时间戳的消息: topic={}", msg.topic);
+        return None;
+    }
+    let header = msg.data.split_to(HEADER_LEN);
+    let timestamp = HybridTimestamp::from_bytes(header.as_ref().try_into().ok()?);
+    match clock.update(timestamp) {
+        Ok(_) => Some(Ok(msg)),
+        Err(e @ Error::ClockSkew { .. }) => {
+            tracing::warn!("丢弃来自 {:?} 的消息: {}", msg.sender, e);
+            None
+        }
+        Err(e) => Some(Err(e)),
+    }
+}
+
+#[async_trait]
+impl MessageBroker for ClockedBroker {
+    async fn publish(&self, topic: &str, data: Vec<u8>) -> Result<()> {
+        let mut framed = Vec::with_capacity(HEADER_LEN + data.len());
+        framed.extend_from_slice(&self.clock.now().to_bytes());
+        framed.extend_from_slice(&data);
+        self.inner.publish(topic, framed).await
+    }
+
+    async fn subscribe(
+        &self,
+        topic: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+        let clock = self.clock.clone();
+        let stream = self.inner.subscribe(topic).await?;
+        Ok(Box::pin(stream.filter_map(move |msg| {
+            let msg = msg.map(|msg| receive(&clock, msg)).unwrap_or_else(|e| Some(Err(e)));
+            async move { msg }
+        })))
+    }
+
+    async fn unsubscribe(&self, topic: &str) -> Result<()> {
+        self.inner.unsubscribe(topic).await
+    }
+
+    async fn subscribed_topics(&self) -> Result<Vec<String>> {
+        self.inner.subscribed_topics().await
+    }
+}
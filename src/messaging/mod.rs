@@ -41,6 +41,16 @@ pub trait MessageBroker: Send + Sync + 'static {
     async fn subscribed_topics(&self) -> Result<Vec<String>>;
 }
 
+/// 可以模拟网络分区的进程内实现，用于多节点测试
+#[cfg(feature = "simulation")]
+pub mod sim;
+
+mod clocked;
+mod tcp;
+
+pub use clocked::ClockedBroker;
+pub use tcp::TcpBroker;
+
 /// 内存实现（可选，便于测试/单机）
 pub mod memory {
     use super::*;
@@ -65,21 +75,25 @@ pub mod memory {
                 id: id.into(),
             }
         }
+
+        /// 把消息投递给订阅了其 topic 的订阅者，保留消息原有的发送者
+        pub(crate) fn deliver(&self, msg: Message) {
+            if let Some(subs) = self.topics.get(&msg.topic) {
+                for tx in subs.iter() {
+                    let _ = tx.send(Ok(msg.clone()));
+                }
+            }
+        }
     }
 
     #[async_trait]
     impl MessageBroker for InMemoryBroker {
         async fn publish(&self, topic: &str, data: Vec<u8>) -> Result<()> {
-            let msg = Message {
+            self.deliver(Message {
                 topic: topic.to_string(),
                 data: Bytes::from(data),
                 sender: Some(self.id.clone()),
-            };
-            if let Some(subs) = self.topics.get(topic) {
-                for tx in subs.iter() {
-                    let _ = tx.send(Ok(msg.clone()));
-                }
-            }
+            });
             Ok(())
         }
 
@@ -0,0 +1,265 @@
+use super::{Message, MessageBroker};
+use crate::utils::SeededRng;
+use crate::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use dashmap::DashMap;
+use futures::{stream, Stream};
+use std::collections::{HashMap, HashSet};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+type Subscribers = Vec<(String, UnboundedSender<Result<Message>>)>;
+
+/// 进程内的模拟网络，用于在单个进程里测试多节点协议
+///
+/// 每个节点通过 [`SimulatedNetwork::broker`] 得到自己的 broker，消息只投递给与发送方连通的节点。
+/// 可以隔离单个节点、把节点划分为互不连通的分区，或阻断单向链路。
+///
+/// 设置 [`NetworkFaults`] 后节点之间的消息按概率丢弃、延迟或乱序。每条链路各用一个由种子和链路两端
+/// 决定的随机数序列，因此同一种子下每条链路上第 n 条消息的命运总是相同，与其他链路的消息交错无关。
+/// 延迟通过 tokio 的计时器实现，在暂停时间的运行时中按虚拟时间推进。
+#[derive(Clone, Default)]
+pub struct SimulatedNetwork {
+    topics: Arc<DashMap<String, Subscribers>>,
+    links: Arc<Mutex<Links>>,
+    faults: Arc<Mutex<FaultState>>,
+}
+
+/// 节点之间消息的故障注入参数，同一节点内的消息不受影响
+#[derive(Clone, Debug, Default)]
+pub struct NetworkFaults {
+    /// 消息被丢弃的概率
+    pub drop_probability: f64,
+    /// 投递延迟在 `[min_delay, max_delay]` 内均匀选取
+    pub min_delay: Duration,
+    pub max_delay: Duration,
+    /// 消息再额外推迟 `max_delay` 的概率，之后发出的消息会先于它到达
+    pub reorder_probability: f64,
+}
+
+/// 模拟网络对节点之间消息的统计
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetworkStats {
+    pub delivered: u64,
+    pub dropped: u64,
+    /// 投递时有延迟的消息数（包含在 `delivered` 中）
+    pub delayed: u64,
+}
+
+#[derive(Default)]
+struct FaultState {
+    seed: u64,
+    faults: NetworkFaults,
+    /// 每条有向链路的随机数序列
+    rngs: HashMap<(String, String), SeededRng>,
+    stats: NetworkStats,
+}
+
+#[derive(Default)]
+struct Links {
+    isolated: HashSet<String>,
+    /// 节点所在的分区编号，未列出的节点与所有节点连通
+    partitions: HashMap<String, usize>,
+    blocked: HashSet<(String, String)>,
+}
+
+impl SimulatedNetwork {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 以 `node_id` 身份收发消息的 broker
+    pub fn broker(&self, node_id: impl Into<String>) -> SimulatedBroker {
+        SimulatedBroker {
+            network: self.clone(),
+            id: node_id.into(),
+        }
+    }
+
+    /// 按 `seed` 注入 `faults`
+    pub fn with_faults(self, seed: u64, faults: NetworkFaults) -> Self {
+        {
+            let mut state = self.faults.lock().unwrap();
+            state.seed = seed;
+            state.rngs.clear();
+        }
+        self.set_faults(faults);
+        self
+    }
+
+    /// 修改故障参数，各链路的随机数序列继续推进
+    pub fn set_faults(&self, faults: NetworkFaults) {
+        self.faults.lock().unwrap().faults = faults;
+    }
+
+    pub fn stats(&self) -> NetworkStats {
+        self.faults.lock().unwrap().stats
+    }
+
+    /// 断开节点与其他所有节点的连接
+    pub fn isolate(&self, node_id: &str) {
+        self.links.lock().unwrap().isolated.insert(node_id.to_string());
+    }
+
+    /// 把节点划分为若干互不连通的分区
+    pub fn partition(&self, groups: &[&[&str]]) {
+        let mut links = self.links.lock().unwrap();
+        links.partitions = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(i, group)| group.iter().map(move |node| (node.to_string(), i)))
+            .collect();
+    }
+
+    /// 阻断从 `from` 到 `to` 的单向链路
+    pub fn block(&self, from: &str, to: &str) {
+        self.links
+            .lock()
+            .unwrap()
+            .blocked
+            .insert((from.to_string(), to.to_string()));
+    }
+
+    /// 恢复被隔离节点的连接，分区和单向阻断保持不变
+    pub fn reconnect(&self, node_id: &str) {
+        self.links.lock().unwrap().isolated.remove(node_id);
+    }
+
+    /// 恢复全部连接
+    pub fn heal(&self) {
+        *self.links.lock().unwrap() = Links::default();
+    }
+
+    /// 从 `from` 发出的消息能否到达 `to`
+    pub fn connected(&self, from: &str, to: &str) -> bool {
+        if from == to {
+            return true;
+        }
+        let links = self.links.lock().unwrap();
+        if links.isolated.contains(from) || links.isolated.contains(to) {
+            return false;
+        }
+        if let (Some(a), Some(b)) = (links.partitions.get(from), links.partitions.get(to)) {
+            if a != b {
+                return false;
+            }
+        }
+        !links.blocked.contains(&(from.to_string(), to.to_string()))
+    }
+
+    /// 从 `from` 发往 `to` 的下一条消息的投递延迟，丢弃时为 `None`
+    fn fate(&self, from: &str, to: &str) -> Option<Duration> {
+        if from == to {
+            return Some(Duration::ZERO);
+        }
+        let mut state = self.faults.lock().unwrap();
+        let FaultState {
+            seed,
+            faults,
+            rngs,
+            stats,
+        } = &mut *state;
+        let rng = rngs
+            .entry((from.to_string(), to.to_string()))
+            .or_insert_with(|| SeededRng::new(*seed ^ link_hash(from, to)));
+        // 每条消息总是消耗同样多的随机数，参数变化不影响之后消息的序列
+        let dropped = rng.chance(faults.drop_probability);
+        let mut delay = rng.duration(faults.min_delay, faults.max_delay);
+        if rng.chance(faults.reorder_probability) {
+            delay += faults.max_delay;
+        }
+        if dropped {
+            stats.dropped += 1;
+            return None;
+        }
+        stats.delivered += 1;
+        if !delay.is_zero() {
+            stats.delayed += 1;
+        }
+        Some(delay)
+    }
+}
+
+/// 链路两端的 FNV-1a 哈希，用于派生链路的随机数种子
+fn link_hash(from: &str, to: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in from.bytes().chain([0xff]).chain(to.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// [`SimulatedNetwork`] 上某个节点的 broker
+#[derive(Clone)]
+pub struct SimulatedBroker {
+    network: SimulatedNetwork,
+    id: String,
+}
+
+#[async_trait]
+impl MessageBroker for SimulatedBroker {
+    async fn publish(&self, topic: &str, data: Vec<u8>) -> Result<()> {
+        let msg = Message {
+            topic: topic.to_string(),
+            data: Bytes::from(data),
+            sender: Some(self.id.clone()),
+        };
+        if let Some(subs) = self.network.topics.get(topic) {
+            for (node, tx) in subs.iter() {
+                if !self.network.connected(&self.id, node) {
+                    continue;
+                }
+                match self.network.fate(&self.id, node) {
+                    None => {}
+                    Some(delay) if delay.is_zero() => {
+                        let _ = tx.send(Ok(msg.clone()));
+                    }
+                    Some(delay) => {
+                        let (tx, msg) = (tx.clone(), msg.clone());
+                        tokio::spawn(async move {
+                            tokio::time::sleep(delay).await;
+                            let _ = tx.send(Ok(msg));
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn subscribe(
+        &self,
+        topic: &str,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+        let (tx, rx) = unbounded_channel();
+        self.network
+            .topics
+            .entry(topic.to_string())
+            .or_default()
+            .push((self.id.clone(), tx));
+        Ok(Box::pin(stream::unfold(rx, |mut rx| async {
+            rx.recv().await.map(|evt| (evt, rx))
+        })))
+    }
+
+    async fn unsubscribe(&self, topic: &str) -> Result<()> {
+        if let Some(mut subs) = self.network.topics.get_mut(topic) {
+            subs.retain(|(node, _)| *node != self.id);
+        }
+        Ok(())
+    }
+
+    async fn subscribed_topics(&self) -> Result<Vec<String>> {
+        Ok(self
+            .network
+            .topics
+            .iter()
+            .filter(|entry| entry.value().iter().any(|(node, _)| *node == self.id))
+            .map(|entry| entry.key().clone())
+            .collect())
+    }
+}
@@ -0,0 +1,199 @@
+use super::memory::InMemoryBroker;
+use super::{Message, MessageBroker};
+use crate::error::Error;
+use crate::membership::{MembershipManager, NodeState};
+use crate::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use dashmap::DashMap;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::task::{JoinHandle, JoinSet};
+
+/// 建立到其他节点连接的超时
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 单条消息的大小上限
+const MAX_FRAME: usize = 64 * 1024 * 1024;
+
+/// 节点之间传输的一条消息
+#[derive(Serialize, Deserialize)]
+struct Frame {
+    topic: String,
+    data: Bytes,
+    sender: Option<String>,
+}
+
+/// 基于 TCP 的跨节点 broker，用于多机部署
+///
+/// 在本节点的成员地址上监听 TCP 连接（与 SWIM 的 UDP 端口相同），发布的消息投递给本地订阅者，
+/// 并发送给成员列表中其他未宕机的节点，由对方投递给其本地订阅者。到每个节点保持一条连接，
+/// 发送失败时重连一次，仍然失败只记录日志：与进程内实现一样，消息可能丢失，由上层的超时和重试处理。
+pub struct TcpBroker {
+    node_id: String,
+    local: InMemoryBroker,
+    membership: Arc<dyn MembershipManager>,
+    connections: DashMap<SocketAddr, Arc<Mutex<Option<TcpStream>>>>,
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl TcpBroker {
+    /// 在 `addr` 上监听，`membership` 提供其他节点的地址
+    pub async fn start(
+        node_id: impl Into<String>,
+        addr: SocketAddr,
+        membership: Arc<dyn MembershipManager>,
+    ) -> Result<Self> {
+        let node_id = node_id.into();
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let local = InMemoryBroker::new(node_id.clone());
+        let task = {
+            let local = local.clone();
+            tokio::spawn(async move {
+                // 连接的读取任务随监听任务一起结束
+                let mut readers = JoinSet::new();
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            readers.spawn(receive(stream, local.clone()));
+                        }
+                        Err(e) => tracing::warn!("接受消息连接失败: {}", e),
+                    }
+                    while readers.try_join_next().is_some() {}
+                }
+            })
+        };
+        Ok(Self {
+            node_id,
+            local,
+            membership,
+            connections: DashMap::new(),
+            addr,
+            task,
+        })
+    }
+
+    /// 实际监听的地址
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 其他未宕机节点的地址
+    async fn peers(&self) -> Result<Vec<SocketAddr>> {
+        Ok(self
+            .membership
+            .get_nodes()
+            .await?
+            .into_iter()
+            .filter(|node| node.id != self.node_id && node.state != NodeState::Down)
+            .map(|node| node.address)
+            .collect())
+    }
+
+    async fn send(&self, peer: SocketAddr, frame: &[u8]) -> Result<()> {
+        let connection = self.connections.entry(peer).or_default().clone();
+        let mut connection = connection.lock().await;
+        for _ in 0..2 {
+            if connection.is_none() {
+                let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(peer))
+                    .await
+                    .map_err(|_| Error::Communication(format!("连接 {} 超时", peer)))??;
+                stream.set_nodelay(true)?;
+                *connection = Some(stream);
+            }
+            if let Some(stream) = connection.as_mut() {
+                match stream.write_all(frame).await {
+                    Ok(()) => return Ok(()),
+                    Err(e) => {
+                        tracing::debug!("向 {} 发送消息失败，重新连接: {}", peer, e);
+                        *connection = None;
+                    }
+                }
+            }
+        }
+        Err(Error::Communication(format!("无法向 {} 发送消息", peer)))
+    }
+}
+
+impl Drop for TcpBroker {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// 读取一条连接上的消息并投递给本地订阅者，连接关闭或出错时结束
+async fn receive(mut stream: TcpStream, local: InMemoryBroker) {
+    loop {
+        let mut len = [0u8; 4];
+        if stream.read_exact(&mut len).await.is_err() {
+            return;
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_FRAME {
+            tracing::warn!("消息过大: {} 字节，关闭连接", len);
+            return;
+        }
+        let mut data = vec![0u8; len];
+        if stream.read_exact(&mut data).await.is_err() {
+            return;
+        }
+        match serde_json::from_slice::<Frame>(&data) {
+            Ok(frame) => local.deliver(Message {
+                topic: frame.topic,
+                data: frame.data,
+                sender: frame.sender,
+            }),
+            Err(e) => tracing::warn!("无法解析收到的消息: {}", e),
+        }
+    }
+}
+
+#[async_trait]
+impl MessageBroker for TcpBroker {
+    async fn publish(&self, topic: &str, data: Vec<u8>) -> Result<()> {
+        let frame = Frame {
+            topic: topic.to_string(),
+            data: Bytes::from(data),
+            sender: Some(self.node_id.clone()),
+        };
+        let encoded = serde_json::to_vec(&frame)?;
+        let mut buf = Vec::with_capacity(4 + encoded.len());
+        buf.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&encoded);
+
+        let peers = self.peers().await?;
+        let sends = peers.iter().map(|peer| self.send(*peer, &buf));
+        for (peer, result) in peers.iter().zip(futures::future::join_all(sends).await) {
+            if let Err(e) = result {
+                tracing::debug!("消息未送达 {}: {}", peer, e);
+            }
+        }
+        self.local.deliver(Message {
+            topic: frame.topic,
+            data: frame.data,
+            sender: frame.sender,
+        });
+        Ok(())
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<Pin<Box<dyn Stream<Item = Result<Message>> + Send>>> {
+        self.local.subscribe(topic).await
+    }
+
+    async fn unsubscribe(&self, topic: &str) -> Result<()> {
+        self.local.unsubscribe(topic).await
+    }
+
+    async fn subscribed_topics(&self) -> Result<Vec<String>> {
+        self.local.subscribed_topics().await
+    }
+}
@@ -0,0 +1,245 @@
+//! 整个集群的确定性模拟
+//!
+//! [`Simulation`] 在一个进程里启动 N 个完整的 [`Coretex`] 节点：单线程调度器、暂停的 tokio 时间
+//! （所有任务都在等待时直接跳到下一个计时器）以及注入故障的 [`SimulatedNetwork`]。
+//! 网络故障、时钟偏差、Raft 选举超时的抖动和测试中的随机选择都来自同一个种子，
+//! 失败的运行可以用种子复现。
+
+use crate::config::Config;
+use crate::error::Error;
+use crate::membership::{InMemoryMembership, MembershipManager, NodeState};
+use crate::messaging::sim::{NetworkFaults, SimulatedNetwork};
+use crate::messaging::{ClockedBroker, MessageBroker};
+use crate::storage::{InMemoryEngine, StorageEngine};
+use crate::utils::{HybridClock, SeededRng};
+use crate::{Coretex, NodeServices, Result};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// 虚拟时钟的起点（毫秒），模拟开始时各节点的物理时钟都从这里起算
+pub const SIMULATION_EPOCH_MS: u64 = 1_700_000_000_000;
+
+const DEFAULT_CONFIG: &str = r#"
+[node]
+bind_address = "127.0.0.1:7000"
+data_dir = "./data"
+seed_nodes = []
+
+[storage]
+engine = "memory"
+
+[replication]
+factor = 3
+read_quorum = 2
+write_quorum = 2
+
+[consistency]
+mode = "Eventual"
+vector_clock_enabled = false
+"#;
+
+/// 模拟的参数
+pub struct Simulation {
+    seed: u64,
+    nodes: usize,
+    config: Config,
+    faults: NetworkFaults,
+    max_clock_skew: Duration,
+}
+
+impl Simulation {
+    /// 三个节点、N=3/R=2/W=2 的最终一致集群，网络没有故障
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            nodes: 3,
+            config: toml::from_str(DEFAULT_CONFIG).expect("默认模拟配置无效"),
+            faults: NetworkFaults::default(),
+            max_clock_skew: Duration::ZERO,
+        }
+    }
+
+    pub fn with_nodes(mut self, nodes: usize) -> Self {
+        self.nodes = nodes;
+        self
+    }
+
+    /// 所有节点共用的配置，`node` 部分被忽略
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_faults(mut self, faults: NetworkFaults) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    /// 每个节点的物理时钟超前 `[0, max]` 内由种子决定的偏差
+    pub fn with_clock_skew(mut self, max: Duration) -> Self {
+        self.max_clock_skew = max;
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// 在新的单线程、虚拟时间运行时中启动集群并运行 `test`
+    ///
+    /// 不能在其他 tokio 运行时中调用，测试应使用普通的 `#[test]`。
+    pub fn run<F, Fut, T>(self, test: F) -> Result<T>
+    where
+        F: FnOnce(SimCluster) -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .start_paused(true)
+            .build()?;
+        runtime.block_on(async move {
+            let cluster = SimCluster::start(self).await?;
+            Ok(test(cluster).await)
+        })
+    }
+}
+
+/// 模拟中的一个节点
+pub struct SimNode {
+    pub id: String,
+    pub coretex: Coretex,
+    pub clock: Arc<HybridClock>,
+    /// 物理时钟相对虚拟时间的偏差
+    pub skew: Duration,
+    _services: NodeServices,
+}
+
+/// 运行中的模拟集群
+pub struct SimCluster {
+    seed: u64,
+    network: SimulatedNetwork,
+    membership: Arc<InMemoryMembership>,
+    nodes: Vec<SimNode>,
+    rng: Mutex<SeededRng>,
+    start: tokio::time::Instant,
+}
+
+impl SimCluster {
+    async fn start(simulation: Simulation) -> Result<Self> {
+        let Simulation {
+            seed,
+            nodes: count,
+            config,
+            faults,
+            max_clock_skew,
+        } = simulation;
+        let mut rng = SeededRng::new(seed);
+        let network = SimulatedNetwork::new().with_faults(rng.next_u64(), faults);
+        let membership = Arc::new(InMemoryMembership::new());
+        let start = tokio::time::Instant::now();
+
+        // 先注册全部节点，各节点的分布环启动时即包含整个集群
+        let ids: Vec<String> = (1..=count).map(|i| format!("n{}", i)).collect();
+        let mut configs = HashMap::new();
+        for (i, id) in ids.iter().enumerate() {
+            let address = SocketAddr::from(([127, 0, 0, 1], 7000 + i as u16));
+            let mut config = config.clone();
+            config.node.bind_address = address;
+            config.consistency.raft.election_seed = Some(rng.next_u64());
+            configs.insert(id.clone(), Arc::new(config));
+            membership.register_node_with_id(id.clone(), address, HashMap::new());
+            membership.update_node_state(id, NodeState::Active).await?;
+        }
+
+        let mut nodes = Vec::new();
+        for id in ids {
+            let config = configs.remove(&id).expect("节点配置");
+            let skew = rng.duration(Duration::ZERO, max_clock_skew);
+            let base = SIMULATION_EPOCH_MS + skew.as_millis() as u64;
+            let clock = Arc::new(HybridClock::with_physical_clock(
+                Duration::from_millis(config.clock.max_offset_ms),
+                Arc::new(move || base + start.elapsed().as_millis() as u64),
+            ));
+            let storage: Arc<dyn StorageEngine> = Arc::new(InMemoryEngine::new(id.clone()));
+            let messaging: Arc<dyn MessageBroker> =
+                Arc::new(ClockedBroker::new(Arc::new(network.broker(id.clone())), clock.clone()));
+            let (coretex, services) =
+                Coretex::assemble(config, &id, storage, membership.clone(), messaging, clock.clone()).await?;
+            nodes.push(SimNode {
+                id,
+                coretex,
+                clock,
+                skew,
+                _services: services,
+            });
+        }
+        Ok(Self {
+            seed,
+            network,
+            membership,
+            nodes,
+            rng: Mutex::new(rng),
+            start,
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn nodes(&self) -> &[SimNode] {
+        &self.nodes
+    }
+
+    pub fn node(&self, id: &str) -> Option<&SimNode> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// 用于分区、隔离和调整故障参数
+    pub fn network(&self) -> &SimulatedNetwork {
+        &self.network
+    }
+
+    /// 模拟开始以来经过的虚拟时间
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// 推进虚拟时间，期间其他任务照常运行
+    pub async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+
+    /// `[0, bound)` 内由种子决定的随机数，用于测试中的随机选择
+    pub fn random(&self, bound: u64) -> u64 {
+        self.rng.lock().unwrap().below(bound)
+    }
+
+    pub fn chance(&self, p: f64) -> bool {
+        self.rng.lock().unwrap().chance(p)
+    }
+
+    /// 模拟节点宕机：断开网络并在成员中标记为 `Down`
+    pub async fn crash(&self, id: &str) -> Result<()> {
+        self.check(id)?;
+        self.network.isolate(id);
+        self.membership.update_node_state(id, NodeState::Down).await
+    }
+
+    /// 恢复宕机的节点，其他节点随后回放暂存的提示
+    pub async fn recover(&self, id: &str) -> Result<()> {
+        self.check(id)?;
+        self.network.reconnect(id);
+        self.membership.update_node_state(id, NodeState::Active).await
+    }
+
+    fn check(&self, id: &str) -> Result<()> {
+        match self.node(id) {
+            Some(_) => Ok(()),
+            None => Err(Error::Membership(format!("模拟中没有节点 {}", id))),
+        }
+    }
+}
@@ -51,7 +51,7 @@ impl StorageEngine for InMemoryEngine {
             .iter()
             .filter(|entry| {
                 let key = entry.key();
-                key >= &start_key && end_key.as_ref().map_or(true, |end| key < end)
+                key >= &start_key && end_key.as_ref().is_none_or(|end| key < end)
             })
             .map(|entry| {
                 Ok(KeyValue {
@@ -61,7 +61,11 @@ impl StorageEngine for InMemoryEngine {
             })
             .collect::<Vec<_>>();
 
-        // 应用限制
+        // 按 key 排序后应用限制
+        items.sort_by(|a, b| match (a, b) {
+            (Ok(a), Ok(b)) => a.key.cmp(&b.key),
+            _ => std::cmp::Ordering::Equal,
+        });
         if let Some(limit) = limit {
             items.truncate(limit);
         }
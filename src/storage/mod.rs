@@ -16,6 +16,7 @@ pub use memory::InMemoryEngine;
 
 use crate::Result;
 
+#[derive(Clone, Debug)]
 pub struct KeyValue {
     pub key: Bytes,
     pub value: Bytes,
@@ -39,6 +40,7 @@ pub trait StorageEngine: Send + Sync + 'static {
     fn name(&self) -> &str;
 }
 
+#[derive(Clone, Debug)]
 pub enum WriteOperation {
     Put { key: Bytes, value: Bytes },
     Delete { key: Bytes },
@@ -0,0 +1,151 @@
+use crate::error::Error;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 逻辑计数占用的低位数
+const LOGICAL_BITS: u32 = 16;
+
+/// 混合逻辑时钟（HLC）的时间戳
+///
+/// 高 48 位为物理时间（毫秒），低 16 位为同一毫秒内的逻辑计数，
+/// 整体作为 `u64` 比较即为时间戳的先后顺序。逻辑计数溢出时进位到物理部分。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct HybridTimestamp(u64);
+
+impl HybridTimestamp {
+    pub fn new(physical_ms: u64, logical: u16) -> Self {
+        Self((physical_ms << LOGICAL_BITS) | logical as u64)
+    }
+
+    pub fn from_u64(value: u64) -> Self {
+        Self(value)
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// 物理部分（Unix 毫秒）
+    pub fn physical_ms(self) -> u64 {
+        self.0 >> LOGICAL_BITS
+    }
+
+    pub fn logical(self) -> u16 {
+        self.0 as u16
+    }
+
+    pub fn to_bytes(self) -> [u8; 8] {
+        self.0.to_be_bytes()
+    }
+
+    pub fn from_bytes(bytes: [u8; 8]) -> Self {
+        Self(u64::from_be_bytes(bytes))
+    }
+}
+
+impl fmt::Display for HybridTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.physical_ms(), self.logical())
+    }
+}
+
+/// 物理时间来源，返回 Unix 毫秒
+pub type PhysicalClock = Arc<dyn Fn() -> u64 + Send + Sync>;
+
+/// 混合逻辑时钟
+///
+/// 本地事件和发送消息时调用 [`HybridClock::now`]，收到消息时用发送方的时间戳调用
+/// [`HybridClock::update`]。返回的时间戳严格递增，并且不早于所有已观察到的时间戳，
+/// 同时与物理时间的偏差有界，可用于最后写入者胜出、墓碑时间和 TTL。
+///
+/// 远端时间戳超前本地物理时间超过 `max_offset` 时视为时钟偏差过大，
+/// 拒绝该时间戳而不推进本地时钟，避免一个时钟出错的节点把整个集群的时间拉向未来。
+pub struct HybridClock {
+    last: AtomicU64,
+    max_offset: Duration,
+    physical: PhysicalClock,
+    rejected: AtomicU64,
+}
+
+impl HybridClock {
+    pub fn new(max_offset: Duration) -> Self {
+        Self::with_physical_clock(max_offset, Arc::new(wall_clock_ms))
+    }
+
+    /// 使用指定的物理时间来源，便于模拟时钟偏差
+    pub fn with_physical_clock(max_offset: Duration, physical: PhysicalClock) -> Self {
+        Self {
+            last: AtomicU64::new(0),
+            max_offset,
+            physical,
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    pub fn max_offset(&self) -> Duration {
+        self.max_offset
+    }
+
+    /// 本地事件或发送消息时的时间戳
+    pub fn now(&self) -> HybridTimestamp {
+        self.advance(HybridTimestamp::default())
+    }
+
+    /// 收到时间戳为 `remote` 的消息，返回接收事件的时间戳
+    ///
+    /// `remote` 超前本地物理时间超过 `max_offset` 时返回 [`Error::ClockSkew`]，本地时钟不变。
+    pub fn update(&self, remote: HybridTimestamp) -> Result<HybridTimestamp> {
+        let physical = (self.physical)();
+        let offset = remote.physical_ms().saturating_sub(physical);
+        if offset > self.max_offset.as_millis() as u64 {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return Err(Error::ClockSkew {
+                offset_ms: offset,
+                max_offset_ms: self.max_offset.as_millis() as u64,
+            });
+        }
+        Ok(self.advance(remote))
+    }
+
+    /// 最近一次发出的时间戳，不推进时钟
+    pub fn last(&self) -> HybridTimestamp {
+        HybridTimestamp(self.last.load(Ordering::SeqCst))
+    }
+
+    /// 因时钟偏差被拒绝的远端时间戳数量
+    pub fn skew_rejections(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// 取物理时间、上次时间戳 +1 与 `floor` +1 中的最大者
+    fn advance(&self, floor: HybridTimestamp) -> HybridTimestamp {
+        let physical = HybridTimestamp::new((self.physical)(), 0).0;
+        let previous = self
+            .last
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(physical.max(last + 1).max(floor.0 + 1))
+            })
+            .unwrap_or_default();
+        HybridTimestamp(physical.max(previous + 1).max(floor.0 + 1))
+    }
+}
+
+impl Default for HybridClock {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(DEFAULT_MAX_OFFSET_MS))
+    }
+}
+
+/// 默认允许的最大时钟偏差（毫秒）
+pub const DEFAULT_MAX_OFFSET_MS: u64 = 500;
+
+fn wall_clock_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
@@ -1,12 +1,20 @@
-/// 工具模块
-/// 包含常用的工具函数和辅助结构
+//! 工具模块
+//! 包含常用的工具函数和辅助结构
+
+mod hlc;
+mod rng;
+
+pub use hlc::{HybridClock, HybridTimestamp, PhysicalClock, DEFAULT_MAX_OFFSET_MS};
+pub use rng::SeededRng;
 
 /// 简单的ID生成器
 pub fn generate_id() -> String {
     uuid::Uuid::new_v4().to_string()
 }
 
-/// 时间戳生成器
+/// 时间戳生成器（秒级墙上时间）
+///
+/// 只适合粗粒度的记录，需要对事件排序时使用 [`HybridClock`]。
 pub fn current_timestamp() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
     SystemTime::now()
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+/// 可由种子复现的伪随机数生成器（SplitMix64）
+///
+/// 只用于模拟和测试：同一个种子总是产生同一个序列，便于复现故障。
+#[derive(Clone, Debug)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// `[0, bound)` 内的随机数，`bound` 为 0 时返回 0
+    pub fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            return 0;
+        }
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    /// 以概率 `p` 返回 `true`
+    pub fn chance(&mut self, p: f64) -> bool {
+        // 取高 53 位得到 [0, 1) 内均匀分布的浮点数
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        unit < p
+    }
+
+    /// `[min, max]` 内均匀选取的时长
+    pub fn duration(&mut self, min: Duration, max: Duration) -> Duration {
+        let span = max.saturating_sub(min).as_micros() as u64;
+        min + Duration::from_micros(self.below(span + 1))
+    }
+}
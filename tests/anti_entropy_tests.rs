@@ -0,0 +1,220 @@
+mod common;
+
+use common::{Cluster, NODES};
+use coretex::{
+    config::AntiEntropyConfig,
+    consistency::{
+        AntiEntropyService, Crdt, CrdtOp, HintStore, MerkleTree, ReplicaClient, ReplicaOp, ReplicaReply, Stamped,
+        VectorClock, Version,
+    },
+    distribution::{HashRange, PartitionRange},
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_millis(200);
+
+impl Cluster {
+    /// 直接向副本发送请求的客户端，不经过协调者
+    async fn writer(&self) -> ReplicaClient {
+        ReplicaClient::start("writer", self.broker("writer")).await.unwrap()
+    }
+
+    /// 只写入 `replicas` 上的版本
+    async fn write(&self, client: &ReplicaClient, replicas: &[&str], key: &str, writer: &str, value: &str) {
+        let mut clock = VectorClock::new();
+        clock.increment(writer);
+        let version = Version {
+            clock,
+            value: Some(Bytes::from(value.to_string())),
+            timestamp: 0,
+            deps: Default::default(),
+        };
+        for replica in replicas {
+            let op = ReplicaOp::PutVersion {
+                key: Bytes::from(key.to_string()),
+                version: version.clone(),
+            };
+            client.call(replica, op, TIMEOUT).await.unwrap();
+        }
+    }
+
+    async fn service(&self, node: &str, max_keys_per_sec: u64) -> AntiEntropyService {
+        let client = Arc::new(ReplicaClient::start(node, self.broker(node)).await.unwrap());
+        let config = AntiEntropyConfig {
+            enabled: false,
+            max_keys_per_sec,
+            ..Default::default()
+        };
+        AntiEntropyService::start(self.storages[node].clone(), self.ring.clone(), client, 3, &config, TIMEOUT)
+    }
+
+    async fn values(&self, node: &str, key: &str) -> Vec<Bytes> {
+        let versions: Vec<Version> = match self.storages[node].get(key.as_bytes()).await.unwrap() {
+            Some(data) => serde_json::from_slice(&data).unwrap(),
+            None => Vec::new(),
+        };
+        let mut values: Vec<Bytes> = versions.into_iter().filter_map(|v| v.value).collect();
+        values.sort();
+        values
+    }
+}
+
+fn full_range() -> PartitionRange {
+    PartitionRange::Hash(HashRange::new(0, None))
+}
+
+#[test]
+fn test_merkle_diff_finds_only_changed_leaves() {
+    let entries: Vec<(Vec<u8>, u64)> = (0..200).map(|i| (format!("key-{}", i).into_bytes(), i)).collect();
+    let tree = |entries: &[(Vec<u8>, u64)]| MerkleTree::build(6, entries.iter().map(|(k, d)| (&k[..], *d)));
+
+    let ours = tree(&entries);
+    assert!(ours.diff(&tree(&entries)).is_empty());
+    assert_eq!(ours.depth(), 6);
+
+    let mut changed = entries.clone();
+    changed[42].1 = 0;
+    let differing = ours.diff(&tree(&changed));
+    assert_eq!(differing.len(), 1);
+    assert_ne!(ours.root(), tree(&changed).root());
+
+    let mut missing = entries.clone();
+    missing.remove(7);
+    missing.remove(99);
+    assert!(!ours.diff(&tree(&missing)).is_empty());
+    assert!(ours.diff(&tree(&missing)).len() <= 2);
+
+    // 深度不同时返回全部叶子
+    let shallow = MerkleTree::build(2, entries.iter().map(|(k, d)| (&k[..], *d)));
+    assert_eq!(shallow.diff(&ours).len(), 4);
+}
+
+#[tokio::test]
+async fn test_anti_entropy_converges_replicas() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let writer = cluster.writer().await;
+    for i in 0..20 {
+        cluster.write(&writer, &["n1", "n2"], &format!("key-{}", i), "a", "v").await;
+    }
+    cluster.write(&writer, &["n3"], "only-n3", "c", "x").await;
+    // 并发写入在同步后成为 siblings
+    cluster.write(&writer, &["n1"], "conflict", "a", "left").await;
+    cluster.write(&writer, &["n2"], "conflict", "b", "right").await;
+
+    let n1 = cluster.service("n1", 0).await;
+    let first = n1.run_round().await;
+    assert!(first.ranges_differing > 0);
+    assert!(first.keys_repaired >= 21);
+    n1.run_round().await;
+
+    for node in NODES {
+        assert_eq!(cluster.values(node, "key-7").await, vec![Bytes::from("v")]);
+        assert_eq!(cluster.values(node, "only-n3").await, vec![Bytes::from("x")]);
+        assert_eq!(
+            cluster.values(node, "conflict").await,
+            vec![Bytes::from("left"), Bytes::from("right")]
+        );
+    }
+
+    let settled = n1.run_round().await;
+    assert!(settled.ranges_compared > 0);
+    assert_eq!(settled.ranges_differing, 0);
+    assert_eq!(settled.keys_repaired, 0);
+}
+
+#[tokio::test]
+async fn test_anti_entropy_skips_hints_and_limits_rate() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let writer = cluster.writer().await;
+    let hints = HintStore::open(cluster.storages["n1"].clone(), &Default::default())
+        .await
+        .unwrap();
+    let op = ReplicaOp::Put {
+        key: Bytes::from_static(b"k"),
+        value: Bytes::from_static(b"v"),
+        timestamp: 1,
+    };
+    hints.add("n2", op).await.unwrap();
+
+    let n1 = cluster.service("n1", 20).await;
+    assert_eq!(n1.sync_range(&full_range(), "n2").await.unwrap(), 0);
+    assert_eq!(cluster.storages["n2"].scan(b"", None, None).await.unwrap().count().await, 0);
+
+    for i in 0..10 {
+        cluster.write(&writer, &["n1"], &format!("key-{}", i), "a", "v").await;
+    }
+    let started = Instant::now();
+    assert_eq!(n1.sync_range(&full_range(), "n2").await.unwrap(), 10);
+    assert!(started.elapsed() >= Duration::from_millis(400));
+    assert_eq!(cluster.values("n2", "key-3").await, vec![Bytes::from("v")]);
+}
+
+#[tokio::test]
+async fn test_anti_entropy_merges_crdt_states() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let writer = cluster.writer().await;
+    // 两个副本各自执行了不同的自增
+    for (replica, delta) in [("n1", 3), ("n2", 4)] {
+        let op = ReplicaOp::UpdateCrdt {
+            key: Bytes::from_static(b"counter"),
+            op: CrdtOp::GCounterIncrement(delta),
+            timestamp: 0,
+            op_id: format!("op-{}", replica),
+        };
+        writer.call(replica, op, TIMEOUT).await.unwrap();
+    }
+
+    let n1 = cluster.service("n1", 0).await;
+    assert_eq!(n1.sync_range(&full_range(), "n2").await.unwrap(), 1);
+    assert_eq!(n1.sync_range(&full_range(), "n3").await.unwrap(), 1);
+    for replica in NODES {
+        let op = ReplicaOp::GetCrdt {
+            key: Bytes::from_static(b"counter"),
+        };
+        let reply = writer.call(replica, op, TIMEOUT).await.unwrap();
+        let ReplicaReply::Crdt(Some(Crdt::GCounter(counter))) = reply else {
+            panic!("副本 {} 没有计数器", replica);
+        };
+        assert_eq!(counter.value(), 7);
+    }
+    assert_eq!(n1.sync_range(&full_range(), "n2").await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_anti_entropy_keeps_latest_stamped_value() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let writer = cluster.writer().await;
+    let put = |value: &'static [u8], timestamp| ReplicaOp::Put {
+        key: Bytes::from_static(b"plain"),
+        value: Bytes::from_static(value),
+        timestamp,
+    };
+    writer.call("n1", put(b"old", 1), TIMEOUT).await.unwrap();
+    writer.call("n2", put(b"new", 2), TIMEOUT).await.unwrap();
+    let delete = ReplicaOp::Delete {
+        key: Bytes::from_static(b"deleted"),
+        timestamp: 5,
+    };
+    writer.call("n1", delete, TIMEOUT).await.unwrap();
+    let stale = ReplicaOp::Put {
+        key: Bytes::from_static(b"deleted"),
+        value: Bytes::from_static(b"v"),
+        timestamp: 4,
+    };
+    writer.call("n2", stale, TIMEOUT).await.unwrap();
+
+    let n1 = cluster.service("n1", 0).await;
+    assert_eq!(n1.sync_range(&full_range(), "n2").await.unwrap(), 2);
+    for replica in ["n1", "n2"] {
+        let stored = |key: &'static [u8]| {
+            let storage = cluster.storages[replica].clone();
+            async move { Stamped::from_stored(storage.get(key).await.unwrap()) }
+        };
+        assert_eq!(stored(b"plain").await, Stamped::new(2, Some(Bytes::from_static(b"new"))));
+        assert_eq!(stored(b"deleted").await, Stamped::new(5, None));
+    }
+    assert_eq!(n1.sync_range(&full_range(), "n2").await.unwrap(), 0);
+}
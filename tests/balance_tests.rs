@@ -0,0 +1,272 @@
+mod common;
+
+use common::{eventually, Cluster};
+use coretex::{
+    config::{BalanceMetric, DistributionConfig, LoadAwareConfig},
+    consistency::{ConsistencyManager, RAFT_PREFIX},
+    distribution::{
+        adjust_load_factors, BalanceReport, ConsistentHashRing, DistributionNode,
+        DistributionStrategy, JumpHashing, LoadBalancer, LoadTracker, NodeStats, SharedRing,
+    },
+    messaging::{memory::InMemoryBroker, MessageBroker},
+    storage::{InMemoryEngine, StorageEngine},
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const REPLICAS: usize = 1;
+
+fn ring(ids: &[&str]) -> SharedRing {
+    let mut strategy = ConsistentHashRing::new();
+    for id in ids {
+        strategy.add_node(DistributionNode::new(*id, 100));
+    }
+    SharedRing::new(Box::new(strategy))
+}
+
+fn engines(ids: &[&str]) -> HashMap<String, Arc<dyn StorageEngine>> {
+    ids.iter()
+        .map(|id| (id.to_string(), Arc::new(InMemoryEngine::new(*id)) as Arc<dyn StorageEngine>))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_report_counts_keys_and_bytes() {
+    let ids = ["n1", "n2", "n3"];
+    let ring = ring(&ids);
+    let engines = engines(&ids);
+    let snapshot = ring.snapshot();
+    for i in 0..300 {
+        let key = format!("key-{:03}", i);
+        let primary = snapshot.get_primary(key.as_bytes()).unwrap();
+        engines[&primary.id].put(key.as_bytes(), b"value").await.unwrap();
+    }
+    // Raft 状态不计入分区数据
+    engines["n1"].put(&[RAFT_PREFIX, b"g/state"].concat(), b"state").await.unwrap();
+
+    let tracker = LoadTracker::new(snapshot.partition_map(REPLICAS));
+    let report = BalanceReport::collect(&ring, &engines, &tracker, REPLICAS).await.unwrap();
+
+    assert_eq!(report.nodes.len(), 3);
+    assert_eq!(report.nodes.iter().map(|n| n.keys).sum::<u64>(), 300);
+    assert_eq!(report.ranges.iter().map(|r| r.keys).sum::<u64>(), 300);
+    assert_eq!(report.keys.total, 300.0);
+    assert_eq!(report.bytes.total, 300.0 * (7.0 + 5.0));
+    assert!(report.nodes.iter().all(|n| n.load_factor == 1.0));
+}
+
+#[tokio::test]
+async fn test_report_tracks_request_rates() {
+    let ids = ["n1", "n2"];
+    let ring = ring(&ids);
+    let engines = engines(&ids);
+    let tracker = LoadTracker::new(ring.snapshot().partition_map(REPLICAS));
+
+    // 所有请求集中在同一个 key 上
+    for _ in 0..100 {
+        tracker.record(b"hot");
+    }
+    let report = BalanceReport::collect(&ring, &engines, &tracker, REPLICAS).await.unwrap();
+    let hot = ring.snapshot().get_primary(b"hot").unwrap();
+    let busy: Vec<_> = report.nodes.iter().filter(|n| n.requests_per_sec > 0.0).collect();
+    assert_eq!(busy.len(), 1);
+    assert_eq!(busy[0].node_id, hot.id);
+    assert_eq!(report.ranges.iter().filter(|r| r.requests_per_sec > 0.0).count(), 1);
+    assert!(report.requests.max_over_ideal > 1.9);
+
+    // 分布环更新后旧的请求统计不再计入
+    ring.add_node(DistributionNode::new("n3", 100));
+    let report = BalanceReport::collect(&ring, &engines, &tracker, REPLICAS).await.unwrap();
+    assert_eq!(report.requests.total, 0.0);
+}
+
+#[tokio::test]
+async fn test_adjust_load_factors_shifts_tokens_from_overloaded_node() {
+    let ids = ["n1", "n2", "n3"];
+    let ring = ring(&ids);
+    let engines = engines(&ids);
+    let snapshot = ring.snapshot();
+    for i in 0..300 {
+        let key = format!("key-{:03}", i);
+        let primary = snapshot.get_primary(key.as_bytes()).unwrap();
+        // n1 上的数据远大于其他节点
+        let value = if primary.id == "n1" { vec![0u8; 100] } else { vec![0u8; 10] };
+        engines[&primary.id].put(key.as_bytes(), &value).await.unwrap();
+    }
+    let tokens_before = snapshot.strategy.tokens("n1").len();
+
+    let tracker = LoadTracker::new(snapshot.partition_map(REPLICAS));
+    let report = BalanceReport::collect(&ring, &engines, &tracker, REPLICAS).await.unwrap();
+    let config = LoadAwareConfig::default();
+    let changes = adjust_load_factors(&ring, &report, &config);
+
+    let n1 = changes.iter().find(|c| c.node_id == "n1").unwrap();
+    assert_eq!(n1.from, 1.0);
+    assert_eq!(n1.to, 1.0 - config.max_step);
+    assert!(changes.iter().filter(|c| c.node_id != "n1").all(|c| c.to > c.from));
+
+    assert_eq!(ring.epoch(), 1);
+    let snapshot = ring.snapshot();
+    assert_eq!(snapshot.strategy.load_factor("n1"), 1.0 - config.max_step);
+    assert!(snapshot.strategy.tokens("n1").len() < tokens_before);
+    // 权重代表容量，不随负载调整
+    assert!(snapshot.strategy.all_nodes().iter().all(|n| n.weight == 100));
+}
+
+#[tokio::test]
+async fn test_adjust_load_factors_within_tolerance_or_unsupported() {
+    let ids = ["n1", "n2"];
+    let engines = engines(&ids);
+    let config = LoadAwareConfig {
+        metric: BalanceMetric::Keys,
+        ..LoadAwareConfig::default()
+    };
+
+    // 没有数据时不调整
+    let balanced = ring(&ids);
+    let tracker = LoadTracker::new(balanced.snapshot().partition_map(REPLICAS));
+    let report = BalanceReport::collect(&balanced, &engines, &tracker, REPLICAS).await.unwrap();
+    assert!(adjust_load_factors(&balanced, &report, &config).is_empty());
+    assert_eq!(balanced.epoch(), 0);
+
+    // jump hashing 不支持负载系数
+    let mut jump = JumpHashing::new();
+    for id in ids {
+        jump.add_node(DistributionNode::new(id, 100));
+    }
+    let jump = SharedRing::new(Box::new(jump));
+    for i in 0..50 {
+        engines["n1"].put(format!("key-{}", i).as_bytes(), b"v").await.unwrap();
+    }
+    let tracker = LoadTracker::new(jump.snapshot().partition_map(REPLICAS));
+    let report = BalanceReport::collect(&jump, &engines, &tracker, REPLICAS).await.unwrap();
+    assert!(report.keys.relative > config.tolerance);
+    assert!(adjust_load_factors(&jump, &report, &config).is_empty());
+    assert_eq!(jump.epoch(), 0);
+}
+
+#[test]
+fn test_load_aware_config() {
+    let config: DistributionConfig = toml::from_str(
+        r#"
+        strategy = "Rendezvous"
+
+        [load_aware]
+        metric = "Requests"
+        tolerance = 0.2
+        "#,
+    )
+    .unwrap();
+    let load_aware = config.load_aware.unwrap();
+    assert_eq!(load_aware.metric, BalanceMetric::Requests);
+    assert_eq!(load_aware.tolerance, 0.2);
+    assert_eq!(load_aware.max_step, 0.25);
+    assert_eq!(load_aware.interval_ms, 60000);
+
+    let config: DistributionConfig = toml::from_str("").unwrap();
+    assert!(config.load_aware.is_none());
+}
+
+#[tokio::test]
+async fn test_report_from_exchanged_stats_matches_local_report() {
+    let ids = ["n1", "n2", "n3"];
+    let ring = ring(&ids);
+    let engines = engines(&ids);
+    let snapshot = ring.snapshot();
+    for i in 0..300 {
+        let key = format!("key-{:03}", i);
+        for replica in snapshot.get_replicas(key.as_bytes(), 2) {
+            engines[&replica.id].put(key.as_bytes(), b"value").await.unwrap();
+        }
+    }
+    let map = snapshot.partition_map(REPLICAS);
+    let tracker = LoadTracker::new(map.clone());
+    tracker.record(b"key-001");
+
+    // 每个节点只扫描自己的存储，请求统计来自协调了请求的节点
+    let mut stats = Vec::new();
+    for id in ids {
+        let tracked = (id == "n2").then_some(&tracker);
+        stats.push(NodeStats::collect(id, engines[id].as_ref(), &map, tracked).await.unwrap());
+    }
+    let exchanged = BalanceReport::from_stats(&ring, &stats, REPLICAS);
+    let local = BalanceReport::collect(&ring, &engines, &tracker, REPLICAS).await.unwrap();
+
+    assert_eq!(exchanged.keys.total, 600.0);
+    assert_eq!(exchanged.ranges.iter().map(|r| r.keys).sum::<u64>(), 300);
+    for (a, b) in exchanged.nodes.iter().zip(&local.nodes) {
+        assert_eq!((a.keys, a.bytes), (b.keys, b.bytes));
+        assert_eq!(a.requests_per_sec > 0.0, b.requests_per_sec > 0.0);
+    }
+    assert_eq!(exchanged.nodes.iter().filter(|n| n.requests_per_sec > 0.0).count(), 1);
+}
+
+#[tokio::test]
+async fn test_coordinator_feeds_load_tracker() {
+    let cluster = Cluster::in_memory(&common::NODES).await;
+    let map = cluster.ring.snapshot().partition_map(REPLICAS);
+    let tracker = Arc::new(LoadTracker::new(map));
+    let coordinator = cluster.coordinator("n1", 2, 2).await.with_load_tracker(tracker.clone());
+
+    for _ in 0..10 {
+        coordinator.put(b"busy", b"v").await.unwrap();
+        coordinator.get(b"busy").await.unwrap();
+    }
+    let report = BalanceReport::collect(&cluster.ring, &cluster.storages, &tracker, REPLICAS).await.unwrap();
+    let busy: Vec<_> = report.ranges.iter().filter(|r| r.requests_per_sec > 0.0).collect();
+    assert_eq!(busy.len(), 1);
+    assert!(busy[0].range.contains(b"busy"));
+    assert_eq!(report.nodes.iter().filter(|n| n.requests_per_sec > 0.0).count(), 1);
+}
+
+#[tokio::test]
+async fn test_load_balancer_adjusts_every_ring() {
+    let ids = ["n1", "n2", "n3"];
+    let broker: Arc<dyn MessageBroker> = Arc::new(InMemoryBroker::new("cluster"));
+    let engines = engines(&ids);
+    let rings: Vec<Arc<SharedRing>> = ids.iter().map(|_| Arc::new(ring(&ids))).collect();
+    let snapshot = rings[0].snapshot();
+    for i in 0..300 {
+        let key = format!("key-{:03}", i);
+        let primary = snapshot.get_primary(key.as_bytes()).unwrap();
+        // n1 上的数据远大于其他节点
+        let value = if primary.id == "n1" { vec![0u8; 100] } else { vec![0u8; 10] };
+        engines[&primary.id].put(key.as_bytes(), &value).await.unwrap();
+    }
+
+    let config = LoadAwareConfig {
+        interval_ms: 100,
+        ..LoadAwareConfig::default()
+    };
+    let mut balancers = Vec::new();
+    for (id, ring) in ids.iter().zip(&rings) {
+        let tracker = Arc::new(LoadTracker::new(ring.snapshot().partition_map(REPLICAS)));
+        let balancer = LoadBalancer::start(
+            *id,
+            ring.clone(),
+            engines[*id].clone(),
+            tracker,
+            broker.clone(),
+            REPLICAS,
+            config.clone(),
+        )
+        .await
+        .unwrap();
+        balancers.push(balancer);
+    }
+
+    // 统计在节点之间交换，n1 汇总后调整，所有节点的分布环采用同样的负载系数
+    let converged = eventually(Duration::from_secs(5), || async {
+        let factors: Vec<Vec<f64>> = rings
+            .iter()
+            .map(|ring| {
+                let snapshot = ring.snapshot();
+                ids.iter().map(|id| snapshot.strategy.load_factor(id)).collect()
+            })
+            .collect();
+        factors[0][0] < 1.0 && factors.iter().all(|f| *f == factors[0])
+    })
+    .await;
+    assert!(converged);
+}
@@ -0,0 +1,203 @@
+mod common;
+
+use common::{Cluster, NODES};
+use coretex::{
+    api::{ClientApi, CoordinatorClient},
+    consistency::{
+        ConsistencyManager, QuorumConsistencyManager, ReplicaClient, ReplicaOp, SessionToken, VectorClock, Version,
+    },
+    messaging::sim::SimulatedNetwork,
+};
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_millis(400);
+
+impl Cluster {
+    /// R=1、W=1 并启用向量时钟的协调者，副本之间可能短暂不一致
+    async fn causal(&self, id: &str) -> QuorumConsistencyManager {
+        self.coordinator(id, 1, 1)
+            .await
+            .with_timeout(TIMEOUT)
+            .with_vector_clocks(true)
+    }
+
+    /// 只让 `id` 能连通 `replicas`
+    fn restrict(&self, id: &str, replicas: &[&str]) {
+        for replica in NODES.into_iter().filter(|r| !replicas.contains(r)) {
+            self.sim().block(id, replica);
+        }
+    }
+
+    /// 在 `delay` 之后把 `from` 上的版本复制到 `to`，模拟滞后副本追上
+    fn catch_up_later(&self, from: &str, to: &str, key: &'static str, delay: Duration) -> tokio::task::JoinHandle<()> {
+        let storage = self.storages[from].clone();
+        let broker = self.broker("admin");
+        let to = to.to_string();
+        tokio::spawn(async move {
+            let admin = ReplicaClient::start("admin", broker).await.unwrap();
+            tokio::time::sleep(delay).await;
+            let data = storage.get(key.as_bytes()).await.unwrap().unwrap();
+            let versions: Vec<Version> = serde_json::from_slice(&data).unwrap();
+            for version in versions {
+                let op = ReplicaOp::PutVersion {
+                    key: Bytes::from(key),
+                    version,
+                };
+                admin.call(&to, op, TIMEOUT).await.unwrap();
+            }
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_read_your_writes_across_coordinators() {
+    let cluster = Cluster::simulated(SimulatedNetwork::new()).await;
+    let writer = cluster.causal("c1").await;
+    let reader = CoordinatorClient::new(Arc::new(cluster.causal("c2").await));
+    // 写入只到达 n1，读取只能访问 n2、n3
+    cluster.restrict("c1", &["n1"]);
+    cluster.restrict("c2", &["n2", "n3"]);
+
+    let token = writer.put_causal(b"key", b"v1", &SessionToken::new()).await.unwrap();
+    assert!(token.dependency(b"key").is_some());
+    assert_eq!(reader.get(b"key").await.unwrap(), None);
+
+    // 副本没有追上时宁可失败也不返回旧值
+    assert!(reader.get_causal(b"key", &token).await.is_err());
+
+    let catch_up = cluster.catch_up_later("n1", "n2", "key", Duration::from_millis(50));
+    let (value, token) = reader.get_causal(b"key", &token).await.unwrap();
+    catch_up.await.unwrap();
+    assert_eq!(value, Some(Bytes::from("v1")));
+
+    // 在读到的版本之上写入，覆盖而不是成为并发版本
+    cluster.sim().heal();
+    let token = reader.put_causal(b"key", b"v2", &token).await.unwrap();
+    let (value, _) = writer.get_causal(b"key", &token).await.unwrap();
+    assert_eq!(value, Some(Bytes::from("v2")));
+    assert_eq!(writer.get_versioned(b"key").await.unwrap().values.len(), 1);
+}
+
+#[tokio::test]
+async fn test_dependencies_carried_across_keys() {
+    let cluster = Cluster::simulated(SimulatedNetwork::new()).await;
+    let writer = cluster.causal("c1").await;
+    let reader = cluster.causal("c2").await;
+
+    cluster.restrict("c1", &["n1"]);
+    let token = writer.put_causal(b"post", b"hello", &SessionToken::new()).await.unwrap();
+    cluster.sim().heal();
+    let token = writer.put_causal(b"reply", b"world", &token).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    // 新会话读到 reply 后，也必须能读到它依赖的 post
+    cluster.restrict("c2", &["n2", "n3"]);
+    let (value, session) = reader.get_causal(b"reply", &SessionToken::new()).await.unwrap();
+    assert_eq!(value, Some(Bytes::from("world")));
+    assert_eq!(session.dependency(b"post"), token.dependency(b"post"));
+    assert!(reader.get_causal(b"post", &session).await.is_err());
+
+    let catch_up = cluster.catch_up_later("n1", "n3", "post", Duration::from_millis(50));
+    let (value, _) = reader.get_causal(b"post", &session).await.unwrap();
+    catch_up.await.unwrap();
+    assert_eq!(value, Some(Bytes::from("hello")));
+}
+
+#[tokio::test]
+async fn test_monotonic_reads_and_delete() {
+    let cluster = Cluster::simulated(SimulatedNetwork::new()).await;
+    let a = cluster.causal("c1").await;
+    let b = cluster.causal("c2").await;
+
+    let token = a.put_causal(b"key", b"v1", &SessionToken::new()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    cluster.restrict("c1", &["n1"]);
+    let token = a.put_causal(b"key", b"v2", &token).await.unwrap();
+
+    // 读到 v2 之后换到只能访问旧副本的协调者，不会退回 v1
+    let (value, token) = a.get_causal(b"key", &token).await.unwrap();
+    assert_eq!(value, Some(Bytes::from("v2")));
+    cluster.restrict("c2", &["n2", "n3"]);
+    assert_eq!(b.get(b"key").await.unwrap(), Some(Bytes::from("v1")));
+    assert!(b.get_causal(b"key", &token).await.is_err());
+
+    cluster.sim().heal();
+    let token = b.delete_causal(b"key", &token).await.unwrap();
+    let (value, _) = a.get_causal(b"key", &token).await.unwrap();
+    assert_eq!(value, None);
+}
+
+/// 某个副本上 `key` 的版本
+async fn stored(cluster: &Cluster, replica: &str, key: &[u8]) -> Vec<Version> {
+    match cluster.storages[replica].get(key).await.unwrap() {
+        Some(data) => serde_json::from_slice(&data).unwrap(),
+        None => Vec::new(),
+    }
+}
+
+#[tokio::test]
+async fn test_applied_dependencies_are_not_persisted() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    // R + W > N：写法定数确认的写入对任何法定数读取可见
+    let writer = cluster.coordinator("c1", 2, 2).await.with_vector_clocks(true);
+    let token = writer.put_causal(b"a", b"1", &SessionToken::new()).await.unwrap();
+    assert!(token.is_applied(b"a", token.dependency(b"a").unwrap()));
+    assert!(token.pending_dependencies_except(b"b").is_empty());
+
+    writer.put_causal(b"b", b"2", &token).await.unwrap();
+    let replica = &cluster.replicas(b"b")[0];
+    let versions = stored(&cluster, replica, b"b").await;
+    assert_eq!(versions.len(), 1);
+    assert!(versions[0].deps.is_empty());
+}
+
+#[tokio::test]
+async fn test_persisted_dependencies_are_capped() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let writer = cluster
+        .coordinator("c1", 1, 1)
+        .await
+        .with_timeout(TIMEOUT)
+        .with_vector_clocks(true)
+        .with_max_dependencies(1);
+    let mut token = SessionToken::new();
+    for key in [&b"a"[..], b"b", b"c"] {
+        token = writer.put_causal(key, b"v", &token).await.unwrap();
+    }
+
+    // 写入 c 时多出的依赖 a 先写回全部副本，c 只保存 b
+    let replica = &cluster.replicas(b"c")[0];
+    let versions = stored(&cluster, replica, b"c").await;
+    let deps: Vec<&[u8]> = versions[0].deps.keys().map(|k| k.as_ref()).collect();
+    assert_eq!(deps, vec![&b"b"[..]]);
+    for replica in cluster.replicas(b"a") {
+        assert_eq!(stored(&cluster, &replica, b"a").await.len(), 1);
+    }
+    assert!(token.is_applied(b"a", token.dependency(b"a").unwrap()));
+    assert!(!token.is_applied(b"b", token.dependency(b"b").unwrap()));
+}
+
+#[test]
+fn test_session_token_round_trip_and_merge() {
+    let mut clock = VectorClock::new();
+    clock.increment("n1");
+    let mut a = SessionToken::new();
+    a.observe(b"x", &clock);
+
+    let mut later = clock.clone();
+    later.increment("n2");
+    let mut b = SessionToken::new();
+    b.observe(b"x", &later);
+    b.observe(b"y", &clock);
+
+    a.merge(&b);
+    assert_eq!(a.len(), 2);
+    assert_eq!(a.dependency(b"x"), Some(&later));
+    assert_eq!(a.dependencies_except(b"x").len(), 1);
+
+    let decoded = SessionToken::from_bytes(&a.to_bytes().unwrap()).unwrap();
+    assert_eq!(decoded, a);
+    assert!(SessionToken::from_bytes(b"not a token").is_err());
+}
@@ -0,0 +1,159 @@
+//! 集成测试共用的集群脚手架：分布环、各节点的存储和副本服务、成员以及协调者
+#![allow(dead_code)]
+
+use coretex::{
+    config::ReplicationConfig,
+    consistency::{QuorumConsistencyManager, ReplicaServer},
+    distribution::{ConsistentHashRing, DistributionNode, DistributionStrategy, PartitionMapExchange, SharedRing},
+    membership::{InMemoryMembership, MembershipManager, NodeState},
+    messaging::{memory::InMemoryBroker, sim::SimulatedNetwork, MessageBroker},
+    storage::{InMemoryEngine, StorageEngine},
+    utils::HybridClock,
+};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const NODES: [&str; 3] = ["n1", "n2", "n3"];
+
+/// 节点之间的消息层
+#[derive(Clone)]
+pub enum Network {
+    /// 所有节点共用的内存消息总线
+    Memory(Arc<dyn MessageBroker>),
+    /// 可以注入故障和分区的模拟网络
+    Simulated(SimulatedNetwork),
+}
+
+impl Network {
+    pub fn memory() -> Self {
+        Network::Memory(Arc::new(InMemoryBroker::new("cluster")))
+    }
+
+    pub fn broker(&self, id: &str) -> Arc<dyn MessageBroker> {
+        match self {
+            Network::Memory(broker) => broker.clone(),
+            Network::Simulated(network) => Arc::new(network.broker(id)),
+        }
+    }
+}
+
+/// 同一分布环上的一组节点，各自有内存存储，部分节点运行副本服务
+pub struct Cluster {
+    pub network: Network,
+    pub ring: Arc<SharedRing>,
+    pub membership: Arc<InMemoryMembership>,
+    pub storages: HashMap<String, Arc<dyn StorageEngine>>,
+    pub servers: HashMap<String, ReplicaServer>,
+}
+
+impl Cluster {
+    /// `nodes` 组成分布环并在成员中标记为 `Active`，其中 `running` 的节点启动副本服务
+    pub async fn start(network: Network, nodes: Vec<DistributionNode>, running: &[&str]) -> Self {
+        let membership = Arc::new(InMemoryMembership::new());
+        let mut strategy = ConsistentHashRing::new();
+        let mut storages = HashMap::new();
+        for (i, node) in nodes.into_iter().enumerate() {
+            let address = SocketAddr::from(([127, 0, 0, 1], 9000 + i as u16));
+            membership.register_node_with_id(node.id.clone(), address, HashMap::new());
+            membership.update_node_state(&node.id, NodeState::Active).await.unwrap();
+            let storage: Arc<dyn StorageEngine> = Arc::new(InMemoryEngine::new(&node.id));
+            storages.insert(node.id.clone(), storage);
+            strategy.add_node(node);
+        }
+        let mut cluster = Self {
+            network,
+            ring: Arc::new(SharedRing::new(Box::new(strategy))),
+            membership,
+            storages,
+            servers: HashMap::new(),
+        };
+        for id in running {
+            cluster.start_server(id).await;
+        }
+        cluster
+    }
+
+    /// [`NODES`] 三个节点，通过内存消息总线通信，只有 `running` 中的节点启动副本服务
+    pub async fn in_memory(running: &[&str]) -> Self {
+        Self::start(Network::memory(), nodes(&NODES), running).await
+    }
+
+    /// [`NODES`] 三个节点都在模拟网络上启动副本服务
+    pub async fn simulated(network: SimulatedNetwork) -> Self {
+        Self::start(Network::Simulated(network), nodes(&NODES), &NODES).await
+    }
+
+    pub fn broker(&self, id: &str) -> Arc<dyn MessageBroker> {
+        self.network.broker(id)
+    }
+
+    /// 模拟网络，用于分区和注入故障
+    pub fn sim(&self) -> &SimulatedNetwork {
+        match &self.network {
+            Network::Simulated(network) => network,
+            Network::Memory(_) => panic!("集群没有使用模拟网络"),
+        }
+    }
+
+    pub async fn start_server(&mut self, id: &str) {
+        let server = ReplicaServer::start(id, self.storages[id].clone(), self.broker(id))
+            .await
+            .unwrap();
+        self.servers.insert(id.to_string(), server);
+    }
+
+    /// key 的三个副本，按偏好顺序
+    pub fn replicas(&self, key: &[u8]) -> Vec<String> {
+        let ring = self.ring.snapshot();
+        ring.get_replicas(key, 3).into_iter().map(|n| n.id).collect()
+    }
+
+    /// 以 `id` 为协调节点、N=3 的协调者
+    pub async fn coordinator(&self, id: &str, read_quorum: usize, write_quorum: usize) -> QuorumConsistencyManager {
+        QuorumConsistencyManager::start(id, self.ring.clone(), self.broker(id), replication(read_quorum, write_quorum))
+            .await
+            .unwrap()
+    }
+}
+
+/// 在 `broker` 上为节点启动分区映射交换，强一致模式按交换的映射划分 Raft 组
+pub async fn exchange(ring: &Arc<SharedRing>, broker: Arc<dyn MessageBroker>, replica_count: usize) -> PartitionMapExchange {
+    PartitionMapExchange::start(ring.clone(), broker, Arc::new(HybridClock::default()), replica_count)
+        .await
+        .unwrap()
+}
+
+/// 权重相同的节点
+pub fn nodes(ids: &[&str]) -> Vec<DistributionNode> {
+    ids.iter().map(|id| DistributionNode::new(*id, 100)).collect()
+}
+
+/// N=3 的复制配置，其余参数取默认值
+pub fn replication(read_quorum: usize, write_quorum: usize) -> ReplicationConfig {
+    ReplicationConfig {
+        factor: 3,
+        read_quorum,
+        write_quorum,
+        hinted_handoff: Default::default(),
+        requests: Default::default(),
+    }
+}
+
+/// 每 20ms 检查一次，最多等待 `limit`
+pub async fn eventually<F, Fut>(limit: Duration, mut check: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let deadline = tokio::time::Instant::now() + limit;
+    while tokio::time::Instant::now() < deadline {
+        if check().await {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    check().await
+}
@@ -0,0 +1,359 @@
+mod common;
+
+use common::{replication, Cluster, NODES};
+use coretex::{
+    api::{ClientApi, CoordinatorClient},
+    config::ReadRepairMode,
+    consistency::{
+        reconcile, CausalContext, Causality, ConsistencyEvent, ConsistencyManager, LastWriterWins,
+        QuorumConsistencyManager, ReplicaClient, ReplicaOp, ResolverRegistry, Stamped, VectorClock, Version,
+    },
+    error::Error,
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+impl Cluster {
+    async fn stored_versions(&self, id: &str, key: &[u8]) -> Vec<Version> {
+        match self.storages[id].get(key).await.unwrap() {
+            Some(data) => serde_json::from_slice(&data).unwrap(),
+            None => Vec::new(),
+        }
+    }
+
+    async fn versioned(&self, id: &str) -> QuorumConsistencyManager {
+        self.coordinator(id, 2, 2).await.with_timeout(TIMEOUT).with_vector_clocks(true)
+    }
+
+    async fn copies(&self, key: &[u8]) -> usize {
+        let mut copies = 0;
+        for storage in self.storages.values() {
+            if storage.get(key).await.unwrap().is_some() {
+                copies += 1;
+            }
+        }
+        copies
+    }
+}
+
+#[tokio::test]
+async fn test_quorum_write_and_read() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let a = cluster.coordinator("n1", 2, 2).await.with_timeout(TIMEOUT);
+    let b = cluster.coordinator("n2", 2, 2).await.with_timeout(TIMEOUT);
+
+    a.put(b"key", b"value").await.unwrap();
+    assert!(cluster.copies(b"key").await >= 2);
+    assert_eq!(b.get(b"key").await.unwrap().unwrap().as_ref(), b"value");
+
+    // 其余副本在后台完成写入
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(cluster.copies(b"key").await, 3);
+
+    b.delete(b"key").await.unwrap();
+    assert!(a.get(b"key").await.unwrap().is_none());
+    assert!(a.get(b"missing").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_quorum_tolerates_one_unavailable_replica() {
+    let cluster = Cluster::in_memory(&["n1", "n2"]).await;
+    let coordinator = cluster.coordinator("n1", 2, 2).await.with_timeout(TIMEOUT);
+
+    coordinator.put(b"key", b"value").await.unwrap();
+    assert_eq!(coordinator.get(b"key").await.unwrap().unwrap().as_ref(), b"value");
+    assert!(cluster.storages["n3"].get(b"key").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_quorum_not_met() {
+    let cluster = Cluster::in_memory(&["n1"]).await;
+    let coordinator = cluster.coordinator("n1", 2, 2).await.with_timeout(TIMEOUT);
+
+    match coordinator.put(b"key", b"value").await {
+        Err(Error::QuorumNotMet { required, acknowledged }) => {
+            assert_eq!(required, 2);
+            assert_eq!(acknowledged, 1);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(matches!(
+        coordinator.get(b"key").await,
+        Err(Error::QuorumNotMet { required: 2, acknowledged: 1 })
+    ));
+
+    // W=1 / R=1 时单个副本即可
+    let relaxed = cluster.coordinator("n1", 1, 1).await.with_timeout(TIMEOUT);
+    relaxed.put(b"key", b"value").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_replicas_keep_latest_timestamp() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let coordinator = cluster.coordinator("n1", 3, 3).await.with_timeout(TIMEOUT);
+    let client = ReplicaClient::start("n1", cluster.broker("n1")).await.unwrap();
+    let key = Bytes::from_static(b"key");
+    let put = |value: &'static str, timestamp: u64| ReplicaOp::Put {
+        key: key.clone(),
+        value: Bytes::from(value),
+        timestamp,
+    };
+
+    // 迟到的旧写入不覆盖之后的写入
+    coordinator.put(b"key", b"new").await.unwrap();
+    for node in NODES {
+        client.call(node, put("old", 1), Duration::from_millis(100)).await.unwrap();
+    }
+    assert_eq!(coordinator.get(b"key").await.unwrap(), Some(Bytes::from("new")));
+
+    // 删除保存为带时间戳的墓碑，同样不被更早的写入覆盖
+    coordinator.delete(b"key").await.unwrap();
+    for node in NODES {
+        client.call(node, put("old", 1), Duration::from_millis(100)).await.unwrap();
+        let stored = cluster.storages[node].get(b"key").await.unwrap();
+        assert_eq!(Stamped::from_stored(stored).value, None);
+    }
+    assert_eq!(coordinator.get(b"key").await.unwrap(), None);
+
+    // 读取取时间戳最晚的值，而不是多数副本的值
+    let latest = Stamped::from_stored(cluster.storages["n1"].get(b"key").await.unwrap()).timestamp + 1;
+    client.call("n3", put("latest", latest), Duration::from_millis(100)).await.unwrap();
+    assert_eq!(coordinator.get(b"key").await.unwrap(), Some(Bytes::from("latest")));
+}
+
+#[tokio::test]
+async fn test_quorum_larger_than_replication_factor_rejected() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let replication = replication(4, 2);
+    let result =
+        QuorumConsistencyManager::start("n1", cluster.ring.clone(), cluster.broker("n1"), replication).await;
+    assert!(matches!(result, Err(Error::Configuration(_))));
+}
+
+fn clock(entries: &[(&str, u64)]) -> VectorClock {
+    let mut clock = VectorClock::new();
+    for (node, counter) in entries {
+        clock.advance(node, *counter);
+    }
+    clock
+}
+
+#[test]
+fn test_vector_clock_causality() {
+    let a = clock(&[("n1", 1)]);
+    let b = clock(&[("n1", 2)]);
+    let c = clock(&[("n2", 1)]);
+    assert_eq!(a.compare(&b), Causality::Before);
+    assert_eq!(b.compare(&a), Causality::After);
+    assert_eq!(a.compare(&a.clone()), Causality::Equal);
+    assert_eq!(b.compare(&c), Causality::Concurrent);
+
+    let mut merged = b.clone();
+    merged.merge(&c);
+    assert_eq!(merged, clock(&[("n1", 2), ("n2", 1)]));
+    assert!(merged.descends(&b) && merged.descends(&c));
+
+    let version = |clock: VectorClock, value: &'static [u8]| Version {
+        clock,
+        value: Some(value.into()),
+        timestamp: 0,
+        deps: Default::default(),
+    };
+    let kept = reconcile(vec![version(a, b"a"), version(c, b"c"), version(b, b"b")]);
+    let values: Vec<_> = kept.iter().map(|v| v.value.clone().unwrap()).collect();
+    assert_eq!(values, vec![&b"c"[..], &b"b"[..]]);
+}
+
+#[tokio::test]
+async fn test_concurrent_writes_become_siblings() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let a = cluster.versioned("n1").await;
+    let b = cluster.versioned("n2").await;
+
+    // 两个协调者的盲写互相并发
+    a.put(b"cart", b"apple").await.unwrap();
+    b.put(b"cart", b"pear").await.unwrap();
+    let siblings = a.get_versioned(b"cart").await.unwrap();
+    assert!(siblings.is_conflict());
+    assert_eq!(siblings.values, vec![&b"apple"[..], &b"pear"[..]]);
+    assert_eq!(a.get(b"cart").await.unwrap().unwrap().as_ref(), b"apple");
+
+    // 客户端保存上下文后带着它写入合并结果
+    let context = CausalContext::from_bytes(&siblings.context.to_bytes().unwrap()).unwrap();
+    b.put_with_context(b"cart", b"apple,pear", &context).await.unwrap();
+    let merged = a.get_versioned(b"cart").await.unwrap();
+    assert_eq!(merged.values, vec![&b"apple,pear"[..]]);
+    assert!(merged.context.clock().descends(context.clock()));
+}
+
+#[tokio::test]
+async fn test_versioned_overwrite_and_delete() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let a = cluster.versioned("n1").await;
+    let b = cluster.versioned("n2").await;
+
+    a.put(b"key", b"v1").await.unwrap();
+    a.put(b"key", b"v2").await.unwrap();
+    let siblings = b.get_versioned(b"key").await.unwrap();
+    assert_eq!(siblings.values, vec![&b"v2"[..]]);
+
+    // 删除覆盖所有可见版本
+    b.put(b"key", b"v3").await.unwrap();
+    a.delete(b"key").await.unwrap();
+    let siblings = b.get_versioned(b"key").await.unwrap();
+    assert!(siblings.values.is_empty());
+    assert!(!siblings.context.clock().is_empty());
+    assert!(b.get(b"key").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_client_returns_siblings() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let a = CoordinatorClient::new(Arc::new(cluster.versioned("n1").await));
+    let b = CoordinatorClient::new(Arc::new(cluster.versioned("n2").await));
+
+    a.put(b"key", b"x").await.unwrap();
+    b.put(b"key", b"y").await.unwrap();
+    let siblings = a.get_versioned(b"key").await.unwrap();
+    assert_eq!(siblings.values.len(), 2);
+    a.put_with_context(b"key", b"xy", &siblings.context).await.unwrap();
+    assert_eq!(b.get(b"key").await.unwrap().unwrap().as_ref(), b"xy");
+}
+
+#[tokio::test]
+async fn test_conflicts_resolved_per_namespace() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let mut resolvers = ResolverRegistry::new();
+    resolvers.register("lww/", Arc::new(LastWriterWins));
+    let a = cluster.versioned("n1").await.with_resolvers(resolvers);
+    let b = cluster.versioned("n2").await;
+
+    for key in [&b"lww/key"[..], &b"raw/key"[..]] {
+        a.put(key, b"first").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(2)).await;
+        b.put(key, b"second").await.unwrap();
+    }
+
+    let resolved = a.get_versioned(b"lww/key").await.unwrap();
+    assert_eq!(resolved.values, vec![&b"second"[..]]);
+    assert_eq!(a.get(b"lww/key").await.unwrap().unwrap().as_ref(), b"second");
+    assert_eq!(a.get_versioned(b"raw/key").await.unwrap().values.len(), 2);
+
+    // 解决后的上下文覆盖两个版本，带着它写入不再产生冲突
+    a.put_with_context(b"lww/key", b"third", &resolved.context).await.unwrap();
+    assert_eq!(b.get_versioned(b"lww/key").await.unwrap().values, vec![&b"third"[..]]);
+}
+
+#[tokio::test]
+async fn test_read_repair_updates_stale_replica() {
+    let mut cluster = Cluster::in_memory(&["n1", "n2"]).await;
+    let writer = cluster.versioned("n1").await;
+    writer.put(b"key", b"value").await.unwrap();
+    assert!(cluster.stored_versions("n3", b"key").await.is_empty());
+
+    // n3 恢复后读取全部三个副本并同步修复
+    cluster.start_server("n3").await;
+    let reader = cluster
+        .coordinator("n2", 3, 2)
+        .await
+        .with_timeout(TIMEOUT)
+        .with_vector_clocks(true)
+        .with_read_repair(ReadRepairMode::Sync);
+    let mut events = reader.watch_events().await.unwrap();
+    reader.read_repair(b"key").await.unwrap();
+
+    let repaired = cluster.stored_versions("n3", b"key").await;
+    assert_eq!(repaired.len(), 1);
+    assert_eq!(repaired[0].value.as_deref(), Some(&b"value"[..]));
+    match events.next().await.unwrap() {
+        ConsistencyEvent::ReadRepair { key, repaired_value } => {
+            assert_eq!(key.as_ref(), b"key");
+            assert_eq!(repaired_value.as_ref(), b"value");
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_read_repair_without_vector_clocks_uses_timestamps() {
+    let mut cluster = Cluster::in_memory(&["n1", "n2"]).await;
+    let writer = cluster.coordinator("n1", 2, 2).await.with_timeout(TIMEOUT);
+    writer.put(b"key", b"old").await.unwrap();
+    writer.put(b"key", b"new").await.unwrap();
+    writer.put(b"gone", b"value").await.unwrap();
+    writer.delete(b"gone").await.unwrap();
+    // n3 上保存的是更早的写入
+    cluster.storages["n3"]
+        .put(b"key", &Stamped::new(1, Some(Bytes::from_static(b"old"))).encode())
+        .await
+        .unwrap();
+    cluster.storages["n3"]
+        .put(b"gone", &Stamped::new(1, Some(Bytes::from_static(b"value"))).encode())
+        .await
+        .unwrap();
+
+    cluster.start_server("n3").await;
+    let reader = cluster.coordinator("n2", 3, 2).await.with_timeout(TIMEOUT);
+    reader.read_repair(b"key").await.unwrap();
+    reader.read_repair(b"gone").await.unwrap();
+
+    let stored = |key: &'static [u8]| {
+        let storage = cluster.storages["n3"].clone();
+        async move { Stamped::from_stored(storage.get(key).await.unwrap()) }
+    };
+    assert_eq!(stored(b"key").await.value, Some(Bytes::from_static(b"new")));
+    let tombstone = stored(b"gone").await;
+    assert!(tombstone.timestamp > 1);
+    assert_eq!(tombstone.value, None);
+}
+
+#[tokio::test]
+async fn test_read_repair_modes() {
+    let mut cluster = Cluster::in_memory(&["n1", "n2"]).await;
+    let writer = cluster.versioned("n1").await;
+    writer.put(b"background", b"v").await.unwrap();
+    writer.put(b"disabled", b"v").await.unwrap();
+    cluster.start_server("n3").await;
+
+    let background = cluster.versioned("n2").await.with_read_repair(ReadRepairMode::Background);
+    let disabled = cluster.versioned("n2").await.with_read_repair(ReadRepairMode::Disabled);
+    assert!(background.get(b"background").await.unwrap().is_some());
+    assert!(disabled.get(b"disabled").await.unwrap().is_some());
+
+    // 后台修复会等待读取返回时尚未响应的副本
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(cluster.stored_versions("n3", b"background").await.len(), 1);
+    assert!(cluster.stored_versions("n3", b"disabled").await.is_empty());
+}
+
+#[tokio::test]
+async fn test_read_repair_writes_back_resolved_version() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let a = cluster.versioned("n1").await;
+    let b = cluster.versioned("n2").await;
+    a.put(b"key", b"first").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(2)).await;
+    b.put(b"key", b"second").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    for id in NODES {
+        assert_eq!(cluster.stored_versions(id, b"key").await.len(), 2);
+    }
+
+    let resolving = cluster
+        .coordinator("n3", 3, 2)
+        .await
+        .with_timeout(TIMEOUT)
+        .with_vector_clocks(true)
+        .with_resolvers(ResolverRegistry::new().with_default(Arc::new(LastWriterWins)))
+        .with_read_repair(ReadRepairMode::Sync);
+    assert_eq!(resolving.get(b"key").await.unwrap().unwrap().as_ref(), b"second");
+    for id in NODES {
+        let stored = cluster.stored_versions(id, b"key").await;
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].value.as_deref(), Some(&b"second"[..]));
+    }
+}
@@ -0,0 +1,195 @@
+mod common;
+
+use common::{Cluster, NODES};
+use coretex::{
+    api::{ClientApi, CoordinatorClient},
+    config::ReadRepairMode,
+    consistency::{ConsistencyManager, Crdt, CrdtOp, GCounter, LwwMap, LwwRegister, OrSet, PnCounter},
+    error::Error,
+};
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+impl Cluster {
+    async fn stored(&self, id: &str, key: &[u8]) -> Option<Crdt> {
+        let data = self.storages[id].get(key).await.unwrap()?;
+        Crdt::decode(&data)
+    }
+}
+
+fn bytes(s: &str) -> Bytes {
+    Bytes::from(s.to_string())
+}
+
+#[test]
+fn test_counters_merge_idempotently() {
+    let mut a = GCounter::default();
+    let mut b = GCounter::default();
+    a.increment("n1", 2);
+    b.increment("n2", 5);
+    b.increment("n1", 1);
+    let mut ab = a.clone();
+    ab.merge(&b);
+    let mut ba = b.clone();
+    ba.merge(&a);
+    assert_eq!(ab, ba);
+    assert_eq!(ab.value(), 7);
+    ab.merge(&b);
+    assert_eq!(ab.value(), 7);
+
+    let mut pn = PnCounter::default();
+    pn.increment("n1", 10);
+    pn.increment("n2", -4);
+    let mut other = PnCounter::default();
+    other.increment("n2", -1);
+    pn.merge(&other);
+    assert_eq!(pn.value(), 6);
+}
+
+#[test]
+fn test_or_set_add_wins_over_concurrent_remove() {
+    let mut base = OrSet::default();
+    base.add("n1", bytes("x"));
+    base.add("n1", bytes("y"));
+
+    // 一个副本删除 x，另一个副本并发地再次添加 x 并删除 y
+    let mut a = base.clone();
+    a.remove(b"x");
+    let mut b = base.clone();
+    b.add("n2", bytes("x"));
+    b.remove(b"y");
+
+    let mut ab = a.clone();
+    ab.merge(&b);
+    let mut ba = b.clone();
+    ba.merge(&a);
+    assert_eq!(ab, ba);
+    assert_eq!(ab.elements(), vec![bytes("x")]);
+
+    // 已观察到的删除不会被旧状态复活
+    ab.merge(&base);
+    assert!(!ab.contains(b"y"));
+
+    let encoded = Crdt::OrSet(ab.clone()).encode().unwrap();
+    assert_eq!(Crdt::decode(&encoded), Some(Crdt::OrSet(ab)));
+    assert_eq!(Crdt::decode(b"plain value"), None);
+}
+
+#[test]
+fn test_lww_types_keep_latest_write() {
+    let mut a = LwwRegister::default();
+    a.set(Some(bytes("old")), 10, "n1");
+    let mut b = LwwRegister::default();
+    b.set(Some(bytes("new")), 20, "n2");
+    let mut ab = a.clone();
+    ab.merge(&b);
+    b.merge(&a);
+    assert_eq!(ab, b);
+    assert_eq!(ab.value(), Some(&bytes("new")));
+
+    let mut m = LwwMap::default();
+    m.put(bytes("f1"), bytes("v1"), 10, "n1");
+    m.put(bytes("f2"), bytes("v2"), 10, "n1");
+    let mut other = LwwMap::default();
+    other.remove(bytes("f1"), 20, "n2");
+    other.put(bytes("f2"), bytes("stale"), 5, "n2");
+    m.merge(&other);
+    assert_eq!(m.get(b"f1"), None);
+    assert_eq!(m.entries(), vec![(bytes("f2"), bytes("v2"))]);
+
+    let err = Crdt::apply(Some(Crdt::LwwMap(m)), "n1", CrdtOp::SetAdd(bytes("x")), 0).unwrap_err();
+    assert!(err.to_string().contains("LwwMap"));
+}
+
+#[tokio::test]
+async fn test_concurrent_increments_are_not_lost() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let c1 = cluster.coordinator("c1", 2, 2).await.with_timeout(TIMEOUT);
+    let c2 = cluster.coordinator("c2", 2, 2).await.with_timeout(TIMEOUT);
+    let a: Arc<dyn ClientApi> = Arc::new(CoordinatorClient::new(Arc::new(c1)));
+    let b: Arc<dyn ClientApi> = Arc::new(CoordinatorClient::new(Arc::new(c2)));
+
+    let tasks: Vec<_> = (0..20)
+        .map(|i| {
+            let client = if i % 2 == 0 { a.clone() } else { b.clone() };
+            tokio::spawn(async move { client.increment(b"counter", if i % 5 == 0 { -1 } else { 2 }).await })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+    let Some(Crdt::PnCounter(counter)) = a.get_crdt(b"counter").await.unwrap() else {
+        panic!("没有读到计数器");
+    };
+    assert_eq!(counter.value(), 16 * 2 - 4);
+    assert_eq!(b.increment(b"counter", 0).await.unwrap(), 28);
+}
+
+#[tokio::test]
+async fn test_set_operations_through_client() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let coordinator = cluster.coordinator("c1", 2, 2).await.with_timeout(TIMEOUT);
+    let client = CoordinatorClient::new(Arc::new(coordinator));
+
+    client.add_element(b"tags", b"red").await.unwrap();
+    client.add_element(b"tags", b"blue").await.unwrap();
+    client.remove_element(b"tags", b"red").await.unwrap();
+    let Some(Crdt::OrSet(set)) = client.get_crdt(b"tags").await.unwrap() else {
+        panic!("没有读到集合");
+    };
+    assert_eq!(set.elements(), vec![bytes("blue")]);
+
+    let op = CrdtOp::MapPut {
+        field: bytes("name"),
+        value: bytes("coretex"),
+    };
+    client.update_crdt(b"profile", op).await.unwrap();
+    assert!(client.increment(b"profile", 1).await.is_err());
+    assert_eq!(client.get_crdt(b"missing").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_read_repair_merges_stale_replica() {
+    let mut cluster = Cluster::in_memory(&["n1", "n2"]).await;
+    let coordinator = cluster
+        .coordinator("c1", 3, 2)
+        .await
+        .with_timeout(TIMEOUT)
+        .with_read_repair(ReadRepairMode::Sync);
+    // 更新由第一个副本执行，超时的副本可能已执行过，不会换到下一个副本
+    let key = (0..100)
+        .map(|i| format!("counter-{}", i))
+        .find(|key| cluster.replicas(key.as_bytes())[0] != "n3")
+        .unwrap();
+    let key = key.as_bytes();
+    coordinator.update_crdt(key, CrdtOp::GCounterIncrement(5)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(cluster.stored("n3", key).await, None);
+
+    cluster.start_server("n3").await;
+    let merged = coordinator.get_crdt(key).await.unwrap().unwrap();
+    assert_eq!(cluster.stored("n3", key).await, Some(merged));
+}
+
+#[tokio::test]
+async fn test_update_does_not_move_on_after_a_timeout() {
+    let cluster = Cluster::in_memory(&["n1", "n2"]).await;
+    let coordinator = cluster.coordinator("c1", 2, 2).await.with_timeout(TIMEOUT);
+    let key = (0..100)
+        .map(|i| format!("counter-{}", i))
+        .find(|key| cluster.replicas(key.as_bytes())[0] == "n3")
+        .unwrap();
+
+    // 超时的副本可能已经执行了更新，换一个副本执行会重复计数
+    let err = coordinator
+        .update_crdt(key.as_bytes(), CrdtOp::GCounterIncrement(5))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Timeout(_)));
+    for id in ["n1", "n2"] {
+        assert_eq!(cluster.stored(id, key.as_bytes()).await, None);
+    }
+}
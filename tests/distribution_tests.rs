@@ -0,0 +1,471 @@
+mod common;
+
+use common::{eventually, replication};
+use coretex::{
+    config::{DistributionConfig, DistributionKind},
+    consistency::{ConsistencyManager, QuorumConsistencyManager, ReplicaServer},
+    distribution::{
+        self, ConsistentHashRing, DistributionNode, DistributionStrategy, JumpHashing, KeyRange,
+        RangePartitioner, RangeSplitService, RendezvousHashing, SharedRing, SplitPolicy,
+    },
+    membership::{Node, NodeState},
+    messaging::{memory::InMemoryBroker, MessageBroker},
+    storage::{InMemoryEngine, StorageEngine},
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_replicas_are_distinct_nodes() {
+    let mut ring = ConsistentHashRing::new();
+    ring.add_node(DistributionNode::new("a", 100));
+    ring.add_node(DistributionNode::new("b", 100));
+
+    // Asking for more replicas than nodes returns each node once
+    let replicas = ring.get_replicas(b"key", 5);
+    assert_eq!(replicas.len(), 2);
+    let ids: HashSet<_> = replicas.iter().map(|n| n.id.clone()).collect();
+    assert_eq!(ids.len(), 2);
+
+    // The primary is always the first replica
+    let primary = ring.get_primary(b"key").unwrap();
+    assert_eq!(replicas[0].id, primary.id);
+}
+
+#[test]
+fn test_replicas_spread_across_zones() {
+    let mut ring = ConsistentHashRing::new();
+    for zone in ["z1", "z2", "z3"] {
+        for i in 0..3 {
+            let node = DistributionNode::new(format!("{}-n{}", zone, i), 100).with_zone(zone);
+            ring.add_node(node);
+        }
+    }
+
+    for i in 0..100 {
+        let key = format!("key-{}", i);
+        let replicas = ring.get_replicas(key.as_bytes(), 3);
+        let zones: HashSet<_> = replicas.iter().map(|n| n.zone.clone()).collect();
+        assert_eq!(zones.len(), 3, "replicas for {} share a zone", key);
+    }
+}
+
+#[test]
+fn test_replicas_fall_back_to_racks_then_nodes() {
+    let mut ring = ConsistentHashRing::new();
+    // Two zones, zone z1 has two racks
+    ring.add_node(DistributionNode::new("a", 100).with_zone("z1").with_rack("r1"));
+    ring.add_node(DistributionNode::new("b", 100).with_zone("z1").with_rack("r1"));
+    ring.add_node(DistributionNode::new("c", 100).with_zone("z1").with_rack("r2"));
+    ring.add_node(DistributionNode::new("d", 100).with_zone("z2").with_rack("r1"));
+
+    for i in 0..50 {
+        let key = format!("key-{}", i);
+        let replicas = ring.get_replicas(key.as_bytes(), 3);
+        assert_eq!(replicas.len(), 3);
+        // Both zones are covered
+        let zones: HashSet<_> = replicas.iter().map(|n| n.zone.clone()).collect();
+        assert_eq!(zones.len(), 2);
+        // Zone z1 contributes one node from each of its racks
+        let racks: HashSet<_> = replicas
+            .iter()
+            .filter(|n| n.zone.as_deref() == Some("z1"))
+            .map(|n| n.rack.clone())
+            .collect();
+        assert_eq!(racks.len(), 2);
+
+        // Asking for all four nodes still fills in the remaining node
+        assert_eq!(ring.get_replicas(key.as_bytes(), 4).len(), 4);
+    }
+}
+
+#[test]
+fn test_distribution_node_from_membership_metadata() {
+    let node = Node {
+        id: "n1".to_string(),
+        address: "127.0.0.1:9000".parse().unwrap(),
+        state: NodeState::Active,
+        metadata: HashMap::from([
+            ("zone".to_string(), "us-east-1a".to_string()),
+            ("rack".to_string(), "r7".to_string()),
+            ("weight".to_string(), "200".to_string()),
+        ]),
+    };
+    let dist = DistributionNode::from(&node);
+    assert_eq!(dist.id, "n1");
+    assert_eq!(dist.weight, 200);
+    assert_eq!(dist.zone.as_deref(), Some("us-east-1a"));
+    assert_eq!(dist.rack.as_deref(), Some("r7"));
+}
+
+#[test]
+fn test_alternative_strategies_are_drop_in() {
+    let strategies: Vec<Box<dyn DistributionStrategy>> = vec![
+        Box::new(RendezvousHashing::new()),
+        Box::new(JumpHashing::new()),
+    ];
+    for mut strategy in strategies {
+        assert!(strategy.get_primary(b"key").is_none());
+        for i in 0..4 {
+            strategy.add_node(DistributionNode::new(format!("n{}", i), 100));
+        }
+        let primary = strategy.get_primary(b"key").unwrap();
+        let replicas = strategy.get_replicas(b"key", 6);
+        assert_eq!(replicas.len(), 4);
+        assert_eq!(replicas[0].id, primary.id);
+        let ids: HashSet<_> = replicas.iter().map(|n| n.id.clone()).collect();
+        assert_eq!(ids.len(), 4);
+
+        // Removing the most recently added node keeps other keys in place
+        let mut stable = Vec::new();
+        for i in 0..100 {
+            let key = format!("key-{}", i);
+            let owner = strategy.get_primary(key.as_bytes()).unwrap();
+            if owner.id != "n3" {
+                stable.push((key, owner.id));
+            }
+        }
+        strategy.remove_node("n3");
+        assert_eq!(strategy.all_nodes().len(), 3);
+        for (key, owner) in stable {
+            assert_eq!(strategy.get_primary(key.as_bytes()).unwrap().id, owner);
+        }
+    }
+}
+
+#[test]
+fn test_jump_placement_independent_of_membership_history() {
+    let mut forward = JumpHashing::new();
+    for id in ["a", "b", "c", "d", "e"] {
+        forward.add_node(DistributionNode::new(id, 100));
+    }
+    forward.remove_node("b");
+
+    // Same final membership reached through a different insert/remove order
+    let mut shuffled = JumpHashing::new();
+    for id in ["e", "b", "c", "x", "a", "d"] {
+        shuffled.add_node(DistributionNode::new(id, 100));
+    }
+    shuffled.remove_node("x");
+    shuffled.remove_node("b");
+
+    let ids = |s: &JumpHashing| s.all_nodes().into_iter().map(|n| n.id).collect::<Vec<_>>();
+    assert_eq!(ids(&forward), ids(&shuffled));
+    for i in 0..200 {
+        let key = format!("key-{}", i);
+        let replicas = |s: &JumpHashing| {
+            s.get_replicas(key.as_bytes(), 3).into_iter().map(|n| n.id).collect::<Vec<_>>()
+        };
+        assert_eq!(replicas(&forward), replicas(&shuffled));
+    }
+    assert_eq!(forward.ownership(3), shuffled.ownership(3));
+}
+
+#[test]
+fn test_strategy_selected_from_config() {
+    let config: DistributionConfig = toml::from_str("strategy = \"Rendezvous\"").unwrap();
+    assert_eq!(config.strategy, DistributionKind::Rendezvous);
+    assert_eq!(config.partitions, distribution::DEFAULT_PARTITIONS);
+
+    let mut strategy = distribution::from_config(&config);
+    strategy.add_node(DistributionNode::new("a", 100));
+    assert_eq!(strategy.get_primary(b"key").unwrap().id, "a");
+
+    // A config file without a [distribution] section falls back to the hash ring
+    let default = DistributionConfig::default();
+    assert_eq!(default.strategy, DistributionKind::ConsistentHash);
+}
+
+#[test]
+fn test_range_partitioner_split_and_route_scan() {
+    let mut ranges = RangePartitioner::new();
+    ranges.add_node(DistributionNode::new("a", 100));
+    assert_eq!(ranges.ranges(), vec![(KeyRange::full(), "a".to_string())]);
+
+    // A joining node gets a range: the key space is split at its midpoint
+    ranges.add_node(DistributionNode::new("b", 100));
+    let layout = ranges.ranges();
+    assert_eq!(layout.len(), 2);
+    assert_eq!(layout[0].0, KeyRange::new(&b""[..], Some(vec![0x7f].into())));
+    assert_ne!(layout[0].1, layout[1].1);
+
+    assert!(ranges.split_range(b"m"));
+    assert!(!ranges.split_range(b"m"));
+
+    // Adjacent keys on the same side of a boundary share a primary
+    let left = ranges.get_primary(b"apple").unwrap();
+    assert_eq!(ranges.get_primary(b"banana").unwrap().id, left.id);
+    let right = ranges.get_primary(&[0x90]).unwrap();
+    assert_ne!(left.id, right.id);
+
+    // A scan crossing the boundary is routed to both owners with clipped sub-ranges
+    let routes = ranges.route_scan(b"x", Some(&[0x90]));
+    assert_eq!(routes.len(), 2);
+    assert_eq!(routes[0].range, KeyRange::new(&b"x"[..], Some(vec![0x7f].into())));
+    assert_eq!(routes[0].node.id, left.id);
+    assert_eq!(routes[1].range, KeyRange::new(vec![0x7f], Some(vec![0x90].into())));
+    assert_eq!(routes[1].node.id, right.id);
+
+    // A scan inside one range touches a single node
+    assert_eq!(ranges.route_scan(b"a", Some(b"c")).len(), 1);
+}
+
+#[test]
+fn test_range_partitioner_gives_every_node_a_range() {
+    let mut ranges = RangePartitioner::new();
+    for id in ["a", "b", "c", "d", "e"] {
+        ranges.add_node(DistributionNode::new(id, 100));
+    }
+    let owners: HashSet<_> = ranges.ranges().into_iter().map(|(_, owner)| owner).collect();
+    assert_eq!(owners.len(), 5);
+
+    // Every node computes the same layout from the same membership
+    let mut other = RangePartitioner::new();
+    for id in ["a", "b", "c", "d", "e"] {
+        other.add_node(DistributionNode::new(id, 100));
+    }
+    assert_eq!(other.ranges(), ranges.ranges());
+}
+
+#[test]
+fn test_shared_ring_records_load_and_routes_scans() {
+    let policy = SplitPolicy {
+        max_bytes: 1000,
+        max_load: u64::MAX,
+        merge_ratio: 0.0,
+    };
+    let mut strategy = RangePartitioner::with_policy(policy);
+    strategy.add_node(DistributionNode::new("a", 100));
+    let ring = SharedRing::new(Box::new(strategy));
+
+    for i in 0..100 {
+        ring.record_write(format!("key-{:03}", i).as_bytes(), 20);
+    }
+    // Recording load does not publish a new ring
+    assert_eq!(ring.epoch(), 0);
+    assert!(ring.split_and_merge());
+    assert_eq!(ring.epoch(), 1);
+    assert!(ring.snapshot().route_scan(b"key-000", Some(b"key-100")).len() > 1);
+
+    // Hash strategies route a scan to every node
+    let mut hashed = ConsistentHashRing::new();
+    hashed.add_node(DistributionNode::new("a", 100));
+    hashed.add_node(DistributionNode::new("b", 100));
+    let hashed = SharedRing::new(Box::new(hashed));
+    hashed.record_write(b"key", 10);
+    assert!(!hashed.split_and_merge());
+    let routes = hashed.snapshot().route_scan(b"a", Some(b"b"));
+    assert_eq!(routes.len(), 2);
+    assert!(routes.iter().all(|r| r.range == KeyRange::new(&b"a"[..], Some("b".into()))));
+}
+
+fn range_ring(ids: &[&str], policy: SplitPolicy) -> Arc<SharedRing> {
+    let mut strategy = RangePartitioner::with_policy(policy);
+    for id in ids {
+        strategy.add_node(DistributionNode::new(*id, 100));
+    }
+    Arc::new(SharedRing::new(Box::new(strategy)))
+}
+
+#[tokio::test]
+async fn test_range_split_service_keeps_layouts_consistent() {
+    let policy = SplitPolicy {
+        max_bytes: 1000,
+        max_load: u64::MAX,
+        merge_ratio: 0.0,
+    };
+    let broker: Arc<dyn MessageBroker> = Arc::new(InMemoryBroker::new("cluster"));
+    let rings = [range_ring(&["a", "b"], policy.clone()), range_ring(&["a", "b"], policy)];
+    let mut services = Vec::new();
+    for (id, ring) in ["a", "b"].iter().zip(&rings) {
+        let service = RangeSplitService::start(*id, ring.clone(), broker.clone(), Duration::from_millis(50))
+            .await
+            .unwrap();
+        services.push(service);
+    }
+
+    // Writes coordinated by "b" are forwarded to "a", which splits the range and shares the layout
+    for i in 0..100 {
+        rings[1].record_write(format!("key-{:03}", i).as_bytes(), 20);
+    }
+    let consistent = eventually(Duration::from_secs(5), || async {
+        let a = rings[0].snapshot().ownership(1);
+        let b = rings[1].snapshot().ownership(1);
+        a.len() > 2 && a == b
+    })
+    .await;
+    assert!(consistent);
+}
+
+#[tokio::test]
+async fn test_quorum_writes_record_range_load() {
+    let broker: Arc<dyn MessageBroker> = Arc::new(InMemoryBroker::new("cluster"));
+    let ring = range_ring(&["a", "b"], SplitPolicy::default());
+    let mut servers = Vec::new();
+    for id in ["a", "b"] {
+        let storage: Arc<dyn StorageEngine> = Arc::new(InMemoryEngine::new(id));
+        servers.push(ReplicaServer::start(id, storage, broker.clone()).await.unwrap());
+    }
+    let mut config = replication(1, 1);
+    config.factor = 2;
+    let coordinator = QuorumConsistencyManager::start("a", ring.clone(), broker.clone(), config)
+        .await
+        .unwrap();
+
+    for i in 0..10 {
+        coordinator.put(format!("key-{}", i).as_bytes(), b"value").await.unwrap();
+    }
+    coordinator.get(b"key-0").await.unwrap();
+    let usage = ring.snapshot().strategy.drain_usage();
+    assert_eq!(usage.iter().map(|u| u.load).sum::<u64>(), 11);
+    assert_eq!(usage.iter().map(|u| u.bytes).sum::<u64>(), 10 * (5 + 5));
+}
+
+#[test]
+fn test_range_partitioner_splits_by_size_and_merges() {
+    let policy = SplitPolicy {
+        max_bytes: 1000,
+        max_load: u64::MAX,
+        merge_ratio: 0.5,
+    };
+    let mut ranges = RangePartitioner::with_policy(policy);
+    ranges.add_node(DistributionNode::new("a", 100));
+
+    for i in 0..100 {
+        ranges.record_write(format!("key-{:03}", i).as_bytes(), 20);
+    }
+    assert!(ranges.split_and_merge());
+    assert_eq!(ranges.ranges().len(), 2);
+    let left = ranges.stats(b"key-000").unwrap();
+    let right = ranges.stats(b"key-099").unwrap();
+    assert_eq!(left.bytes + right.bytes, 2000);
+
+    // Both halves are well above the merge threshold, nothing changes
+    assert!(!ranges.split_and_merge());
+
+    // Merging explicitly restores a single range
+    assert!(ranges.merge_with_next(b""));
+    assert_eq!(ranges.ranges(), vec![(KeyRange::full(), "a".to_string())]);
+}
+
+#[test]
+fn test_range_partitioner_refuses_to_merge_ranges_of_different_owners() {
+    let mut ranges = RangePartitioner::new();
+    ranges.add_node(DistributionNode::new("a", 100));
+    ranges.split_range(b"m");
+    ranges.add_node(DistributionNode::new("b", 100));
+    let before = ranges.ranges();
+    assert_ne!(before[0].1, before[1].1);
+
+    // Merging would silently move the right range to another node
+    assert!(!ranges.merge_with_next(b""));
+    assert_eq!(ranges.ranges(), before);
+}
+
+#[test]
+fn test_range_partitioner_reassigns_ranges_of_removed_node() {
+    let mut ranges = RangePartitioner::new();
+    ranges.add_node(DistributionNode::new("a", 100));
+    for at in ["d", "h", "p"] {
+        ranges.split_range(at.as_bytes());
+    }
+    ranges.add_node(DistributionNode::new("b", 100));
+    assert!(ranges.ranges().iter().any(|(_, owner)| owner == "b"));
+
+    ranges.remove_node("a");
+    assert!(ranges.ranges().iter().all(|(_, owner)| owner == "b"));
+    assert_eq!(ranges.get_replicas(b"x", 3).len(), 1);
+}
+
+#[tokio::test]
+async fn test_cluster_ring_follows_membership_events() {
+    use coretex::distribution::{ClusterRing, NodeAction, RingPolicy};
+    use coretex::membership::{InMemoryMembership, MembershipManager};
+    use std::sync::Arc;
+
+    let membership = Arc::new(InMemoryMembership::new());
+    let existing = membership
+        .register_node("127.0.0.1:9000".parse().unwrap(), HashMap::new())
+        .await
+        .unwrap();
+    membership.update_node_state(&existing, NodeState::Active).await.unwrap();
+
+    let policy = RingPolicy {
+        down: NodeAction::Exclude,
+        ..RingPolicy::default()
+    };
+    let ring = ClusterRing::start(membership.clone(), Box::new(ConsistentHashRing::new()), policy)
+        .await
+        .unwrap();
+    // Nodes that were already Active are in the initial ring
+    let initial = ring.snapshot();
+    assert_eq!(initial.epoch, 1);
+    assert!(initial.contains(&existing));
+
+    // A joining node only enters the ring once it becomes Active
+    let metadata = HashMap::from([("zone".to_string(), "z2".to_string())]);
+    let joined = membership
+        .register_node("127.0.0.1:9001".parse().unwrap(), metadata)
+        .await
+        .unwrap();
+    membership.update_node_state(&joined, NodeState::Active).await.unwrap();
+    ring.wait_for_epoch(2).await.unwrap();
+    let snapshot = ring.snapshot();
+    assert_eq!(snapshot.epoch, 2);
+    let node = snapshot.strategy.all_nodes().into_iter().find(|n| n.id == joined).unwrap();
+    assert_eq!(node.zone.as_deref(), Some("z2"));
+
+    // Older snapshots stay valid for requests that are still routing with them
+    assert!(!initial.contains(&joined));
+
+    membership.update_node_state(&joined, NodeState::Down).await.unwrap();
+    ring.wait_for_epoch(3).await.unwrap();
+    assert!(!ring.snapshot().contains(&joined));
+
+    membership.unregister_node(&existing).await.unwrap();
+    ring.wait_for_epoch(4).await.unwrap();
+    assert!(ring.snapshot().strategy.all_nodes().is_empty());
+}
+
+#[tokio::test]
+async fn test_shared_ring_copy_on_write_snapshots() {
+    use coretex::distribution::SharedRing;
+    use std::sync::Arc;
+
+    let shared = Arc::new(SharedRing::new(Box::new(ConsistentHashRing::new())));
+    assert_eq!(shared.add_node(DistributionNode::new("a", 100)), 1);
+    let before = shared.snapshot();
+
+    // Readers route against a snapshot while writers publish new versions concurrently
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let shared = shared.clone();
+            tokio::spawn(async move {
+                for i in 0..1000 {
+                    let snapshot = shared.snapshot();
+                    let key = format!("key-{}", i);
+                    let replicas = snapshot.get_replicas(key.as_bytes(), 2);
+                    assert_eq!(replicas[0], snapshot.get_primary(key.as_bytes()).unwrap());
+                    assert_eq!(replicas.len(), snapshot.strategy.all_nodes().len().min(2));
+                }
+            })
+        })
+        .collect();
+    for i in 0..10 {
+        shared.add_node(DistributionNode::new(format!("n{}", i), 100));
+    }
+    for reader in readers {
+        reader.await.unwrap();
+    }
+
+    assert_eq!(shared.epoch(), 11);
+    assert_eq!(before.epoch, 1);
+    assert_eq!(before.strategy.all_nodes().len(), 1);
+    assert_eq!(shared.snapshot().strategy.all_nodes().len(), 11);
+
+    // Removing an unknown node does not publish a new version
+    assert_eq!(shared.remove_node("missing"), 11);
+    assert_eq!(shared.remove_node("n0"), 12);
+    shared.wait_for_epoch(12).await.unwrap();
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use common::{Cluster, NODES};
+use coretex::consistency::{ConsistencyEvent, ConsistencyManager, EventBus, EventFilter};
+use bytes::Bytes;
+use futures::StreamExt;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_millis(200);
+
+fn committed(key: &str, value: &str) -> ConsistencyEvent {
+    ConsistencyEvent::WriteCommitted {
+        key: Bytes::from(key.to_string()),
+        value: Bytes::from(value.to_string()),
+    }
+}
+
+async fn next(events: &mut futures::stream::BoxStream<'static, ConsistencyEvent>) -> ConsistencyEvent {
+    tokio::time::timeout(Duration::from_secs(1), events.next())
+        .await
+        .expect("等待事件超时")
+        .expect("事件流已结束")
+}
+
+#[tokio::test]
+async fn test_subscribers_receive_filtered_commits() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let coordinator = cluster.coordinator("c1", 3, 3).await.with_timeout(TIMEOUT);
+    let mut all = coordinator.watch_events().await.unwrap();
+    let mut users = coordinator.subscribe_events(EventFilter::prefix("user/")).await.unwrap();
+    let mut carts = coordinator
+        .subscribe_events(EventFilter::prefix("cart/").with_prefix("order/"))
+        .await
+        .unwrap();
+
+    coordinator.put(b"user/1", b"alice").await.unwrap();
+    coordinator.put(b"cart/1", b"apple").await.unwrap();
+    coordinator.put(b"order/1", b"paid").await.unwrap();
+
+    for expected in [committed("user/1", "alice"), committed("cart/1", "apple"), committed("order/1", "paid")] {
+        assert_eq!(format!("{:?}", next(&mut all).await), format!("{:?}", expected));
+    }
+    assert_eq!(format!("{:?}", next(&mut users).await), format!("{:?}", committed("user/1", "alice")));
+    assert_eq!(format!("{:?}", next(&mut carts).await), format!("{:?}", committed("cart/1", "apple")));
+    assert_eq!(format!("{:?}", next(&mut carts).await), format!("{:?}", committed("order/1", "paid")));
+
+    // 过滤掉的事件不会出现在之后的流中
+    coordinator.put(b"user/2", b"bob").await.unwrap();
+    assert_eq!(format!("{:?}", next(&mut users).await), format!("{:?}", committed("user/2", "bob")));
+}
+
+#[tokio::test]
+async fn test_slow_subscriber_is_told_how_many_events_it_missed() {
+    let bus = EventBus::new(2);
+    let mut slow = bus.subscribe(EventFilter::all());
+    let mut filtered = bus.subscribe(EventFilter::prefix("b"));
+    for i in 0..4 {
+        bus.publish(committed(&format!("a{}", i), "v"));
+    }
+    bus.publish(committed("b1", "v"));
+    assert_eq!(bus.subscriber_count(), 2);
+
+    assert!(matches!(next(&mut slow).await, ConsistencyEvent::Lagged { missed: 3 }));
+    assert_eq!(format!("{:?}", next(&mut slow).await), format!("{:?}", committed("a3", "v")));
+    assert_eq!(format!("{:?}", next(&mut slow).await), format!("{:?}", committed("b1", "v")));
+
+    // 不符合过滤条件的事件不占用订阅者的队列
+    assert_eq!(format!("{:?}", next(&mut filtered).await), format!("{:?}", committed("b1", "v")));
+    for i in 2..5 {
+        bus.publish(committed(&format!("b{}", i), "v"));
+    }
+    assert!(matches!(next(&mut filtered).await, ConsistencyEvent::Lagged { missed: 1 }));
+    assert_eq!(format!("{:?}", next(&mut filtered).await), format!("{:?}", committed("b3", "v")));
+    assert_eq!(format!("{:?}", next(&mut filtered).await), format!("{:?}", committed("b4", "v")));
+
+    drop(slow);
+    assert_eq!(bus.subscriber_count(), 1);
+}
+
+#[tokio::test]
+async fn test_concurrent_writes_are_reported_as_conflicts() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let a = cluster.coordinator("c1", 3, 3).await.with_timeout(TIMEOUT).with_vector_clocks(true);
+    let b = cluster.coordinator("c2", 3, 3).await.with_timeout(TIMEOUT).with_vector_clocks(true);
+    let mut events = a.subscribe_events(EventFilter::prefix("cart/")).await.unwrap();
+
+    // 两个协调者都不带上下文写入，产生并发版本
+    a.put(b"cart/1", b"apple").await.unwrap();
+    b.put(b"cart/1", b"pear").await.unwrap();
+    assert_eq!(format!("{:?}", next(&mut events).await), format!("{:?}", committed("cart/1", "apple")));
+
+    let siblings = a.get_versioned(b"cart/1").await.unwrap();
+    assert_eq!(siblings.values.len(), 2);
+    match next(&mut events).await {
+        ConsistencyEvent::WriteConflict { key, old, new } => {
+            assert_eq!(key.as_ref(), b"cart/1");
+            let mut values = vec![old, new];
+            values.sort();
+            assert_eq!(values, vec![Bytes::from("apple"), Bytes::from("pear")]);
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+
+    // 覆盖全部版本后不再有冲突
+    a.put_with_context(b"cart/1", b"both", &siblings.context).await.unwrap();
+    assert_eq!(format!("{:?}", next(&mut events).await), format!("{:?}", committed("cart/1", "both")));
+    assert_eq!(a.get(b"cart/1").await.unwrap().as_deref(), Some(&b"both"[..]));
+    assert!(tokio::time::timeout(Duration::from_millis(100), events.next()).await.is_err());
+}
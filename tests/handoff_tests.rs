@@ -0,0 +1,223 @@
+mod common;
+
+use common::{eventually, nodes, replication, Cluster, Network};
+use coretex::{
+    config::HintedHandoffConfig,
+    consistency::{
+        ConsistencyLevel, ConsistencyManager, HandoffService, HintStore, NodeHealth, QuorumConsistencyManager, ReplicaClient,
+        ReplicaOp, Stamped, HINT_PREFIX,
+    },
+    error::Error,
+    membership::{MembershipManager, NodeState},
+    storage::{InMemoryEngine, StorageEngine},
+};
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::Duration;
+
+const IDS: [&str; 4] = ["n1", "n2", "n3", "n4"];
+
+impl Cluster {
+    /// 使用宽松法定数的协调者，`handoff` 决定是否向后续节点发送提示
+    async fn sloppy_coordinator(&self, write_quorum: usize, handoff: bool) -> QuorumConsistencyManager {
+        let mut replication = replication(2, write_quorum);
+        replication.hinted_handoff = HintedHandoffConfig {
+            enabled: handoff,
+            ..Default::default()
+        };
+        let health = NodeHealth::start(self.membership.clone()).await.unwrap();
+        QuorumConsistencyManager::start("coordinator", self.ring.clone(), self.broker("coordinator"), replication)
+            .await
+            .unwrap()
+            .with_timeout(Duration::from_millis(100))
+            .with_health(Arc::new(health))
+    }
+
+    /// key 的副本以及不在副本中的另一个节点
+    fn placement(&self, key: &[u8]) -> (Vec<String>, String) {
+        let replicas = self.replicas(key);
+        let spare = IDS.iter().find(|id| !replicas.iter().any(|r| r == *id)).unwrap().to_string();
+        (replicas, spare)
+    }
+
+    async fn set_state(&self, id: &str, state: NodeState) {
+        self.membership.update_node_state(id, state).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// 四个节点、三副本的集群，全部节点启动副本服务
+async fn cluster() -> Cluster {
+    Cluster::start(Network::memory(), nodes(&IDS), &IDS).await
+}
+
+#[tokio::test]
+async fn test_write_to_down_replica_is_hinted_and_replayed() {
+    let cluster = cluster().await;
+    let manager = cluster.sloppy_coordinator(3, true).await;
+    let (replicas, spare) = cluster.placement(b"k");
+    let down = &replicas[0];
+    cluster.set_state(down, NodeState::Down).await;
+
+    // 提示写入的确认计入写法定数
+    manager.put(b"k", b"v1").await.unwrap();
+    let hints = cluster.servers[&spare].hints();
+    assert_eq!(hints.len(), 1);
+    assert_eq!(hints.hints_for(down).await.unwrap().len(), 1);
+    assert_eq!(cluster.storages[down].get(b"k").await.unwrap(), None);
+    assert_eq!(cluster.storages[&spare].get(b"k").await.unwrap(), None);
+
+    let client = Arc::new(ReplicaClient::start(spare.clone(), cluster.broker(&spare)).await.unwrap());
+    let _handoff = HandoffService::start(
+        hints.clone(),
+        client,
+        cluster.membership.clone(),
+        Duration::from_millis(100),
+        Duration::from_secs(60),
+    )
+    .await
+    .unwrap();
+    cluster.set_state(down, NodeState::Active).await;
+
+    let stored = cluster.storages[down].get(b"k").await.unwrap();
+    assert_eq!(Stamped::from_stored(stored).value, Some(Bytes::from_static(b"v1")));
+    assert!(hints.is_empty());
+    assert!(hints.hints_for(down).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_hinted_writes_do_not_satisfy_all() {
+    let cluster = cluster().await;
+    let manager = cluster.sloppy_coordinator(2, true).await;
+    let (replicas, _) = cluster.placement(b"k");
+    cluster.set_state(&replicas[0], NodeState::Down).await;
+
+    // 提示只计入法定数，ALL 需要偏好列表中的每个副本都确认
+    let err = manager.put_with_level(b"k", b"v1", ConsistencyLevel::All).await.unwrap_err();
+    assert!(matches!(
+        err,
+        Error::QuorumNotMet {
+            required: 3,
+            acknowledged: 2
+        }
+    ));
+    let written = manager.put_with_level(b"k", b"v2", ConsistencyLevel::Quorum).await.unwrap();
+    assert!(written.acknowledged >= 2);
+}
+
+#[tokio::test]
+async fn test_failed_replay_is_retried_periodically() {
+    let mut cluster = cluster().await;
+    let manager = cluster.sloppy_coordinator(3, true).await;
+    let (replicas, spare) = cluster.placement(b"k");
+    let down = replicas[0].clone();
+    cluster.set_state(&down, NodeState::Down).await;
+    manager.put(b"k", b"v1").await.unwrap();
+
+    // 原副本恢复为 Active 时副本服务还没有启动，第一次回放失败
+    cluster.servers.remove(&down);
+    let hints = cluster.servers[&spare].hints();
+    let client = Arc::new(ReplicaClient::start(spare.clone(), cluster.broker(&spare)).await.unwrap());
+    let _handoff = HandoffService::start(
+        hints.clone(),
+        client,
+        cluster.membership.clone(),
+        Duration::from_millis(50),
+        Duration::from_millis(100),
+    )
+    .await
+    .unwrap();
+    cluster.set_state(&down, NodeState::Active).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(hints.len(), 1);
+
+    cluster.start_server(&down).await;
+    let storage = cluster.storages[&down].clone();
+    let replayed = eventually(Duration::from_secs(2), || async {
+        let stored = storage.get(b"k").await.unwrap();
+        Stamped::from_stored(stored).value == Some(Bytes::from_static(b"v1"))
+    })
+    .await;
+    assert!(replayed);
+    assert!(hints.is_empty());
+}
+
+#[tokio::test]
+async fn test_user_keys_in_hint_namespace_are_rejected() {
+    let cluster = cluster().await;
+    let manager = cluster.coordinator("coordinator", 2, 2).await;
+    let key = [HINT_PREFIX, b"n1/forged"].concat();
+
+    assert!(matches!(manager.put(&key, b"v").await, Err(Error::Consistency(_))));
+    assert!(matches!(manager.get(&key).await, Err(Error::Consistency(_))));
+    assert!(matches!(manager.delete(&key).await, Err(Error::Consistency(_))));
+    for id in IDS {
+        assert_eq!(cluster.storages[id].get(&key).await.unwrap(), None);
+    }
+}
+
+#[tokio::test]
+async fn test_write_without_handoff_misses_quorum() {
+    let cluster = cluster().await;
+    let manager = cluster.sloppy_coordinator(3, false).await;
+    let (replicas, spare) = cluster.placement(b"k");
+    cluster.set_state(&replicas[0], NodeState::Down).await;
+
+    let err = manager.put(b"k", b"v1").await.unwrap_err();
+    assert!(matches!(
+        err,
+        Error::QuorumNotMet {
+            required: 3,
+            acknowledged: 2
+        }
+    ));
+    assert!(cluster.servers[&spare].hints().is_empty());
+
+    // W=2 时其余健康副本仍然满足法定数，读取跳过宕机副本
+    let manager = cluster.sloppy_coordinator(2, false).await;
+    manager.put(b"k", b"v2").await.unwrap();
+    assert_eq!(manager.get(b"k").await.unwrap(), Some(Bytes::from_static(b"v2")));
+}
+
+#[tokio::test]
+async fn test_hint_store_limits_and_expiry() {
+    let storage: Arc<dyn StorageEngine> = Arc::new(InMemoryEngine::new("n1"));
+    let op = |value: &'static [u8]| ReplicaOp::Put {
+        key: Bytes::from_static(b"k"),
+        value: Bytes::from_static(value),
+        timestamp: 1,
+    };
+
+    let config = HintedHandoffConfig {
+        enabled: true,
+        max_hints: 2,
+        hint_ttl_secs: 3600,
+        ..Default::default()
+    };
+    let store = HintStore::open(storage.clone(), &config).await.unwrap();
+    store.add("n2", op(b"a")).await.unwrap();
+    store.add("n3", op(b"b")).await.unwrap();
+    assert!(matches!(store.add("n2", op(b"c")).await, Err(Error::Storage(_))));
+    assert_eq!(store.len(), 2);
+
+    // 重新打开时从存储中恢复计数，同一目标的提示按写入顺序返回
+    let store = HintStore::open(storage.clone(), &config).await.unwrap();
+    assert_eq!(store.len(), 2);
+    let hints = store.hints_for("n2").await.unwrap();
+    assert_eq!(hints.len(), 1);
+    assert_eq!(hints[0].1.target, "n2");
+
+    let expired = HintStore::open(
+        storage,
+        &HintedHandoffConfig {
+            hint_ttl_secs: 0,
+            ..config
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(expired.purge_expired().await.unwrap(), 2);
+    assert!(expired.is_empty());
+    expired.add("n2", op(b"d")).await.unwrap();
+    assert_eq!(expired.len(), 1);
+}
@@ -0,0 +1,221 @@
+mod common;
+
+use common::{exchange, nodes, replication, NODES};
+use coretex::{
+    config::RaftConfig,
+    consistency::{
+        history::{Call, History, HistoryClient, Outcome},
+        ConsistencyManager, QuorumConsistencyManager, ReplicaServer, StrongConsistencyManager,
+    },
+    distribution::{ConsistentHashRing, DistributionStrategy, SharedRing},
+    messaging::sim::SimulatedNetwork,
+    storage::{InMemoryEngine, StorageEngine},
+};
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn ring() -> Arc<SharedRing> {
+    let mut strategy = ConsistentHashRing::new();
+    for node in nodes(&NODES) {
+        strategy.add_node(node);
+    }
+    Arc::new(SharedRing::new(Box::new(strategy)))
+}
+
+fn put(v: &str) -> Call {
+    Call::Put(Bytes::from(v.to_string()))
+}
+
+fn read(v: Option<&str>) -> Outcome {
+    Outcome::Ok(v.map(|v| Bytes::from(v.to_string())))
+}
+
+/// 每个客户端依次对若干 key 读写，写入的值全局唯一
+async fn workload(client: Arc<HistoryClient>, rounds: usize) {
+    for i in 0..rounds {
+        let key = format!("k{}", i % 3);
+        let _ = match i % 4 {
+            0 | 2 => client.put(key.as_bytes(), format!("{}-{}", client.name(), i).as_bytes()).await,
+            1 => client.get(key.as_bytes()).await.map(|_| ()),
+            _ => client.get(format!("k{}", (i + 1) % 3).as_bytes()).await.map(|_| ()),
+        };
+    }
+}
+
+/// R=1、W=1 并启用向量时钟的协调者
+async fn weak_coordinator(network: &SimulatedNetwork, ring: &Arc<SharedRing>, id: &str) -> Arc<dyn ConsistencyManager> {
+    let broker = Arc::new(network.broker(id));
+    let manager = QuorumConsistencyManager::start(id, ring.clone(), broker, replication(1, 1))
+        .await
+        .unwrap()
+        .with_vector_clocks(true)
+        .with_timeout(Duration::from_millis(300));
+    Arc::new(manager)
+}
+
+#[test]
+fn test_linearizability_checker() {
+    // 顺序的写入和读取
+    let history = History::new();
+    let w = history.invoke("a", b"k", put("1"));
+    history.complete(w, Outcome::Ok(None));
+    let r = history.invoke("b", b"k", Call::Get);
+    history.complete(r, read(Some("1")));
+    assert!(history.check_linearizable().is_empty());
+
+    // 与写入并发的读取可以读到旧值或新值，但新值被读到后不能再读到旧值
+    let w = history.invoke("a", b"k", put("2"));
+    let r1 = history.invoke("b", b"k", Call::Get);
+    history.complete(r1, read(Some("1")));
+    let r2 = history.invoke("c", b"k", Call::Get);
+    history.complete(r2, read(Some("2")));
+    assert!(history.check_linearizable().is_empty());
+    let r3 = history.invoke("b", b"k", Call::Get);
+    history.complete(r3, read(Some("1")));
+    history.complete(w, Outcome::Ok(None));
+    let anomalies = history.check_linearizable();
+    assert_eq!(anomalies.len(), 1);
+    assert_eq!(anomalies[0].key, Bytes::from("k"));
+    assert!(anomalies[0].to_string().contains("b get -> 1"));
+
+    // 结果未知的写入可以在之后任意时间生效，也可以从未生效
+    let history = History::new();
+    let w = history.invoke("a", b"k", put("x"));
+    history.complete(w, Outcome::Unknown);
+    let r = history.invoke("b", b"k", Call::Get);
+    history.complete(r, read(None));
+    let r = history.invoke("b", b"k", Call::Get);
+    history.complete(r, read(Some("x")));
+    let d = history.invoke("a", b"k", Call::Delete);
+    history.complete(d, Outcome::Ok(None));
+    assert!(history.check_linearizable().is_empty());
+    let r = history.invoke("c", b"k", Call::Get);
+    history.complete(r, read(Some("x")));
+    assert_eq!(history.check_linearizable().len(), 1);
+}
+
+#[test]
+fn test_causal_checker_finds_session_violations() {
+    // 读不到自己之前的写入
+    let history = History::new();
+    let w = history.invoke("a", b"k", put("1"));
+    history.complete(w, Outcome::Ok(None));
+    let r = history.invoke("a", b"k", Call::Get);
+    history.complete(r, read(None));
+    let anomalies = history.check_causal();
+    assert_eq!(anomalies.len(), 1);
+    assert_eq!(anomalies[0].operations.len(), 2);
+
+    // 单调读：读到 2 之后又读到被 2 覆盖的 1
+    let history = History::new();
+    let w1 = history.invoke("a", b"k", put("1"));
+    history.complete(w1, Outcome::Ok(None));
+    let w2 = history.invoke("a", b"k", put("2"));
+    history.complete(w2, Outcome::Ok(None));
+    let r = history.invoke("b", b"k", Call::Get);
+    history.complete(r, read(Some("2")));
+    assert!(history.check_causal().is_empty());
+    let r = history.invoke("b", b"k", Call::Get);
+    history.complete(r, read(Some("1")));
+    assert_eq!(history.check_causal().len(), 1);
+
+    // 并发写入互不影响，读到任意一个都满足因果一致；跨 key 的依赖同样要满足
+    let history = History::new();
+    let x = history.invoke("a", b"x", put("a1"));
+    history.complete(x, Outcome::Ok(None));
+    let y = history.invoke("b", b"x", put("b1"));
+    history.complete(y, Outcome::Ok(None));
+    let r = history.invoke("c", b"x", Call::Get);
+    history.complete(r, read(Some("a1")));
+    assert!(history.check_causal().is_empty());
+    let post = history.invoke("a", b"y", put("reply"));
+    history.complete(post, Outcome::Ok(None));
+    let r = history.invoke("c", b"y", Call::Get);
+    history.complete(r, read(Some("reply")));
+    let r = history.invoke("c", b"x", Call::Get);
+    history.complete(r, read(None));
+    assert_eq!(history.check_causal().len(), 1);
+}
+
+#[tokio::test]
+async fn test_strong_mode_histories_are_linearizable() {
+    let network = SimulatedNetwork::new();
+    let ring = ring();
+    let config = RaftConfig {
+        election_timeout_min_ms: 100,
+        election_timeout_max_ms: 200,
+        heartbeat_interval_ms: 30,
+        request_timeout_ms: 2000,
+        ..Default::default()
+    };
+    let mut managers: Vec<Arc<dyn ConsistencyManager>> = Vec::new();
+    let mut exchanges = Vec::new();
+    for id in NODES {
+        let storage: Arc<dyn StorageEngine> = Arc::new(InMemoryEngine::new(id));
+        let maps = exchange(&ring, Arc::new(network.broker(id)), 3).await;
+        let manager = StrongConsistencyManager::start(
+            id,
+            maps.subscribe(),
+            storage,
+            Arc::new(network.broker(id)),
+            config.clone(),
+        )
+        .await
+        .unwrap();
+        managers.push(Arc::new(manager));
+        exchanges.push(maps);
+    }
+
+    let history = History::new();
+    let tasks: Vec<_> = (0..6)
+        .map(|i| {
+            let client = Arc::new(history.client(&format!("c{}", i), managers[i % 3].clone()));
+            tokio::spawn(workload(client, 12))
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(history.len(), 72);
+    let anomalies = history.check_linearizable();
+    assert!(anomalies.is_empty(), "{}", anomalies[0]);
+    assert!(history.check_causal().is_empty());
+}
+
+#[tokio::test]
+async fn test_session_tokens_preserve_causality_on_weak_quorums() {
+    let network = SimulatedNetwork::new();
+    let ring = ring();
+    let mut servers = Vec::new();
+    for id in NODES {
+        let storage: Arc<dyn StorageEngine> = Arc::new(InMemoryEngine::new(id));
+        servers.push(ReplicaServer::start(id, storage, Arc::new(network.broker(id))).await.unwrap());
+    }
+    let writer = weak_coordinator(&network, &ring, "w").await;
+    let reader = weak_coordinator(&network, &ring, "r").await;
+    // 写入只到达 n1，读取只能访问 n2
+    network.block("w", "n2");
+    network.block("w", "n3");
+    network.block("r", "n1");
+    network.block("r", "n3");
+
+    let history = History::new();
+    let plain_writer = history.client("plain", writer.clone());
+    let plain_reader = history.client("plain", reader.clone());
+    plain_writer.put(b"k", b"v1").await.unwrap();
+    assert_eq!(plain_reader.get(b"k").await.unwrap(), None);
+    assert_eq!(history.check_causal().len(), 1);
+
+    // 带会话令牌的读取等到依赖的版本到达后才返回，超时则结果未知
+    let history = History::new();
+    let session = history.causal_client("session", writer);
+    session.put(b"k", b"v2").await.unwrap();
+    let session = session.with_manager(reader);
+    assert!(session.get(b"k").await.is_err());
+    // 恢复连通后读取能从 n1 得到依赖的版本
+    network.heal();
+    assert_eq!(session.get(b"k").await.unwrap().as_deref(), Some(&b"v2"[..]));
+    assert!(history.check_causal().is_empty());
+    drop(servers);
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use common::replication;
+use coretex::{
+    consistency::{ConsistencyManager, LastWriterWins, QuorumConsistencyManager, ReplicaServer, ResolverRegistry},
+    distribution::{ConsistentHashRing, DistributionNode, DistributionStrategy, SharedRing},
+    error::Error,
+    messaging::{memory::InMemoryBroker, ClockedBroker, MessageBroker},
+    storage::{InMemoryEngine, StorageEngine},
+    utils::{HybridClock, HybridTimestamp, PhysicalClock},
+};
+use futures::StreamExt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MAX_OFFSET: Duration = Duration::from_millis(500);
+
+/// 可以手动调整的物理时间
+fn manual_clock(start: u64) -> (Arc<AtomicU64>, PhysicalClock) {
+    let now = Arc::new(AtomicU64::new(start));
+    let physical = now.clone();
+    (now, Arc::new(move || physical.load(Ordering::SeqCst)))
+}
+
+/// 相对墙上时间偏移 `offset_ms` 的物理时间
+fn skewed_clock(offset_ms: i64) -> Arc<HybridClock> {
+    let physical: PhysicalClock = Arc::new(move || {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as i64;
+        (now + offset_ms) as u64
+    });
+    Arc::new(HybridClock::with_physical_clock(MAX_OFFSET, physical))
+}
+
+#[test]
+fn test_timestamps_strictly_increase() {
+    let (now, physical) = manual_clock(1_000);
+    let clock = HybridClock::with_physical_clock(MAX_OFFSET, physical);
+
+    let a = clock.now();
+    let b = clock.now();
+    assert_eq!(a, HybridTimestamp::new(1_000, 0));
+    assert_eq!(b, HybridTimestamp::new(1_000, 1));
+
+    // 物理时间回拨时仍然递增
+    now.store(900, Ordering::SeqCst);
+    let c = clock.now();
+    assert!(c > b);
+    assert_eq!(c.physical_ms(), 1_000);
+
+    now.store(2_000, Ordering::SeqCst);
+    assert_eq!(clock.now(), HybridTimestamp::new(2_000, 0));
+    assert_eq!(HybridTimestamp::from_bytes(c.to_bytes()), c);
+}
+
+#[test]
+fn test_update_follows_remote_within_bound() {
+    let (_, physical) = manual_clock(1_000);
+    let clock = HybridClock::with_physical_clock(MAX_OFFSET, physical);
+
+    let remote = HybridTimestamp::new(1_300, 5);
+    let received = clock.update(remote).unwrap();
+    assert!(received > remote);
+    assert!(clock.now() > received);
+
+    // 落后的远端时间戳不会让时钟后退
+    let before = clock.last();
+    assert!(clock.update(HybridTimestamp::new(10, 0)).unwrap() > before);
+}
+
+#[test]
+fn test_skewed_remote_rejected() {
+    let (_, physical) = manual_clock(1_000);
+    let clock = HybridClock::with_physical_clock(MAX_OFFSET, physical);
+    let before = clock.now();
+
+    let err = clock.update(HybridTimestamp::new(1_000 + 60_000, 0)).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::ClockSkew {
+            offset_ms: 60_000,
+            max_offset_ms: 500
+        }
+    ));
+    assert_eq!(clock.skew_rejections(), 1);
+    assert_eq!(clock.last(), before);
+}
+
+#[tokio::test]
+async fn test_clocked_broker_ticks_on_receive_and_drops_skewed() {
+    let network: Arc<dyn MessageBroker> = Arc::new(InMemoryBroker::new("cluster"));
+    let ahead = skewed_clock(300);
+    let local = skewed_clock(0);
+    let broken = skewed_clock(60_000);
+    let sender = ClockedBroker::new(network.clone(), ahead.clone());
+    let faulty = ClockedBroker::new(network.clone(), broken);
+    let receiver = ClockedBroker::new(network, local.clone());
+
+    let mut messages = receiver.subscribe("topic").await.unwrap();
+    faulty.publish("topic", b"from the future".to_vec()).await.unwrap();
+    sender.publish("topic", b"hello".to_vec()).await.unwrap();
+
+    let msg = messages.next().await.unwrap().unwrap();
+    assert_eq!(msg.data.as_ref(), b"hello");
+    assert!(local.last() > ahead.last());
+    assert_eq!(local.skew_rejections(), 1);
+}
+
+#[tokio::test]
+async fn test_last_writer_wins_respects_causality_across_skew() {
+    let network: Arc<dyn MessageBroker> = Arc::new(InMemoryBroker::new("cluster"));
+    let mut strategy = ConsistentHashRing::new();
+    let mut servers = Vec::new();
+    for id in ["n1", "n2", "n3"] {
+        strategy.add_node(DistributionNode::new(id, 100));
+        let storage: Arc<dyn StorageEngine> = Arc::new(InMemoryEngine::new(id));
+        let broker = Arc::new(ClockedBroker::new(network.clone(), skewed_clock(0)));
+        servers.push(ReplicaServer::start(id, storage, broker).await.unwrap());
+    }
+    let ring = Arc::new(SharedRing::new(Box::new(strategy)));
+    let replication = replication(3, 3);
+    let coordinator = |id: &'static str, clock: Arc<HybridClock>| {
+        let broker = Arc::new(ClockedBroker::new(network.clone(), clock.clone()));
+        let ring = ring.clone();
+        let replication = replication.clone();
+        async move {
+            let mut resolvers = ResolverRegistry::new();
+            resolvers.register("", Arc::new(LastWriterWins));
+            QuorumConsistencyManager::start(id, ring, broker, replication)
+                .await
+                .unwrap()
+                .with_vector_clocks(true)
+                .with_resolvers(resolvers)
+                .with_clock(clock)
+        }
+    };
+    // a 的物理时钟快 400ms，b 的准确
+    let a = coordinator("a", skewed_clock(400)).await;
+    let b = coordinator("b", skewed_clock(0)).await;
+
+    a.put(b"key", b"first").await.unwrap();
+    // b 读取后再写入，因果上晚于 a 的写入，尽管 b 的物理时钟更慢
+    assert_eq!(b.get(b"key").await.unwrap().unwrap().as_ref(), b"first");
+    b.put(b"key", b"second").await.unwrap();
+    assert_eq!(a.get(b"key").await.unwrap().unwrap().as_ref(), b"second");
+}
@@ -0,0 +1,228 @@
+mod common;
+
+use bytes::Bytes;
+use common::{nodes, Cluster, NODES};
+use coretex::{
+    config::{DistributionConfig, HotKeyConfig, HotKeyMitigation, ReadRepairMode},
+    consistency::{ConsistencyManager, Stamped},
+    distribution::{HotKeyCache, HotKeyDetector},
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn config(mitigation: HotKeyMitigation) -> HotKeyConfig {
+    HotKeyConfig {
+        sample_every: 1,
+        window_ms: 60_000,
+        threshold: 1.0,
+        capacity: 8,
+        mitigation,
+        cache_ttl_ms: 60_000,
+    }
+}
+
+fn detect(detector: &HotKeyDetector, key: &[u8]) {
+    // 阈值 1 次/秒、窗口 60 秒，即窗口内 60 次请求
+    for _ in 0..60 {
+        detector.record(key);
+    }
+}
+
+#[test]
+fn test_detects_hot_key_and_reports_stats() {
+    let detector = HotKeyDetector::new(config(HotKeyMitigation::None));
+    for i in 0..20 {
+        detector.record(format!("cold-{}", i).as_bytes());
+    }
+    assert!(!detector.record(b"hot"));
+    detect(&detector, b"hot");
+    assert!(detector.is_hot(b"hot"));
+    assert!(!detector.is_hot(b"cold-0"));
+
+    let stats = detector.stats();
+    assert_eq!(stats.requests, 81);
+    assert_eq!(stats.sampled, 81);
+    assert_eq!(stats.hot_keys.len(), 1);
+    assert_eq!(stats.hot_keys[0].key.as_ref(), b"hot");
+    assert!(stats.hot_keys[0].requests_per_sec >= 1.0);
+}
+
+#[test]
+fn test_sampling_and_bounded_candidates() {
+    let detector = HotKeyDetector::new(HotKeyConfig {
+        sample_every: 4,
+        capacity: 2,
+        ..config(HotKeyMitigation::None)
+    });
+    // 大量不同的冷 key 挤占候选位置时热点仍能被识别
+    for i in 0..400 {
+        detector.record(b"hot");
+        detector.record(format!("cold-{}", i).as_bytes());
+    }
+    let stats = detector.stats();
+    assert_eq!(stats.requests, 800);
+    assert_eq!(stats.sampled, 200);
+    assert!(detector.is_hot(b"hot"));
+}
+
+#[test]
+fn test_hot_key_cools_down_after_quiet_window() {
+    let detector = HotKeyDetector::new(config(HotKeyMitigation::None));
+    detect(&detector, b"hot");
+    assert!(detector.is_hot(b"hot"));
+
+    // 刚结束的窗口里仍然很热
+    detector.rotate();
+    assert!(detector.is_hot(b"hot"));
+
+    // 一个没有请求的窗口之后冷却
+    detector.rotate();
+    assert!(!detector.is_hot(b"hot"));
+    assert!(detector.stats().hot_keys.is_empty());
+}
+
+#[test]
+fn test_spread_reads_across_replicas() {
+    let replicas = nodes(&NODES);
+
+    let detector = HotKeyDetector::new(config(HotKeyMitigation::SpreadReads));
+    detect(&detector, b"hot");
+    let chosen: HashSet<String> = (0..3)
+        .map(|_| detector.read_replica(b"hot", &replicas).unwrap().id)
+        .collect();
+    assert_eq!(chosen.len(), 3);
+    for _ in 0..3 {
+        assert_eq!(detector.read_replica(b"cold", &replicas).unwrap().id, "n1");
+    }
+
+    // 只检测时总是读主节点
+    let detector = HotKeyDetector::new(config(HotKeyMitigation::None));
+    detect(&detector, b"hot");
+    for _ in 0..3 {
+        assert_eq!(detector.read_replica(b"hot", &replicas).unwrap().id, "n1");
+    }
+}
+
+#[tokio::test]
+async fn test_cache_evicts_expired_then_oldest_entries() {
+    let cache = HotKeyCache::new(Duration::from_millis(20), 2);
+    cache.insert(b"a", Some(Bytes::from_static(b"1")));
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    cache.insert(b"b", Some(Bytes::from_static(b"2")));
+    // 已满时先清理过期的 a
+    cache.insert(b"c", Some(Bytes::from_static(b"3")));
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get(b"a"), None);
+
+    // 都未过期时淘汰最早缓存的 b
+    cache.insert(b"d", None);
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get(b"b"), None);
+    assert_eq!(cache.get(b"c"), Some(Some(Bytes::from_static(b"3"))));
+    assert_eq!(cache.get(b"d"), Some(None));
+
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    cache.purge_expired();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn test_hot_key_config() {
+    let config: DistributionConfig = toml::from_str(
+        r#"
+        [hot_keys]
+        mitigation = "Cache"
+        threshold = 500.0
+        "#,
+    )
+    .unwrap();
+    let hot_keys = config.hot_keys.unwrap();
+    assert_eq!(hot_keys.mitigation, HotKeyMitigation::Cache);
+    assert_eq!(hot_keys.threshold, 500.0);
+    assert_eq!(hot_keys.sample_every, 16);
+    assert_eq!(hot_keys.cache_ttl_ms, 100);
+}
+
+/// 三个副本的集群，各副本上 `key` 的值为其节点 id（时间戳相同）
+async fn cluster(key: &[u8]) -> Cluster {
+    let cluster = Cluster::in_memory(&NODES).await;
+    for (id, storage) in &cluster.storages {
+        let value = Stamped::new(1, Some(Bytes::from(id.clone()))).encode();
+        storage.put(key, &value).await.unwrap();
+    }
+    cluster
+}
+
+#[tokio::test]
+async fn test_coordinator_spreads_hot_reads() {
+    let cluster = cluster(b"hot").await;
+    let detector = Arc::new(HotKeyDetector::new(config(HotKeyMitigation::SpreadReads)));
+    // 关闭读修复，保留各副本不同的值以区分响应来自哪个副本
+    let manager = cluster
+        .coordinator("n1", 1, 1)
+        .await
+        .with_read_repair(ReadRepairMode::Disabled)
+        .with_hot_keys(detector.clone());
+
+    // 冷 key 的读取发给所有副本，先到的响应不一定来自哪个副本；热点后只发给轮到的一个副本
+    for _ in 0..60 {
+        manager.get(b"hot").await.unwrap();
+    }
+    assert!(detector.is_hot(b"hot"));
+    let mut served = HashSet::new();
+    for _ in 0..6 {
+        served.insert(manager.get(b"hot").await.unwrap().unwrap());
+    }
+    assert_eq!(served.len(), 3);
+    assert_eq!(detector.stats().requests, 66);
+}
+
+#[tokio::test]
+async fn test_coordinator_caches_hot_reads() {
+    let cluster = cluster(b"hot").await;
+    let detector = Arc::new(HotKeyDetector::new(config(HotKeyMitigation::Cache)));
+    let manager = cluster.coordinator("n1", 1, 1).await.with_hot_keys(detector.clone());
+
+    for _ in 0..60 {
+        manager.get(b"hot").await.unwrap();
+    }
+    let cached = manager.get(b"hot").await.unwrap();
+    for _ in 0..10 {
+        assert_eq!(manager.get(b"hot").await.unwrap(), cached);
+    }
+    // 缓存命中同样计入检测
+    assert_eq!(detector.stats().requests, 71);
+
+    // 本协调者的写入使缓存失效
+    manager.put(b"hot", b"new").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(manager.get(b"hot").await.unwrap(), Some(Bytes::from("new")));
+}
+
+#[tokio::test]
+async fn test_coordinator_cache_expires() {
+    let cluster = cluster(b"hot").await;
+    let config = HotKeyConfig {
+        cache_ttl_ms: 20,
+        ..config(HotKeyMitigation::Cache)
+    };
+    let manager = cluster
+        .coordinator("n1", 3, 1)
+        .await
+        .with_hot_keys(Arc::new(HotKeyDetector::new(config)));
+    for _ in 0..60 {
+        manager.get(b"hot").await.unwrap();
+    }
+    let cached = manager.get(b"hot").await.unwrap();
+
+    // 绕过本协调者的写入在缓存有效期内不可见
+    let newer = Stamped::new(2, Some(Bytes::from_static(b"v2"))).encode();
+    for storage in cluster.storages.values() {
+        storage.put(b"hot", &newer).await.unwrap();
+    }
+    assert_eq!(manager.get(b"hot").await.unwrap(), cached);
+
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert_eq!(manager.get(b"hot").await.unwrap(), Some(Bytes::from_static(b"v2")));
+}
@@ -1,10 +1,12 @@
 use coretex::{
     storage::{StorageEngine, InMemoryEngine},
     membership::{MembershipManager, InMemoryMembership, NodeState},
-    messaging::{MessageBroker, memory::InMemoryBroker},
-    consistency::{ConsistencyManager, DummyConsistencyManager},
+    messaging::{MessageBroker, TcpBroker, memory::InMemoryBroker},
+    consistency::{ConsistencyManager, DummyConsistencyManager, ReplicaClient, ReplicaOp, ReplicaReply, ReplicaServer, Stamped},
 };
-use std::{collections::HashMap, net::SocketAddr};
+use bytes::Bytes;
+use futures::StreamExt;
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 
 #[tokio::test]
 async fn test_storage_engine_basic_operations() {
@@ -78,4 +80,54 @@ async fn test_consistency_manager() {
     
     consistency.delete(key).await.unwrap();
     consistency.read_repair(key).await.unwrap();
-}
\ No newline at end of file
+}
+/// 两个节点各自在本地端口上启动 TCP broker，并在共享的成员列表中登记实际地址
+async fn tcp_brokers() -> (TcpBroker, TcpBroker) {
+    let membership = Arc::new(InMemoryMembership::new());
+    let any: SocketAddr = "127.0.0.1:0".parse().unwrap();
+    let a = TcpBroker::start("node-a", any, membership.clone()).await.unwrap();
+    let b = TcpBroker::start("node-b", any, membership.clone()).await.unwrap();
+    membership.register_node_with_id("node-a", a.local_addr(), HashMap::new());
+    membership.register_node_with_id("node-b", b.local_addr(), HashMap::new());
+    (a, b)
+}
+
+#[tokio::test]
+async fn test_tcp_broker_delivers_between_nodes() {
+    let (a, b) = tcp_brokers().await;
+    let mut remote = b.subscribe("events").await.unwrap();
+    let mut local = a.subscribe("events").await.unwrap();
+
+    a.publish("events", b"hello".to_vec()).await.unwrap();
+
+    for stream in [&mut remote, &mut local] {
+        let msg = tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(msg.data.as_ref(), b"hello");
+        assert_eq!(msg.sender.as_deref(), Some("node-a"));
+    }
+}
+
+#[tokio::test]
+async fn test_replica_calls_over_tcp_broker() {
+    let (a, b) = tcp_brokers().await;
+    let storage: Arc<dyn StorageEngine> = Arc::new(InMemoryEngine::new("node-b"));
+    let _server = ReplicaServer::start("node-b", storage.clone(), Arc::new(b)).await.unwrap();
+    let client = ReplicaClient::start("node-a", Arc::new(a)).await.unwrap();
+    let timeout = Duration::from_secs(5);
+
+    let put = ReplicaOp::Put {
+        key: Bytes::from_static(b"k"),
+        value: Bytes::from_static(b"v"),
+        timestamp: 1,
+    };
+    client.call("node-b", put, timeout).await.unwrap();
+    let stored = Stamped::from_stored(storage.get(b"k").await.unwrap());
+    assert_eq!(stored.value, Some(Bytes::from_static(b"v")));
+
+    let reply = client.call("node-b", ReplicaOp::Get { key: Bytes::from_static(b"k") }, timeout).await.unwrap();
+    assert!(matches!(reply, ReplicaReply::Value(value) if value == stored));
+}
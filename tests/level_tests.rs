@@ -0,0 +1,151 @@
+mod common;
+
+use common::{Cluster, Network};
+use coretex::{
+    api::{ClientApi, CoordinatorClient},
+    consistency::{ConsistencyLevel, ConsistencyManager, QuorumConsistencyManager},
+    distribution::DistributionNode,
+    error::Error,
+    messaging::sim::SimulatedNetwork,
+};
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 两个可用区各两个节点
+const NODES: [(&str, &str); 4] = [("n1", "a"), ("n2", "a"), ("n3", "b"), ("n4", "b")];
+
+impl Cluster {
+    async fn zoned() -> Self {
+        let nodes = NODES
+            .into_iter()
+            .map(|(id, zone)| DistributionNode::new(id, 100).with_zone(zone))
+            .collect();
+        let ids: Vec<_> = NODES.into_iter().map(|(id, _)| id).collect();
+        Cluster::start(Network::Simulated(SimulatedNetwork::new()), nodes, &ids).await
+    }
+
+    /// 配置的 R=W=1，各测试通过级别覆盖
+    async fn tunable(&self, id: &str, vector_clocks: bool) -> QuorumConsistencyManager {
+        self.coordinator(id, 1, 1)
+            .await
+            .with_timeout(Duration::from_millis(200))
+            .with_vector_clocks(vector_clocks)
+    }
+
+    /// 副本中包含 `local` 全部节点的一个 key
+    fn key_replicated_on(&self, local: &[&str]) -> String {
+        (0..1000)
+            .map(|i| format!("key-{}", i))
+            .find(|key| {
+                let replicas = self.replicas(key.as_bytes());
+                local.iter().all(|id| replicas.iter().any(|r| r == id))
+            })
+            .unwrap()
+    }
+}
+
+#[tokio::test]
+async fn test_levels_report_acknowledged_replicas() {
+    let cluster = Cluster::zoned().await;
+    let client = CoordinatorClient::new(Arc::new(cluster.tunable("n1", false).await));
+
+    let written = client.put_with_level(b"key", b"v", ConsistencyLevel::One).await.unwrap();
+    assert_eq!(written.acknowledged, 1);
+    let written = client.put_with_level(b"key", b"v", ConsistencyLevel::Quorum).await.unwrap();
+    assert_eq!(written.acknowledged, 2);
+    let written = client.put_with_level(b"key", b"v", ConsistencyLevel::All).await.unwrap();
+    assert_eq!(written.acknowledged, 3);
+
+    let read = client.get_with_level(b"key", ConsistencyLevel::All).await.unwrap();
+    assert_eq!(read.value, Some(Bytes::from("v")));
+    assert_eq!(read.acknowledged, 3);
+    let deleted = client.delete_with_level(b"key", ConsistencyLevel::Quorum).await.unwrap();
+    assert_eq!(deleted.acknowledged, 2);
+    assert_eq!(client.get_with_level(b"key", ConsistencyLevel::All).await.unwrap().value, None);
+}
+
+#[tokio::test]
+async fn test_all_fails_when_replica_unreachable() {
+    let cluster = Cluster::zoned().await;
+    let coordinator = cluster.tunable("n1", true).await;
+    let key = cluster.key_replicated_on(&["n1"]);
+    let down = cluster.replicas(key.as_bytes()).into_iter().find(|r| r != "n1").unwrap();
+    cluster.sim().isolate(&down);
+
+    let err = coordinator
+        .put_with_level(key.as_bytes(), b"v", ConsistencyLevel::All)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::QuorumNotMet { required: 3, acknowledged: 2 }));
+    let written = coordinator
+        .put_with_level(key.as_bytes(), b"v", ConsistencyLevel::Quorum)
+        .await
+        .unwrap();
+    assert_eq!(written.acknowledged, 2);
+    let read = coordinator.get_with_level(key.as_bytes(), ConsistencyLevel::Quorum).await.unwrap();
+    assert_eq!(read.value, Some(Bytes::from("v")));
+
+    // 不指定级别时仍按配置的 R/W
+    coordinator.put(key.as_bytes(), b"v2").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_local_quorum_counts_only_local_zone() {
+    let cluster = Cluster::zoned().await;
+    let coordinator = cluster.tunable("n1", false).await;
+    let key = cluster.key_replicated_on(&["n1", "n2"]);
+
+    let written = coordinator
+        .put_with_level(key.as_bytes(), b"v", ConsistencyLevel::LocalQuorum)
+        .await
+        .unwrap();
+    assert_eq!(written.acknowledged, 2);
+
+    // 远端可用区不可达不影响本地法定数
+    cluster.sim().partition(&[&["n1", "n2"], &["n3", "n4"]]);
+    let read = coordinator
+        .get_with_level(key.as_bytes(), ConsistencyLevel::LocalQuorum)
+        .await
+        .unwrap();
+    assert_eq!(read.value, Some(Bytes::from("v")));
+
+    // 本地副本只剩一个时，即使远端副本可用也无法满足
+    cluster.sim().heal();
+    cluster.sim().isolate("n2");
+    let err = coordinator
+        .get_with_level(key.as_bytes(), ConsistencyLevel::LocalQuorum)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::QuorumNotMet { required: 2, acknowledged: 1 }));
+    let read = coordinator
+        .get_with_level(key.as_bytes(), ConsistencyLevel::Quorum)
+        .await
+        .unwrap();
+    assert_eq!(read.acknowledged, 2);
+}
+
+#[tokio::test]
+async fn test_local_quorum_without_local_replicas_fails_fast() {
+    let cluster = Cluster::zoned().await;
+    // 四个可用区、三个副本时总有 key 的副本不在 n5 所在的可用区
+    cluster.ring.add_node(DistributionNode::new("n5", 100).with_zone("c"));
+    cluster.ring.add_node(DistributionNode::new("n6", 100).with_zone("d"));
+    let key = (0..1000)
+        .map(|i| format!("key-{}", i))
+        .find(|key| !cluster.replicas(key.as_bytes()).iter().any(|r| r == "n5"))
+        .unwrap();
+    for (id, _) in NODES {
+        cluster.sim().block("n5", id);
+    }
+    let coordinator = cluster.tunable("n5", false).await;
+
+    let started = Instant::now();
+    let err = coordinator
+        .put_with_level(key.as_bytes(), b"v", ConsistencyLevel::LocalQuorum)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::QuorumNotMet { required: 1, acknowledged: 0 }));
+    // 不等待任何副本超时
+    assert!(started.elapsed() < Duration::from_millis(100));
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use bytes::Bytes;
+use common::{Cluster, NODES};
+use coretex::{
+    api::client::Client,
+    consistency::{ConsistencyManager, ReplicaClient, ReplicaOp, ReplicaReply},
+    distribution::{
+        ConsistentHashRing, DistributionNode, DistributionStrategy, HashFunction, PartitionMap,
+        PartitionMapExchange, RangePartitioner, RendezvousHashing, SharedRing,
+    },
+    messaging::memory::InMemoryBroker,
+    utils::HybridClock,
+};
+use std::hash::Hasher;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn clock() -> Arc<HybridClock> {
+    Arc::new(HybridClock::default())
+}
+
+fn node(id: &str, port: u16) -> DistributionNode {
+    DistributionNode::new(id, 100).with_address(format!("127.0.0.1:{}", port).parse().unwrap())
+}
+
+#[test]
+fn test_hash_function_is_siphash13() {
+    for len in 0..40 {
+        let data: Vec<u8> = (0..len as u8).collect();
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        hasher.write(&data);
+        assert_eq!(HashFunction::SipHash13.hash(&data), hasher.finish());
+    }
+}
+
+#[test]
+fn test_partition_map_round_trip_routes_like_strategy() {
+    let mut range = RangePartitioner::new();
+    range.add_node(node("a", 9000));
+    range.split_range(b"key-5");
+    range.add_node(node("b", 9001));
+    range.add_node(node("c", 9002));
+    let strategies: Vec<Box<dyn DistributionStrategy>> = vec![
+        Box::new(ConsistentHashRing::new()),
+        Box::new(RendezvousHashing::new()),
+        Box::new(range),
+    ];
+
+    for mut strategy in strategies {
+        if strategy.all_nodes().is_empty() {
+            for (i, id) in ["a", "b", "c"].iter().enumerate() {
+                strategy.add_node(node(id, 9000 + i as u16));
+            }
+        }
+        let map = strategy.partition_map(2);
+        let decoded = PartitionMap::from_bytes(&map.to_bytes().unwrap()).unwrap();
+        assert_eq!(decoded, map);
+        assert_eq!(decoded.nodes.len(), 3);
+
+        for i in 0..100 {
+            let key = format!("key-{}", i);
+            let expected = strategy.get_replicas(key.as_bytes(), 2);
+            let routed: Vec<DistributionNode> =
+                decoded.replicas(key.as_bytes()).into_iter().cloned().collect();
+            assert_eq!(routed, expected);
+            assert_eq!(decoded.primary_address(key.as_bytes()), expected[0].address);
+        }
+    }
+
+    // Ring maps carry the node tokens
+    let mut ring = ConsistentHashRing::with_virtual_nodes(8);
+    ring.add_node(node("a", 9000));
+    assert_eq!(ring.partition_map(1).nodes[0].tokens.len(), 8);
+}
+
+async fn eventually(exchange: &PartitionMapExchange, ring: &SharedRing) -> Arc<PartitionMap> {
+    let expected = ring.snapshot().partition_map(2);
+    let mut latest = exchange.latest();
+    for _ in 0..100 {
+        if latest.partitions == expected.partitions {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        latest = exchange.latest();
+    }
+    latest
+}
+
+#[tokio::test]
+async fn test_partition_map_exchanged_between_nodes() {
+    let broker = InMemoryBroker::new("cluster");
+    let ring_a = Arc::new(SharedRing::new(Box::new(ConsistentHashRing::new())));
+    let ring_b = Arc::new(SharedRing::new(Box::new(ConsistentHashRing::new())));
+    let exchange_a = PartitionMapExchange::start(ring_a.clone(), Arc::new(broker.clone()), clock(), 2)
+        .await
+        .unwrap();
+    let exchange_b = PartitionMapExchange::start(ring_b.clone(), Arc::new(broker.clone()), clock(), 2)
+        .await
+        .unwrap();
+
+    // b has a higher local epoch, the later map from a still wins
+    ring_b.add_node(node("x", 9000));
+    ring_b.add_node(node("y", 9001));
+    ring_b.add_node(node("z", 9002));
+    let latest = eventually(&exchange_a, &ring_b).await;
+    assert_eq!(latest.epoch, 3);
+    assert_eq!(latest.nodes.len(), 3);
+
+    ring_a.add_node(node("a", 9000));
+    ring_a.add_node(node("b", 9001));
+
+    let latest = eventually(&exchange_b, &ring_a).await;
+    assert_eq!(latest.epoch, 2);
+    assert_eq!(latest.nodes, ring_a.snapshot().partition_map(2).nodes);
+    assert_eq!(latest.partitions, ring_a.snapshot().partition_map(2).partitions);
+    assert_eq!(exchange_a.latest().version, latest.version);
+}
+
+#[tokio::test]
+async fn test_client_routes_from_downloaded_map() {
+    let cluster = Cluster::in_memory(&NODES).await;
+    let coordinator = cluster.coordinator("n1", 2, 3).await;
+    for i in 0..20 {
+        coordinator.put(format!("key-{}", i).as_bytes(), b"v").await.unwrap();
+    }
+    let broker = Arc::new(InMemoryBroker::new("cluster"));
+    let exchange = PartitionMapExchange::start(cluster.ring.clone(), broker, clock(), 3)
+        .await
+        .unwrap();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    exchange.serve(listener);
+
+    let map = Client::new(addr).await.unwrap().partition_map().await.unwrap();
+    assert_eq!(map, *exchange.latest());
+
+    // Read straight from the primary named by the downloaded map, bypassing the coordinator
+    let client = ReplicaClient::start("client", cluster.broker("client")).await.unwrap();
+    for i in 0..20 {
+        let key = format!("key-{}", i);
+        let replicas: Vec<String> = map.replicas(key.as_bytes()).into_iter().map(|n| n.id.clone()).collect();
+        assert_eq!(replicas, cluster.replicas(key.as_bytes()));
+        let op = ReplicaOp::Get {
+            key: Bytes::from(key.clone()),
+        };
+        let ReplicaReply::Value(stamped) = client.call(&replicas[0], op, Duration::from_secs(1)).await.unwrap() else {
+            panic!("unexpected reply for {}", key);
+        };
+        assert_eq!(stamped.value, Some(Bytes::from_static(b"v")));
+    }
+}
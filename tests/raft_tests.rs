@@ -0,0 +1,328 @@
+mod common;
+
+use common::{eventually, exchange, nodes, NODES};
+use coretex::{
+    config::RaftConfig,
+    consistency::{ConsistencyManager, RaftGroup, RaftRole, StrongConsistencyManager},
+    distribution::{ConsistentHashRing, DistributionNode, DistributionStrategy, PartitionMapExchange, SharedRing},
+    error::Error,
+    messaging::sim::SimulatedNetwork,
+    storage::{InMemoryEngine, StorageEngine},
+};
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+struct Cluster {
+    network: SimulatedNetwork,
+    ring: Arc<SharedRing>,
+    storages: HashMap<String, Arc<dyn StorageEngine>>,
+    exchanges: HashMap<String, PartitionMapExchange>,
+    managers: HashMap<String, StrongConsistencyManager>,
+    config: RaftConfig,
+}
+
+impl Cluster {
+    async fn start(snapshot_threshold: u64) -> Self {
+        let mut strategy = ConsistentHashRing::new();
+        for node in nodes(&NODES) {
+            strategy.add_node(node);
+        }
+        let storages = NODES
+            .iter()
+            .map(|id| (id.to_string(), Arc::new(InMemoryEngine::new(*id)) as Arc<dyn StorageEngine>))
+            .collect();
+        let config = RaftConfig {
+            election_timeout_min_ms: 100,
+            election_timeout_max_ms: 200,
+            heartbeat_interval_ms: 30,
+            snapshot_threshold,
+            max_append_entries: 8,
+            request_timeout_ms: 2000,
+            startup_timeout_ms: 1000,
+            election_seed: None,
+        };
+        let mut cluster = Self {
+            network: SimulatedNetwork::new(),
+            ring: Arc::new(SharedRing::new(Box::new(strategy))),
+            storages,
+            exchanges: HashMap::new(),
+            managers: HashMap::new(),
+            config,
+        };
+        cluster.restart().await;
+        cluster
+    }
+
+    /// 在新的网络上用原有存储重新启动全部节点
+    async fn restart(&mut self) {
+        self.managers.clear();
+        self.exchanges.clear();
+        self.network = SimulatedNetwork::new();
+        let ids: Vec<String> = self.storages.keys().cloned().collect();
+        for id in ids {
+            self.start_node(&id).await;
+        }
+    }
+
+    async fn start_node(&mut self, id: &str) {
+        let maps = exchange(&self.ring, Arc::new(self.network.broker(id)), 3).await;
+        let manager = StrongConsistencyManager::start(
+            id,
+            maps.subscribe(),
+            self.storages[id].clone(),
+            Arc::new(self.network.broker(id)),
+            self.config.clone(),
+        )
+        .await
+        .unwrap();
+        self.exchanges.insert(id.to_string(), maps);
+        self.managers.insert(id.to_string(), manager);
+    }
+
+    /// 新节点以空存储加入分布环
+    async fn join(&mut self, id: &str) {
+        self.storages
+            .insert(id.to_string(), Arc::new(InMemoryEngine::new(id)) as Arc<dyn StorageEngine>);
+        self.ring.add_node(DistributionNode::new(id, 100));
+        self.start_node(id).await;
+    }
+
+    /// 等待 `candidates` 中出现 leader
+    async fn leader(&self, candidates: &[&str]) -> String {
+        let mut leader = None;
+        let found = eventually(Duration::from_secs(4), || {
+            leader = candidates.iter().find(|id| {
+                self.managers[**id]
+                    .raft_status()
+                    .iter()
+                    .any(|(_, status)| status.role == RaftRole::Leader)
+            });
+            let found = leader.is_some();
+            async move { found }
+        })
+        .await;
+        assert!(found, "未选出 leader");
+        leader.unwrap().to_string()
+    }
+
+    async fn stored(&self, id: &str, key: &str) -> Option<Bytes> {
+        self.storages[id].get(key.as_bytes()).await.unwrap()
+    }
+}
+
+#[tokio::test]
+async fn test_strong_reads_see_latest_write_from_any_node() {
+    let cluster = Cluster::start(1000).await;
+    assert_eq!(cluster.managers["n1"].groups().len(), 1);
+    cluster.leader(&NODES).await;
+
+    cluster.managers["n1"].put(b"k", b"v1").await.unwrap();
+    for id in NODES {
+        assert_eq!(cluster.managers[id].get(b"k").await.unwrap(), Some(Bytes::from("v1")));
+    }
+    cluster.managers["n2"].put(b"k", b"v2").await.unwrap();
+    assert_eq!(cluster.managers["n3"].get(b"k").await.unwrap(), Some(Bytes::from("v2")));
+    cluster.managers["n3"].delete(b"k").await.unwrap();
+    assert_eq!(cluster.managers["n1"].get(b"k").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_isolated_leader_is_replaced() {
+    let cluster = Cluster::start(1000).await;
+    let old = cluster.leader(&NODES).await;
+    cluster.managers[old.as_str()].put(b"k", b"v1").await.unwrap();
+
+    cluster.network.isolate(&old);
+    let rest: Vec<&str> = NODES.into_iter().filter(|id| *id != old).collect();
+    cluster.managers[rest[0]].put(b"k", b"v2").await.unwrap();
+    let new = cluster.leader(&rest).await;
+    assert_ne!(new, old);
+
+    // 被隔离的旧 leader 无法提交
+    let err = cluster.managers[old.as_str()].put(b"k", b"stale").await.unwrap_err();
+    assert!(matches!(err, Error::Consistency(_)));
+
+    cluster.network.heal();
+    let caught_up = eventually(Duration::from_secs(4), || async {
+        cluster.stored(&old, "k").await == Some(Bytes::from("v2"))
+    })
+    .await;
+    assert!(caught_up);
+    assert_eq!(cluster.managers[old.as_str()].get(b"k").await.unwrap(), Some(Bytes::from("v2")));
+    let status = &cluster.managers[old.as_str()].raft_status()[0].1;
+    assert_eq!(status.role, RaftRole::Follower);
+}
+
+#[tokio::test]
+async fn test_lagging_follower_installs_snapshot() {
+    let cluster = Cluster::start(5).await;
+    let leader = cluster.leader(&NODES).await;
+    let lagging = NODES.into_iter().find(|id| *id != leader).unwrap();
+    cluster.network.isolate(lagging);
+
+    for i in 0..20 {
+        let key = format!("key-{}", i);
+        cluster.managers[leader.as_str()].put(key.as_bytes(), b"v").await.unwrap();
+    }
+    let status = &cluster.managers[leader.as_str()].raft_status()[0].1;
+    assert!(status.snapshot_index >= 5);
+
+    cluster.network.heal();
+    assert!(
+        eventually(Duration::from_secs(4), || async {
+            for i in 0..20 {
+                if cluster.stored(lagging, &format!("key-{}", i)).await.is_none() {
+                    return false;
+                }
+            }
+            true
+        })
+        .await
+    );
+    assert!(cluster.managers[lagging].raft_status()[0].1.snapshot_index >= 5);
+}
+
+#[tokio::test]
+async fn test_restart_recovers_from_storage() {
+    let mut cluster = Cluster::start(4).await;
+    cluster.leader(&NODES).await;
+    for i in 0..10 {
+        let key = format!("key-{}", i);
+        cluster.managers["n1"].put(key.as_bytes(), key.as_bytes()).await.unwrap();
+    }
+    let term = cluster.managers["n1"].raft_status()[0].1.term;
+
+    cluster.restart().await;
+    cluster.leader(&NODES).await;
+    assert!(cluster.managers["n2"].raft_status()[0].1.term >= term);
+    assert_eq!(
+        cluster.managers["n2"].get(b"key-7").await.unwrap(),
+        Some(Bytes::from("key-7"))
+    );
+    cluster.managers["n3"].put(b"key-7", b"new").await.unwrap();
+    assert_eq!(cluster.managers["n1"].get(b"key-7").await.unwrap(), Some(Bytes::from("new")));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_start_waits_for_enough_nodes_in_the_ring() {
+    let mut strategy = ConsistentHashRing::new();
+    strategy.add_node(DistributionNode::new("n1", 100));
+    let ring = Arc::new(SharedRing::new(Box::new(strategy)));
+    let network = SimulatedNetwork::new();
+    let storage: Arc<dyn StorageEngine> = Arc::new(InMemoryEngine::new("n1"));
+    let config = RaftConfig {
+        startup_timeout_ms: 1000,
+        ..Default::default()
+    };
+    let maps = exchange(&ring, Arc::new(network.broker("n1")), 3).await;
+    let start = || {
+        StrongConsistencyManager::start(
+            "n1",
+            maps.subscribe(),
+            storage.clone(),
+            Arc::new(network.broker("n1")),
+            config.clone(),
+        )
+    };
+
+    // 只有本节点时不会组成单成员的 Raft 组
+    assert!(matches!(start().await, Err(Error::Consistency(_))));
+
+    // 等待期间发现其他节点后按完整的成员启动
+    let joining = tokio::spawn({
+        let ring = ring.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            ring.add_node(DistributionNode::new("n2", 100));
+            ring.add_node(DistributionNode::new("n3", 100));
+        }
+    });
+    let manager = start().await.unwrap();
+    joining.await.unwrap();
+    assert!(manager.groups().iter().all(|group| group.members.len() == 3));
+}
+
+#[test]
+fn test_group_ids_follow_ranges_not_members() {
+    let ring = SharedRing::new(Box::new(ConsistentHashRing::new()));
+    for node in nodes(&NODES) {
+        ring.add_node(node);
+    }
+    let before = RaftGroup::from_map(&ring.snapshot().partition_map(3));
+    assert_eq!(before.len(), 1);
+
+    // 新节点加入后成员改变，从同一位置开始的分区仍属于原来的组
+    ring.add_node(DistributionNode::new("n4", 100));
+    let map = ring.snapshot().partition_map(3);
+    let after = RaftGroup::from_map(&map);
+    assert!(after.len() > 1);
+    assert!(after.iter().any(|group| group.id == before[0].id));
+    assert_eq!(RaftGroup::from_map(&map), after);
+    for group in &after {
+        assert!(!group.id.contains('+'));
+    }
+}
+
+#[tokio::test]
+async fn test_reads_are_not_appended_to_the_log() {
+    let cluster = Cluster::start(1000).await;
+    let leader = cluster.leader(&NODES).await;
+    cluster.managers["n1"].put(b"k", b"v").await.unwrap();
+    let committed = cluster.managers[leader.as_str()].raft_status()[0].1.commit_index;
+
+    for id in NODES {
+        for _ in 0..5 {
+            assert_eq!(cluster.managers[id].get(b"k").await.unwrap(), Some(Bytes::from("v")));
+        }
+    }
+    assert_eq!(cluster.managers[leader.as_str()].raft_status()[0].1.commit_index, committed);
+}
+
+#[tokio::test]
+async fn test_groups_reconfigure_when_a_node_joins() {
+    let mut cluster = Cluster::start(1000).await;
+    cluster.leader(&NODES).await;
+    for i in 0..20 {
+        let key = format!("key-{}", i);
+        cluster.managers["n1"].put(key.as_bytes(), key.as_bytes()).await.unwrap();
+    }
+    let before = cluster.managers["n1"].groups();
+
+    cluster.join("n4").await;
+    let expected = RaftGroup::from_map(&cluster.ring.snapshot().partition_map(3));
+    assert!(
+        eventually(Duration::from_secs(4), || async {
+            cluster.managers.values().all(|m| *m.groups() == expected)
+        })
+        .await
+    );
+    assert!(expected.iter().any(|group| group.id == before[0].id));
+
+    // 新成员通过日志或快照获得所在组的数据，各节点都读到原有的值
+    assert!(
+        eventually(Duration::from_secs(4), || async {
+            for i in 0..20 {
+                let key = format!("key-{}", i);
+                let group = expected.iter().find(|g| g.contains(key.as_bytes())).unwrap();
+                if group.members.iter().any(|m| m == "n4") && cluster.stored("n4", &key).await.is_none() {
+                    return false;
+                }
+            }
+            true
+        })
+        .await
+    );
+    for id in ["n1", "n2", "n3", "n4"] {
+        for i in 0..20 {
+            let key = format!("key-{}", i);
+            assert_eq!(
+                cluster.managers[id].get(key.as_bytes()).await.unwrap(),
+                Some(Bytes::from(key.clone()))
+            );
+        }
+    }
+    cluster.managers["n4"].put(b"key-0", b"new").await.unwrap();
+    assert_eq!(cluster.managers["n2"].get(b"key-0").await.unwrap(), Some(Bytes::from("new")));
+}
@@ -0,0 +1,260 @@
+mod common;
+
+use common::{eventually, nodes, Cluster, Network};
+use coretex::{
+    consistency::{ConsistencyManager, Crdt, GCounter, ReplicaClient, Stamped, HINT_PREFIX, RAFT_PREFIX},
+    distribution::{
+        ConsistentHashRing, DistributionNode, DistributionStrategy, PartitionRange, RangePartitioner,
+        RebalanceExecutor, RebalancePlanner, RebalanceService, RendezvousHashing, Throttle, Transfer,
+    },
+    storage::{InMemoryEngine, StorageEngine},
+};
+use bytes::Bytes;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const REPLICAS: usize = 2;
+
+fn engines(ids: &[&str]) -> HashMap<String, Arc<dyn StorageEngine>> {
+    ids.iter()
+        .map(|id| (id.to_string(), Arc::new(InMemoryEngine::new(*id)) as Arc<dyn StorageEngine>))
+        .collect()
+}
+
+async fn load(strategy: &dyn DistributionStrategy, engines: &HashMap<String, Arc<dyn StorageEngine>>) {
+    for i in 0..200 {
+        let key = format!("key-{:03}", i);
+        for node in strategy.get_replicas(key.as_bytes(), REPLICAS) {
+            engines[&node.id].put(key.as_bytes(), b"v").await.unwrap();
+        }
+    }
+}
+
+async fn assert_placement(
+    strategy: &dyn DistributionStrategy,
+    engines: &HashMap<String, Arc<dyn StorageEngine>>,
+) {
+    for i in 0..200 {
+        let key = format!("key-{:03}", i);
+        let replicas: Vec<String> = strategy
+            .get_replicas(key.as_bytes(), REPLICAS)
+            .into_iter()
+            .map(|n| n.id)
+            .collect();
+        for (id, engine) in engines {
+            let present = engine.get(key.as_bytes()).await.unwrap().is_some();
+            assert_eq!(present, replicas.contains(id), "{} on {}", key, id);
+        }
+    }
+}
+
+#[test]
+fn test_ownership_tiles_key_space() {
+    let mut ring = ConsistentHashRing::new();
+    ring.add_node(DistributionNode::new("a", 100));
+    ring.add_node(DistributionNode::new("b", 100));
+    let ownership = ring.ownership(REPLICAS);
+
+    for i in 0..100 {
+        let key = format!("key-{}", i);
+        let owners: Vec<_> = ownership.iter().filter(|o| o.range.contains(key.as_bytes())).collect();
+        assert_eq!(owners.len(), 1);
+        let expected: Vec<String> =
+            ring.get_replicas(key.as_bytes(), REPLICAS).into_iter().map(|n| n.id).collect();
+        assert_eq!(owners[0].replicas, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_rebalance_after_adding_node() {
+    let engines = engines(&["a", "b", "c"]);
+    let strategies: Vec<Box<dyn DistributionStrategy>> =
+        vec![Box::new(ConsistentHashRing::new()), Box::new(RendezvousHashing::new())];
+
+    for mut strategy in strategies {
+        for engine in engines.values() {
+            for i in 0..200 {
+                engine.delete(format!("key-{:03}", i).as_bytes()).await.unwrap();
+            }
+        }
+        strategy.add_node(DistributionNode::new("a", 100));
+        strategy.add_node(DistributionNode::new("b", 100));
+        load(strategy.as_ref(), &engines).await;
+
+        let before = strategy.ownership(REPLICAS);
+        strategy.add_node(DistributionNode::new("c", 100));
+        let after = strategy.ownership(REPLICAS);
+
+        let plan = RebalancePlanner::diff(&before, &after).unwrap();
+        assert!(!plan.transfers.is_empty());
+        assert!(plan.transfers.iter().all(|t| t.to == "c"));
+
+        // 节点本地的 Raft 状态和提示不随分区迁移或清理
+        let internal = [[RAFT_PREFIX, b"g/state"].concat(), [HINT_PREFIX, b"a/1"].concat()];
+        for key in &internal {
+            engines["a"].put(key, b"local").await.unwrap();
+        }
+
+        let executor = RebalanceExecutor::new(engines.clone(), Throttle::default());
+        let progress = executor.execute(&plan).await.unwrap();
+        assert_eq!(progress.transfers_completed, plan.transfers.len());
+        assert!(progress.keys_copied > 0);
+        assert_eq!(progress.keys_copied, progress.keys_released);
+
+        assert_placement(strategy.as_ref(), &engines).await;
+        for key in &internal {
+            assert!(engines["a"].get(key).await.unwrap().is_some());
+            assert!(engines["c"].get(key).await.unwrap().is_none());
+            engines["a"].delete(key).await.unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_rebalance_range_partitions_after_removing_node() {
+    let engines = engines(&["a", "b", "c"]);
+    let mut ranges = RangePartitioner::new();
+    ranges.add_node(DistributionNode::new("a", 100));
+    for at in ["key-050", "key-100", "key-150"] {
+        ranges.split_range(at.as_bytes());
+    }
+    ranges.add_node(DistributionNode::new("b", 100));
+    ranges.add_node(DistributionNode::new("c", 100));
+    load(&ranges, &engines).await;
+
+    let before = ranges.ownership(REPLICAS);
+    ranges.remove_node("b");
+    let after = ranges.ownership(REPLICAS);
+
+    let plan = RebalancePlanner::diff(&before, &after).unwrap();
+    assert!(plan.transfers.iter().all(|t| matches!(t.range, PartitionRange::Key(_))));
+    assert!(plan.transfers.iter().all(|t| t.from != "b"));
+    assert!(plan.releases.iter().all(|(_, node)| node == "b"));
+
+    let throttle = Throttle {
+        max_concurrent: 1,
+        bytes_per_sec: None,
+        batch_size: 7,
+    };
+    RebalanceExecutor::new(engines.clone(), throttle).execute(&plan).await.unwrap();
+
+    let remaining: HashMap<_, _> = engines.into_iter().filter(|(id, _)| id != "b").collect();
+    assert_placement(&ranges, &remaining).await;
+}
+
+#[test]
+fn test_rebalance_plan_rejects_mixed_partitioning() {
+    let mut ring = ConsistentHashRing::new();
+    ring.add_node(DistributionNode::new("a", 100));
+    let mut ranges = RangePartitioner::new();
+    ranges.add_node(DistributionNode::new("a", 100));
+
+    assert!(RebalancePlanner::diff(&ring.ownership(1), &ranges.ownership(1)).is_err());
+    assert!(RebalancePlanner::diff(&[], &ring.ownership(1)).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_rebalance_merges_with_target_and_released_replicas() {
+    let engines = engines(&["a", "b", "c"]);
+    let mut ring = ConsistentHashRing::new();
+    ring.add_node(DistributionNode::new("a", 100));
+    ring.add_node(DistributionNode::new("b", 100));
+    let before = ring.ownership(REPLICAS);
+    ring.add_node(DistributionNode::new("c", 100));
+    let plan = RebalancePlanner::diff(&before, &ring.ownership(REPLICAS)).unwrap();
+
+    // 一个迁往 c、且由另一个旧副本释放的 key
+    let (key, transfer, released) = (0..1000)
+        .map(|i| format!("key-{}", i))
+        .find_map(|key| {
+            let transfer = plan.transfers.iter().find(|t| t.range.contains(key.as_bytes()))?;
+            let (_, released) = plan
+                .releases
+                .iter()
+                .find(|(range, node)| range.contains(key.as_bytes()) && *node != transfer.from)?;
+            Some((key, transfer.clone(), released.clone()))
+        })
+        .unwrap();
+    let counter = |counts: &[(&str, u64)]| {
+        let mut counter = GCounter::default();
+        for (actor, delta) in counts {
+            counter.increment(actor, *delta);
+        }
+        Crdt::GCounter(counter).encode().unwrap()
+    };
+    // 目标上有过期的副本，被释放的旧副本上有迁移来源没有的写入
+    engines[&transfer.from].put(key.as_bytes(), &counter(&[("a", 5)])).await.unwrap();
+    engines[&released].put(key.as_bytes(), &counter(&[("a", 5), ("b", 2)])).await.unwrap();
+    engines["c"].put(key.as_bytes(), &counter(&[("a", 1), ("c", 3)])).await.unwrap();
+
+    // 带时间戳的值保留较晚的一个
+    let stamped: Vec<(String, &Transfer)> = (0..1000)
+        .map(|i| format!("stamped-{}", i))
+        .filter_map(|key| Some((key.clone(), plan.transfers.iter().find(|t| t.range.contains(key.as_bytes()))?)))
+        .take(2)
+        .collect();
+    let stamp = |timestamp, value: &'static str| Stamped::new(timestamp, Some(Bytes::from(value))).encode();
+    for ((key, transfer), (source, target)) in stamped.iter().zip([(10, 5), (5, 10)]) {
+        engines[&transfer.from].put(key.as_bytes(), &stamp(source, "source")).await.unwrap();
+        engines["c"].put(key.as_bytes(), &stamp(target, "target")).await.unwrap();
+    }
+
+    RebalanceExecutor::new(engines.clone(), Throttle::default()).execute(&plan).await.unwrap();
+
+    let stored = engines["c"].get(key.as_bytes()).await.unwrap().unwrap();
+    assert_eq!(Crdt::decode(&stored), Crdt::decode(&counter(&[("a", 5), ("b", 2), ("c", 3)])));
+    assert!(engines[&released].get(key.as_bytes()).await.unwrap().is_none());
+    for ((key, _), expected) in stamped.iter().zip(["source", "target"]) {
+        let stored = engines["c"].get(key.as_bytes()).await.unwrap();
+        assert_eq!(Stamped::from_stored(stored).value, Some(Bytes::from(expected)));
+    }
+}
+
+#[tokio::test]
+async fn test_nodes_rebalance_their_share_after_ring_change() {
+    let ids = ["n1", "n2", "n3", "n4"];
+    let cluster = Cluster::start(Network::memory(), nodes(&ids), &ids).await;
+    let mut services = HashMap::new();
+    for id in ids {
+        let client = Arc::new(ReplicaClient::start(id, cluster.broker(id)).await.unwrap());
+        let service = RebalanceService::start(
+            id,
+            cluster.ring.clone(),
+            cluster.storages[id].clone(),
+            client,
+            3,
+            Throttle::default(),
+            Duration::from_millis(200),
+        );
+        services.insert(id, service);
+    }
+    let coordinator = cluster.coordinator("c", 3, 3).await;
+    for i in 0..100 {
+        coordinator.put(format!("key-{}", i).as_bytes(), b"v").await.unwrap();
+    }
+
+    // n4 离开分布环：其余节点把 n4 交出的分区补到新副本，n4 转交后清理
+    cluster.ring.remove_node("n4");
+    let placed = eventually(Duration::from_secs(5), || async {
+        for i in 0..100 {
+            let key = format!("key-{}", i);
+            let replicas = cluster.replicas(key.as_bytes());
+            for id in ids {
+                let stored = cluster.storages[id].get(key.as_bytes()).await.unwrap();
+                if stored.is_some() != replicas.iter().any(|r| r == id) {
+                    return false;
+                }
+            }
+        }
+        true
+    })
+    .await;
+    assert!(placed);
+    assert!(services["n4"].progress().keys_released > 0);
+    let copied: u64 = services.values().map(|s| s.progress().keys_copied).sum();
+    assert!(copied > 0);
+    for i in 0..100 {
+        assert_eq!(coordinator.get(format!("key-{}", i).as_bytes()).await.unwrap(), Some(Bytes::from("v")));
+    }
+}
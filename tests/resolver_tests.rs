@@ -0,0 +1,97 @@
+use bytes::Bytes;
+use coretex::{
+    config::{ConflictResolution, ConsistencyConfig, ConsistencyMode, ReadRepairMode},
+    consistency::{
+        ConflictResolver, HighestVectorClock, LastWriterWins, MergeFn, ResolverRegistry,
+        VectorClock, Version,
+    },
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn version(entries: &[(&str, u64)], value: Option<&'static [u8]>, timestamp: u64) -> Version {
+    let mut clock = VectorClock::new();
+    for (node, counter) in entries {
+        clock.advance(node, *counter);
+    }
+    Version {
+        clock,
+        value: value.map(Bytes::from_static),
+        timestamp,
+        deps: Default::default(),
+    }
+}
+
+fn siblings() -> Vec<Version> {
+    vec![
+        version(&[("n1", 3)], Some(b"a"), 200),
+        version(&[("n2", 1)], Some(b"b"), 300),
+    ]
+}
+
+#[test]
+fn test_builtin_resolvers() {
+    assert_eq!(LastWriterWins.resolve(b"k", &siblings()).unwrap().as_ref(), b"b");
+    assert_eq!(HighestVectorClock.resolve(b"k", &siblings()).unwrap().as_ref(), b"a");
+
+    // 较新的墓碑胜出时结果为删除
+    let deleted = vec![
+        version(&[("n1", 1)], Some(b"a"), 100),
+        version(&[("n2", 1)], None, 200),
+    ];
+    assert!(LastWriterWins.resolve(b"k", &deleted).is_none());
+}
+
+#[test]
+fn test_registry_uses_longest_namespace() {
+    let union = MergeFn(|_key: &[u8], values: &[Option<Bytes>]| {
+        let mut items: Vec<&[u8]> = values.iter().flatten().map(|v| v.as_ref()).collect();
+        items.sort();
+        Some(Bytes::from(items.join(&b","[..])))
+    });
+    let mut registry = ResolverRegistry::new().with_default(Arc::new(LastWriterWins));
+    registry.register("cart/", Arc::new(union));
+    registry.keep_siblings("cart/raw/");
+
+    let resolved = registry.resolve(b"cart/1", siblings());
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(resolved[0].value.as_deref(), Some(&b"a,b"[..]));
+    assert_eq!(resolved[0].clock.get("n1"), 3);
+    assert_eq!(resolved[0].clock.get("n2"), 1);
+    assert_eq!(resolved[0].timestamp, 300);
+
+    assert_eq!(registry.resolve(b"cart/raw/1", siblings()).len(), 2);
+    assert_eq!(registry.resolve(b"user/1", siblings())[0].value.as_deref(), Some(&b"b"[..]));
+}
+
+#[test]
+fn test_registry_from_config() {
+    let config = ConsistencyConfig {
+        mode: ConsistencyMode::Eventual,
+        vector_clock_enabled: true,
+        conflict_resolution: ConflictResolution::Siblings,
+        namespaces: HashMap::from([("session/".to_string(), ConflictResolution::LastWriterWins)]),
+        read_repair: ReadRepairMode::default(),
+        anti_entropy: Default::default(),
+        raft: Default::default(),
+        event_buffer: 1024,
+        max_causal_dependencies: 32,
+    };
+    let registry = ResolverRegistry::from_config(&config);
+    assert_eq!(registry.resolve(b"cart/1", siblings()).len(), 2);
+    assert_eq!(registry.resolve(b"session/1", siblings()).len(), 1);
+
+    let parsed: ConsistencyConfig = toml::from_str(
+        r#"
+        mode = "Eventual"
+        vector_clock_enabled = true
+        conflict_resolution = "HighestVectorClock"
+
+        [namespaces]
+        "session/" = "LastWriterWins"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(parsed.conflict_resolution, ConflictResolution::HighestVectorClock);
+    assert_eq!(parsed.namespaces["session/"], ConflictResolution::LastWriterWins);
+}
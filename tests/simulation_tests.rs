@@ -0,0 +1,243 @@
+use coretex::{
+    config::Config,
+    consistency::history::History,
+    messaging::sim::{NetworkFaults, NetworkStats},
+    simulation::{SimCluster, Simulation, SIMULATION_EPOCH_MS},
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+fn faults() -> NetworkFaults {
+    NetworkFaults {
+        drop_probability: 0.1,
+        min_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(30),
+        reorder_probability: 0.1,
+    }
+}
+
+/// 每个节点上一个客户端并发地随机读写，返回各操作的结果和网络统计
+async fn random_workload(cluster: SimCluster) -> (Vec<String>, NetworkStats, Vec<Duration>) {
+    let cluster = Arc::new(cluster);
+    let history = History::new();
+    let tasks: Vec<_> = cluster
+        .nodes()
+        .iter()
+        .map(|node| {
+            let client = history.client(&format!("c-{}", node.id), node.coretex.consistency.clone());
+            let cluster = cluster.clone();
+            tokio::spawn(async move {
+                for i in 0..15 {
+                    let key = format!("k{}", cluster.random(4));
+                    let _ = if cluster.chance(0.5) {
+                        client.put(key.as_bytes(), format!("{}-{}", client.name(), i).as_bytes()).await
+                    } else {
+                        client.get(key.as_bytes()).await.map(|_| ())
+                    };
+                    cluster.sleep(Duration::from_millis(cluster.random(50))).await;
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let outcomes = history
+        .operations()
+        .iter()
+        .map(|op| format!("{} {:?} {:?} {:?}", op.client, op.key, op.call, op.outcome))
+        .collect();
+    let skews = cluster.nodes().iter().map(|node| node.skew).collect();
+    (outcomes, cluster.network().stats(), skews)
+}
+
+fn simulate(seed: u64) -> (Vec<String>, NetworkStats, Vec<Duration>) {
+    Simulation::new(seed)
+        .with_nodes(4)
+        .with_faults(faults())
+        .with_clock_skew(Duration::from_millis(100))
+        .run(random_workload)
+        .unwrap()
+}
+
+#[test]
+fn test_same_seed_reproduces_the_run() {
+    let (outcomes, stats, skews) = simulate(7);
+    assert_eq!(outcomes.len(), 60);
+    assert!(stats.dropped > 0 && stats.delayed > 0, "{:?}", stats);
+    assert!(outcomes.iter().any(|o| o.ends_with("Unknown")));
+    assert_eq!(simulate(7), (outcomes.clone(), stats, skews.clone()));
+
+    let (other, other_stats, other_skews) = simulate(8);
+    assert!(other != outcomes || other_stats != stats || other_skews != skews);
+}
+
+#[test]
+fn test_same_seed_reproduces_strong_mode_elections() {
+    let config: Config = toml::from_str(
+        r#"
+        [node]
+        bind_address = "127.0.0.1:7000"
+        data_dir = "./data"
+        seed_nodes = []
+
+        [storage]
+        engine = "memory"
+
+        [replication]
+        factor = 3
+        read_quorum = 2
+        write_quorum = 2
+
+        [consistency]
+        mode = "Strong"
+        vector_clock_enabled = false
+        "#,
+    )
+    .unwrap();
+    let simulate = |seed| {
+        Simulation::new(seed)
+            .with_config(config.clone())
+            .with_faults(faults())
+            .run(random_workload)
+            .unwrap()
+    };
+    // 选举超时的抖动也由种子决定，leader 和各操作的结果都可以复现
+    let (outcomes, stats, skews) = simulate(3);
+    assert!(outcomes.iter().any(|o| o.contains("Ok")), "{:?}", outcomes);
+    assert_eq!(simulate(3), (outcomes, stats, skews));
+}
+
+#[test]
+fn test_virtual_time_and_partitions() {
+    let started = Instant::now();
+    Simulation::new(1)
+        .run(|cluster| async move {
+            let n1 = cluster.node("n1").unwrap();
+            let n3 = cluster.node("n3").unwrap();
+            n1.coretex.consistency.put(b"key", b"v1").await.unwrap();
+
+            // n3 与其他节点分区后无法凑齐读法定数，n1 一侧仍可写入
+            cluster.network().partition(&[&["n1", "n2"], &["n3"]]);
+            assert!(n3.coretex.consistency.get(b"key").await.is_err());
+            n1.coretex.consistency.put(b"key", b"v2").await.unwrap();
+
+            // 一小时的虚拟时间立即过去，各节点的混合逻辑时钟随之推进
+            cluster.sleep(Duration::from_secs(3600)).await;
+            assert!(cluster.elapsed() >= Duration::from_secs(3600));
+            assert!(n1.clock.now().physical_ms() >= SIMULATION_EPOCH_MS + 3_600_000);
+
+            // 恢复后 n3 重新可用；未启用向量时钟时它上面的旧值要等反熵或覆盖写才会更新
+            cluster.network().heal();
+            assert!(n3.coretex.consistency.get(b"key").await.is_ok());
+            n3.coretex.consistency.put(b"key", b"v3").await.unwrap();
+            assert_eq!(n1.coretex.consistency.get(b"key").await.unwrap().as_deref(), Some(&b"v3"[..]));
+        })
+        .unwrap();
+    assert!(started.elapsed() < Duration::from_secs(30));
+}
+
+#[test]
+fn test_crashed_node_receives_hints_after_recovery() {
+    Simulation::new(3)
+        .with_nodes(4)
+        .run(|cluster| async move {
+            let n1 = cluster.node("n1").unwrap();
+            for id in ["n2", "n3", "n4"] {
+                cluster.crash(id).await.unwrap();
+                assert!(cluster.node(id).is_some());
+                cluster.recover(id).await.unwrap();
+            }
+            assert!(cluster.crash("n9").await.is_err());
+
+            // 任意宕机一个节点，宽松法定数仍能写入，恢复后提示回放到该节点
+            cluster.crash("n2").await.unwrap();
+            for i in 0..10 {
+                let key = format!("key{}", i);
+                n1.coretex.consistency.put(key.as_bytes(), b"v").await.unwrap();
+            }
+            cluster.recover("n2").await.unwrap();
+            cluster.sleep(Duration::from_secs(5)).await;
+            let n2 = cluster.node("n2").unwrap();
+            let mut stored = 0;
+            for i in 0..10 {
+                let key = format!("key{}", i);
+                if n2.coretex.storage.get(key.as_bytes()).await.unwrap().is_some() {
+                    stored += 1;
+                }
+            }
+            // 4 个节点、3 个副本，n2 至少是部分 key 的副本
+            assert!(stored > 0);
+        })
+        .unwrap();
+}
+
+#[test]
+fn test_range_strategy_splits_and_keeps_serving() {
+    let config: Config = toml::from_str(
+        r#"
+        [node]
+        bind_address = "127.0.0.1:7000"
+        data_dir = "./data"
+        seed_nodes = []
+
+        [storage]
+        engine = "memory"
+
+        [replication]
+        factor = 3
+        read_quorum = 2
+        write_quorum = 2
+
+        [consistency]
+        mode = "Eventual"
+        vector_clock_enabled = false
+
+        [distribution]
+        strategy = "Range"
+
+        [distribution.range]
+        max_bytes = 1000
+        interval_ms = 500
+        "#,
+    )
+    .unwrap();
+    Simulation::new(5)
+        .with_nodes(4)
+        .with_config(config)
+        .run(|cluster| async move {
+            let initial = cluster.node("n1").unwrap().coretex.ring.shared().snapshot().ownership(1).len();
+            for (i, node) in (0..200).zip(cluster.nodes().iter().cycle()) {
+                let key = format!("key-{:03}", i);
+                node.coretex.consistency.put(key.as_bytes(), key.as_bytes()).await.unwrap();
+            }
+            cluster.sleep(Duration::from_secs(10)).await;
+
+            // 写入负载汇总到 n1，分裂后的范围划分在所有节点上一致
+            let layouts: Vec<_> = cluster
+                .nodes()
+                .iter()
+                .map(|node| node.coretex.ring.shared().snapshot().ownership(3))
+                .collect();
+            assert!(layouts[0].len() > initial, "{:?}", layouts[0]);
+            assert!(layouts.iter().all(|layout| *layout == layouts[0]));
+
+            // 归属变化后数据迁移到新的副本，任一节点都能读到所有写入
+            for i in 0..200 {
+                let key = format!("key-{:03}", i);
+                let owners = layouts[0].iter().find(|o| o.range.contains(key.as_bytes())).unwrap();
+                for id in &owners.replicas {
+                    let stored = cluster.node(id).unwrap().coretex.storage.get(key.as_bytes()).await.unwrap();
+                    assert!(stored.is_some(), "{} 不在副本 {} 上", key, id);
+                }
+            }
+            for node in cluster.nodes() {
+                for i in 0..200 {
+                    let key = format!("key-{:03}", i);
+                    let value = node.coretex.consistency.get(key.as_bytes()).await.unwrap();
+                    assert_eq!(value.as_deref(), Some(key.as_bytes()));
+                }
+            }
+        })
+        .unwrap();
+}
@@ -0,0 +1,274 @@
+mod common;
+
+use common::eventually;
+use coretex::{
+    config::{MembershipConfig, NodeConfig, NODE_ID_FILE},
+    error::Error,
+    membership::{
+        BrokerTransport, GossipTransport, MembershipEvent, MembershipManager, NodeState, SwimMembership, UdpTransport,
+    },
+    messaging::sim::SimulatedNetwork,
+};
+use futures::{FutureExt, StreamExt};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn config() -> MembershipConfig {
+    MembershipConfig {
+        probe_interval_ms: 100,
+        probe_timeout_ms: 30,
+        suspicion_timeout_ms: 500,
+        sync_interval_ms: 1000,
+        dead_member_timeout_ms: 3000,
+        ..Default::default()
+    }
+}
+
+fn addr(i: usize) -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 7000 + i as u16))
+}
+
+/// 节点 `n{i}` 在模拟网络中的成员管理，以 `seeds` 为种子加入并公布为 Active
+async fn start(network: &SimulatedNetwork, i: usize, seeds: Vec<SocketAddr>) -> SwimMembership {
+    let id = format!("n{}", i);
+    let transport = BrokerTransport::start(Arc::new(network.broker(id.clone())), addr(i)).await.unwrap();
+    let membership = SwimMembership::start(id.clone(), Arc::new(transport), seeds, config()).await.unwrap();
+    membership.update_node_state(&id, NodeState::Active).await.unwrap();
+    membership
+}
+
+/// n1 没有种子，其余节点以 n1 为种子
+async fn cluster(network: &SimulatedNetwork, size: usize) -> Vec<SwimMembership> {
+    let mut nodes = vec![start(network, 1, Vec::new()).await];
+    for i in 2..=size {
+        nodes.push(start(network, i, vec![addr(1)]).await);
+    }
+    nodes
+}
+
+async fn view(membership: &SwimMembership) -> BTreeMap<String, NodeState> {
+    let nodes = membership.get_nodes().await.unwrap();
+    nodes.into_iter().map(|n| (n.id, n.state)).collect()
+}
+
+fn expected(states: &[(&str, NodeState)]) -> BTreeMap<String, NodeState> {
+    states.iter().map(|(id, state)| (id.to_string(), state.clone())).collect()
+}
+
+async fn all_see(nodes: &[&SwimMembership], states: &BTreeMap<String, NodeState>) -> bool {
+    for node in nodes {
+        if view(node).await != *states {
+            return false;
+        }
+    }
+    true
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_nodes_join_through_seeds_and_converge() {
+    let network = SimulatedNetwork::new();
+    let nodes = cluster(&network, 5).await;
+    let ids = ["n1", "n2", "n3", "n4", "n5"];
+    let active = expected(&ids.map(|id| (id, NodeState::Active)));
+    let all: Vec<&SwimMembership> = nodes.iter().collect();
+    assert!(eventually(Duration::from_secs(5), || all_see(&all, &active)).await);
+
+    // 后加入的节点只知道 n1，其余成员经捎带的变更得知
+    let late = start(&network, 6, vec![addr(1)]).await;
+    let mut events = nodes[4].watch_nodes().await.unwrap();
+    let mut states = active.clone();
+    states.insert("n6".to_string(), NodeState::Active);
+    let all: Vec<&SwimMembership> = nodes.iter().chain([&late]).collect();
+    assert!(eventually(Duration::from_secs(5), || all_see(&all, &states)).await);
+    match events.next().await {
+        Some(Ok(MembershipEvent::NodeJoined(node))) => {
+            assert_eq!(node.id, "n6");
+            assert_eq!(node.address, addr(6));
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_failed_node_is_declared_down_and_recovers() {
+    let network = SimulatedNetwork::new();
+    let nodes = cluster(&network, 4).await;
+    let all: Vec<&SwimMembership> = nodes.iter().collect();
+    let active = expected(&[
+        ("n1", NodeState::Active),
+        ("n2", NodeState::Active),
+        ("n3", NodeState::Active),
+        ("n4", NodeState::Active),
+    ]);
+    assert!(eventually(Duration::from_secs(5), || all_see(&all, &active)).await);
+    let mut events = nodes[0].watch_nodes().await.unwrap();
+
+    // 被隔离的 n3 先被怀疑，怀疑超时后判定为宕机
+    network.isolate("n3");
+    let mut down = active.clone();
+    down.insert("n3".to_string(), NodeState::Down);
+    let others = [&nodes[0], &nodes[1], &nodes[3]];
+    assert!(eventually(Duration::from_secs(5), || all_see(&others, &down)).await);
+    match events.next().await {
+        Some(Ok(MembershipEvent::NodeStateChanged { id, state })) => {
+            assert_eq!(id, "n3");
+            assert_eq!(state, NodeState::Down);
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+
+    // 恢复连通后 n3 通过成员表同步得知自己被判定宕机，以新的化身号反驳
+    network.reconnect("n3");
+    assert!(eventually(Duration::from_secs(10), || all_see(&all, &active)).await);
+    // 其他节点在 n3 隔离期间的宕机判定不会使 n1 误判健康的节点
+    match events.next().await {
+        Some(Ok(MembershipEvent::NodeStateChanged { id, state })) => {
+            assert_eq!(id, "n3");
+            assert_eq!(state, NodeState::Active);
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+    assert!(events.next().now_or_never().is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_indirect_probes_prevent_false_suspicion() {
+    let network = SimulatedNetwork::new();
+    let nodes = cluster(&network, 3).await;
+    let all: Vec<&SwimMembership> = nodes.iter().collect();
+    let active = expected(&[
+        ("n1", NodeState::Active),
+        ("n2", NodeState::Active),
+        ("n3", NodeState::Active),
+    ]);
+    assert!(eventually(Duration::from_secs(5), || all_see(&all, &active)).await);
+
+    // n1 发往 n2 的消息全部丢失，n2 仍可经 n3 间接确认存活
+    network.block("n1", "n2");
+    let mut events = nodes[0].watch_nodes().await.unwrap();
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    assert!(all_see(&all, &active).await);
+    if let Some(event) = events.next().now_or_never() {
+        panic!("unexpected event: {:?}", event);
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_state_changes_and_leaves_are_gossiped() {
+    let network = SimulatedNetwork::new();
+    let nodes = cluster(&network, 3).await;
+    let all: Vec<&SwimMembership> = nodes.iter().collect();
+    let mut states = expected(&[
+        ("n1", NodeState::Active),
+        ("n2", NodeState::Active),
+        ("n3", NodeState::Active),
+    ]);
+    assert!(eventually(Duration::from_secs(5), || all_see(&all, &states)).await);
+
+    // 只能修改本节点
+    assert!(matches!(
+        nodes[0].update_node_state("n2", NodeState::Down).await,
+        Err(Error::Membership(_))
+    ));
+    assert!(nodes[0].register_node(addr(2), HashMap::new()).await.is_err());
+    let metadata = HashMap::from([("zone".to_string(), "b".to_string())]);
+    assert_eq!(nodes[1].register_node(addr(2), metadata).await.unwrap(), "n2");
+
+    nodes[1].update_node_state("n2", NodeState::Leaving).await.unwrap();
+    states.insert("n2".to_string(), NodeState::Leaving);
+    assert!(eventually(Duration::from_secs(5), || all_see(&all, &states)).await);
+    let n2 = nodes[2].get_node("n2").await.unwrap().unwrap();
+    assert_eq!(n2.metadata.get("zone").map(String::as_str), Some("b"));
+
+    // 离开的节点从其他成员的视图中移除，而不是被判定为宕机
+    let mut events = nodes[0].watch_nodes().await.unwrap();
+    nodes[1].unregister_node("n2").await.unwrap();
+    states.remove("n2");
+    let rest = [&nodes[0], &nodes[2]];
+    assert!(eventually(Duration::from_secs(5), || all_see(&rest, &states)).await);
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    assert!(nodes[2].get_node("n2").await.unwrap().is_none());
+    assert!(matches!(events.next().await, Some(Ok(MembershipEvent::NodeLeft(id))) if id == "n2"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_dead_members_are_reaped_and_can_rejoin_with_the_same_id() {
+    let network = SimulatedNetwork::new();
+    let mut nodes = cluster(&network, 3).await;
+    let all: Vec<&SwimMembership> = nodes.iter().collect();
+    let mut states = expected(&[
+        ("n1", NodeState::Active),
+        ("n2", NodeState::Active),
+        ("n3", NodeState::Active),
+    ]);
+    assert!(eventually(Duration::from_secs(5), || all_see(&all, &states)).await);
+    let mut events = nodes[0].watch_nodes().await.unwrap();
+
+    // n3 宕机后先被判定为 Down，宕机超时后从成员表中移除
+    network.isolate("n3");
+    drop(nodes.pop());
+    assert!(matches!(
+        events.next().await,
+        Some(Ok(MembershipEvent::NodeStateChanged { id, state: NodeState::Down })) if id == "n3"
+    ));
+    assert!(matches!(events.next().await, Some(Ok(MembershipEvent::NodeLeft(id))) if id == "n3"));
+    states.remove("n3");
+    let rest: Vec<&SwimMembership> = nodes.iter().collect();
+    assert!(eventually(Duration::from_secs(5), || all_see(&rest, &states)).await);
+
+    // 以同一标识重启的节点反驳墓碑后重新加入，不会留下两个成员
+    network.reconnect("n3");
+    nodes.push(start(&network, 3, vec![addr(1)]).await);
+    states.insert("n3".to_string(), NodeState::Active);
+    let all: Vec<&SwimMembership> = nodes.iter().collect();
+    assert!(eventually(Duration::from_secs(10), || all_see(&all, &states)).await);
+}
+
+#[test]
+fn test_node_id_is_persisted_under_data_dir() {
+    let data_dir = std::env::temp_dir().join(format!("coretex-node-id-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&data_dir);
+    let mut node = NodeConfig {
+        node_id: None,
+        bind_address: addr(1),
+        client_address: None,
+        data_dir: data_dir.clone(),
+        seed_nodes: Vec::new(),
+        zone: None,
+        rack: None,
+    };
+    let id = node.resolve_node_id().unwrap();
+    assert_eq!(node.resolve_node_id().unwrap(), id);
+    assert_eq!(std::fs::read_to_string(data_dir.join(NODE_ID_FILE)).unwrap(), id);
+
+    // 配置的标识优先
+    node.node_id = Some("node-1".to_string());
+    assert_eq!(node.resolve_node_id().unwrap(), "node-1");
+    std::fs::remove_dir_all(&data_dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_udp_transport_converges() {
+    let mut nodes = Vec::new();
+    let mut seeds = Vec::new();
+    for i in 1..=3 {
+        let transport = UdpTransport::bind(SocketAddr::from(([127, 0, 0, 1], 0))).await.unwrap();
+        let address = transport.local_addr();
+        let id = format!("u{}", i);
+        let membership = SwimMembership::start(id.clone(), Arc::new(transport), seeds.clone(), config())
+            .await
+            .unwrap();
+        membership.update_node_state(&id, NodeState::Active).await.unwrap();
+        seeds = vec![address];
+        nodes.push(membership);
+    }
+    let all: Vec<&SwimMembership> = nodes.iter().collect();
+    let active = expected(&[
+        ("u1", NodeState::Active),
+        ("u2", NodeState::Active),
+        ("u3", NodeState::Active),
+    ]);
+    assert!(eventually(Duration::from_secs(10), || all_see(&all, &active)).await);
+}
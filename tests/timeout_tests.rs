@@ -0,0 +1,182 @@
+mod common;
+
+use common::{Cluster, NODES};
+use coretex::{
+    api::client::Client,
+    consistency::{ConsistencyManager, Crdt, CrdtOp, QuorumConsistencyManager, RetryPolicy},
+    error::Error,
+    messaging::sim::{NetworkFaults, SimulatedNetwork},
+};
+use std::time::Duration;
+use tokio::time::Instant;
+
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+impl Cluster {
+    /// N=3、R=2、W=2，副本超时 100ms 并启用向量时钟的协调者
+    async fn versioned(&self, id: &str) -> QuorumConsistencyManager {
+        self.coordinator(id, 2, 2)
+            .await
+            .with_vector_clocks(true)
+            .with_timeout(TIMEOUT)
+    }
+}
+
+#[test]
+fn test_retry_policy_backoff() {
+    let policy = RetryPolicy::new(5, Duration::from_millis(10)).with_max_backoff(Duration::from_millis(50));
+    let backoffs: Vec<_> = (1..=4).map(|retry| policy.backoff(retry).as_millis()).collect();
+    assert_eq!(backoffs, vec![10, 20, 40, 50]);
+    assert_eq!(RetryPolicy::default().max_attempts, 1);
+
+    assert!(RetryPolicy::is_retryable(&Error::Timeout("副本 n1 响应超时".into())));
+    assert!(!RetryPolicy::is_retryable(&Error::Storage("磁盘已满".into())));
+    assert!(!RetryPolicy::is_retryable(&Error::QuorumNotMet {
+        required: 2,
+        acknowledged: 1
+    }));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_timeout_is_distinct_from_quorum_failure() {
+    let cluster = Cluster::simulated(SimulatedNetwork::new()).await;
+    for id in NODES {
+        cluster.sim().block("c", id);
+    }
+
+    // 副本请求各自超时后才返回：所有请求都已结束，确认不足
+    let coordinator = cluster.versioned("c").await;
+    assert!(matches!(
+        coordinator.put(b"key", b"v").await,
+        Err(Error::QuorumNotMet {
+            required: 2,
+            acknowledged: 0
+        })
+    ));
+
+    // 截止时间早于副本超时：请求仍在进行时返回超时
+    let coordinator = cluster
+        .versioned("c")
+        .await
+        .with_request_timeout(Duration::from_millis(50));
+    let started = Instant::now();
+    assert!(matches!(coordinator.get(b"key").await, Err(Error::Timeout(_))));
+    assert_eq!(started.elapsed(), Duration::from_millis(50));
+
+    // 重试同样不会超过截止时间
+    let coordinator = cluster
+        .versioned("c")
+        .await
+        .with_retry(RetryPolicy::new(10, Duration::from_millis(10)))
+        .with_request_timeout(Duration::from_millis(300));
+    let started = Instant::now();
+    assert!(matches!(coordinator.put(b"key", b"v").await, Err(Error::Timeout(_))));
+    assert_eq!(started.elapsed(), Duration::from_millis(300));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_retries_recover_dropped_messages() {
+    let faults = NetworkFaults {
+        drop_probability: 0.3,
+        ..Default::default()
+    };
+    let cluster = Cluster::simulated(SimulatedNetwork::new().with_faults(11, faults)).await;
+
+    let plain = cluster.versioned("plain").await;
+    let mut failures = 0;
+    for i in 0..20 {
+        let key = format!("plain{}", i);
+        failures += usize::from(plain.put(key.as_bytes(), b"v").await.is_err());
+    }
+    assert!(failures > 0);
+
+    let retrying = cluster
+        .versioned("retrying")
+        .await
+        .with_retry(RetryPolicy::new(8, Duration::from_millis(10)));
+    for i in 0..20 {
+        let key = format!("key{}", i);
+        retrying.put(key.as_bytes(), b"v").await.unwrap();
+        assert_eq!(retrying.get(key.as_bytes()).await.unwrap().as_deref(), Some(&b"v"[..]));
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_crdt_updates_are_retried_once_and_bounded_by_deadline() {
+    let faults = NetworkFaults {
+        drop_probability: 0.3,
+        ..Default::default()
+    };
+    let cluster = Cluster::simulated(SimulatedNetwork::new().with_faults(5, faults)).await;
+    let coordinator = cluster
+        .versioned("c")
+        .await
+        .with_retry(RetryPolicy::new(8, Duration::from_millis(10)));
+
+    // 响应丢失后重试同一个副本，副本按操作 id 去重，不会重复计数
+    for _ in 0..20 {
+        coordinator.update_crdt(b"counter", CrdtOp::GCounterIncrement(1)).await.unwrap();
+    }
+    cluster.sim().set_faults(NetworkFaults::default());
+    let Some(Crdt::GCounter(counter)) = coordinator.get_crdt(b"counter").await.unwrap() else {
+        panic!("没有读到计数器");
+    };
+    assert_eq!(counter.value(), 20);
+
+    // 副本都不响应时在请求截止时间返回超时
+    for id in NODES {
+        cluster.sim().block("c", id);
+    }
+    let coordinator = coordinator.with_request_timeout(Duration::from_millis(250));
+    let started = Instant::now();
+    let err = coordinator
+        .update_crdt(b"counter", CrdtOp::GCounterIncrement(1))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Timeout(_)));
+    assert_eq!(started.elapsed(), Duration::from_millis(250));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_hedged_read_avoids_slow_replica() {
+    let cluster = Cluster::simulated(SimulatedNetwork::new()).await;
+    let writer = cluster.versioned("w").await;
+    writer.put(b"key", b"v").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let reader = cluster
+        .versioned("r")
+        .await
+        .with_hedging(Some(Duration::from_millis(20)));
+    assert_eq!(reader.get(b"key").await.unwrap().as_deref(), Some(&b"v"[..]));
+    assert_eq!(reader.hedged_reads(), 0);
+
+    // 第一个副本不响应时，对冲延迟后向第三个副本读取，不必等到副本超时
+    cluster.sim().block("r", &cluster.replicas(b"key")[0]);
+    let started = Instant::now();
+    assert_eq!(reader.get(b"key").await.unwrap().as_deref(), Some(&b"v"[..]));
+    assert!(started.elapsed() < Duration::from_millis(100));
+    assert_eq!(reader.hedged_reads(), 1);
+
+    // 写入不对冲，仍然发给全部副本
+    reader.put(b"key", b"v2").await.unwrap();
+    assert_eq!(reader.hedged_reads(), 1);
+}
+
+#[tokio::test]
+async fn test_client_times_out_on_silent_server() {
+    // 接受连接但从不响应的服务端
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+
+    let client = Client::new(addr).await.unwrap().with_timeout(Duration::from_millis(100));
+    assert!(matches!(client.get(b"key").await, Err(Error::Timeout(_))));
+    assert!(matches!(client.put(b"key", b"v").await, Err(Error::Timeout(_))));
+    server.abort();
+}